@@ -6,13 +6,16 @@
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::collections::{BTreeMap, BTreeSet};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
 use std::fmt::{Debug, Formatter};
 
 use miette::{bail, ensure, miette, Result};
 use rand::prelude::*;
+use smartstring::{LazyCompact, SmartString};
 
-use crate::data::value::DataValue;
+use crate::data::decimal::Decimal;
+use crate::data::value::{DataValue, Num};
 
 pub(crate) struct Aggregation {
     pub(crate) name: &'static str,
@@ -385,6 +388,45 @@ impl NormalAggrObj for AggrCollect {
     }
 }
 
+define_aggr!(AGGR_GROUP_CONCAT, false);
+
+/// Joins strings with a separator, in the order in which they are aggregated
+pub(crate) struct AggrGroupConcat {
+    sep: SmartString<LazyCompact>,
+    accum: String,
+    is_empty: bool,
+}
+
+impl AggrGroupConcat {
+    fn new(sep: SmartString<LazyCompact>) -> Self {
+        Self {
+            sep,
+            accum: String::new(),
+            is_empty: true,
+        }
+    }
+}
+
+impl NormalAggrObj for AggrGroupConcat {
+    fn set(&mut self, value: &DataValue) -> Result<()> {
+        match value {
+            DataValue::Str(s) => {
+                if !self.is_empty {
+                    self.accum.push_str(&self.sep);
+                }
+                self.accum.push_str(s);
+                self.is_empty = false;
+                Ok(())
+            }
+            v => bail!("cannot apply 'group_concat' to {:?}", v),
+        }
+    }
+
+    fn get(&self) -> Result<DataValue> {
+        Ok(DataValue::from(self.accum.as_str()))
+    }
+}
+
 define_aggr!(AGGR_CHOICE_RAND, false);
 
 pub(crate) struct AggrChoiceRand {
@@ -436,12 +478,33 @@ impl NormalAggrObj for AggrCount {
 }
 
 define_aggr!(AGGR_VARIANCE, false);
+define_aggr!(AGGR_VARIANCE_POP, false);
+define_aggr!(AGGR_STD_DEV, false);
+define_aggr!(AGGR_STD_DEV_POP, false);
 
-#[derive(Default)]
+/// Variance or standard deviation, accumulated with Welford's online algorithm,
+/// which is numerically stable and needs constant memory.
+/// The sample variants divide by `n - 1` and the population variants by `n`.
 pub(crate) struct AggrVariance {
+    name: &'static str,
+    sample: bool,
+    std_dev: bool,
     count: i64,
-    sum: f64,
-    sum_sq: f64,
+    mean: f64,
+    m2: f64,
+}
+
+impl AggrVariance {
+    fn new(name: &'static str, sample: bool, std_dev: bool) -> Self {
+        Self {
+            name,
+            sample,
+            std_dev,
+            count: 0,
+            mean: 0.,
+            m2: 0.,
+        }
+    }
 }
 
 impl NormalAggrObj for AggrVariance {
@@ -449,53 +512,124 @@ impl NormalAggrObj for AggrVariance {
         match value {
             DataValue::Num(n) => {
                 let f = n.get_float();
-                self.sum += f;
-                self.sum_sq += f * f;
                 self.count += 1;
+                let delta = f - self.mean;
+                self.mean += delta / self.count as f64;
+                self.m2 += delta * (f - self.mean);
             }
-            v => bail!("cannot compute 'variance': encountered value {:?}", v),
+            v => bail!("cannot compute '{}': encountered value {:?}", self.name, v),
         }
         Ok(())
     }
 
     fn get(&self) -> Result<DataValue> {
-        let ct = self.count as f64;
-        Ok(DataValue::from(
-            (self.sum_sq - self.sum * self.sum / ct) / (ct - 1.),
-        ))
+        let denom = if self.sample {
+            self.count - 1
+        } else {
+            self.count
+        };
+        if denom <= 0 {
+            return Ok(DataValue::Null);
+        }
+        let var = (self.m2 / denom as f64).max(0.);
+        Ok(DataValue::from(if self.std_dev { var.sqrt() } else { var }))
     }
 }
 
-define_aggr!(AGGR_STD_DEV, false);
+// Exact percentiles cannot be computed in a single pass with bounded memory, so like `collect`
+// these aggregations keep every value of a group in memory. Nothing is streamed or spilled
+// to disk, so each group has to fit in memory.
+define_aggr!(AGGR_MEDIAN, false);
+define_aggr!(AGGR_PERCENTILE, false);
 
-#[derive(Default)]
-pub(crate) struct AggrStdDev {
-    count: i64,
-    sum: f64,
-    sum_sq: f64,
+/// Continuous percentile, interpolating linearly between the two closest values.
+/// All values of the group are kept in memory until the result is asked for.
+pub(crate) struct AggrPercentile {
+    p: f64,
+    values: Vec<f64>,
 }
 
-impl NormalAggrObj for AggrStdDev {
+impl AggrPercentile {
+    fn new(p: f64) -> Self {
+        Self { p, values: vec![] }
+    }
+}
+
+impl NormalAggrObj for AggrPercentile {
     fn set(&mut self, value: &DataValue) -> Result<()> {
         match value {
             DataValue::Num(n) => {
-                let f = n.get_float();
-                self.sum += f;
-                self.sum_sq += f * f;
-                self.count += 1;
+                self.values.push(n.get_float());
             }
-            v => bail!("cannot compute 'std_dev': encountered value {:?}", v),
+            v => bail!("cannot compute 'percentile': encountered value {:?}", v),
         }
         Ok(())
     }
 
     fn get(&self) -> Result<DataValue> {
-        let ct = self.count as f64;
-        let var = (self.sum_sq - self.sum * self.sum / ct) / (ct - 1.);
-        Ok(DataValue::from(var.sqrt()))
+        if self.values.is_empty() {
+            return Ok(DataValue::Null);
+        }
+        let mut values = self.values.clone();
+        values.sort_by(|a, b| a.total_cmp(b));
+        let pos = self.p * (values.len() - 1) as f64;
+        let lower = pos.floor() as usize;
+        let upper = pos.ceil() as usize;
+        let frac = pos - lower as f64;
+        Ok(DataValue::from(
+            values[lower] + (values[upper] - values[lower]) * frac,
+        ))
     }
 }
 
+define_aggr!(AGGR_PERCENTILE_DISC, false);
+
+/// Discrete percentile: the smallest value such that at least the given fraction
+/// of all values are not greater than it. Works for any values, not only numbers.
+/// All values of the group are kept in memory until the result is asked for.
+pub(crate) struct AggrPercentileDisc {
+    p: f64,
+    values: Vec<DataValue>,
+}
+
+impl AggrPercentileDisc {
+    fn new(p: f64) -> Self {
+        Self { p, values: vec![] }
+    }
+}
+
+impl NormalAggrObj for AggrPercentileDisc {
+    fn set(&mut self, value: &DataValue) -> Result<()> {
+        self.values.push(value.clone());
+        Ok(())
+    }
+
+    fn get(&self) -> Result<DataValue> {
+        if self.values.is_empty() {
+            return Ok(DataValue::Null);
+        }
+        let mut values = self.values.clone();
+        values.sort();
+        let idx = ((self.p * values.len() as f64).ceil() as usize).max(1) - 1;
+        Ok(values[idx].clone())
+    }
+}
+
+fn percentile_arg(name: &str, args: &[DataValue]) -> Result<f64> {
+    let p = args
+        .first()
+        .ok_or_else(|| miette!("'{}' requires the percentile as an argument", name))?
+        .get_float()
+        .ok_or_else(|| miette!("the argument to '{}' must be a number", name))?;
+    ensure!(
+        (0. ..=1.).contains(&p),
+        "the argument to '{}' must be between 0 and 1, got {}",
+        name,
+        p
+    );
+    Ok(p)
+}
+
 define_aggr!(AGGR_MEAN, false);
 
 #[derive(Default)]
@@ -526,6 +660,9 @@ define_aggr!(AGGR_SUM, false);
 #[derive(Default)]
 pub(crate) struct AggrSum {
     sum: f64,
+    // exact sum, kept as long as all values seen are decimals
+    decimal_sum: Option<Decimal>,
+    seen_num: bool,
 }
 
 impl NormalAggrObj for AggrSum {
@@ -533,6 +670,15 @@ impl NormalAggrObj for AggrSum {
         match value {
             DataValue::Num(n) => {
                 self.sum += n.get_float();
+                self.seen_num = true;
+            }
+            DataValue::Decimal(d) => {
+                self.sum += d.to_f64();
+                if !self.seen_num {
+                    let acc = self.decimal_sum.take().unwrap_or_else(|| Decimal::from(0));
+                    self.decimal_sum =
+                        Some(acc.add(d).ok_or_else(|| miette!("decimal out of range"))?);
+                }
             }
             v => bail!("cannot compute 'sum': encountered value {:?}", v),
         }
@@ -540,7 +686,10 @@ impl NormalAggrObj for AggrSum {
     }
 
     fn get(&self) -> Result<DataValue> {
-        Ok(DataValue::from(self.sum))
+        Ok(match &self.decimal_sum {
+            Some(d) if !self.seen_num => DataValue::Decimal(d.clone()),
+            _ => DataValue::from(self.sum),
+        })
     }
 }
 
@@ -879,6 +1028,115 @@ impl MeetAggrObj for MeetAggrMinCost {
     }
 }
 
+define_aggr!(AGGR_MIN_COST_K, false);
+define_aggr!(AGGR_MAX_COST_K, false);
+
+/// Keeps the `k` entries with the smallest (or largest) costs among `[payload, cost]` lists.
+/// Only `k` entries are ever held, in a heap whose top is the entry to evict next.
+pub(crate) struct AggrCostK {
+    name: &'static str,
+    k: usize,
+    largest: bool,
+    heap: BinaryHeap<CostEntry>,
+}
+
+impl AggrCostK {
+    fn new(name: &'static str, k: usize, largest: bool) -> Self {
+        Self {
+            name,
+            k,
+            largest,
+            // k comes from the query, the heap only grows with the rows seen
+            heap: BinaryHeap::new(),
+        }
+    }
+}
+
+/// Entries are ordered by cost, then by payload, from the best to the worst
+struct CostEntry {
+    cost: DataValue,
+    payload: DataValue,
+    largest: bool,
+}
+
+impl PartialEq for CostEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for CostEntry {}
+
+impl PartialOrd for CostEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CostEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let ord = self
+            .cost
+            .cmp(&other.cost)
+            .then_with(|| self.payload.cmp(&other.payload));
+        if self.largest {
+            ord.reverse()
+        } else {
+            ord
+        }
+    }
+}
+
+impl NormalAggrObj for AggrCostK {
+    fn set(&mut self, value: &DataValue) -> Result<()> {
+        match value {
+            DataValue::List(l) => {
+                ensure!(
+                    l.len() == 2,
+                    "'{}' requires a list of exactly two items as argument",
+                    self.name
+                );
+                self.heap.push(CostEntry {
+                    cost: l[1].clone(),
+                    payload: l[0].clone(),
+                    largest: self.largest,
+                });
+                if self.heap.len() > self.k {
+                    self.heap.pop();
+                }
+                Ok(())
+            }
+            v => bail!("cannot compute '{}' on {:?}", self.name, v),
+        }
+    }
+
+    fn get(&self) -> Result<DataValue> {
+        let mut entries: Vec<_> = self.heap.iter().collect();
+        entries.sort();
+        Ok(DataValue::List(
+            entries
+                .into_iter()
+                .map(|e| DataValue::List(vec![e.payload.clone(), e.cost.clone()]))
+                .collect(),
+        ))
+    }
+}
+
+fn cost_k_arg(name: &str, args: &[DataValue]) -> Result<usize> {
+    let arg = args
+        .first()
+        .ok_or_else(|| miette!("'{}' requires the number of entries to keep", name))?;
+    let k = arg.get_int().ok_or_else(|| {
+        miette!(
+            "the argument to '{}' must be an integer, got {:?}",
+            name,
+            arg
+        )
+    })?;
+    ensure!(k > 0, "argument to '{}' must be positive, got {}", name, k);
+    Ok(k as usize)
+}
+
 define_aggr!(AGGR_SHORTEST, true);
 
 #[derive(Default)]
@@ -983,175 +1241,85 @@ impl MeetAggrObj for MeetAggrChoice {
 }
 
 define_aggr!(AGGR_BIT_AND, true);
+define_aggr!(AGGR_BIT_OR, true);
+define_aggr!(AGGR_BIT_XOR, false);
 
-#[derive(Default)]
-pub(crate) struct AggrBitAnd {
-    res: Vec<u8>,
-}
-
-impl NormalAggrObj for AggrBitAnd {
-    fn set(&mut self, value: &DataValue) -> Result<()> {
-        match value {
-            DataValue::Bytes(bs) => {
-                if self.res.is_empty() {
-                    self.res = bs.to_vec();
-                } else {
-                    ensure!(
-                        self.res.len() == bs.len(),
-                        "operands of 'bit_and' must have the same lengths, got {:x?} and {:x?}",
-                        self.res,
-                        bs
-                    );
-                    for (l, r) in self.res.iter_mut().zip(bs.iter()) {
-                        *l &= *r;
-                    }
-                }
-                Ok(())
+/// Combines `right` into `left` bitwise. The operands are either both integers,
+/// or both byte arrays of the same length.
+/// An empty byte array, the initial value of the meet aggregations, takes on the other operand.
+fn combine_bits(
+    name: &str,
+    left: &mut DataValue,
+    right: &DataValue,
+    op: fn(i64, i64) -> i64,
+) -> Result<()> {
+    match (&mut *left, right) {
+        (DataValue::Bytes(l), DataValue::Bytes(r)) => {
+            if l.is_empty() {
+                *l = r.clone();
+                return Ok(());
+            }
+            ensure!(
+                l.len() == r.len(),
+                "operands of '{}' must have the same lengths, got {:x?} and {:x?}",
+                name,
+                l,
+                r
+            );
+            for (l, r) in l.iter_mut().zip(r.iter()) {
+                *l = op(*l as i64, *r as i64) as u8;
             }
-            v => bail!("cannot apply 'bit_and' to {:?}", v),
         }
+        (DataValue::Num(Num::Int(l)), DataValue::Num(Num::Int(r))) => *l = op(*l, *r),
+        (DataValue::Bytes(l), DataValue::Num(Num::Int(_))) if l.is_empty() => *left = right.clone(),
+        (l, r) => bail!("cannot apply '{}' to {:?} and {:?}", name, l, r),
     }
-
-    fn get(&self) -> Result<DataValue> {
-        Ok(DataValue::Bytes(self.res.clone()))
-    }
+    Ok(())
 }
 
-pub(crate) struct MeetAggrBitAnd;
-
-impl MeetAggrObj for MeetAggrBitAnd {
-    fn init_val(&self) -> DataValue {
-        DataValue::Bytes(vec![])
-    }
-
-    fn update(&self, left: &mut DataValue, right: &DataValue) -> Result<bool> {
-        match (left, right) {
-            (DataValue::Bytes(left), DataValue::Bytes(right)) => {
-                if left == right {
-                    return Ok(false);
-                }
-                if left.is_empty() {
-                    *left = right.clone();
-                    return Ok(true);
-                }
-                ensure!(
-                    left.len() == right.len(),
-                    "operands of 'bit_and' must have the same lengths, got {:x?} and {:x?}",
-                    left,
-                    right
-                );
-                for (l, r) in left.iter_mut().zip(right.iter()) {
-                    *l &= *r;
-                }
+pub(crate) struct AggrBitwise {
+    name: &'static str,
+    op: fn(i64, i64) -> i64,
+    res: DataValue,
+}
 
-                Ok(true)
-            }
-            v => bail!("cannot apply 'bit_and' to {:?}", v),
+impl AggrBitwise {
+    fn new(name: &'static str, op: fn(i64, i64) -> i64) -> Self {
+        Self {
+            name,
+            op,
+            res: DataValue::Bytes(vec![]),
         }
     }
 }
 
-define_aggr!(AGGR_BIT_OR, true);
-
-#[derive(Default)]
-pub(crate) struct AggrBitOr {
-    res: Vec<u8>,
-}
-
-impl NormalAggrObj for AggrBitOr {
+impl NormalAggrObj for AggrBitwise {
     fn set(&mut self, value: &DataValue) -> Result<()> {
-        match value {
-            DataValue::Bytes(bs) => {
-                if self.res.is_empty() {
-                    self.res = bs.to_vec();
-                } else {
-                    ensure!(
-                        self.res.len() == bs.len(),
-                        "operands of 'bit_or' must have the same lengths, got {:x?} and {:x?}",
-                        self.res,
-                        bs
-                    );
-                    for (l, r) in self.res.iter_mut().zip(bs.iter()) {
-                        *l |= *r;
-                    }
-                }
-                Ok(())
-            }
-            v => bail!("cannot apply 'bit_or' to {:?}", v),
-        }
+        combine_bits(self.name, &mut self.res, value, self.op)
     }
 
     fn get(&self) -> Result<DataValue> {
-        Ok(DataValue::Bytes(self.res.clone()))
+        Ok(self.res.clone())
     }
 }
 
-pub(crate) struct MeetAggrBitOr;
+pub(crate) struct MeetAggrBitwise {
+    name: &'static str,
+    op: fn(i64, i64) -> i64,
+}
 
-impl MeetAggrObj for MeetAggrBitOr {
+impl MeetAggrObj for MeetAggrBitwise {
     fn init_val(&self) -> DataValue {
         DataValue::Bytes(vec![])
     }
 
     fn update(&self, left: &mut DataValue, right: &DataValue) -> Result<bool> {
-        match (left, right) {
-            (DataValue::Bytes(left), DataValue::Bytes(right)) => {
-                if left == right {
-                    return Ok(false);
-                }
-                if left.is_empty() {
-                    *left = right.clone();
-                    return Ok(true);
-                }
-                ensure!(
-                    left.len() == right.len(),
-                    "operands of 'bit_or' must have the same lengths, got {:x?} and {:x?}",
-                    left,
-                    right
-                );
-                for (l, r) in left.iter_mut().zip(right.iter()) {
-                    *l |= *r;
-                }
-
-                Ok(true)
-            }
-            v => bail!("cannot apply 'bit_or' to {:?}", v),
-        }
-    }
-}
-
-define_aggr!(AGGR_BIT_XOR, false);
-
-#[derive(Default)]
-pub(crate) struct AggrBitXor {
-    res: Vec<u8>,
-}
-
-impl NormalAggrObj for AggrBitXor {
-    fn set(&mut self, value: &DataValue) -> Result<()> {
-        match value {
-            DataValue::Bytes(bs) => {
-                if self.res.is_empty() {
-                    self.res = bs.to_vec();
-                } else {
-                    ensure!(
-                        self.res.len() == bs.len(),
-                        "operands of 'bit_xor' must have the same lengths, got {:x?} and {:x?}",
-                        self.res,
-                        bs
-                    );
-                    for (l, r) in self.res.iter_mut().zip(bs.iter()) {
-                        *l ^= *r;
-                    }
-                }
-                Ok(())
-            }
-            v => bail!("cannot apply 'bit_xor' to {:?}", v),
+        if left == right {
+            return Ok(false);
         }
-    }
-
-    fn get(&self) -> Result<DataValue> {
-        Ok(DataValue::Bytes(self.res.clone()))
+        let prev = left.clone();
+        combine_bits(self.name, left, right, self.op)?;
+        Ok(*left != prev)
     }
 }
 
@@ -1166,7 +1334,11 @@ pub(crate) fn parse_aggr(name: &str) -> Option<&'static Aggregation> {
         "count" => &AGGR_COUNT,
         "count_unique" => &AGGR_COUNT_UNIQUE,
         "variance" => &AGGR_VARIANCE,
+        "variance_samp" => &AGGR_VARIANCE,
+        "variance_pop" => &AGGR_VARIANCE_POP,
         "std_dev" => &AGGR_STD_DEV,
+        "std_dev_samp" => &AGGR_STD_DEV,
+        "std_dev_pop" => &AGGR_STD_DEV_POP,
         "sum" => &AGGR_SUM,
         "product" => &AGGR_PRODUCT,
         "min" => &AGGR_MIN,
@@ -1174,14 +1346,20 @@ pub(crate) fn parse_aggr(name: &str) -> Option<&'static Aggregation> {
         "mean" => &AGGR_MEAN,
         "choice" => &AGGR_CHOICE,
         "collect" => &AGGR_COLLECT,
+        "group_concat" => &AGGR_GROUP_CONCAT,
         "shortest" => &AGGR_SHORTEST,
         "min_cost" => &AGGR_MIN_COST,
+        "min_cost_k" => &AGGR_MIN_COST_K,
+        "max_cost_k" => &AGGR_MAX_COST_K,
         "bit_and" => &AGGR_BIT_AND,
         "bit_or" => &AGGR_BIT_OR,
         "bit_xor" => &AGGR_BIT_XOR,
         "latest_by" => &AGGR_LATEST_BY,
         "smallest_by" => &AGGR_SMALLEST_BY,
         "choice_rand" => &AGGR_CHOICE_RAND,
+        "median" => &AGGR_MEDIAN,
+        "percentile" => &AGGR_PERCENTILE,
+        "percentile_disc" => &AGGR_PERCENTILE_DISC,
         _ => return None,
     })
 }
@@ -1194,8 +1372,14 @@ impl Aggregation {
             name if name == AGGR_MIN.name => Box::new(MeetAggrMin),
             name if name == AGGR_MAX.name => Box::new(MeetAggrMax),
             name if name == AGGR_CHOICE.name => Box::new(MeetAggrChoice),
-            name if name == AGGR_BIT_AND.name => Box::new(MeetAggrBitAnd),
-            name if name == AGGR_BIT_OR.name => Box::new(MeetAggrBitOr),
+            name if name == AGGR_BIT_AND.name => Box::new(MeetAggrBitwise {
+                name,
+                op: |l, r| l & r,
+            }),
+            name if name == AGGR_BIT_OR.name => Box::new(MeetAggrBitwise {
+                name,
+                op: |l, r| l | r,
+            }),
             name if name == AGGR_UNION.name => Box::new(MeetAggrUnion),
             name if name == AGGR_INTERSECTION.name => Box::new(MeetAggrIntersection),
             name if name == AGGR_SHORTEST.name => Box::new(MeetAggrShortest),
@@ -1217,20 +1401,37 @@ impl Aggregation {
             name if name == AGGR_MIN.name => Box::new(AggrMin::default()),
             name if name == AGGR_MAX.name => Box::new(AggrMax::default()),
             name if name == AGGR_MEAN.name => Box::new(AggrMean::default()),
-            name if name == AGGR_VARIANCE.name => Box::new(AggrVariance::default()),
-            name if name == AGGR_STD_DEV.name => Box::new(AggrStdDev::default()),
+            name if name == AGGR_VARIANCE.name => Box::new(AggrVariance::new(name, true, false)),
+            name if name == AGGR_VARIANCE_POP.name => {
+                Box::new(AggrVariance::new(name, false, false))
+            }
+            name if name == AGGR_STD_DEV.name => Box::new(AggrVariance::new(name, true, true)),
+            name if name == AGGR_STD_DEV_POP.name => Box::new(AggrVariance::new(name, false, true)),
             name if name == AGGR_CHOICE.name => Box::new(AggrChoice::default()),
-            name if name == AGGR_BIT_AND.name => Box::new(AggrBitAnd::default()),
-            name if name == AGGR_BIT_OR.name => Box::new(AggrBitOr::default()),
-            name if name == AGGR_BIT_XOR.name => Box::new(AggrBitXor::default()),
+            name if name == AGGR_BIT_AND.name => Box::new(AggrBitwise::new(name, |l, r| l & r)),
+            name if name == AGGR_BIT_OR.name => Box::new(AggrBitwise::new(name, |l, r| l | r)),
+            name if name == AGGR_BIT_XOR.name => Box::new(AggrBitwise::new(name, |l, r| l ^ r)),
             name if name == AGGR_UNIQUE.name => Box::new(AggrUnique::default()),
             name if name == AGGR_UNION.name => Box::new(AggrUnion::default()),
             name if name == AGGR_INTERSECTION.name => Box::new(AggrIntersection::default()),
             name if name == AGGR_SHORTEST.name => Box::new(AggrShortest::default()),
             name if name == AGGR_MIN_COST.name => Box::new(AggrMinCost::default()),
+            name if name == AGGR_MIN_COST_K.name => {
+                Box::new(AggrCostK::new(name, cost_k_arg(name, args)?, false))
+            }
+            name if name == AGGR_MAX_COST_K.name => {
+                Box::new(AggrCostK::new(name, cost_k_arg(name, args)?, true))
+            }
             name if name == AGGR_LATEST_BY.name => Box::new(AggrLatestBy::default()),
             name if name == AGGR_SMALLEST_BY.name => Box::new(AggrSmallestBy::default()),
             name if name == AGGR_CHOICE_RAND.name => Box::new(AggrChoiceRand::default()),
+            name if name == AGGR_MEDIAN.name => Box::new(AggrPercentile::new(0.5)),
+            name if name == AGGR_PERCENTILE.name => {
+                Box::new(AggrPercentile::new(percentile_arg("percentile", args)?))
+            }
+            name if name == AGGR_PERCENTILE_DISC.name => Box::new(AggrPercentileDisc::new(
+                percentile_arg("percentile_disc", args)?,
+            )),
             name if name == AGGR_COLLECT.name => Box::new({
                 if args.is_empty() {
                     AggrCollect::default()
@@ -1249,6 +1450,16 @@ impl Aggregation {
                     AggrCollect::new(arg as usize)
                 }
             }),
+            name if name == AGGR_GROUP_CONCAT.name => {
+                Box::new(AggrGroupConcat::new(match args.first() {
+                    None => ",".into(),
+                    Some(DataValue::Str(s)) => s.clone(),
+                    Some(v) => bail!(
+                        "the separator of 'group_concat' must be a string, got {:?}",
+                        v
+                    ),
+                }))
+            }
             _ => unreachable!(),
         });
         Ok(())
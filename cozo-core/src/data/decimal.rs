@@ -0,0 +1,331 @@
+/*
+ * Copyright 2022, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display, Formatter};
+use std::str::FromStr;
+
+use num_bigint::{BigInt, Sign};
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use serde::de::Visitor;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Largest number of decimal places, in either direction, a decimal may carry
+pub(crate) const MAX_DECIMAL_SCALE: i64 = 1024;
+
+/// Number of decimal places added to the operands' when dividing
+const DIV_EXTRA_SCALE: i64 = 16;
+
+/// Decimal number of arbitrary precision, with the value `mantissa * 10^-scale`.
+///
+/// Decimals are kept normalized, with no trailing zeros in the mantissa,
+/// so that equal numbers have equal representations.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Decimal {
+    mantissa: BigInt,
+    scale: i64,
+}
+
+impl Decimal {
+    fn new(mut mantissa: BigInt, mut scale: i64) -> Option<Self> {
+        if mantissa.is_zero() {
+            return Some(Self { mantissa, scale: 0 });
+        }
+        let ten = BigInt::from(10);
+        loop {
+            let (q, r) = (&mantissa / &ten, &mantissa % &ten);
+            if !r.is_zero() {
+                break;
+            }
+            mantissa = q;
+            scale -= 1;
+        }
+        if scale.abs() > MAX_DECIMAL_SCALE {
+            return None;
+        }
+        Some(Self { mantissa, scale })
+    }
+
+    /// Converts a float through its shortest exact representation, `None` for NaN and infinities
+    pub(crate) fn from_f64(f: f64) -> Option<Self> {
+        if !f.is_finite() {
+            return None;
+        }
+        Self::from_str(&f.to_string()).ok()
+    }
+
+    pub(crate) fn to_f64(&self) -> f64 {
+        f64::from_str(&self.to_string()).unwrap()
+    }
+
+    /// Integer part, rounded towards zero, if it fits into an `i64`
+    pub(crate) fn trunc_to_i64(&self) -> Option<i64> {
+        if self.scale <= 0 {
+            (&self.mantissa * pow10(-self.scale)).to_i64()
+        } else {
+            (&self.mantissa / pow10(self.scale)).to_i64()
+        }
+    }
+
+    pub(crate) fn is_zero(&self) -> bool {
+        self.mantissa.is_zero()
+    }
+
+    pub(crate) fn signum(&self) -> i64 {
+        match self.mantissa.sign() {
+            Sign::Minus => -1,
+            Sign::NoSign => 0,
+            Sign::Plus => 1,
+        }
+    }
+
+    pub(crate) fn neg(&self) -> Self {
+        Self {
+            mantissa: -&self.mantissa,
+            scale: self.scale,
+        }
+    }
+
+    pub(crate) fn abs(&self) -> Self {
+        Self {
+            mantissa: self.mantissa.abs(),
+            scale: self.scale,
+        }
+    }
+
+    fn aligned(&self, scale: i64) -> BigInt {
+        &self.mantissa * pow10(scale - self.scale)
+    }
+
+    pub(crate) fn add(&self, other: &Self) -> Option<Self> {
+        let scale = self.scale.max(other.scale);
+        Self::new(self.aligned(scale) + other.aligned(scale), scale)
+    }
+
+    pub(crate) fn sub(&self, other: &Self) -> Option<Self> {
+        self.add(&other.neg())
+    }
+
+    pub(crate) fn mul(&self, other: &Self) -> Option<Self> {
+        Self::new(&self.mantissa * &other.mantissa, self.scale + other.scale)
+    }
+
+    /// Quotient rounded half away from zero, with 16 more decimal places than the operands.
+    /// `None` when dividing by zero.
+    pub(crate) fn div(&self, other: &Self) -> Option<Self> {
+        if other.is_zero() {
+            return None;
+        }
+        let scale = self.scale.max(other.scale).max(0) + DIV_EXTRA_SCALE;
+        let shift = scale - self.scale + other.scale;
+        let (num, den) = if shift >= 0 {
+            (&self.mantissa * pow10(shift), other.mantissa.clone())
+        } else {
+            (self.mantissa.clone(), &other.mantissa * pow10(-shift))
+        };
+        Self::new(div_round(num, &den), scale)
+    }
+
+    /// Rounds half away from zero to the given number of decimal places
+    pub(crate) fn round(&self, places: i64) -> Option<Self> {
+        let places = clamp_places(places);
+        if self.scale <= places {
+            return Some(self.clone());
+        }
+        let den = pow10(self.scale - places);
+        Self::new(div_round(self.mantissa.clone(), &den), places)
+    }
+
+    /// Rounds towards negative infinity to the given number of decimal places
+    pub(crate) fn floor(&self, places: i64) -> Option<Self> {
+        let places = clamp_places(places);
+        if self.scale <= places {
+            return Some(self.clone());
+        }
+        let den = pow10(self.scale - places);
+        // the mantissa has no trailing zeros, so the division is never exact here
+        let mut q = &self.mantissa / &den;
+        if self.mantissa.is_negative() {
+            q -= 1;
+        }
+        Self::new(q, places)
+    }
+
+    /// Rounds towards positive infinity to the given number of decimal places
+    pub(crate) fn ceil(&self, places: i64) -> Option<Self> {
+        self.neg().floor(places).map(|d| d.neg())
+    }
+
+    /// The digits of the absolute value without trailing zeros, and the exponent `e`
+    /// such that the value is `0.digits * 10^e`.
+    /// Comparing the exponents, and then the digits as strings, orders by magnitude.
+    pub(crate) fn to_exp_digits(&self) -> (i64, String) {
+        let digits = self.mantissa.magnitude().to_string();
+        (digits.len() as i64 - self.scale, digits)
+    }
+
+    pub(crate) fn from_exp_digits(is_negative: bool, exp: i64, digits: &str) -> Option<Self> {
+        let mut mantissa = BigInt::from_str(digits).ok()?;
+        if is_negative {
+            mantissa = -mantissa;
+        }
+        Self::new(mantissa, digits.len() as i64 - exp)
+    }
+}
+
+/// Rounding to numbers of places beyond the scales of decimals gives the same results,
+/// or results out of range, as rounding to the nearest ones outside
+fn clamp_places(places: i64) -> i64 {
+    places.clamp(-MAX_DECIMAL_SCALE - 1, MAX_DECIMAL_SCALE + 1)
+}
+
+/// `10^exp`, the exponents stay small as the scales of decimals are bounded
+fn pow10(exp: i64) -> BigInt {
+    num_traits::pow(BigInt::from(10), exp as usize)
+}
+
+fn div_round(num: BigInt, den: &BigInt) -> BigInt {
+    let (q, r) = (&num / den, &num % den);
+    if r.abs() * 2 >= den.abs() {
+        if num.is_negative() == den.is_negative() {
+            q + BigInt::one()
+        } else {
+            q - BigInt::one()
+        }
+    } else {
+        q
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(i: i64) -> Self {
+        Self::new(BigInt::from(i), 0).unwrap()
+    }
+}
+
+/// Error for strings that are not decimal numbers, or that are out of range
+#[derive(Debug)]
+pub struct BadDecimal;
+
+impl FromStr for Decimal {
+    type Err = BadDecimal;
+
+    /// Parses numbers such as `-12.50` or `1.2e-3`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (num, exp) = match s.find(['e', 'E']) {
+            None => (s, 0),
+            Some(i) => (&s[..i], i64::from_str(&s[i + 1..]).map_err(|_| BadDecimal)?),
+        };
+        let (is_negative, num) = match num.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, num.strip_prefix('+').unwrap_or(num)),
+        };
+        let (int_part, frac_part) = num.split_once('.').unwrap_or((num, ""));
+        if int_part.is_empty() && frac_part.is_empty()
+            || !int_part
+                .bytes()
+                .chain(frac_part.bytes())
+                .all(|c| c.is_ascii_digit())
+            || exp.abs() > MAX_DECIMAL_SCALE
+        {
+            return Err(BadDecimal);
+        }
+        let mut mantissa =
+            BigInt::from_str(&format!("0{int_part}{frac_part}")).map_err(|_| BadDecimal)?;
+        if is_negative {
+            mantissa = -mantissa;
+        }
+        Self::new(mantissa, frac_part.len() as i64 - exp).ok_or(BadDecimal)
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.mantissa.is_negative() {
+            f.write_str("-")?;
+        }
+        let digits = self.mantissa.magnitude().to_string();
+        if self.scale <= 0 {
+            f.write_str(&digits)?;
+            for _ in 0..-self.scale {
+                f.write_str("0")?;
+            }
+        } else {
+            let scale = self.scale as usize;
+            if digits.len() > scale {
+                let (int_part, frac_part) = digits.split_at(digits.len() - scale);
+                write!(f, "{int_part}.{frac_part}")?;
+            } else {
+                write!(f, "0.{}{digits}", "0".repeat(scale - digits.len()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Decimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let sign_ord = self.signum().cmp(&other.signum());
+        if sign_ord != Ordering::Equal || self.is_zero() {
+            return sign_ord;
+        }
+        let magnitude_ord = self.to_exp_digits().cmp(&other.to_exp_digits());
+        if self.mantissa.is_negative() {
+            magnitude_ord.reverse()
+        } else {
+            magnitude_ord
+        }
+    }
+}
+
+impl Serialize for Decimal {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(DecimalVisitor)
+    }
+}
+
+struct DecimalVisitor;
+
+impl<'de> Visitor<'de> for DecimalVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("decimal number as a string")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Decimal::from_str(v).map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+    }
+}
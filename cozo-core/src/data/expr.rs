@@ -10,6 +10,7 @@ use std::cmp::{max, min};
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{Debug, Display, Formatter};
 use std::mem;
+use std::sync::Arc;
 
 use itertools::Itertools;
 use miette::{bail, miette, Diagnostic, Result};
@@ -18,10 +19,11 @@ use serde::{Deserializer, Serializer};
 use smartstring::{LazyCompact, SmartString};
 use thiserror::Error;
 
+use crate::data::decimal::Decimal;
 use crate::data::functions::*;
-use crate::data::relation::NullableColType;
+use crate::data::relation::{ColType, ColumnDef, NullableColType};
 use crate::data::symb::Symbol;
-use crate::data::value::{DataValue, LARGEST_UTF_CHAR};
+use crate::data::value::{DataValue, GeoPoint, Interval, Num, LARGEST_UTF_CHAR};
 use crate::parse::expr::expr2bytecode;
 use crate::parse::SourceSpan;
 
@@ -45,12 +47,33 @@ pub enum Bytecode {
         #[serde(skip)]
         span: SourceSpan,
     },
+    /// pop 1 (2 for folds), push 1
+    ListFn {
+        kind: ListFnKind,
+        param_pos: Option<usize>,
+        body: Vec<Bytecode>,
+        #[serde(skip)]
+        span: SourceSpan,
+    },
+    /// pop n, push 1
+    #[serde(skip)]
+    UserApply {
+        func: UserFn,
+        arity: usize,
+        span: SourceSpan,
+    },
     /// pop 1
     JumpIfFalse {
         jump_to: usize,
         #[serde(skip)]
         span: SourceSpan,
     },
+    /// pop 1 if null, otherwise unchanged
+    JumpIfNotNull {
+        jump_to: usize,
+        #[serde(skip)]
+        span: SourceSpan,
+    },
     /// unchanged
     Goto {
         jump_to: usize,
@@ -78,6 +101,8 @@ pub fn eval_bytecode_pred(
 ) -> Result<bool> {
     match eval_bytecode(bytecodes, bindings, stack)? {
         DataValue::Bool(b) => Ok(b),
+        // unknown, e.g. a comparison involving null
+        DataValue::Null => Ok(false),
         v => bail!(PredicateTypeError(span, v)),
     }
 }
@@ -135,17 +160,57 @@ pub fn eval_bytecode(
                 stack.push(result);
                 pointer += 1;
             }
+            Bytecode::ListFn {
+                kind,
+                param_pos,
+                body,
+                span,
+            } => {
+                let frame_start = stack.len() - kind.arity();
+                let mut body_stack = vec![];
+                let result = eval_list_fn(
+                    *kind,
+                    &stack[frame_start..],
+                    *param_pos,
+                    bindings.as_ref(),
+                    *span,
+                    |local| eval_bytecode(body, local, &mut body_stack),
+                )?;
+                stack.truncate(frame_start);
+                stack.push(result);
+                pointer += 1;
+            }
+            Bytecode::UserApply { func, arity, span } => {
+                let frame_start = stack.len() - *arity;
+                let args_frame = &stack[frame_start..];
+                let result = (func.inner)(args_frame)
+                    .map_err(|err| EvalRaisedError(*span, err.to_string()))?;
+                stack.truncate(frame_start);
+                stack.push(result);
+                pointer += 1;
+            }
             Bytecode::JumpIfFalse { jump_to, span } => {
                 let val = stack.pop().unwrap();
-                let cond = val
-                    .get_bool()
-                    .ok_or_else(|| PredicateTypeError(*span, val))?;
+                let cond = match val {
+                    DataValue::Null => false,
+                    val => val
+                        .get_bool()
+                        .ok_or_else(|| PredicateTypeError(*span, val))?,
+                };
                 if cond {
                     pointer += 1;
                 } else {
                     pointer = *jump_to;
                 }
             }
+            Bytecode::JumpIfNotNull { jump_to, .. } => {
+                if *stack.last().unwrap() == DataValue::Null {
+                    stack.pop();
+                    pointer += 1;
+                } else {
+                    pointer = *jump_to;
+                }
+            }
             Bytecode::Goto { jump_to, .. } => {
                 pointer = *jump_to;
             }
@@ -182,6 +247,33 @@ pub enum Expr {
         #[serde(skip)]
         span: SourceSpan,
     },
+    /// Application of `map`, `filter` or `fold` to a list and a lambda
+    ListFn {
+        /// Which function is applied
+        kind: ListFnKind,
+        /// The list, followed by the initial accumulator for folds
+        args: Box<[Expr]>,
+        /// Parameters of the lambda
+        params: Box<[Symbol]>,
+        /// Position of the first parameter in the tuple extended with the parameters
+        param_pos: Option<usize>,
+        /// Body of the lambda
+        body: Box<Expr>,
+        /// Source span
+        #[serde(skip)]
+        span: SourceSpan,
+    },
+    /// Application of a function registered with [crate::Db::register_fn].
+    /// Such expressions cannot be persisted.
+    #[serde(skip)]
+    UserApply {
+        /// The function to apply
+        func: UserFn,
+        /// Arguments to the application
+        args: Box<[Expr]>,
+        /// Source span
+        span: SourceSpan,
+    },
     /// Unbound function application
     UnboundApply {
         /// Op representing the function to apply
@@ -225,6 +317,26 @@ impl Display for Expr {
                 }
                 writer.finish()
             }
+            Expr::ListFn {
+                kind,
+                args,
+                params,
+                body,
+                ..
+            } => {
+                write!(f, "{}(", kind.name())?;
+                for arg in args.iter() {
+                    write!(f, "{arg}, ")?;
+                }
+                write!(f, "|{}| {body})", params.iter().join(", "))
+            }
+            Expr::UserApply { func, args, .. } => {
+                let mut writer = f.debug_tuple(&func.name);
+                for arg in args.iter() {
+                    writer.field(arg);
+                }
+                writer.finish()
+            }
             Expr::UnboundApply { op, args, .. } => {
                 let mut writer = f.debug_tuple(op);
                 for arg in args.iter() {
@@ -265,6 +377,104 @@ struct BadEntityId(DataValue, #[label] SourceSpan);
 #[diagnostic(code(eval::throw))]
 struct EvalRaisedError(#[label] SourceSpan, #[help] String);
 
+/// Higher-order functions over lists, taking a lambda as their last argument
+#[derive(Copy, Clone, PartialEq, Eq, serde_derive::Serialize, serde_derive::Deserialize, Debug)]
+pub enum ListFnKind {
+    /// `map(list, |x| ..)`
+    Map,
+    /// `filter(list, |x| ..)`
+    Filter,
+    /// `fold(list, init, |acc, x| ..)`
+    Fold,
+}
+
+impl ListFnKind {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "map" => ListFnKind::Map,
+            "filter" => ListFnKind::Filter,
+            "fold" => ListFnKind::Fold,
+            _ => return None,
+        })
+    }
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ListFnKind::Map => "map",
+            ListFnKind::Filter => "filter",
+            ListFnKind::Fold => "fold",
+        }
+    }
+    /// Number of arguments besides the lambda
+    pub(crate) fn arity(&self) -> usize {
+        match self {
+            ListFnKind::Map | ListFnKind::Filter => 1,
+            ListFnKind::Fold => 2,
+        }
+    }
+    /// Number of parameters of the lambda
+    pub(crate) fn num_params(&self) -> usize {
+        match self {
+            ListFnKind::Map | ListFnKind::Filter => 1,
+            ListFnKind::Fold => 2,
+        }
+    }
+}
+
+/// Applies a list function, `eval_body` evaluating the lambda against the tuple
+/// extended with the parameters at `param_pos`
+fn eval_list_fn(
+    kind: ListFnKind,
+    args: &[DataValue],
+    param_pos: Option<usize>,
+    bindings: &[DataValue],
+    span: SourceSpan,
+    mut eval_body: impl FnMut(&[DataValue]) -> Result<DataValue>,
+) -> Result<DataValue> {
+    #[derive(Error, Diagnostic, Debug)]
+    #[error("'{0}' requires a list, got {1:?}")]
+    #[diagnostic(code(eval::list_fn_not_list))]
+    struct NotListError(&'static str, DataValue, #[label] SourceSpan);
+
+    let list = args[0]
+        .get_slice()
+        .ok_or_else(|| NotListError(kind.name(), args[0].clone(), span))?;
+    let pos =
+        param_pos.ok_or_else(|| miette!("lambda parameters of '{}' are unbound", kind.name()))?;
+    let mut local = bindings.to_vec();
+    local.resize(local.len().max(pos + kind.num_params()), DataValue::Null);
+    Ok(match kind {
+        ListFnKind::Map => {
+            let mut ret = Vec::with_capacity(list.len());
+            for x in list {
+                local[pos] = x.clone();
+                ret.push(eval_body(&local)?);
+            }
+            DataValue::List(ret)
+        }
+        ListFnKind::Filter => {
+            let mut ret = vec![];
+            for x in list {
+                local[pos] = x.clone();
+                match eval_body(&local)? {
+                    DataValue::Bool(true) => ret.push(x.clone()),
+                    DataValue::Bool(false) | DataValue::Null => {}
+                    v => bail!(PredicateTypeError(span, v)),
+                }
+            }
+            DataValue::List(ret)
+        }
+        ListFnKind::Fold => {
+            let mut acc = args[1].clone();
+            for x in list {
+                local[pos] = acc;
+                local[pos + 1] = x.clone();
+                acc = eval_body(&local)?;
+            }
+            acc
+        }
+    })
+}
+
 impl Expr {
     pub(crate) fn compile(&self) -> Result<Vec<Bytecode>> {
         let mut collector = vec![];
@@ -275,7 +485,9 @@ impl Expr {
         match self {
             Expr::Binding { var, .. } => var.span,
             Expr::Const { span, .. } | Expr::Apply { span, .. } | Expr::Cond { span, .. } => *span,
-            Expr::UnboundApply { span, .. } => *span,
+            Expr::ListFn { span, .. }
+            | Expr::UserApply { span, .. }
+            | Expr::UnboundApply { span, .. } => *span,
         }
     }
     pub(crate) fn get_binding(&self) -> Option<&Symbol> {
@@ -343,7 +555,7 @@ impl Expr {
                 *tuple_pos = Some(found_idx)
             }
             Expr::Const { .. } => {}
-            Expr::Apply { args, .. } => {
+            Expr::Apply { args, .. } | Expr::UserApply { args, .. } => {
                 for arg in args.iter_mut() {
                     arg.fill_binding_indices(binding_map)?;
                 }
@@ -354,6 +566,25 @@ impl Expr {
                     val.fill_binding_indices(binding_map)?;
                 }
             }
+            Expr::ListFn {
+                args,
+                params,
+                param_pos,
+                body,
+                ..
+            } => {
+                for arg in args.iter_mut() {
+                    arg.fill_binding_indices(binding_map)?;
+                }
+                // the parameters are placed after all bindings of the enclosing tuple
+                let pos = binding_map.values().max().map_or(0, |max| max + 1);
+                let mut body_map = binding_map.clone();
+                for (i, param) in params.iter().enumerate() {
+                    body_map.insert(param.clone(), pos + i);
+                }
+                body.fill_binding_indices(&body_map)?;
+                *param_pos = Some(pos);
+            }
             Expr::UnboundApply { op, span, .. } => {
                 bail!(NoImplementationError(*span, op.to_string()));
             }
@@ -375,7 +606,7 @@ impl Expr {
                 }
             }
             Expr::Const { .. } => {}
-            Expr::Apply { args, .. } => {
+            Expr::Apply { args, .. } | Expr::UserApply { args, .. } | Expr::ListFn { args, .. } => {
                 for arg in args.iter() {
                     arg.do_binding_indices(coll)?;
                 }
@@ -410,6 +641,57 @@ impl Expr {
         }
     }
     pub(crate) fn partial_eval(&mut self) -> Result<()> {
+        if let Expr::Apply { op, args, span } = self {
+            if **op == OP_COALESCE {
+                let span = *span;
+                let mut remaining = vec![];
+                for (i, mut arg) in mem::take(args).into_vec().into_iter().enumerate() {
+                    // only the first argument is always evaluated,
+                    // errors in the others must wait until they are reached
+                    if i == 0 {
+                        arg.partial_eval()?;
+                    } else {
+                        let _ = arg.partial_eval();
+                    }
+                    match arg {
+                        Expr::Const {
+                            val: DataValue::Null,
+                            ..
+                        } => {}
+                        Expr::Const { .. } => {
+                            remaining.push(arg);
+                            break;
+                        }
+                        arg => remaining.push(arg),
+                    }
+                }
+                *self = match remaining.len() {
+                    0 => Expr::Const {
+                        val: DataValue::Null,
+                        span,
+                    },
+                    1 => remaining.pop().unwrap(),
+                    _ => Expr::Apply {
+                        op: &OP_COALESCE,
+                        args: remaining.into(),
+                        span,
+                    },
+                };
+                return Ok(());
+            }
+        }
+        // user-defined functions may have side effects, so they are never evaluated ahead of time
+        if let Expr::UserApply { args, .. } = self {
+            for arg in args.iter_mut() {
+                arg.partial_eval()?;
+            }
+        }
+        if let Expr::ListFn { args, body, .. } = self {
+            for arg in args.iter_mut() {
+                arg.partial_eval()?;
+            }
+            body.partial_eval()?;
+        }
         if let Expr::Apply { args, span, .. } = self {
             let span = *span;
             let mut all_evaluated = true;
@@ -456,7 +738,7 @@ impl Expr {
                 coll.insert(var.clone());
             }
             Expr::Const { .. } => {}
-            Expr::Apply { args, .. } => {
+            Expr::Apply { args, .. } | Expr::UserApply { args, .. } => {
                 for arg in args.iter() {
                     arg.collect_bindings(coll)?;
                 }
@@ -467,6 +749,19 @@ impl Expr {
                     val.collect_bindings(coll)?;
                 }
             }
+            Expr::ListFn {
+                args, params, body, ..
+            } => {
+                for arg in args.iter() {
+                    arg.collect_bindings(coll)?;
+                }
+                let mut body_coll = BTreeSet::new();
+                body.collect_bindings(&mut body_coll)?;
+                for param in params.iter() {
+                    body_coll.remove(param);
+                }
+                coll.extend(body_coll);
+            }
             Expr::UnboundApply { op, span, .. } => {
                 bail!(NoImplementationError(*span, op.to_string()));
             }
@@ -493,6 +788,16 @@ impl Expr {
                     .clone()),
             },
             Expr::Const { val, .. } => Ok(val.clone()),
+            Expr::Apply { op, args, .. } if **op == OP_COALESCE => {
+                // short-circuits: arguments after the first non-null one are not evaluated
+                for arg in args.iter() {
+                    let val = arg.eval(bindings.as_ref())?;
+                    if val != DataValue::Null {
+                        return Ok(val);
+                    }
+                }
+                Ok(DataValue::Null)
+            }
             Expr::Apply { op, args, .. } => {
                 let args: Box<[DataValue]> = args
                     .iter()
@@ -501,6 +806,35 @@ impl Expr {
                 Ok((op.inner)(&args)
                     .map_err(|err| EvalRaisedError(self.span(), err.to_string()))?)
             }
+            Expr::ListFn {
+                kind,
+                args,
+                param_pos,
+                body,
+                span,
+                ..
+            } => {
+                let args: Box<[DataValue]> = args
+                    .iter()
+                    .map(|v| v.eval(bindings.as_ref()))
+                    .try_collect()?;
+                eval_list_fn(
+                    *kind,
+                    &args,
+                    *param_pos,
+                    bindings.as_ref(),
+                    *span,
+                    |local| body.eval(local),
+                )
+            }
+            Expr::UserApply { func, args, .. } => {
+                let args: Box<[DataValue]> = args
+                    .iter()
+                    .map(|v| v.eval(bindings.as_ref()))
+                    .try_collect()?;
+                Ok((func.inner)(&args)
+                    .map_err(|err| EvalRaisedError(self.span(), err.to_string()))?)
+            }
             Expr::Cond { clauses, .. } => {
                 for (cond, val) in clauses {
                     let cond_val = cond.eval(bindings.as_ref())?;
@@ -521,7 +855,11 @@ impl Expr {
     }
     pub(crate) fn extract_bound(&self, target: &Symbol) -> Result<ValueRange> {
         Ok(match self {
-            Expr::Binding { .. } | Expr::Const { .. } | Expr::Cond { .. } => ValueRange::default(),
+            Expr::Binding { .. }
+            | Expr::Const { .. }
+            | Expr::Cond { .. }
+            | Expr::ListFn { .. }
+            | Expr::UserApply { .. } => ValueRange::default(),
             Expr::Apply { op, args, .. } => match op.name {
                 n if n == OP_GE.name || n == OP_GT.name => {
                     if let Some(symb) = args[0].get_binding() {
@@ -600,6 +938,73 @@ impl Expr {
                     }
                     ValueRange::default()
                 }
+                n if n == OP_INTERVAL_CONTAINS.name => {
+                    if let Some(symb) = args[0].get_binding() {
+                        if let Some(val) = args[1].get_const() {
+                            if target == symb {
+                                // intervals are sorted by their start first, so the ones
+                                // containing a given time or interval cannot start after it
+                                let at = match val {
+                                    DataValue::Interval(iv) => Some(iv.start),
+                                    v => val2ts(v),
+                                };
+                                if let Some(at) = at {
+                                    let lower = DataValue::Interval(Interval {
+                                        start: Interval::UNBOUNDED_START,
+                                        end: Interval::UNBOUNDED_START,
+                                    });
+                                    let upper = DataValue::Interval(Interval {
+                                        start: at,
+                                        end: Interval::UNBOUNDED_END,
+                                    });
+                                    return Ok(ValueRange::new(lower, upper));
+                                }
+                            }
+                        }
+                    }
+                    ValueRange::default()
+                }
+                n if n == OP_GEO_IN_BOX.name || n == OP_GEO_WITHIN.name => {
+                    if let Some(symb) = args[0].get_binding() {
+                        if target == symb {
+                            // points inside a box sort between its corners, boxes crossing
+                            // the antimeridian are widened to all longitudes
+                            let corners = match (args[1].get_const(), args[2].get_const()) {
+                                (Some(sw), Some(ne)) if n == OP_GEO_IN_BOX.name => {
+                                    match (val2point(sw), val2point(ne)) {
+                                        (Some(sw), Some(ne)) => Some(if sw.lon() <= ne.lon() {
+                                            (sw, ne)
+                                        } else {
+                                            (
+                                                GeoPoint::new(sw.lat(), -180.).unwrap(),
+                                                GeoPoint::new(ne.lat(), 180.).unwrap(),
+                                            )
+                                        }),
+                                        _ => None,
+                                    }
+                                }
+                                (Some(center), Some(meters)) => {
+                                    match (val2point(center), meters.get_float()) {
+                                        (Some(center), Some(meters)) => {
+                                            Some(center.bounding_box(meters))
+                                        }
+                                        _ => None,
+                                    }
+                                }
+                                _ => None,
+                            };
+                            if let Some((sw, ne)) = corners {
+                                if sw.lat() <= ne.lat() {
+                                    return Ok(ValueRange::new(
+                                        DataValue::Point(sw),
+                                        DataValue::Point(ne),
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                    ValueRange::default()
+                }
                 _ => ValueRange::default(),
             },
             Expr::UnboundApply { op, span, .. } => {
@@ -618,7 +1023,7 @@ impl Expr {
                 coll.insert(var.to_string());
             }
             Expr::Const { .. } => {}
-            Expr::Apply { args, .. } => {
+            Expr::Apply { args, .. } | Expr::UserApply { args, .. } => {
                 for arg in args.iter() {
                     arg.do_get_variables(coll)?;
                 }
@@ -629,6 +1034,19 @@ impl Expr {
                     act.do_get_variables(coll)?;
                 }
             }
+            Expr::ListFn {
+                args, params, body, ..
+            } => {
+                for arg in args.iter() {
+                    arg.do_get_variables(coll)?;
+                }
+                let mut body_coll = BTreeSet::new();
+                body.do_get_variables(&mut body_coll)?;
+                for param in params.iter() {
+                    body_coll.remove(param.name.as_str());
+                }
+                coll.extend(body_coll);
+            }
             Expr::UnboundApply { op, span, .. } => {
                 bail!(NoImplementationError(*span, op.to_string()));
             }
@@ -657,16 +1075,19 @@ impl Expr {
     }
 }
 
+/// Bounds of the columns bound to `symbols`. `col_types` are their types if they are known.
 pub(crate) fn compute_bounds(
     filters: &[Expr],
     symbols: &[Symbol],
+    col_types: Option<&[ColumnDef]>,
 ) -> Result<(Vec<DataValue>, Vec<DataValue>)> {
     let mut lowers = vec![];
     let mut uppers = vec![];
-    for current in symbols {
+    for (i, current) in symbols.iter().enumerate() {
+        let col_type = col_types.map(|defs| &defs[i].typing.coltype);
         let mut cur_bound = ValueRange::default();
         for filter in filters {
-            let nxt = filter.extract_bound(current)?;
+            let nxt = filter.extract_bound(current)?.fit_numbers(col_type);
             cur_bound = cur_bound.merge(nxt);
         }
         lowers.push(cur_bound.lower);
@@ -713,6 +1134,23 @@ impl ValueRange {
             upper: val,
         }
     }
+    /// Filters compare numbers and decimals by value, but every number sorts before every
+    /// decimal. Bounds are converted or loosened so that no value the column may hold is left out.
+    fn fit_numbers(self, col_type: Option<&ColType>) -> Self {
+        let holds_nums = !matches!(col_type, Some(ColType::Decimal));
+        let holds_decimals = !matches!(col_type, Some(ColType::Int | ColType::Float));
+        let lower = match self.lower {
+            DataValue::Num(Num::Int(i)) if !holds_nums => DataValue::Decimal(Decimal::from(i)),
+            DataValue::Decimal(_) if holds_nums => DataValue::Null,
+            v => v,
+        };
+        let upper = match self.upper {
+            DataValue::Num(Num::Int(i)) if holds_decimals => DataValue::Decimal(Decimal::from(i)),
+            DataValue::Num(Num::Float(_)) if holds_decimals => DataValue::Bot,
+            v => v,
+        };
+        Self { lower, upper }
+    }
 }
 
 impl Default for ValueRange {
@@ -741,6 +1179,28 @@ pub trait CustomOp {
     fn call(&self, args: &[DataValue]) -> Result<DataValue>;
 }
 
+/// A scalar function registered with [crate::Db::register_fn]
+#[derive(Clone)]
+pub struct UserFn {
+    pub(crate) name: SmartString<LazyCompact>,
+    pub(crate) arity: usize,
+    pub(crate) inner: Arc<dyn Fn(&[DataValue]) -> Result<DataValue> + Send + Sync>,
+}
+
+impl PartialEq for UserFn {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for UserFn {}
+
+impl Debug for UserFn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "UserFn({}/{})", self.name, self.arity)
+    }
+}
+
 impl serde::Serialize for &'_ Op {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
@@ -798,6 +1258,9 @@ pub(crate) fn get_op(name: &str) -> Option<&'static Op> {
         "json" => &OP_JSON,
         "set_json_path" => &OP_SET_JSON_PATH,
         "remove_json_path" => &OP_REMOVE_JSON_PATH,
+        "get_path" => &OP_GET_PATH,
+        "set_path" => &OP_SET_PATH,
+        "merge" => &OP_MERGE,
         "parse_json" => &OP_PARSE_JSON,
         "dump_json" => &OP_DUMP_JSON,
         "json_object" => &OP_JSON_OBJECT,
@@ -813,6 +1276,7 @@ pub(crate) fn get_op(name: &str) -> Option<&'static Op> {
         "floor" => &OP_FLOOR,
         "ceil" => &OP_CEIL,
         "round" => &OP_ROUND,
+        "clamp" => &OP_CLAMP,
         "mod" => &OP_MOD,
         "max" => &OP_MAX,
         "min" => &OP_MIN,
@@ -872,6 +1336,10 @@ pub(crate) fn get_op(name: &str) -> Option<&'static Op> {
         "is_infinite" => &OP_IS_INFINITE,
         "is_nan" => &OP_IS_NAN,
         "is_uuid" => &OP_IS_UUID,
+        "is_timestamp" => &OP_IS_TIMESTAMP,
+        "is_decimal" => &OP_IS_DECIMAL,
+        "is_interval" => &OP_IS_INTERVAL,
+        "is_point" => &OP_IS_POINT,
         "is_vec" => &OP_IS_VEC,
         "length" => &OP_LENGTH,
         "sorted" => &OP_SORTED,
@@ -879,6 +1347,10 @@ pub(crate) fn get_op(name: &str) -> Option<&'static Op> {
         "append" => &OP_APPEND,
         "prepend" => &OP_PREPEND,
         "unicode_normalize" => &OP_UNICODE_NORMALIZE,
+        "nfc" => &OP_NFC,
+        "nfkc" => &OP_NFKC,
+        #[cfg(feature = "collation")]
+        "collate_key" => &OP_COLLATE_KEY,
         "haversine" => &OP_HAVERSINE,
         "haversine_deg_input" => &OP_HAVERSINE_DEG_INPUT,
         "deg_to_rad" => &OP_DEG_TO_RAD,
@@ -887,28 +1359,48 @@ pub(crate) fn get_op(name: &str) -> Option<&'static Op> {
         "maybe_get" => &OP_MAYBE_GET,
         "chars" => &OP_CHARS,
         "slice_string" => &OP_SLICE_STRING,
+        "str_slice" => &OP_STR_SLICE,
+        "str_index_of" => &OP_STR_INDEX_OF,
+        "split" => &OP_SPLIT,
+        "byte_length" => &OP_BYTE_LENGTH,
+        "char_length" => &OP_CHAR_LENGTH,
         "from_substrings" => &OP_FROM_SUBSTRINGS,
         "slice" => &OP_SLICE,
         "regex_matches" => &OP_REGEX_MATCHES,
         "regex_replace" => &OP_REGEX_REPLACE,
         "regex_replace_all" => &OP_REGEX_REPLACE_ALL,
         "regex_extract" => &OP_REGEX_EXTRACT,
+        "regex_extract_all" => &OP_REGEX_EXTRACT,
         "regex_extract_first" => &OP_REGEX_EXTRACT_FIRST,
         "t2s" => &OP_T2S,
         "encode_base64" => &OP_ENCODE_BASE64,
         "decode_base64" => &OP_DECODE_BASE64,
+        "encode_hex" => &OP_ENCODE_HEX,
+        "decode_hex" => &OP_DECODE_HEX,
+        "sha256" => &OP_SHA256,
+        "blake3" => &OP_BLAKE3,
+        "md5" => &OP_MD5,
         "first" => &OP_FIRST,
         "last" => &OP_LAST,
         "chunks" => &OP_CHUNKS,
         "chunks_exact" => &OP_CHUNKS_EXACT,
         "windows" => &OP_WINDOWS,
+        "zip" => &OP_ZIP,
+        "unzip" => &OP_UNZIP,
+        "index_of" => &OP_INDEX_OF,
+        "unique" => &OP_UNIQUE,
+        "list_union" => &OP_LIST_UNION,
+        "list_intersection" => &OP_LIST_INTERSECTION,
         "to_int" => &OP_TO_INT,
         "to_float" => &OP_TO_FLOAT,
+        "to_decimal" => &OP_TO_DECIMAL,
         "to_string" => &OP_TO_STRING,
         "l2_dist" => &OP_L2_DIST,
         "l2_normalize" => &OP_L2_NORMALIZE,
         "ip_dist" => &OP_IP_DIST,
         "cos_dist" => &OP_COS_DIST,
+        "dot" => &OP_DOT,
+        "cos_sim" => &OP_COS_SIM,
         "int_range" => &OP_INT_RANGE,
         "rand_float" => &OP_RAND_FLOAT,
         "rand_bernoulli" => &OP_RAND_BERNOULLI,
@@ -920,14 +1412,42 @@ pub(crate) fn get_op(name: &str) -> Option<&'static Op> {
         "difference" => &OP_DIFFERENCE,
         "to_uuid" => &OP_TO_UUID,
         "to_bool" => &OP_TO_BOOL,
+        "try_to_int" => &OP_TRY_TO_INT,
+        "try_to_float" => &OP_TRY_TO_FLOAT,
+        "try_to_decimal" => &OP_TRY_TO_DECIMAL,
+        "try_to_string" => &OP_TRY_TO_STRING,
+        "try_to_uuid" => &OP_TRY_TO_UUID,
+        "try_to_bool" => &OP_TRY_TO_BOOL,
         "to_unity" => &OP_TO_UNITY,
         "rand_uuid_v1" => &OP_RAND_UUID_V1,
         "rand_uuid_v4" => &OP_RAND_UUID_V4,
+        "rand_uuid_v7" => &OP_RAND_UUID_V7,
         "uuid_timestamp" => &OP_UUID_TIMESTAMP,
         "validity" => &OP_VALIDITY,
         "now" => &OP_NOW,
         "format_timestamp" => &OP_FORMAT_TIMESTAMP,
         "parse_timestamp" => &OP_PARSE_TIMESTAMP,
+        "now_ts" => &OP_NOW_TS,
+        "to_ts" => &OP_TO_TS,
+        "parse_ts" => &OP_PARSE_TS,
+        "format_ts" => &OP_FORMAT_TS,
+        "ts_year" => &OP_TS_YEAR,
+        "ts_month" => &OP_TS_MONTH,
+        "ts_day" => &OP_TS_DAY,
+        "ts_hour" => &OP_TS_HOUR,
+        "ts_minute" => &OP_TS_MINUTE,
+        "ts_second" => &OP_TS_SECOND,
+        "interval" => &OP_INTERVAL,
+        "interval_start" => &OP_INTERVAL_START,
+        "interval_end" => &OP_INTERVAL_END,
+        "interval_contains" => &OP_INTERVAL_CONTAINS,
+        "interval_overlaps" => &OP_INTERVAL_OVERLAPS,
+        "point" => &OP_POINT,
+        "point_lat" => &OP_POINT_LAT,
+        "point_lon" => &OP_POINT_LON,
+        "geo_distance" => &OP_GEO_DISTANCE,
+        "geo_in_box" => &OP_GEO_IN_BOX,
+        "geo_within" => &OP_GEO_WITHIN,
         "vec" => &OP_VEC,
         "rand_vec" => &OP_RAND_VEC,
         _ => return None,
@@ -6,7 +6,8 @@
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::cmp::Reverse;
+use std::borrow::Cow;
+use std::cmp::{Ordering, Reverse};
 use std::collections::BTreeSet;
 use std::mem;
 use std::ops::{Div, Rem};
@@ -15,23 +16,26 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
-use chrono::{DateTime, TimeZone, Utc};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
 use itertools::Itertools;
 #[cfg(target_arch = "wasm32")]
 use js_sys::Date;
+use md5::Md5;
 use miette::{bail, ensure, miette, IntoDiagnostic, Result};
 use num_traits::FloatConst;
 use rand::prelude::*;
 use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
 use smartstring::SmartString;
 use unicode_normalization::UnicodeNormalization;
-use uuid::v1::Timestamp;
 
+use crate::data::decimal::{Decimal, MAX_DECIMAL_SCALE};
 use crate::data::expr::Op;
-use crate::data::json::JsonValue;
+use crate::data::json::{interval2json, point2json, JsonValue};
 use crate::data::relation::VecElementType;
 use crate::data::value::{
-    DataValue, JsonData, Num, RegexWrapper, UuidWrapper, Validity, ValidityTs, Vector,
+    DataValue, GeoPoint, Interval, JsonData, Num, RegexWrapper, Timestamp, UuidWrapper, Validity,
+    ValidityTs, Vector,
 };
 
 macro_rules! define_op {
@@ -45,6 +49,11 @@ macro_rules! define_op {
     };
 }
 
+/// Ordering comparisons and arithmetic give null when any argument is null, as in SQL
+fn has_null(args: &[DataValue]) -> bool {
+    args.contains(&DataValue::Null)
+}
+
 fn ensure_same_value_type(a: &DataValue, b: &DataValue) -> Result<()> {
     use DataValue::*;
     if !matches!(
@@ -57,6 +66,10 @@ fn ensure_same_value_type(a: &DataValue, b: &DataValue) -> Result<()> {
             | (Regex(_), Regex(_))
             | (List(_), List(_))
             | (Set(_), Set(_))
+            | (Timestamp(_), Timestamp(_))
+            | (Decimal(_), Decimal(_))
+            | (Decimal(_), Num(_))
+            | (Num(_), Decimal(_))
             | (Bot, Bot)
     ) {
         bail!(
@@ -182,6 +195,64 @@ pub(crate) fn op_remove_json_path(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::Json(JsonData(result)))
 }
 
+/// A path into a JSON value is a list of object keys and array indices,
+/// a single key or index stands for a path of length one
+fn json_path_arg(path: &DataValue) -> Result<&[DataValue]> {
+    match path {
+        DataValue::List(l) => Ok(l),
+        DataValue::Str(_) | DataValue::Num(_) => Ok(std::slice::from_ref(path)),
+        _ => bail!("json path must be a key, an index, or a list of them"),
+    }
+}
+
+define_op!(OP_GET_PATH, 2, true);
+pub(crate) fn op_get_path(args: &[DataValue]) -> Result<DataValue> {
+    let json = to_json(&args[0]);
+    let path = json_path_arg(&args[1])?;
+    match get_json_path_immutable(&json, path) {
+        Ok(res) => Ok(json2val(res.clone())),
+        Err(err) => match args.get(2) {
+            Some(default) => Ok(default.clone()),
+            None => Err(err),
+        },
+    }
+}
+
+define_op!(OP_SET_PATH, 3, false);
+pub(crate) fn op_set_path(args: &[DataValue]) -> Result<DataValue> {
+    let mut result = to_json(&args[0]);
+    let path = json_path_arg(&args[1])?;
+    *get_json_path(&mut result, path)? = to_json(&args[2]);
+    Ok(DataValue::Json(JsonData(result)))
+}
+
+define_op!(OP_MERGE, 1, true);
+pub(crate) fn op_merge(args: &[DataValue]) -> Result<DataValue> {
+    let mut result = to_json(&args[0]);
+    for arg in &args[1..] {
+        result = merge_json(result, to_json(arg));
+    }
+    Ok(DataValue::Json(JsonData(result)))
+}
+
+/// Objects are merged key by key, recursively. Any other value, arrays included,
+/// is replaced by the later one.
+fn merge_json(value1: JsonValue, value2: JsonValue) -> JsonValue {
+    match (value1, value2) {
+        (JsonValue::Object(mut obj1), JsonValue::Object(obj2)) => {
+            for (key, value2) in obj2 {
+                let merged = match obj1.remove(&key) {
+                    Some(value1) => merge_json(value1, value2),
+                    None => value2,
+                };
+                obj1.insert(key, merged);
+            }
+            JsonValue::Object(obj1)
+        }
+        (_, value2) => value2,
+    }
+}
+
 define_op!(OP_JSON_OBJECT, 0, true);
 pub(crate) fn op_json_object(args: &[DataValue]) -> Result<DataValue> {
     ensure!(
@@ -262,6 +333,14 @@ fn to_json(d: &DataValue) -> JsonValue {
         DataValue::Bot => {
             json!(null)
         }
+        DataValue::Timestamp(ts) => {
+            json!(ts.to_string())
+        }
+        DataValue::Decimal(d) => {
+            json!(d.to_string())
+        }
+        DataValue::Interval(iv) => interval2json(iv),
+        DataValue::Point(p) => point2json(p),
     }
 }
 
@@ -299,6 +378,9 @@ pub(crate) fn op_eq(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::from(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Float(f)), DataValue::Num(Num::Int(i)))
         | (DataValue::Num(Num::Int(i)), DataValue::Num(Num::Float(f))) => *i as f64 == *f,
+        (DataValue::Decimal(d), DataValue::Num(n)) | (DataValue::Num(n), DataValue::Decimal(d)) => {
+            cmp_decimal_num(d, n) == Some(Ordering::Equal)
+        }
         (a, b) => a == b,
     }))
 }
@@ -308,6 +390,26 @@ pub(crate) fn op_is_uuid(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::from(matches!(args[0], DataValue::Uuid(_))))
 }
 
+define_op!(OP_IS_TIMESTAMP, 1, false);
+pub(crate) fn op_is_timestamp(args: &[DataValue]) -> Result<DataValue> {
+    Ok(DataValue::from(matches!(args[0], DataValue::Timestamp(_))))
+}
+
+define_op!(OP_IS_DECIMAL, 1, false);
+pub(crate) fn op_is_decimal(args: &[DataValue]) -> Result<DataValue> {
+    Ok(DataValue::from(matches!(args[0], DataValue::Decimal(_))))
+}
+
+define_op!(OP_IS_INTERVAL, 1, false);
+pub(crate) fn op_is_interval(args: &[DataValue]) -> Result<DataValue> {
+    Ok(DataValue::from(matches!(args[0], DataValue::Interval(_))))
+}
+
+define_op!(OP_IS_POINT, 1, false);
+pub(crate) fn op_is_point(args: &[DataValue]) -> Result<DataValue> {
+    Ok(DataValue::from(matches!(args[0], DataValue::Point(_))))
+}
+
 define_op!(OP_IS_JSON, 1, false);
 pub(crate) fn op_is_json(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::from(matches!(args[0], DataValue::Json(_))))
@@ -335,52 +437,98 @@ pub(crate) fn op_neq(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::from(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Float(f)), DataValue::Num(Num::Int(i)))
         | (DataValue::Num(Num::Int(i)), DataValue::Num(Num::Float(f))) => *i as f64 != *f,
+        (DataValue::Decimal(d), DataValue::Num(n)) | (DataValue::Num(n), DataValue::Decimal(d)) => {
+            cmp_decimal_num(d, n) != Some(Ordering::Equal)
+        }
         (a, b) => a != b,
     }))
 }
 
 define_op!(OP_GT, 2, false);
 pub(crate) fn op_gt(args: &[DataValue]) -> Result<DataValue> {
+    if has_null(args) {
+        return Ok(DataValue::Null);
+    }
     ensure_same_value_type(&args[0], &args[1])?;
     Ok(DataValue::from(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Float(l)), DataValue::Num(Num::Int(r))) => *l > *r as f64,
         (DataValue::Num(Num::Int(l)), DataValue::Num(Num::Float(r))) => *l as f64 > *r,
+        (DataValue::Decimal(l), DataValue::Num(r)) => {
+            cmp_decimal_num(l, r) == Some(Ordering::Greater)
+        }
+        (DataValue::Num(l), DataValue::Decimal(r)) => cmp_decimal_num(r, l) == Some(Ordering::Less),
         (a, b) => a > b,
     }))
 }
 
 define_op!(OP_GE, 2, false);
 pub(crate) fn op_ge(args: &[DataValue]) -> Result<DataValue> {
+    if has_null(args) {
+        return Ok(DataValue::Null);
+    }
     ensure_same_value_type(&args[0], &args[1])?;
     Ok(DataValue::from(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Float(l)), DataValue::Num(Num::Int(r))) => *l >= *r as f64,
         (DataValue::Num(Num::Int(l)), DataValue::Num(Num::Float(r))) => *l as f64 >= *r,
+        (DataValue::Decimal(l), DataValue::Num(r)) => matches!(
+            cmp_decimal_num(l, r),
+            Some(Ordering::Greater | Ordering::Equal)
+        ),
+        (DataValue::Num(l), DataValue::Decimal(r)) => {
+            matches!(
+                cmp_decimal_num(r, l),
+                Some(Ordering::Less | Ordering::Equal)
+            )
+        }
         (a, b) => a >= b,
     }))
 }
 
 define_op!(OP_LT, 2, false);
 pub(crate) fn op_lt(args: &[DataValue]) -> Result<DataValue> {
+    if has_null(args) {
+        return Ok(DataValue::Null);
+    }
     ensure_same_value_type(&args[0], &args[1])?;
     Ok(DataValue::from(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Float(l)), DataValue::Num(Num::Int(r))) => *l < (*r as f64),
         (DataValue::Num(Num::Int(l)), DataValue::Num(Num::Float(r))) => (*l as f64) < *r,
+        (DataValue::Decimal(l), DataValue::Num(r)) => cmp_decimal_num(l, r) == Some(Ordering::Less),
+        (DataValue::Num(l), DataValue::Decimal(r)) => {
+            cmp_decimal_num(r, l) == Some(Ordering::Greater)
+        }
         (a, b) => a < b,
     }))
 }
 
 define_op!(OP_LE, 2, false);
 pub(crate) fn op_le(args: &[DataValue]) -> Result<DataValue> {
+    if has_null(args) {
+        return Ok(DataValue::Null);
+    }
     ensure_same_value_type(&args[0], &args[1])?;
     Ok(DataValue::from(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Float(l)), DataValue::Num(Num::Int(r))) => *l <= (*r as f64),
         (DataValue::Num(Num::Int(l)), DataValue::Num(Num::Float(r))) => (*l as f64) <= *r,
+        (DataValue::Decimal(l), DataValue::Num(r)) => {
+            matches!(
+                cmp_decimal_num(l, r),
+                Some(Ordering::Less | Ordering::Equal)
+            )
+        }
+        (DataValue::Num(l), DataValue::Decimal(r)) => matches!(
+            cmp_decimal_num(r, l),
+            Some(Ordering::Greater | Ordering::Equal)
+        ),
         (a, b) => a <= b,
     }))
 }
 
 define_op!(OP_ADD, 0, true);
 pub(crate) fn op_add(args: &[DataValue]) -> Result<DataValue> {
+    if has_null(args) {
+        return Ok(DataValue::Null);
+    }
     let mut i_accum = 0i64;
     let mut f_accum = 0.0f64;
     for arg in args {
@@ -388,6 +536,8 @@ pub(crate) fn op_add(args: &[DataValue]) -> Result<DataValue> {
             DataValue::Num(Num::Int(i)) => i_accum += i,
             DataValue::Num(Num::Float(f)) => f_accum += f,
             DataValue::Vec(_) => return add_vecs(args),
+            DataValue::Timestamp(_) => return add_to_timestamp(args),
+            DataValue::Decimal(_) => return add_decimals(args),
             _ => bail!("addition requires numbers"),
         }
     }
@@ -398,6 +548,94 @@ pub(crate) fn op_add(args: &[DataValue]) -> Result<DataValue> {
     }
 }
 
+/// Operands of arithmetic involving decimals. Integers are converted to decimals exactly,
+/// but as soon as one operand is a float, all of them are converted to floats.
+enum DecimalOperands {
+    Exact(Vec<Decimal>),
+    Inexact(Vec<f64>),
+}
+
+fn decimal_operands(args: &[DataValue], op_name: &str) -> Result<DecimalOperands> {
+    if args
+        .iter()
+        .any(|arg| matches!(arg, DataValue::Num(Num::Float(_))))
+    {
+        let fs = args
+            .iter()
+            .map(|arg| match arg {
+                DataValue::Num(n) => Ok(n.get_float()),
+                DataValue::Decimal(d) => Ok(d.to_f64()),
+                _ => bail!("{} requires numbers", op_name),
+            })
+            .try_collect()?;
+        Ok(DecimalOperands::Inexact(fs))
+    } else {
+        let ds = args
+            .iter()
+            .map(|arg| match arg {
+                DataValue::Num(Num::Int(i)) => Ok(Decimal::from(*i)),
+                DataValue::Decimal(d) => Ok(d.clone()),
+                _ => bail!("{} requires numbers", op_name),
+            })
+            .try_collect()?;
+        Ok(DecimalOperands::Exact(ds))
+    }
+}
+
+/// Integers compare with decimals exactly, floats by converting the decimal to a float
+fn cmp_decimal_num(d: &Decimal, n: &Num) -> Option<Ordering> {
+    match n {
+        Num::Int(i) => Some(d.cmp(&Decimal::from(*i))),
+        Num::Float(f) => d.to_f64().partial_cmp(f),
+    }
+}
+
+fn add_decimals(args: &[DataValue]) -> Result<DataValue> {
+    Ok(match decimal_operands(args, "addition")? {
+        DecimalOperands::Exact(ds) => DataValue::Decimal(
+            ds.iter()
+                .try_fold(Decimal::from(0), |acc, d| acc.add(d))
+                .ok_or_else(|| miette!("decimal out of range"))?,
+        ),
+        DecimalOperands::Inexact(fs) => DataValue::from(fs.into_iter().sum::<f64>()),
+    })
+}
+
+fn mul_decimals(args: &[DataValue]) -> Result<DataValue> {
+    Ok(match decimal_operands(args, "multiplication")? {
+        DecimalOperands::Exact(ds) => DataValue::Decimal(
+            ds.iter()
+                .try_fold(Decimal::from(1), |acc, d| acc.mul(d))
+                .ok_or_else(|| miette!("decimal out of range"))?,
+        ),
+        DecimalOperands::Inexact(fs) => DataValue::from(fs.into_iter().product::<f64>()),
+    })
+}
+
+/// Adds durations in seconds to a single timestamp
+fn add_to_timestamp(args: &[DataValue]) -> Result<DataValue> {
+    let mut ts = None;
+    let mut nanos = 0i64;
+    for arg in args {
+        match arg {
+            DataValue::Timestamp(t) => {
+                ensure!(ts.is_none(), "cannot add two timestamps together");
+                ts = Some(t.0);
+            }
+            DataValue::Num(n) => {
+                nanos = secs_to_nanos(*n)
+                    .and_then(|d| nanos.checked_add(d))
+                    .ok_or_else(|| miette!("duration out of range"))?
+            }
+            _ => bail!("only durations in seconds can be added to a timestamp"),
+        }
+    }
+    ts.unwrap()
+        .checked_add(nanos)
+        .map(|t| DataValue::Timestamp(Timestamp(t)))
+        .ok_or_else(|| miette!("timestamp out of range"))
+}
+
 fn add_vecs(args: &[DataValue]) -> Result<DataValue> {
     if args.len() == 1 {
         return Ok(args[0].clone());
@@ -482,6 +720,9 @@ pub(crate) fn op_min(args: &[DataValue]) -> Result<DataValue> {
 
 define_op!(OP_SUB, 2, false);
 pub(crate) fn op_sub(args: &[DataValue]) -> Result<DataValue> {
+    if has_null(args) {
+        return Ok(DataValue::Null);
+    }
     Ok(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Int(a)), DataValue::Num(Num::Int(b))) => {
             DataValue::Num(Num::Int(*a - *b))
@@ -495,6 +736,27 @@ pub(crate) fn op_sub(args: &[DataValue]) -> Result<DataValue> {
         (DataValue::Num(Num::Float(a)), DataValue::Num(Num::Int(b))) => {
             DataValue::Num(Num::Float(a - (*b as f64)))
         }
+        (DataValue::Decimal(_), DataValue::Num(_) | DataValue::Decimal(_))
+        | (DataValue::Num(_), DataValue::Decimal(_)) => {
+            match decimal_operands(args, "subtraction")? {
+                DecimalOperands::Exact(ds) => DataValue::Decimal(
+                    ds[0]
+                        .sub(&ds[1])
+                        .ok_or_else(|| miette!("decimal out of range"))?,
+                ),
+                DecimalOperands::Inexact(fs) => DataValue::from(fs[0] - fs[1]),
+            }
+        }
+        (DataValue::Timestamp(a), DataValue::Timestamp(b)) => {
+            let nanos =
+                a.0.checked_sub(b.0)
+                    .ok_or_else(|| miette!("duration out of range"))?;
+            nanos_to_secs(nanos)
+        }
+        (DataValue::Timestamp(a), DataValue::Num(b)) => secs_to_nanos(*b)
+            .and_then(|d| a.0.checked_sub(d))
+            .map(|t| DataValue::Timestamp(Timestamp(t)))
+            .ok_or_else(|| miette!("timestamp out of range"))?,
         (DataValue::Vec(a), DataValue::Vec(b)) => match (a, b) {
             (Vector::F32(a), Vector::F32(b)) => DataValue::Vec(Vector::F32(a - b)),
             (Vector::F64(a), Vector::F64(b)) => DataValue::Vec(Vector::F64(a - b)),
@@ -543,6 +805,9 @@ pub(crate) fn op_sub(args: &[DataValue]) -> Result<DataValue> {
 
 define_op!(OP_MUL, 0, true);
 pub(crate) fn op_mul(args: &[DataValue]) -> Result<DataValue> {
+    if has_null(args) {
+        return Ok(DataValue::Null);
+    }
     let mut i_accum = 1i64;
     let mut f_accum = 1.0f64;
     for arg in args {
@@ -550,6 +815,7 @@ pub(crate) fn op_mul(args: &[DataValue]) -> Result<DataValue> {
             DataValue::Num(Num::Int(i)) => i_accum *= i,
             DataValue::Num(Num::Float(f)) => f_accum *= f,
             DataValue::Vec(_) => return mul_vecs(args),
+            DataValue::Decimal(_) => return mul_decimals(args),
             _ => bail!("multiplication requires numbers"),
         }
     }
@@ -614,6 +880,9 @@ fn mul_vecs(args: &[DataValue]) -> Result<DataValue> {
 
 define_op!(OP_DIV, 2, false);
 pub(crate) fn op_div(args: &[DataValue]) -> Result<DataValue> {
+    if has_null(args) {
+        return Ok(DataValue::Null);
+    }
     Ok(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Int(a)), DataValue::Num(Num::Int(b))) => {
             DataValue::Num(Num::Float((*a as f64) / (*b as f64)))
@@ -627,6 +896,18 @@ pub(crate) fn op_div(args: &[DataValue]) -> Result<DataValue> {
         (DataValue::Num(Num::Float(a)), DataValue::Num(Num::Int(b))) => {
             DataValue::Num(Num::Float(a / (*b as f64)))
         }
+        (DataValue::Decimal(_), DataValue::Num(_) | DataValue::Decimal(_))
+        | (DataValue::Num(_), DataValue::Decimal(_)) => match decimal_operands(args, "division")? {
+            DecimalOperands::Exact(ds) => {
+                ensure!(!ds[1].is_zero(), "decimal division by zero");
+                DataValue::Decimal(
+                    ds[0]
+                        .div(&ds[1])
+                        .ok_or_else(|| miette!("decimal out of range"))?,
+                )
+            }
+            DecimalOperands::Inexact(fs) => DataValue::from(fs[0] / fs[1]),
+        },
         (DataValue::Vec(a), DataValue::Vec(b)) => match (a, b) {
             (Vector::F32(a), Vector::F32(b)) => DataValue::Vec(Vector::F32(a / b)),
             (Vector::F64(a), Vector::F64(b)) => DataValue::Vec(Vector::F64(a / b)),
@@ -669,9 +950,13 @@ pub(crate) fn op_div(args: &[DataValue]) -> Result<DataValue> {
 
 define_op!(OP_MINUS, 1, false);
 pub(crate) fn op_minus(args: &[DataValue]) -> Result<DataValue> {
+    if has_null(args) {
+        return Ok(DataValue::Null);
+    }
     Ok(match &args[0] {
         DataValue::Num(Num::Int(i)) => DataValue::Num(Num::Int(-(*i))),
         DataValue::Num(Num::Float(f)) => DataValue::Num(Num::Float(-(*f))),
+        DataValue::Decimal(d) => DataValue::Decimal(d.neg()),
         DataValue::Vec(Vector::F64(v)) => DataValue::Vec(Vector::F64(0. - v)),
         DataValue::Vec(Vector::F32(v)) => DataValue::Vec(Vector::F32(0. - v)),
         _ => bail!("minus can only be applied to numbers"),
@@ -683,6 +968,7 @@ pub(crate) fn op_abs(args: &[DataValue]) -> Result<DataValue> {
     Ok(match &args[0] {
         DataValue::Num(Num::Int(i)) => DataValue::Num(Num::Int(i.abs())),
         DataValue::Num(Num::Float(f)) => DataValue::Num(Num::Float(f.abs())),
+        DataValue::Decimal(d) => DataValue::Decimal(d.abs()),
         DataValue::Vec(Vector::F64(v)) => DataValue::Vec(Vector::F64(v.mapv(|x| x.abs()))),
         DataValue::Vec(Vector::F32(v)) => DataValue::Vec(Vector::F32(v.mapv(|x| x.abs()))),
         _ => bail!("'abs' requires numbers"),
@@ -704,37 +990,126 @@ pub(crate) fn op_signum(args: &[DataValue]) -> Result<DataValue> {
                 DataValue::from(f64::NAN)
             }
         }
+        DataValue::Decimal(d) => DataValue::from(d.signum()),
         _ => bail!("'signum' requires numbers"),
     })
 }
 
-define_op!(OP_FLOOR, 1, false);
+define_op!(OP_FLOOR, 1, true);
 pub(crate) fn op_floor(args: &[DataValue]) -> Result<DataValue> {
-    Ok(match &args[0] {
-        DataValue::Num(Num::Int(i)) => DataValue::Num(Num::Int(*i)),
-        DataValue::Num(Num::Float(f)) => DataValue::Num(Num::Float(f.floor())),
-        _ => bail!("'floor' requires numbers"),
-    })
+    round_to_digits(args, "floor", Rounding::Floor)
 }
 
-define_op!(OP_CEIL, 1, false);
+define_op!(OP_CEIL, 1, true);
 pub(crate) fn op_ceil(args: &[DataValue]) -> Result<DataValue> {
-    Ok(match &args[0] {
-        DataValue::Num(Num::Int(i)) => DataValue::Num(Num::Int(*i)),
-        DataValue::Num(Num::Float(f)) => DataValue::Num(Num::Float(f.ceil())),
-        _ => bail!("'ceil' requires numbers"),
-    })
+    round_to_digits(args, "ceil", Rounding::Ceil)
 }
 
-define_op!(OP_ROUND, 1, false);
+define_op!(OP_ROUND, 1, true);
 pub(crate) fn op_round(args: &[DataValue]) -> Result<DataValue> {
+    round_to_digits(args, "round", Rounding::HalfAwayFromZero)
+}
+
+#[derive(Copy, Clone)]
+enum Rounding {
+    Floor,
+    Ceil,
+    HalfAwayFromZero,
+}
+
+/// Rounds to the number of decimal places given as the optional second argument.
+/// Negative numbers of places round to tens, hundreds, etc.
+fn round_to_digits(args: &[DataValue], op_name: &str, mode: Rounding) -> Result<DataValue> {
+    let digits = match args.get(1) {
+        None => 0,
+        Some(d) => d
+            .get_int()
+            .ok_or_else(|| miette!("the number of digits for '{}' must be an integer", op_name))?,
+    };
+    // no number has digits beyond these, rounding further gives the same or out of range results
+    let digits = digits.clamp(-MAX_DECIMAL_SCALE - 1, MAX_DECIMAL_SCALE + 1);
     Ok(match &args[0] {
-        DataValue::Num(Num::Int(i)) => DataValue::Num(Num::Int(*i)),
-        DataValue::Num(Num::Float(f)) => DataValue::Num(Num::Float(f.round())),
-        _ => bail!("'round' requires numbers"),
+        DataValue::Num(Num::Int(i)) => {
+            if digits >= 0 {
+                DataValue::from(*i)
+            } else {
+                let m = u32::try_from(-digits)
+                    .ok()
+                    .and_then(|d| 10i64.checked_pow(d))
+                    .ok_or_else(|| miette!("number of digits out of range for '{}'", op_name))?
+                    as i128;
+                let i = *i as i128;
+                let rounded = match mode {
+                    Rounding::Floor => i.div_euclid(m) * m,
+                    Rounding::Ceil => -((-i).div_euclid(m) * m),
+                    Rounding::HalfAwayFromZero => i.signum() * ((i.abs() + m / 2) / m * m),
+                };
+                DataValue::from(
+                    i64::try_from(rounded).map_err(|_| {
+                        miette!("result of '{}' does not fit into an integer", op_name)
+                    })?,
+                )
+            }
+        }
+        DataValue::Num(Num::Float(f)) => {
+            let apply = |x: f64| match mode {
+                Rounding::Floor => x.floor(),
+                Rounding::Ceil => x.ceil(),
+                Rounding::HalfAwayFromZero => x.round(),
+            };
+            if digits == 0 {
+                DataValue::from(apply(*f))
+            } else {
+                ensure!(
+                    digits.abs() <= 308,
+                    "number of digits out of range for '{}'",
+                    op_name
+                );
+                // powers of ten are exact for the common small numbers of digits
+                let scale = 10f64.powi(digits.abs() as i32);
+                if digits < 0 {
+                    DataValue::from(apply(f / scale) * scale)
+                } else if (f * scale).is_finite() {
+                    DataValue::from(apply(f * scale) / scale)
+                } else {
+                    // too large to have any digits at this scale
+                    DataValue::from(*f)
+                }
+            }
+        }
+        DataValue::Decimal(d) => DataValue::Decimal(
+            match mode {
+                Rounding::Floor => d.floor(digits),
+                Rounding::Ceil => d.ceil(digits),
+                Rounding::HalfAwayFromZero => d.round(digits),
+            }
+            .ok_or_else(|| miette!("decimal out of range"))?,
+        ),
+        _ => bail!("'{}' requires numbers", op_name),
     })
 }
 
+define_op!(OP_CLAMP, 3, false);
+pub(crate) fn op_clamp(args: &[DataValue]) -> Result<DataValue> {
+    if has_null(args) {
+        return Ok(DataValue::Null);
+    }
+    let (x, lo, hi) = (&args[0], &args[1], &args[2]);
+    ensure!(
+        op_le(&[lo.clone(), hi.clone()])? == DataValue::from(true),
+        "the lower bound of 'clamp' must not be greater than the upper bound"
+    );
+    Ok(
+        if op_lt(&[x.clone(), lo.clone()])? == DataValue::from(true) {
+            lo.clone()
+        } else if op_gt(&[x.clone(), hi.clone()])? == DataValue::from(true) {
+            hi.clone()
+        } else {
+            x.clone()
+        },
+    )
+}
+
 define_op!(OP_EXP, 1, false);
 pub(crate) fn op_exp(args: &[DataValue]) -> Result<DataValue> {
     let a = match &args[0] {
@@ -1041,6 +1416,9 @@ pub(crate) fn op_sqrt(args: &[DataValue]) -> Result<DataValue> {
 
 define_op!(OP_POW, 2, false);
 pub(crate) fn op_pow(args: &[DataValue]) -> Result<DataValue> {
+    if has_null(args) {
+        return Ok(DataValue::Null);
+    }
     let a = match &args[0] {
         DataValue::Num(Num::Int(i)) => *i as f64,
         DataValue::Num(Num::Float(f)) => *f,
@@ -1068,6 +1446,9 @@ pub(crate) fn op_pow(args: &[DataValue]) -> Result<DataValue> {
 
 define_op!(OP_MOD, 2, false);
 pub(crate) fn op_mod(args: &[DataValue]) -> Result<DataValue> {
+    if has_null(args) {
+        return Ok(DataValue::Null);
+    }
     Ok(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Int(a)), DataValue::Num(Num::Int(b))) => {
             if *b == 0 {
@@ -1090,36 +1471,50 @@ pub(crate) fn op_mod(args: &[DataValue]) -> Result<DataValue> {
 
 define_op!(OP_AND, 0, true);
 pub(crate) fn op_and(args: &[DataValue]) -> Result<DataValue> {
+    let mut seen_null = false;
     for arg in args {
-        if !arg
+        if *arg == DataValue::Null {
+            seen_null = true;
+        } else if !arg
             .get_bool()
             .ok_or_else(|| miette!("'and' requires booleans"))?
         {
             return Ok(DataValue::from(false));
         }
     }
-    Ok(DataValue::from(true))
+    Ok(if seen_null {
+        DataValue::Null
+    } else {
+        DataValue::from(true)
+    })
 }
 
 define_op!(OP_OR, 0, true);
 pub(crate) fn op_or(args: &[DataValue]) -> Result<DataValue> {
+    let mut seen_null = false;
     for arg in args {
-        if arg
+        if *arg == DataValue::Null {
+            seen_null = true;
+        } else if arg
             .get_bool()
             .ok_or_else(|| miette!("'or' requires booleans"))?
         {
             return Ok(DataValue::from(true));
         }
     }
-    Ok(DataValue::from(false))
+    Ok(if seen_null {
+        DataValue::Null
+    } else {
+        DataValue::from(false)
+    })
 }
 
 define_op!(OP_NEGATE, 1, false);
 pub(crate) fn op_negate(args: &[DataValue]) -> Result<DataValue> {
-    if let DataValue::Bool(b) = &args[0] {
-        Ok(DataValue::from(!*b))
-    } else {
-        bail!("'negate' requires booleans");
+    match &args[0] {
+        DataValue::Bool(b) => Ok(DataValue::from(!*b)),
+        DataValue::Null => Ok(DataValue::Null),
+        _ => bail!("'negate' requires booleans"),
     }
 }
 
@@ -1381,28 +1776,60 @@ define_op!(OP_REGEX, 1, false);
 pub(crate) fn op_regex(args: &[DataValue]) -> Result<DataValue> {
     Ok(match &args[0] {
         r @ DataValue::Regex(_) => r.clone(),
-        DataValue::Str(s) => {
-            DataValue::Regex(RegexWrapper(regex::Regex::new(s).map_err(|err| {
-                miette!("The string cannot be interpreted as regex: {}", err)
-            })?))
-        }
+        DataValue::Str(s) => DataValue::Regex(RegexWrapper(str2regex(s)?)),
         _ => bail!("'regex' requires strings"),
     })
 }
 
+pub(crate) fn str2regex(s: &str) -> Result<regex::Regex> {
+    regex::Regex::new(s)
+        .map_err(|err| miette!("The string cannot be interpreted as regex: {}", err))
+}
+
+/// Position of the argument holding the pattern, for functions taking a regex.
+/// Constant string patterns at this position are compiled once, together with the expression.
+pub(crate) fn regex_pattern_pos(op: &Op) -> Option<usize> {
+    if op.name == OP_REGEX.name {
+        Some(0)
+    } else if [
+        OP_REGEX_MATCHES.name,
+        OP_REGEX_REPLACE.name,
+        OP_REGEX_REPLACE_ALL.name,
+        OP_REGEX_EXTRACT.name,
+        OP_REGEX_EXTRACT_FIRST.name,
+    ]
+    .contains(&op.name)
+    {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+/// Patterns that are not constant are given as strings, and compiled on every call
+fn regex_arg<'a>(arg: &'a DataValue, op_name: &str) -> Result<Cow<'a, regex::Regex>> {
+    match arg {
+        DataValue::Regex(r) => Ok(Cow::Borrowed(&r.0)),
+        DataValue::Str(s) => Ok(Cow::Owned(str2regex(s)?)),
+        _ => bail!("'{}' requires a regex or a string as the pattern", op_name),
+    }
+}
+
 define_op!(OP_REGEX_MATCHES, 2, false);
 pub(crate) fn op_regex_matches(args: &[DataValue]) -> Result<DataValue> {
-    match (&args[0], &args[1]) {
-        (DataValue::Str(s), DataValue::Regex(r)) => Ok(DataValue::from(r.0.is_match(s))),
+    let r = regex_arg(&args[1], "regex_matches")?;
+    match &args[0] {
+        DataValue::Str(s) => Ok(DataValue::from(r.is_match(s))),
         _ => bail!("'regex_matches' requires strings"),
     }
 }
 
 define_op!(OP_REGEX_REPLACE, 3, false);
 pub(crate) fn op_regex_replace(args: &[DataValue]) -> Result<DataValue> {
-    match (&args[0], &args[1], &args[2]) {
-        (DataValue::Str(s), DataValue::Regex(r), DataValue::Str(rp)) => {
-            Ok(DataValue::Str(r.0.replace(s, rp as &str).into()))
+    let r = regex_arg(&args[1], "regex_replace")?;
+    match (&args[0], &args[2]) {
+        (DataValue::Str(s), DataValue::Str(rp)) => {
+            Ok(DataValue::Str(r.replace(s, rp as &str).into()))
         }
         _ => bail!("'regex_replace' requires strings"),
     }
@@ -1410,9 +1837,10 @@ pub(crate) fn op_regex_replace(args: &[DataValue]) -> Result<DataValue> {
 
 define_op!(OP_REGEX_REPLACE_ALL, 3, false);
 pub(crate) fn op_regex_replace_all(args: &[DataValue]) -> Result<DataValue> {
-    match (&args[0], &args[1], &args[2]) {
-        (DataValue::Str(s), DataValue::Regex(r), DataValue::Str(rp)) => {
-            Ok(DataValue::Str(r.0.replace_all(s, rp as &str).into()))
+    let r = regex_arg(&args[1], "regex_replace_all")?;
+    match (&args[0], &args[2]) {
+        (DataValue::Str(s), DataValue::Str(rp)) => {
+            Ok(DataValue::Str(r.replace_all(s, rp as &str).into()))
         }
         _ => bail!("'regex_replace' requires strings"),
     }
@@ -1420,12 +1848,13 @@ pub(crate) fn op_regex_replace_all(args: &[DataValue]) -> Result<DataValue> {
 
 define_op!(OP_REGEX_EXTRACT, 2, false);
 pub(crate) fn op_regex_extract(args: &[DataValue]) -> Result<DataValue> {
-    match (&args[0], &args[1]) {
-        (DataValue::Str(s), DataValue::Regex(r)) => {
-            let found =
-                r.0.find_iter(s)
-                    .map(|v| DataValue::from(v.as_str()))
-                    .collect_vec();
+    let r = regex_arg(&args[1], "regex_extract")?;
+    match &args[0] {
+        DataValue::Str(s) => {
+            let found = r
+                .find_iter(s)
+                .map(|v| DataValue::from(v.as_str()))
+                .collect_vec();
             Ok(DataValue::List(found))
         }
         _ => bail!("'regex_extract' requires strings"),
@@ -1434,9 +1863,10 @@ pub(crate) fn op_regex_extract(args: &[DataValue]) -> Result<DataValue> {
 
 define_op!(OP_REGEX_EXTRACT_FIRST, 2, false);
 pub(crate) fn op_regex_extract_first(args: &[DataValue]) -> Result<DataValue> {
-    match (&args[0], &args[1]) {
-        (DataValue::Str(s), DataValue::Regex(r)) => {
-            let found = r.0.find(s).map(|v| DataValue::from(v.as_str()));
+    let r = regex_arg(&args[1], "regex_extract_first")?;
+    match &args[0] {
+        DataValue::Str(s) => {
+            let found = r.find(s).map(|v| DataValue::from(v.as_str()));
             Ok(found.unwrap_or(DataValue::Null))
         }
         _ => bail!("'regex_extract_first' requires strings"),
@@ -1588,6 +2018,88 @@ pub(crate) fn op_unicode_normalize(args: &[DataValue]) -> Result<DataValue> {
     }
 }
 
+define_op!(OP_NFC, 1, false);
+pub(crate) fn op_nfc(args: &[DataValue]) -> Result<DataValue> {
+    match &args[0] {
+        DataValue::Str(s) => Ok(DataValue::Str(s.nfc().collect())),
+        _ => bail!("'nfc' requires strings"),
+    }
+}
+
+define_op!(OP_NFKC, 1, false);
+pub(crate) fn op_nfkc(args: &[DataValue]) -> Result<DataValue> {
+    match &args[0] {
+        DataValue::Str(s) => Ok(DataValue::Str(s.nfkc().collect())),
+        _ => bail!("'nfkc' requires strings"),
+    }
+}
+
+#[cfg(feature = "collation")]
+const MAX_CACHED_COLLATORS: usize = 64;
+
+#[cfg(feature = "collation")]
+lazy_static::lazy_static! {
+    static ref COLLATORS: std::sync::RwLock<
+        std::collections::BTreeMap<(String, String), icu_collator::CollatorBorrowed<'static>>,
+    > = Default::default();
+}
+
+#[cfg(feature = "collation")]
+define_op!(OP_COLLATE_KEY, 1, true);
+/// Sort keys compare bytewise in the same order as the strings under the collation,
+/// so they can be used for sorting, and in keys of stored relations and indices.
+/// Keys from different locales or strengths must not be mixed.
+#[cfg(feature = "collation")]
+pub(crate) fn op_collate_key(args: &[DataValue]) -> Result<DataValue> {
+    use icu_collator::options::{CollatorOptions, Strength};
+    use icu_collator::{Collator, CollatorPreferences};
+    use icu_locale_core::Locale;
+
+    let s = args[0]
+        .get_str()
+        .ok_or_else(|| miette!("'collate_key' requires strings"))?;
+    let locale = match args.get(1) {
+        None => "und",
+        Some(l) => l
+            .get_str()
+            .ok_or_else(|| miette!("the locale for 'collate_key' must be a string"))?,
+    };
+    let strength = match args.get(2) {
+        None => "tertiary",
+        Some(l) => l
+            .get_str()
+            .ok_or_else(|| miette!("the strength for 'collate_key' must be a string"))?,
+    };
+    let parsed_locale = Locale::try_from_str(locale)
+        .map_err(|_| miette!("'{}' is not a valid locale for 'collate_key'", locale))?;
+    let parsed_strength = match strength {
+        "primary" => Strength::Primary,
+        "secondary" => Strength::Secondary,
+        "tertiary" => Strength::Tertiary,
+        "quaternary" => Strength::Quaternary,
+        "identical" => Strength::Identical,
+        s => bail!("unknown collation strength {} for 'collate_key'", s),
+    };
+    // spellings of the same locale share a collator
+    let cache_key = (parsed_locale.to_string(), strength.to_string());
+    let mut key = vec![];
+    if let Some(collator) = COLLATORS.read().unwrap().get(&cache_key) {
+        let Ok(()) = collator.write_sort_key_to(s, &mut key);
+        return Ok(DataValue::Bytes(key));
+    }
+    let mut options = CollatorOptions::default();
+    options.strength = Some(parsed_strength);
+    let collator = Collator::try_new(CollatorPreferences::from(&parsed_locale), options)
+        .map_err(|err| miette!("cannot create collator for '{}': {}", locale, err))?;
+    let Ok(()) = collator.write_sort_key_to(s, &mut key);
+    let mut collators = COLLATORS.write().unwrap();
+    if collators.len() >= MAX_CACHED_COLLATORS {
+        collators.clear();
+    }
+    collators.insert(cache_key, collator);
+    Ok(DataValue::Bytes(key))
+}
+
 define_op!(OP_SORTED, 1, false);
 pub(crate) fn op_sorted(args: &[DataValue]) -> Result<DataValue> {
     let mut arg = args[0]
@@ -1722,6 +2234,108 @@ pub(crate) fn op_windows(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::List(res))
 }
 
+define_op!(OP_ZIP, 1, true);
+pub(crate) fn op_zip(args: &[DataValue]) -> Result<DataValue> {
+    let lists: Vec<_> = args
+        .iter()
+        .map(|arg| {
+            arg.get_slice()
+                .ok_or_else(|| miette!("'zip' requires lists"))
+        })
+        .try_collect()?;
+    let len = lists.iter().map(|l| l.len()).min().unwrap_or(0);
+    Ok(DataValue::List(
+        (0..len)
+            .map(|i| DataValue::List(lists.iter().map(|l| l[i].clone()).collect()))
+            .collect(),
+    ))
+}
+
+define_op!(OP_UNZIP, 1, false);
+pub(crate) fn op_unzip(args: &[DataValue]) -> Result<DataValue> {
+    let rows: Vec<_> = args[0]
+        .get_slice()
+        .ok_or_else(|| miette!("'unzip' requires a list of lists"))?
+        .iter()
+        .map(|row| {
+            row.get_slice()
+                .ok_or_else(|| miette!("'unzip' requires a list of lists"))
+        })
+        .try_collect()?;
+    let width = rows.first().map(|row| row.len()).unwrap_or(0);
+    ensure!(
+        rows.iter().all(|row| row.len() == width),
+        "'unzip' requires lists of the same length"
+    );
+    Ok(DataValue::List(
+        (0..width)
+            .map(|i| DataValue::List(rows.iter().map(|row| row[i].clone()).collect()))
+            .collect(),
+    ))
+}
+
+define_op!(OP_INDEX_OF, 2, false);
+pub(crate) fn op_index_of(args: &[DataValue]) -> Result<DataValue> {
+    let l = args[0]
+        .get_slice()
+        .ok_or_else(|| miette!("first argument of 'index_of' must be a list"))?;
+    Ok(match l.iter().position(|el| *el == args[1]) {
+        None => DataValue::Null,
+        Some(i) => DataValue::from(i as i64),
+    })
+}
+
+/// Removes duplicates, keeping the first occurrence of each value in place
+fn dedup_keep_order(items: Vec<DataValue>) -> Vec<DataValue> {
+    let mut indexed = items.into_iter().enumerate().collect_vec();
+    indexed.sort_by(|(i, a), (j, b)| a.cmp(b).then(i.cmp(j)));
+    indexed.dedup_by(|(_, later), (_, earlier)| later == earlier);
+    indexed.sort_by_key(|(i, _)| *i);
+    indexed.into_iter().map(|(_, v)| v).collect()
+}
+
+define_op!(OP_UNIQUE, 1, false);
+pub(crate) fn op_unique(args: &[DataValue]) -> Result<DataValue> {
+    let l = args[0]
+        .get_slice()
+        .ok_or_else(|| miette!("'unique' requires lists"))?;
+    Ok(DataValue::List(dedup_keep_order(l.to_vec())))
+}
+
+define_op!(OP_LIST_UNION, 1, true);
+pub(crate) fn op_list_union(args: &[DataValue]) -> Result<DataValue> {
+    let mut all = vec![];
+    for arg in args {
+        all.extend_from_slice(
+            arg.get_slice()
+                .ok_or_else(|| miette!("'list_union' requires lists"))?,
+        );
+    }
+    Ok(DataValue::List(dedup_keep_order(all)))
+}
+
+define_op!(OP_LIST_INTERSECTION, 1, true);
+pub(crate) fn op_list_intersection(args: &[DataValue]) -> Result<DataValue> {
+    let first = args[0]
+        .get_slice()
+        .ok_or_else(|| miette!("'list_intersection' requires lists"))?;
+    let mut others = vec![];
+    for arg in &args[1..] {
+        let mut other = arg
+            .get_slice()
+            .ok_or_else(|| miette!("'list_intersection' requires lists"))?
+            .to_vec();
+        other.sort();
+        others.push(other);
+    }
+    let common = first
+        .iter()
+        .filter(|el| others.iter().all(|other| other.binary_search(el).is_ok()))
+        .cloned()
+        .collect_vec();
+    Ok(DataValue::List(dedup_keep_order(common)))
+}
+
 fn get_index(mut i: i64, total: usize, is_upper: bool) -> Result<usize> {
     if i < 0 {
         i += total as i64;
@@ -1814,15 +2428,20 @@ pub(crate) fn op_maybe_get(args: &[DataValue]) -> Result<DataValue> {
 
 define_op!(OP_SLICE, 3, false);
 pub(crate) fn op_slice(args: &[DataValue]) -> Result<DataValue> {
-    let l = args[0]
-        .get_slice()
-        .ok_or_else(|| miette!("first argument to 'slice' mut be a list"))?;
     let m = args[1]
         .get_int()
         .ok_or_else(|| miette!("second argument to 'slice' mut be an integer"))?;
     let n = args[2]
         .get_int()
         .ok_or_else(|| miette!("third argument to 'slice' mut be an integer"))?;
+    if let DataValue::Bytes(b) = &args[0] {
+        let m = get_index(m, b.len(), false)?;
+        let n = get_index(n, b.len(), true)?;
+        return Ok(DataValue::Bytes(b[m..n].to_vec()));
+    }
+    let l = args[0]
+        .get_slice()
+        .ok_or_else(|| miette!("first argument to 'slice' mut be a list or bytes"))?;
     let m = get_index(m, l.len(), false)?;
     let n = get_index(n, l.len(), true)?;
     Ok(DataValue::List(l[m..n].to_vec()))
@@ -1865,6 +2484,83 @@ pub(crate) fn op_slice_string(args: &[DataValue]) -> Result<DataValue> {
     ))
 }
 
+/// Resolves a possibly negative character index against a string of length `len`,
+/// clamping it to the bounds of the string
+fn clamp_str_index(i: i64, len: usize) -> usize {
+    if i < 0 {
+        (len as i64 + i).max(0) as usize
+    } else {
+        (i as usize).min(len)
+    }
+}
+
+define_op!(OP_STR_SLICE, 2, true);
+pub(crate) fn op_str_slice(args: &[DataValue]) -> Result<DataValue> {
+    let s = args[0]
+        .get_str()
+        .ok_or_else(|| miette!("first argument to 'str_slice' must be a string"))?;
+    let len = s.chars().count();
+    let start = args[1]
+        .get_int()
+        .ok_or_else(|| miette!("second argument to 'str_slice' must be an integer"))?;
+    let start = clamp_str_index(start, len);
+    let end = match args.get(2) {
+        None => len,
+        Some(end) => clamp_str_index(
+            end.get_int()
+                .ok_or_else(|| miette!("third argument to 'str_slice' must be an integer"))?,
+            len,
+        ),
+    };
+    Ok(DataValue::Str(
+        s.chars()
+            .skip(start)
+            .take(end.saturating_sub(start))
+            .collect(),
+    ))
+}
+
+define_op!(OP_STR_INDEX_OF, 2, false);
+pub(crate) fn op_str_index_of(args: &[DataValue]) -> Result<DataValue> {
+    match (&args[0], &args[1]) {
+        (DataValue::Str(s), DataValue::Str(sub)) => Ok(match s.find(sub as &str) {
+            None => DataValue::Null,
+            Some(byte_idx) => DataValue::from(s[..byte_idx].chars().count() as i64),
+        }),
+        _ => bail!("'str_index_of' requires strings"),
+    }
+}
+
+define_op!(OP_SPLIT, 2, false);
+pub(crate) fn op_split(args: &[DataValue]) -> Result<DataValue> {
+    match (&args[0], &args[1]) {
+        (DataValue::Str(s), DataValue::Str(sep)) => {
+            ensure!(!sep.is_empty(), "'split' requires a non-empty separator");
+            Ok(DataValue::List(
+                s.split(sep as &str).map(DataValue::from).collect_vec(),
+            ))
+        }
+        _ => bail!("'split' requires strings"),
+    }
+}
+
+define_op!(OP_BYTE_LENGTH, 1, false);
+pub(crate) fn op_byte_length(args: &[DataValue]) -> Result<DataValue> {
+    Ok(DataValue::from(match &args[0] {
+        DataValue::Str(s) => s.len() as i64,
+        DataValue::Bytes(b) => b.len() as i64,
+        _ => bail!("'byte_length' requires strings or bytes"),
+    }))
+}
+
+define_op!(OP_CHAR_LENGTH, 1, false);
+pub(crate) fn op_char_length(args: &[DataValue]) -> Result<DataValue> {
+    match &args[0] {
+        DataValue::Str(s) => Ok(DataValue::from(s.chars().count() as i64)),
+        _ => bail!("'char_length' requires strings"),
+    }
+}
+
 define_op!(OP_FROM_SUBSTRINGS, 1, false);
 pub(crate) fn op_from_substrings(args: &[DataValue]) -> Result<DataValue> {
     let mut ret = String::new();
@@ -1903,6 +2599,73 @@ pub(crate) fn op_encode_base64(args: &[DataValue]) -> Result<DataValue> {
     }
 }
 
+fn hash_input<'a>(arg: &'a DataValue, op_name: &str) -> Result<&'a [u8]> {
+    match arg {
+        DataValue::Str(s) => Ok(s.as_bytes()),
+        DataValue::Bytes(b) => Ok(b),
+        _ => bail!("'{}' requires strings or bytes", op_name),
+    }
+}
+
+define_op!(OP_SHA256, 1, false);
+pub(crate) fn op_sha256(args: &[DataValue]) -> Result<DataValue> {
+    let input = hash_input(&args[0], "sha256")?;
+    Ok(DataValue::Bytes(Sha256::digest(input).to_vec()))
+}
+
+define_op!(OP_BLAKE3, 1, false);
+pub(crate) fn op_blake3(args: &[DataValue]) -> Result<DataValue> {
+    let input = hash_input(&args[0], "blake3")?;
+    Ok(DataValue::Bytes(blake3::hash(input).as_bytes().to_vec()))
+}
+
+// MD5 is broken as a cryptographic hash, only use it to interoperate with legacy systems
+define_op!(OP_MD5, 1, false);
+pub(crate) fn op_md5(args: &[DataValue]) -> Result<DataValue> {
+    let input = hash_input(&args[0], "md5")?;
+    Ok(DataValue::Bytes(Md5::digest(input).to_vec()))
+}
+
+define_op!(OP_ENCODE_HEX, 1, false);
+pub(crate) fn op_encode_hex(args: &[DataValue]) -> Result<DataValue> {
+    match &args[0] {
+        DataValue::Bytes(b) => {
+            let mut s = String::with_capacity(b.len() * 2);
+            for byte in b {
+                s.push(char::from_digit((byte >> 4) as u32, 16).unwrap());
+                s.push(char::from_digit((byte & 0xf) as u32, 16).unwrap());
+            }
+            Ok(DataValue::from(s))
+        }
+        _ => bail!("'encode_hex' requires bytes"),
+    }
+}
+
+pub(crate) fn hex_to_bytes(s: &str) -> Option<Vec<u8>> {
+    let pairs = s.as_bytes().chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    pairs
+        .map(|pair| {
+            let hi = (pair[0] as char).to_digit(16)?;
+            let lo = (pair[1] as char).to_digit(16)?;
+            Some((hi * 16 + lo) as u8)
+        })
+        .collect()
+}
+
+define_op!(OP_DECODE_HEX, 1, false);
+pub(crate) fn op_decode_hex(args: &[DataValue]) -> Result<DataValue> {
+    match &args[0] {
+        DataValue::Str(s) => {
+            let b = hex_to_bytes(s).ok_or_else(|| miette!("Data is not properly encoded"))?;
+            Ok(DataValue::Bytes(b))
+        }
+        _ => bail!("'decode_hex' requires strings"),
+    }
+}
+
 define_op!(OP_DECODE_BASE64, 1, false);
 pub(crate) fn op_decode_base64(args: &[DataValue]) -> Result<DataValue> {
     match &args[0] {
@@ -1930,6 +2693,10 @@ pub(crate) fn op_to_bool(args: &[DataValue]) -> Result<DataValue> {
         DataValue::Set(s) => !s.is_empty(),
         DataValue::Vec(_) => true,
         DataValue::Validity(vld) => vld.is_assert.0,
+        DataValue::Timestamp(_) => true,
+        DataValue::Decimal(d) => !d.is_zero(),
+        DataValue::Interval(iv) => iv.start < iv.end,
+        DataValue::Point(_) => true,
         DataValue::Bot => false,
         DataValue::Json(json) => match &json.0 {
             Value::Null => false,
@@ -1956,6 +2723,10 @@ pub(crate) fn op_to_unity(args: &[DataValue]) -> Result<DataValue> {
         DataValue::Set(s) => i64::from(!s.is_empty()),
         DataValue::Vec(_) => 1,
         DataValue::Validity(vld) => i64::from(vld.is_assert.0),
+        DataValue::Timestamp(_) => 1,
+        DataValue::Decimal(d) => i64::from(!d.is_zero()),
+        DataValue::Interval(iv) => i64::from(iv.start < iv.end),
+        DataValue::Point(_) => 1,
         DataValue::Bot => 0,
         DataValue::Json(json) => match &json.0 {
             Value::Null => 0,
@@ -1987,6 +2758,11 @@ pub(crate) fn op_to_int(args: &[DataValue]) -> Result<DataValue> {
                 .into()
         }
         DataValue::Validity(vld) => DataValue::Num(Num::Int(vld.timestamp.0 .0)),
+        DataValue::Timestamp(ts) => DataValue::Num(Num::Int(ts.0)),
+        DataValue::Decimal(d) => DataValue::Num(Num::Int(
+            d.trunc_to_i64()
+                .ok_or_else(|| miette!("The decimal is too large for an int"))?,
+        )),
         v => bail!("'to_int' does not recognize {:?}", v),
     })
 }
@@ -2007,15 +2783,79 @@ pub(crate) fn op_to_float(args: &[DataValue]) -> Result<DataValue> {
                 .map_err(|_| miette!("The string cannot be interpreted as float"))?
                 .into(),
         },
+        DataValue::Timestamp(ts) => (ts.0 as f64 / NANOS_PER_SEC as f64).into(),
+        DataValue::Decimal(d) => d.to_f64().into(),
         v => bail!("'to_float' does not recognize {:?}", v),
     })
 }
 
+/// Floats are converted through their shortest representation, strings are parsed
+pub(crate) fn val2decimal(v: &DataValue) -> Option<Decimal> {
+    match v {
+        DataValue::Decimal(d) => Some(d.clone()),
+        DataValue::Num(Num::Int(i)) => Some(Decimal::from(*i)),
+        DataValue::Num(Num::Float(f)) => Decimal::from_f64(*f),
+        DataValue::Str(s) => Decimal::from_str(s).ok(),
+        _ => None,
+    }
+}
+
+define_op!(OP_TO_DECIMAL, 1, true);
+pub(crate) fn op_to_decimal(args: &[DataValue]) -> Result<DataValue> {
+    let d = val2decimal(&args[0])
+        .ok_or_else(|| miette!("'to_decimal' cannot interpret {:?} as a decimal", args[0]))?;
+    Ok(DataValue::Decimal(match args.get(1) {
+        None => d,
+        Some(places) => {
+            let places = places
+                .get_int()
+                .ok_or_else(|| miette!("'to_decimal' expects an integer number of places"))?;
+            d.round(places)
+                .ok_or_else(|| miette!("decimal out of range"))?
+        }
+    }))
+}
+
 define_op!(OP_TO_STRING, 1, false);
 pub(crate) fn op_to_string(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::Str(val2str(&args[0]).into()))
 }
 
+/// The `try_` casts return null wherever the corresponding strict cast raises an error
+fn try_cast(cast: fn(&[DataValue]) -> Result<DataValue>, args: &[DataValue]) -> DataValue {
+    cast(args).unwrap_or(DataValue::Null)
+}
+
+define_op!(OP_TRY_TO_BOOL, 1, false);
+pub(crate) fn op_try_to_bool(args: &[DataValue]) -> Result<DataValue> {
+    Ok(try_cast(op_to_bool, args))
+}
+
+define_op!(OP_TRY_TO_INT, 1, false);
+pub(crate) fn op_try_to_int(args: &[DataValue]) -> Result<DataValue> {
+    Ok(try_cast(op_to_int, args))
+}
+
+define_op!(OP_TRY_TO_FLOAT, 1, false);
+pub(crate) fn op_try_to_float(args: &[DataValue]) -> Result<DataValue> {
+    Ok(try_cast(op_to_float, args))
+}
+
+define_op!(OP_TRY_TO_DECIMAL, 1, true);
+pub(crate) fn op_try_to_decimal(args: &[DataValue]) -> Result<DataValue> {
+    Ok(try_cast(op_to_decimal, args))
+}
+
+define_op!(OP_TRY_TO_STRING, 1, false);
+pub(crate) fn op_try_to_string(args: &[DataValue]) -> Result<DataValue> {
+    Ok(try_cast(op_to_string, args))
+}
+
+define_op!(OP_TRY_TO_UUID, 1, false);
+pub(crate) fn op_try_to_uuid(args: &[DataValue]) -> Result<DataValue> {
+    Ok(try_cast(op_to_uuid, args))
+}
+
 fn val2str(arg: &DataValue) -> String {
     match arg {
         DataValue::Str(s) => s.to_string(),
@@ -2254,6 +3094,54 @@ pub(crate) fn op_cos_dist(args: &[DataValue]) -> Result<DataValue> {
     }
 }
 
+define_op!(OP_DOT, 2, false);
+pub(crate) fn op_dot(args: &[DataValue]) -> Result<DataValue> {
+    let a = &args[0];
+    let b = &args[1];
+    match (a, b) {
+        (DataValue::Vec(Vector::F32(a)), DataValue::Vec(Vector::F32(b))) => {
+            if a.len() != b.len() {
+                bail!("'dot' requires two vectors of the same length");
+            }
+            Ok(DataValue::from(a.dot(b) as f64))
+        }
+        (DataValue::Vec(Vector::F64(a)), DataValue::Vec(Vector::F64(b))) => {
+            if a.len() != b.len() {
+                bail!("'dot' requires two vectors of the same length");
+            }
+            Ok(DataValue::from(a.dot(b)))
+        }
+        _ => bail!("'dot' requires two vectors of the same type"),
+    }
+}
+
+define_op!(OP_COS_SIM, 2, false);
+pub(crate) fn op_cos_sim(args: &[DataValue]) -> Result<DataValue> {
+    let a = &args[0];
+    let b = &args[1];
+    match (a, b) {
+        (DataValue::Vec(Vector::F32(a)), DataValue::Vec(Vector::F32(b))) => {
+            if a.len() != b.len() {
+                bail!("'cos_sim' requires two vectors of the same length");
+            }
+            let a_norm = a.dot(a) as f64;
+            let b_norm = b.dot(b) as f64;
+            let dot = a.dot(b) as f64;
+            Ok(DataValue::from(dot / (a_norm * b_norm).sqrt()))
+        }
+        (DataValue::Vec(Vector::F64(a)), DataValue::Vec(Vector::F64(b))) => {
+            if a.len() != b.len() {
+                bail!("'cos_sim' requires two vectors of the same length");
+            }
+            let a_norm = a.dot(a);
+            let b_norm = b.dot(b);
+            let dot = a.dot(b);
+            Ok(DataValue::from(dot / (a_norm * b_norm).sqrt()))
+        }
+        _ => bail!("'cos_sim' requires two vectors of the same type"),
+    }
+}
+
 define_op!(OP_INT_RANGE, 1, true);
 pub(crate) fn op_int_range(args: &[DataValue]) -> Result<DataValue> {
     let [start, end] = match args.len() {
@@ -2360,6 +3248,20 @@ pub(crate) fn op_assert(args: &[DataValue]) -> Result<DataValue> {
     }
 }
 
+define_op!(OP_SUBQUERY_VALUE, 1, false);
+/// Not exposed by name: unwraps the collected rows of a scalar subquery, see `parse::query`.
+pub(crate) fn op_subquery_value(args: &[DataValue]) -> Result<DataValue> {
+    let rows = args[0]
+        .get_slice()
+        .ok_or_else(|| miette!("scalar subquery did not produce a list of rows"))?;
+    ensure!(
+        rows.len() <= 1,
+        "scalar subquery returned {} rows, at most one is allowed",
+        rows.len()
+    );
+    Ok(rows.first().cloned().unwrap_or(DataValue::Null))
+}
+
 define_op!(OP_UNION, 1, true);
 pub(crate) fn op_union(args: &[DataValue]) -> Result<DataValue> {
     let mut ret = BTreeSet::new();
@@ -2524,6 +3426,300 @@ pub(crate) fn str2vld(s: &str) -> Result<ValidityTs> {
     Ok(ValidityTs(Reverse(microseconds as i64)))
 }
 
+const NANOS_PER_SEC: i64 = 1_000_000_000;
+
+fn secs_to_nanos(secs: Num) -> Option<i64> {
+    match secs {
+        Num::Int(i) => i.checked_mul(NANOS_PER_SEC),
+        Num::Float(f) => {
+            let nanos = (f * NANOS_PER_SEC as f64).round();
+            if nanos > i64::MIN as f64 && nanos < i64::MAX as f64 {
+                Some(nanos as i64)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Durations are given in seconds, as integers whenever they are whole
+fn nanos_to_secs(nanos: i64) -> DataValue {
+    if nanos % NANOS_PER_SEC == 0 {
+        DataValue::from(nanos / NANOS_PER_SEC)
+    } else {
+        DataValue::from(nanos as f64 / NANOS_PER_SEC as f64)
+    }
+}
+
+/// Numbers are taken as seconds since the UNIX epoch, strings as RFC 3339 datetimes
+pub(crate) fn val2ts(v: &DataValue) -> Option<Timestamp> {
+    match v {
+        DataValue::Timestamp(ts) => Some(*ts),
+        DataValue::Num(n) => secs_to_nanos(*n).map(Timestamp),
+        DataValue::Str(s) => DateTime::parse_from_rfc3339(s)
+            .ok()
+            .and_then(|dt| Timestamp::from_datetime(&dt)),
+        DataValue::Validity(vld) => vld.timestamp.0 .0.checked_mul(1000).map(Timestamp),
+        _ => None,
+    }
+}
+
+define_op!(OP_NOW_TS, 0, false);
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn op_now_ts(_args: &[DataValue]) -> Result<DataValue> {
+    let nanos = (Date::now() * 1_000_000.) as i64;
+    Ok(DataValue::Timestamp(Timestamp(nanos)))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn op_now_ts(_args: &[DataValue]) -> Result<DataValue> {
+    let now = SystemTime::now();
+    let nanos = now.duration_since(UNIX_EPOCH).unwrap().as_nanos() as i64;
+    Ok(DataValue::Timestamp(Timestamp(nanos)))
+}
+
+define_op!(OP_TO_TS, 1, false);
+pub(crate) fn op_to_ts(args: &[DataValue]) -> Result<DataValue> {
+    val2ts(&args[0])
+        .map(DataValue::Timestamp)
+        .ok_or_else(|| miette!("'to_ts' cannot interpret {:?} as a timestamp", args[0]))
+}
+
+define_op!(OP_PARSE_TS, 1, true);
+pub(crate) fn op_parse_ts(args: &[DataValue]) -> Result<DataValue> {
+    let s = args[0]
+        .get_str()
+        .ok_or_else(|| miette!("'parse_ts' expects a string"))?;
+    let dt = match args.get(1) {
+        None => DateTime::parse_from_rfc3339(s)
+            .map_err(|_| miette!("bad datetime: {}", s))?
+            .with_timezone(&Utc),
+        Some(fmt_v) => {
+            let fmt = fmt_v
+                .get_str()
+                .ok_or_else(|| miette!("'parse_ts' format specification requires a string"))?;
+            // without an offset the datetime is in UTC, without a time it is at midnight
+            match DateTime::parse_from_str(s, fmt) {
+                Ok(dt) => dt.with_timezone(&Utc),
+                Err(_) => match NaiveDateTime::parse_from_str(s, fmt) {
+                    Ok(dt) => dt.and_utc(),
+                    Err(_) => NaiveDate::parse_from_str(s, fmt)
+                        .map_err(|_| miette!("bad datetime: {} for format {}", s, fmt))?
+                        .and_hms_opt(0, 0, 0)
+                        .unwrap()
+                        .and_utc(),
+                },
+            }
+        }
+    };
+    let ts =
+        Timestamp::from_datetime(&dt).ok_or_else(|| miette!("datetime out of range: {}", s))?;
+    Ok(DataValue::Timestamp(ts))
+}
+
+fn get_ts(v: &DataValue, fn_name: &str) -> Result<Timestamp> {
+    match v {
+        DataValue::Timestamp(ts) => Ok(*ts),
+        _ => bail!("'{}' expects a timestamp", fn_name),
+    }
+}
+
+define_op!(OP_FORMAT_TS, 1, true);
+pub(crate) fn op_format_ts(args: &[DataValue]) -> Result<DataValue> {
+    use std::fmt::Write;
+
+    let ts = get_ts(&args[0], "format_ts")?;
+    let s = match args.get(1) {
+        None => ts.to_string(),
+        Some(fmt_v) => {
+            let fmt = fmt_v
+                .get_str()
+                .ok_or_else(|| miette!("'format_ts' format specification requires a string"))?;
+            let mut s = String::new();
+            write!(s, "{}", ts.to_datetime().format(fmt))
+                .map_err(|_| miette!("bad format specification: {}", fmt))?;
+            s
+        }
+    };
+    Ok(DataValue::Str(s.into()))
+}
+
+define_op!(OP_TS_YEAR, 1, false);
+pub(crate) fn op_ts_year(args: &[DataValue]) -> Result<DataValue> {
+    let dt = get_ts(&args[0], "ts_year")?.to_datetime();
+    Ok(DataValue::from(dt.year() as i64))
+}
+
+define_op!(OP_TS_MONTH, 1, false);
+pub(crate) fn op_ts_month(args: &[DataValue]) -> Result<DataValue> {
+    let dt = get_ts(&args[0], "ts_month")?.to_datetime();
+    Ok(DataValue::from(dt.month() as i64))
+}
+
+define_op!(OP_TS_DAY, 1, false);
+pub(crate) fn op_ts_day(args: &[DataValue]) -> Result<DataValue> {
+    let dt = get_ts(&args[0], "ts_day")?.to_datetime();
+    Ok(DataValue::from(dt.day() as i64))
+}
+
+define_op!(OP_TS_HOUR, 1, false);
+pub(crate) fn op_ts_hour(args: &[DataValue]) -> Result<DataValue> {
+    let dt = get_ts(&args[0], "ts_hour")?.to_datetime();
+    Ok(DataValue::from(dt.hour() as i64))
+}
+
+define_op!(OP_TS_MINUTE, 1, false);
+pub(crate) fn op_ts_minute(args: &[DataValue]) -> Result<DataValue> {
+    let dt = get_ts(&args[0], "ts_minute")?.to_datetime();
+    Ok(DataValue::from(dt.minute() as i64))
+}
+
+define_op!(OP_TS_SECOND, 1, false);
+pub(crate) fn op_ts_second(args: &[DataValue]) -> Result<DataValue> {
+    let dt = get_ts(&args[0], "ts_second")?.to_datetime();
+    Ok(DataValue::from(dt.second() as i64))
+}
+
+/// Null bounds are unbounded, others are interpreted as by `to_ts`
+fn make_interval(start: &DataValue, end: &DataValue) -> Option<Interval> {
+    let start = match start {
+        DataValue::Null => Interval::UNBOUNDED_START,
+        v => val2ts(v)?,
+    };
+    let end = match end {
+        DataValue::Null => Interval::UNBOUNDED_END,
+        v => val2ts(v)?,
+    };
+    (start <= end).then_some(Interval { start, end })
+}
+
+/// Intervals are also given as `[start, end]` lists
+pub(crate) fn val2interval(v: &DataValue) -> Option<Interval> {
+    match v {
+        DataValue::Interval(iv) => Some(*iv),
+        DataValue::List(l) if l.len() == 2 => make_interval(&l[0], &l[1]),
+        _ => None,
+    }
+}
+
+fn get_interval(v: &DataValue, fn_name: &str) -> Result<Interval> {
+    match v {
+        DataValue::Interval(iv) => Ok(*iv),
+        _ => bail!("'{}' expects an interval", fn_name),
+    }
+}
+
+define_op!(OP_INTERVAL, 2, false);
+pub(crate) fn op_interval(args: &[DataValue]) -> Result<DataValue> {
+    make_interval(&args[0], &args[1])
+        .map(DataValue::Interval)
+        .ok_or_else(|| {
+            miette!(
+                "'interval' cannot make an interval from {:?} to {:?}",
+                args[0],
+                args[1]
+            )
+        })
+}
+
+define_op!(OP_INTERVAL_START, 1, false);
+pub(crate) fn op_interval_start(args: &[DataValue]) -> Result<DataValue> {
+    let (start, _) = get_interval(&args[0], "interval_start")?.bounds();
+    Ok(start.map_or(DataValue::Null, DataValue::Timestamp))
+}
+
+define_op!(OP_INTERVAL_END, 1, false);
+pub(crate) fn op_interval_end(args: &[DataValue]) -> Result<DataValue> {
+    let (_, end) = get_interval(&args[0], "interval_end")?.bounds();
+    Ok(end.map_or(DataValue::Null, DataValue::Timestamp))
+}
+
+define_op!(OP_INTERVAL_CONTAINS, 2, false);
+pub(crate) fn op_interval_contains(args: &[DataValue]) -> Result<DataValue> {
+    let iv = get_interval(&args[0], "interval_contains")?;
+    Ok(DataValue::from(match &args[1] {
+        DataValue::Interval(other) => iv.contains(other),
+        v => iv.contains_ts(val2ts(v).ok_or_else(|| {
+            miette!("'interval_contains' requires an interval or a point in time")
+        })?),
+    }))
+}
+
+define_op!(OP_INTERVAL_OVERLAPS, 2, false);
+pub(crate) fn op_interval_overlaps(args: &[DataValue]) -> Result<DataValue> {
+    let a = get_interval(&args[0], "interval_overlaps")?;
+    let b = get_interval(&args[1], "interval_overlaps")?;
+    Ok(DataValue::from(a.overlaps(&b)))
+}
+
+/// Points are also given as `[lat, lon]` lists
+pub(crate) fn val2point(v: &DataValue) -> Option<GeoPoint> {
+    match v {
+        DataValue::Point(p) => Some(*p),
+        DataValue::List(l) if l.len() == 2 => GeoPoint::new(l[0].get_float()?, l[1].get_float()?),
+        _ => None,
+    }
+}
+
+fn get_point(v: &DataValue, fn_name: &str) -> Result<GeoPoint> {
+    val2point(v).ok_or_else(|| miette!("'{}' expects a point", fn_name))
+}
+
+define_op!(OP_POINT, 2, false);
+pub(crate) fn op_point(args: &[DataValue]) -> Result<DataValue> {
+    let lat = args[0]
+        .get_float()
+        .ok_or_else(|| miette!("'point' requires numbers"))?;
+    let lon = args[1]
+        .get_float()
+        .ok_or_else(|| miette!("'point' requires numbers"))?;
+    GeoPoint::new(lat, lon)
+        .map(DataValue::Point)
+        .ok_or_else(|| {
+            miette!(
+                "'point' requires a latitude within [-90, 90] and a longitude within [-180, 180], \
+             got {} and {}",
+                lat,
+                lon
+            )
+        })
+}
+
+define_op!(OP_POINT_LAT, 1, false);
+pub(crate) fn op_point_lat(args: &[DataValue]) -> Result<DataValue> {
+    Ok(DataValue::from(get_point(&args[0], "point_lat")?.lat()))
+}
+
+define_op!(OP_POINT_LON, 1, false);
+pub(crate) fn op_point_lon(args: &[DataValue]) -> Result<DataValue> {
+    Ok(DataValue::from(get_point(&args[0], "point_lon")?.lon()))
+}
+
+define_op!(OP_GEO_DISTANCE, 2, false);
+pub(crate) fn op_geo_distance(args: &[DataValue]) -> Result<DataValue> {
+    let a = get_point(&args[0], "geo_distance")?;
+    let b = get_point(&args[1], "geo_distance")?;
+    Ok(DataValue::from(a.distance(&b)))
+}
+
+define_op!(OP_GEO_IN_BOX, 3, false);
+pub(crate) fn op_geo_in_box(args: &[DataValue]) -> Result<DataValue> {
+    let p = get_point(&args[0], "geo_in_box")?;
+    let sw = get_point(&args[1], "geo_in_box")?;
+    let ne = get_point(&args[2], "geo_in_box")?;
+    Ok(DataValue::from(p.in_box(&sw, &ne)))
+}
+
+define_op!(OP_GEO_WITHIN, 3, false);
+pub(crate) fn op_geo_within(args: &[DataValue]) -> Result<DataValue> {
+    let p = get_point(&args[0], "geo_within")?;
+    let center = get_point(&args[1], "geo_within")?;
+    let meters = args[2]
+        .get_float()
+        .ok_or_else(|| miette!("'geo_within' requires a distance in metres"))?;
+    Ok(DataValue::from(p.distance(&center) <= meters))
+}
+
 define_op!(OP_RAND_UUID_V1, 0, false);
 pub(crate) fn op_rand_uuid_v1(_args: &[DataValue]) -> Result<DataValue> {
     let mut rng = rand::thread_rng();
@@ -2533,13 +3729,13 @@ pub(crate) fn op_rand_uuid_v1(_args: &[DataValue]) -> Result<DataValue> {
         let since_epoch: f64 = Date::now();
         let seconds = since_epoch.floor();
         let fractional = (since_epoch - seconds) * 1.0e9;
-        Timestamp::from_unix(uuid_ctx, seconds as u64, fractional as u32)
+        uuid::v1::Timestamp::from_unix(uuid_ctx, seconds as u64, fractional as u32)
     };
     #[cfg(not(target_arch = "wasm32"))]
     let ts = {
         let now = SystemTime::now();
         let since_epoch = now.duration_since(UNIX_EPOCH).unwrap();
-        Timestamp::from_unix(uuid_ctx, since_epoch.as_secs(), since_epoch.subsec_nanos())
+        uuid::v1::Timestamp::from_unix(uuid_ctx, since_epoch.as_secs(), since_epoch.subsec_nanos())
     };
     let mut rand_vals = [0u8; 6];
     rng.fill(&mut rand_vals);
@@ -2547,6 +3743,28 @@ pub(crate) fn op_rand_uuid_v1(_args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::uuid(id))
 }
 
+define_op!(OP_RAND_UUID_V7, 0, false);
+pub(crate) fn op_rand_uuid_v7(_args: &[DataValue]) -> Result<DataValue> {
+    #[cfg(target_arch = "wasm32")]
+    let ts = {
+        let since_epoch: f64 = Date::now();
+        let seconds = since_epoch.floor();
+        let fractional = (since_epoch - seconds) * 1.0e9;
+        uuid::Timestamp::from_unix(uuid::NoContext, seconds as u64, fractional as u32)
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    let ts = {
+        let now = SystemTime::now();
+        let since_epoch = now.duration_since(UNIX_EPOCH).unwrap();
+        uuid::Timestamp::from_unix(
+            uuid::NoContext,
+            since_epoch.as_secs(),
+            since_epoch.subsec_nanos(),
+        )
+    };
+    Ok(DataValue::uuid(uuid::Uuid::new_v7(ts)))
+}
+
 define_op!(OP_RAND_UUID_V4, 0, false);
 pub(crate) fn op_rand_uuid_v4(_args: &[DataValue]) -> Result<DataValue> {
     let id = uuid::Uuid::new_v4();
@@ -2560,7 +3778,7 @@ pub(crate) fn op_uuid_timestamp(args: &[DataValue]) -> Result<DataValue> {
             None => DataValue::Null,
             Some(t) => {
                 let (s, subs) = t.to_unix();
-                let s = (s as f64) + (subs as f64 / 10_000_000.);
+                let s = (s as f64) + (subs as f64 / 1_000_000_000.);
                 s.into()
             }
         },
@@ -11,7 +11,7 @@ use base64::Engine;
 use serde_json::json;
 pub(crate) use serde_json::Value as JsonValue;
 
-use crate::data::value::{DataValue, Num, Vector};
+use crate::data::value::{DataValue, GeoPoint, Interval, Num, Vector};
 use crate::JsonData;
 
 impl From<JsonValue> for DataValue {
@@ -52,6 +52,17 @@ impl<'a> From<&'a JsonValue> for DataValue {
     }
 }
 
+/// Intervals are pairs of RFC 3339 strings, with null for unbounded ends
+pub(crate) fn interval2json(iv: &Interval) -> JsonValue {
+    let (start, end) = iv.bounds();
+    json!([start.map(|ts| ts.to_string()), end.map(|ts| ts.to_string())])
+}
+
+/// Points are `[lat, lon]` pairs
+pub(crate) fn point2json(p: &GeoPoint) -> JsonValue {
+    json!([p.lat(), p.lon()])
+}
+
 impl From<DataValue> for JsonValue {
     fn from(v: DataValue) -> Self {
         match v {
@@ -96,6 +107,10 @@ impl From<DataValue> for JsonValue {
                 json!([v.timestamp.0, v.is_assert])
             }
             DataValue::Json(j) => j.0,
+            DataValue::Timestamp(ts) => JsonValue::String(ts.to_string()),
+            DataValue::Decimal(d) => JsonValue::String(d.to_string()),
+            DataValue::Interval(iv) => interval2json(&iv),
+            DataValue::Point(p) => point2json(&p),
         }
     }
 }
@@ -14,26 +14,48 @@ use std::str::FromStr;
 use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
 use regex::Regex;
 
+use crate::data::decimal::Decimal;
 use crate::data::value::{
-    DataValue, JsonData, Num, RegexWrapper, UuidWrapper, Validity, ValidityTs, Vector,
+    DataValue, GeoPoint, Interval, JsonData, Num, RegexWrapper, Timestamp, UuidWrapper, Validity,
+    ValidityTs, Vector,
 };
 
 const INIT_TAG: u8 = 0x00;
-const NULL_TAG: u8 = 0x01;
-const FALSE_TAG: u8 = 0x02;
-const TRUE_TAG: u8 = 0x03;
-const VEC_TAG: u8 = 0x04;
-const NUM_TAG: u8 = 0x05;
-const STR_TAG: u8 = 0x06;
-const BYTES_TAG: u8 = 0x07;
-const UUID_TAG: u8 = 0x08;
-const REGEX_TAG: u8 = 0x09;
-const LIST_TAG: u8 = 0x0A;
-const SET_TAG: u8 = 0x0B;
-const VLD_TAG: u8 = 0x0C;
-const JSON_TAG: u8 = 0x0D;
+const NULL_TAG: u8 = 0x20;
+const FALSE_TAG: u8 = 0x21;
+const TRUE_TAG: u8 = 0x22;
+const VEC_TAG: u8 = 0x23;
+const NEG_NUM_TAG: u8 = 0x24;
+const NUM_TAG: u8 = 0x25;
+const STR_TAG: u8 = 0x26;
+const BYTES_TAG: u8 = 0x27;
+const UUID_TAG: u8 = 0x28;
+const REGEX_TAG: u8 = 0x29;
+const LIST_TAG: u8 = 0x2A;
+const SET_TAG: u8 = 0x2B;
+const VLD_TAG: u8 = 0x2C;
+const JSON_TAG: u8 = 0x2D;
+const TS_TAG: u8 = 0x2E;
+const UUID_V7_TAG: u8 = 0x2F;
+const DECIMAL_TAG: u8 = 0x30;
+const INTERVAL_TAG: u8 = 0x31;
+const POINT_TAG: u8 = 0x32;
 const BOT_TAG: u8 = 0xFF;
 
+// Keys written before storage version 2 used the tags from 0x01 to 0x12 in the
+// same order, a single tag for all numbers, and numbers and byte strings of fixed
+// size groups. They are still decoded, so that old stores can be re-encoded.
+const LEGACY_NULL_TAG: u8 = 0x01;
+const LEGACY_VEC_TAG: u8 = 0x04;
+const LEGACY_NUM_TAG: u8 = 0x05;
+const LEGACY_POINT_TAG: u8 = 0x12;
+
+const DECIMAL_DIGITS_END: u8 = 0x00;
+
+const BYTES_ESCAPE: u8 = 0x00;
+const BYTES_ESCAPED_ZERO: u8 = 0xFF;
+const BYTES_END: u8 = 0x01;
+
 const VEC_F32: u8 = 0x01;
 const VEC_F64: u8 = 0x02;
 
@@ -69,10 +91,7 @@ pub(crate) trait MemCmpEncoder: Write {
                     }
                 }
             }
-            DataValue::Num(n) => {
-                self.write_u8(NUM_TAG).unwrap();
-                self.encode_num(*n);
-            }
+            DataValue::Num(n) => self.encode_num(*n),
             DataValue::Str(s) => {
                 self.write_u8(STR_TAG).unwrap();
                 self.encode_bytes(s.as_bytes());
@@ -86,6 +105,10 @@ pub(crate) trait MemCmpEncoder: Write {
                 self.write_u8(BYTES_TAG).unwrap();
                 self.encode_bytes(b)
             }
+            DataValue::Uuid(u) if u.is_v7() => {
+                self.write_u8(UUID_V7_TAG).unwrap();
+                self.write_all(u.0.as_bytes()).unwrap();
+            }
             DataValue::Uuid(u) => {
                 self.write_u8(UUID_TAG).unwrap();
                 let (s_l, s_m, s_h, s_rest) = u.0.as_fields();
@@ -121,13 +144,70 @@ pub(crate) trait MemCmpEncoder: Write {
                 self.write_u64::<BigEndian>(ts_flipped).unwrap();
                 self.write_u8(!vld.is_assert.0 as u8).unwrap();
             }
+            DataValue::Timestamp(ts) => {
+                self.write_u8(TS_TAG).unwrap();
+                self.write_u64::<BigEndian>(order_encode_i64(ts.0)).unwrap();
+            }
+            DataValue::Decimal(d) => {
+                self.write_u8(DECIMAL_TAG).unwrap();
+                self.encode_decimal(d);
+            }
+            DataValue::Interval(iv) => {
+                // sorted by start first, so that the intervals valid at a given time
+                // are all found before the one starting at that time
+                self.write_u8(INTERVAL_TAG).unwrap();
+                self.write_u64::<BigEndian>(order_encode_i64(iv.start.0))
+                    .unwrap();
+                self.write_u64::<BigEndian>(order_encode_i64(iv.end.0))
+                    .unwrap();
+            }
+            DataValue::Point(p) => {
+                // the position on the Z-order curve comes first, so that the points
+                // inside a bounding box are found in a range of keys
+                self.write_u8(POINT_TAG).unwrap();
+                self.write_u64::<BigEndian>(p.z_order()).unwrap();
+                self.write_u64::<BigEndian>(order_encode_f64(p.lat()))
+                    .unwrap();
+                self.write_u64::<BigEndian>(order_encode_f64(p.lon()))
+                    .unwrap();
+            }
             DataValue::Bot => self.write_u8(BOT_TAG).unwrap(),
         }
     }
+    /// Sign, then exponent and digits, all inverted for negative numbers
+    fn encode_decimal(&mut self, d: &Decimal) {
+        let signum = d.signum();
+        self.write_u8((signum + 2) as u8).unwrap();
+        if signum == 0 {
+            return;
+        }
+        let (exp, digits) = d.to_exp_digits();
+        let flip = |b: u8| if signum < 0 { !b } else { b };
+        let mut buf = [0u8; 8];
+        BigEndian::write_u64(&mut buf, order_encode_i64(exp));
+        for b in buf
+            .into_iter()
+            .chain(digits.bytes())
+            .chain([DECIMAL_DIGITS_END])
+        {
+            self.write_u8(flip(b)).unwrap();
+        }
+    }
+    /// The bits of the number as a float in groups of seven, with every byte inverted
+    /// for negative numbers, followed by whether it is an integer
     fn encode_num(&mut self, v: Num) {
-        let f = v.get_float();
-        let u = order_encode_f64(f);
-        self.write_u64::<BigEndian>(u).unwrap();
+        let u = v.get_float().to_bits();
+        if u & SIGN_MARK == 0 {
+            self.write_u8(NUM_TAG).unwrap();
+            let (groups, len) = encode_groups(u);
+            self.write_all(&groups[..len]).unwrap();
+        } else {
+            self.write_u8(NEG_NUM_TAG).unwrap();
+            let (groups, len) = encode_groups(u & !SIGN_MARK);
+            for g in &groups[..len] {
+                self.write_u8(!g).unwrap();
+            }
+        }
         match v {
             Num::Int(i) => {
                 if i > -EXACT_INT_BOUND && i < EXACT_INT_BOUND {
@@ -144,26 +224,83 @@ pub(crate) trait MemCmpEncoder: Write {
         }
     }
 
+    /// Zero bytes are escaped and the end is marked, both by two bytes sorting
+    /// before any other continuation, so no encoding is the prefix of another
     fn encode_bytes(&mut self, key: &[u8]) {
-        let len = key.len();
-        let mut index = 0;
-        while index <= len {
-            let remain = len - index;
-            let mut pad: usize = 0;
-            if remain > ENC_GROUP_SIZE {
-                self.write_all(&key[index..index + ENC_GROUP_SIZE]).unwrap();
-            } else {
-                pad = ENC_GROUP_SIZE - remain;
-                self.write_all(&key[index..]).unwrap();
-                self.write_all(&ENC_ASC_PADDING[..pad]).unwrap();
-            }
-            self.write_all(&[ENC_MARKER - (pad as u8)]).unwrap();
-            index += ENC_GROUP_SIZE;
+        let mut start = 0;
+        for (i, b) in key.iter().enumerate() {
+            if *b == 0 {
+                self.write_all(&key[start..i]).unwrap();
+                self.write_all(&[BYTES_ESCAPE, BYTES_ESCAPED_ZERO]).unwrap();
+                start = i + 1;
+            }
         }
+        self.write_all(&key[start..]).unwrap();
+        self.write_all(&[BYTES_ESCAPE, BYTES_END]).unwrap();
     }
 }
 
 pub fn decode_bytes(data: &[u8]) -> (Vec<u8>, &[u8]) {
+    let mut key = vec![];
+    let mut rest = data;
+    loop {
+        let pos = rest.iter().position(|b| *b == BYTES_ESCAPE).unwrap();
+        key.extend_from_slice(&rest[..pos]);
+        let marker = rest[pos + 1];
+        rest = &rest[pos + 2..];
+        if marker == BYTES_ESCAPED_ZERO {
+            key.push(0);
+        } else {
+            debug_assert_eq!(marker, BYTES_END);
+            return (key, rest);
+        }
+    }
+}
+
+/// The number of groups of seven bits needed for 64 bits
+const NUM_GROUPS: usize = 10;
+
+/// Splits `u` into groups of seven bits from the most significant end, leaving out
+/// trailing zero groups. Each group is followed by a bit set if more groups follow,
+/// so that shorter encodings sort before longer ones with the same groups.
+fn encode_groups(u: u64) -> ([u8; NUM_GROUPS], usize) {
+    let v = (u as u128) << 6;
+    let mut groups = [0u8; NUM_GROUPS];
+    let mut len = 1;
+    for (i, g) in groups.iter_mut().enumerate() {
+        *g = ((v >> (63 - 7 * i)) & 0x7F) as u8;
+        if *g != 0 {
+            len = i + 1;
+        }
+    }
+    for g in &mut groups[..len - 1] {
+        *g = (*g << 1) | 1;
+    }
+    groups[len - 1] <<= 1;
+    (groups, len)
+}
+
+fn decode_groups(data: &[u8], flip: u8) -> (u64, &[u8]) {
+    let mut v = 0u128;
+    for (i, b) in data.iter().enumerate() {
+        let b = b ^ flip;
+        v |= ((b >> 1) as u128) << (63 - 7 * i);
+        if b & 1 == 0 {
+            return ((v >> 6) as u64, &data[i + 1..]);
+        }
+    }
+    unreachable!()
+}
+
+fn upgrade_legacy_tag(tag: u8) -> u8 {
+    if tag <= LEGACY_VEC_TAG {
+        tag - LEGACY_NULL_TAG + NULL_TAG
+    } else {
+        tag - LEGACY_NUM_TAG + NUM_TAG
+    }
+}
+
+fn decode_legacy_bytes(data: &[u8]) -> (Vec<u8>, &[u8]) {
     let mut key = Vec::with_capacity(data.len() / (ENC_GROUP_SIZE + 1) * ENC_GROUP_SIZE);
     let mut offset = 0;
     let chunk_len = ENC_GROUP_SIZE + 1;
@@ -221,13 +358,25 @@ fn order_decode_f64(u: u64) -> f64 {
 
 const ENC_GROUP_SIZE: usize = 8;
 const ENC_MARKER: u8 = b'\xff';
-const ENC_ASC_PADDING: [u8; ENC_GROUP_SIZE] = [0; ENC_GROUP_SIZE];
 
 impl Num {
     pub(crate) fn decode_from_key(bs: &[u8]) -> (Self, &[u8]) {
-        let (float_part, remaining) = bs.split_at(8);
-        let fu = BigEndian::read_u64(float_part);
-        let f = order_decode_f64(fu);
+        let (tag, remaining) = bs.split_first().unwrap();
+        let (f, remaining) = match *tag {
+            NUM_TAG => {
+                let (u, remaining) = decode_groups(remaining, 0x00);
+                (f64::from_bits(u), remaining)
+            }
+            NEG_NUM_TAG => {
+                let (u, remaining) = decode_groups(remaining, 0xFF);
+                (f64::from_bits(u | SIGN_MARK), remaining)
+            }
+            LEGACY_NUM_TAG => {
+                let (float_part, remaining) = remaining.split_at(8);
+                (order_decode_f64(BigEndian::read_u64(float_part)), remaining)
+            }
+            _ => unreachable!(),
+        };
         let (tag, remaining) = remaining.split_first().unwrap();
         match *tag {
             IS_FLOAT => (Num::Float(f), remaining),
@@ -257,28 +406,34 @@ impl Num {
 impl DataValue {
     pub(crate) fn decode_from_key(bs: &[u8]) -> (Self, &[u8]) {
         let (tag, remaining) = bs.split_first().unwrap();
-        match *tag {
+        let is_legacy = (LEGACY_NULL_TAG..=LEGACY_POINT_TAG).contains(tag);
+        let (tag, read_bytes): (_, fn(&[u8]) -> (Vec<u8>, &[u8])) = if is_legacy {
+            (upgrade_legacy_tag(*tag), decode_legacy_bytes)
+        } else {
+            (*tag, decode_bytes)
+        };
+        match tag {
             NULL_TAG => (DataValue::Null, remaining),
             FALSE_TAG => (DataValue::from(false), remaining),
             TRUE_TAG => (DataValue::from(true), remaining),
-            NUM_TAG => {
-                let (n, remaining) = Num::decode_from_key(remaining);
+            NUM_TAG | NEG_NUM_TAG => {
+                let (n, remaining) = Num::decode_from_key(bs);
                 (DataValue::Num(n), remaining)
             }
             STR_TAG => {
-                let (bytes, remaining) = decode_bytes(remaining);
+                let (bytes, remaining) = read_bytes(remaining);
                 let s = unsafe { String::from_utf8_unchecked(bytes) };
                 (DataValue::Str(s.into()), remaining)
             }
             JSON_TAG => {
-                let (bytes, remaining) = decode_bytes(remaining);
+                let (bytes, remaining) = read_bytes(remaining);
                 (
                     DataValue::Json(JsonData(serde_json::from_slice(&bytes).unwrap())),
                     remaining,
                 )
             }
             BYTES_TAG => {
-                let (bytes, remaining) = decode_bytes(remaining);
+                let (bytes, remaining) = read_bytes(remaining);
                 (DataValue::Bytes(bytes), remaining)
             }
             UUID_TAG => {
@@ -291,8 +446,13 @@ impl DataValue {
                 let uuid = uuid::Uuid::from_fields(s_l, s_m, s_h, &s_rest);
                 (DataValue::Uuid(UuidWrapper(uuid)), remaining)
             }
+            UUID_V7_TAG => {
+                let (uuid_data, remaining) = remaining.split_at(16);
+                let uuid = uuid::Uuid::from_slice(uuid_data).unwrap();
+                (DataValue::Uuid(UuidWrapper(uuid)), remaining)
+            }
             REGEX_TAG => {
-                let (bytes, remaining) = decode_bytes(remaining);
+                let (bytes, remaining) = read_bytes(remaining);
                 let s = unsafe { String::from_utf8_unchecked(bytes) };
                 (
                     DataValue::Regex(RegexWrapper(Regex::from_str(&s).unwrap())),
@@ -334,6 +494,53 @@ impl DataValue {
                     rest,
                 )
             }
+            TS_TAG => {
+                let (ts_bytes, rest) = remaining.split_at(8);
+                let ts = order_decode_i64(BigEndian::read_u64(ts_bytes));
+                (DataValue::Timestamp(Timestamp(ts)), rest)
+            }
+            INTERVAL_TAG => {
+                let (start_bytes, rest) = remaining.split_at(8);
+                let (end_bytes, rest) = rest.split_at(8);
+                let start = order_decode_i64(BigEndian::read_u64(start_bytes));
+                let end = order_decode_i64(BigEndian::read_u64(end_bytes));
+                (
+                    DataValue::Interval(Interval {
+                        start: Timestamp(start),
+                        end: Timestamp(end),
+                    }),
+                    rest,
+                )
+            }
+            POINT_TAG => {
+                let (_z_bytes, rest) = remaining.split_at(8);
+                let (lat_bytes, rest) = rest.split_at(8);
+                let (lon_bytes, rest) = rest.split_at(8);
+                let lat = order_decode_f64(BigEndian::read_u64(lat_bytes));
+                let lon = order_decode_f64(BigEndian::read_u64(lon_bytes));
+                (DataValue::Point(GeoPoint::new(lat, lon).unwrap()), rest)
+            }
+            DECIMAL_TAG => {
+                let (sign, rest) = remaining.split_first().unwrap();
+                if *sign == 2 {
+                    return (DataValue::Decimal(Decimal::from(0)), rest);
+                }
+                let is_negative = *sign < 2;
+                let flip = |b: u8| if is_negative { !b } else { b };
+                let (exp_bytes, rest) = rest.split_at(8);
+                let mut buf = [0u8; 8];
+                for (t, s) in buf.iter_mut().zip(exp_bytes) {
+                    *t = flip(*s);
+                }
+                let exp = order_decode_i64(BigEndian::read_u64(&buf));
+                let end = rest
+                    .iter()
+                    .position(|b| flip(*b) == DECIMAL_DIGITS_END)
+                    .unwrap();
+                let digits: String = rest[..end].iter().map(|b| flip(*b) as char).collect();
+                let d = Decimal::from_exp_digits(is_negative, exp, &digits).unwrap();
+                (DataValue::Decimal(d), &rest[end + 1..])
+            }
             BOT_TAG => (DataValue::Bot, remaining),
             VEC_TAG => {
                 let (t_tag, remaining) = remaining.split_first().unwrap();
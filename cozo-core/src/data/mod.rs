@@ -7,6 +7,7 @@
  */
 
 pub(crate) mod aggr;
+pub(crate) mod decimal;
 pub(crate) mod expr;
 pub(crate) mod functions;
 pub(crate) mod json;
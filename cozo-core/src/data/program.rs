@@ -51,6 +51,8 @@ pub(crate) struct QueryOutOptions {
     pub(crate) limit: Option<usize>,
     pub(crate) offset: Option<usize>,
     pub(crate) timeout: Option<f64>,
+    /// `Some(0)` turns off the default limit of the database
+    pub(crate) memory_limit: Option<usize>,
     pub(crate) sleep: Option<f64>,
     pub(crate) sorters: Vec<(Symbol, SortDir)>,
     pub(crate) store_relation: Option<(InputRelationHandle, RelationOp, ReturnMutation)>,
@@ -74,6 +76,9 @@ impl Display for QueryOutOptions {
         if let Some(l) = self.timeout {
             writeln!(f, ":timeout {l};")?;
         }
+        if let Some(l) = self.memory_limit {
+            writeln!(f, ":memory_limit {l};")?;
+        }
         for (symb, dir) in &self.sorters {
             write!(f, ":order ")?;
             if *dir == SortDir::Dsc {
@@ -87,6 +92,7 @@ impl Display for QueryOutOptions {
                             metadata: StoredRelationMetadata { keys, non_keys },
                             key_bindings,
                             dep_bindings,
+                            merges,
                             ..
                         },
                         op,
@@ -106,6 +112,9 @@ impl Display for QueryOutOptions {
                 RelationOp::Insert => {
                     write!(f, ":insert ")?;
                 }
+                RelationOp::InsertOrIgnore => {
+                    write!(f, ":insert_or_ignore ")?;
+                }
                 RelationOp::Put => {
                     write!(f, ":put ")?;
                 }
@@ -139,6 +148,9 @@ impl Display for QueryOutOptions {
                 } else {
                     write!(f, " = {bind}")?;
                 }
+                if let Some(merge) = merges.get(&col.name) {
+                    write!(f, " merge {merge}")?;
+                }
             }
             write!(f, " => ")?;
             let mut is_first = true;
@@ -154,6 +166,9 @@ impl Display for QueryOutOptions {
                 } else {
                     write!(f, " = {bind}")?;
                 }
+                if let Some(merge) = merges.get(&col.name) {
+                    write!(f, " merge {merge}")?;
+                }
             }
             writeln!(f, "}};")?;
         }
@@ -195,6 +210,7 @@ pub(crate) enum RelationOp {
     Replace,
     Put,
     Insert,
+    InsertOrIgnore,
     Update,
     Rm,
     Delete,
@@ -231,6 +247,24 @@ impl InputInlineRulesOrFixed {
             InputInlineRulesOrFixed::Fixed { fixed, .. } => fixed.span,
         }
     }
+    pub(crate) fn collect_used_rules(&self, coll: &mut BTreeSet<Symbol>) {
+        match self {
+            InputInlineRulesOrFixed::Rules { rules, .. } => {
+                for rule in rules {
+                    for atom in &rule.body {
+                        atom.collect_used_rules(coll);
+                    }
+                }
+            }
+            InputInlineRulesOrFixed::Fixed { fixed, .. } => {
+                for arg in &fixed.rule_args {
+                    if let FixedRuleArg::InMem { name, .. } = arg {
+                        coll.insert(name.clone());
+                    }
+                }
+            }
+        }
+    }
     // pub(crate) fn used_rule(&self, rule_name: &Symbol) -> bool {
     //     match self {
     //         InputInlineRulesOrFixed::Rules { rules, .. } => rules
@@ -567,6 +601,16 @@ impl InputProgram {
         }
     }
 
+    /// Rules that are applied in the program but not defined by it
+    pub(crate) fn undefined_rules(&self) -> BTreeSet<Symbol> {
+        let mut used = BTreeSet::new();
+        for rules_or_fixed in self.prog.values() {
+            rules_or_fixed.collect_used_rules(&mut used);
+        }
+        used.retain(|name| !self.prog.contains_key(name));
+        used
+    }
+
     pub(crate) fn get_entry_arity(&self) -> Result<usize> {
         if let Some(entry) = self.prog.get(&Symbol::new(PROG_ENTRY, SourceSpan(0, 0))) {
             return match entry {
@@ -1436,19 +1480,22 @@ impl SearchInput {
 
         ensure!(k > 0, ExpectedPosIntForHnswK(self.span));
 
-        let ef_expr = self
-            .parameters
-            .remove("ef")
-            .ok_or_else(|| miette!(HnswRequiredMissing("ef".to_string(), self.span)))?;
-        let ef = ef_expr.eval_to_const()?;
-        let ef = ef.get_int().ok_or(ExpectedPosIntForHnswEf(self.span))?;
-
         #[derive(Debug, Error, Diagnostic)]
         #[error("Expected positive integer for `ef`")]
         #[diagnostic(code(parser::expected_int_for_hnsw_ef))]
         struct ExpectedPosIntForHnswEf(#[label] SourceSpan);
 
-        ensure!(ef > 0, ExpectedPosIntForHnswEf(self.span));
+        // searching fewer than `k` candidates cannot find `k` neighbours
+        let ef = match self.parameters.remove("ef") {
+            None => manifest.ef_construction as i64,
+            Some(ef_expr) => {
+                let ef = ef_expr.eval_to_const()?;
+                let ef = ef.get_int().ok_or(ExpectedPosIntForHnswEf(self.span))?;
+                ensure!(ef > 0, ExpectedPosIntForHnswEf(self.span));
+                ef
+            }
+        }
+        .max(k);
 
         let radius_expr = self.parameters.remove("radius");
         let radius = match radius_expr {
@@ -1691,6 +1738,20 @@ impl Display for InputAtom {
 }
 
 impl InputAtom {
+    pub(crate) fn collect_used_rules(&self, coll: &mut BTreeSet<Symbol>) {
+        match self {
+            InputAtom::Rule { inner } => {
+                coll.insert(inner.name.clone());
+            }
+            InputAtom::Negation { inner, .. } => inner.collect_used_rules(coll),
+            InputAtom::Conjunction { inner, .. } | InputAtom::Disjunction { inner, .. } => {
+                for a in inner {
+                    a.collect_used_rules(coll)
+                }
+            }
+            _ => {}
+        }
+    }
     // pub(crate) fn used_rule(&self, rule_name: &Symbol) -> bool {
     //     match self {
     //         InputAtom::Rule { inner } => inner.name == *rule_name,
@@ -1701,6 +1762,42 @@ impl InputAtom {
     //         _ => false,
     //     }
     // }
+    pub(crate) fn collect_bindings(&self, coll: &mut BTreeSet<Symbol>) -> Result<()> {
+        match self {
+            InputAtom::Rule { inner } => {
+                for arg in &inner.args {
+                    arg.collect_bindings(coll)?;
+                }
+            }
+            InputAtom::NamedFieldRelation { inner } => {
+                for arg in inner.args.values() {
+                    arg.collect_bindings(coll)?;
+                }
+            }
+            InputAtom::Relation { inner } => {
+                for arg in &inner.args {
+                    arg.collect_bindings(coll)?;
+                }
+            }
+            InputAtom::Predicate { inner } => inner.collect_bindings(coll)?,
+            InputAtom::Negation { inner, .. } => inner.collect_bindings(coll)?,
+            InputAtom::Conjunction { inner, .. } | InputAtom::Disjunction { inner, .. } => {
+                for a in inner {
+                    a.collect_bindings(coll)?;
+                }
+            }
+            InputAtom::Unification { inner } => {
+                coll.insert(inner.binding.clone());
+                inner.expr.collect_bindings(coll)?;
+            }
+            InputAtom::Search { inner } => {
+                for arg in inner.bindings.values().chain(inner.parameters.values()) {
+                    arg.collect_bindings(coll)?;
+                }
+            }
+        }
+        Ok(())
+    }
     pub(crate) fn span(&self) -> SourceSpan {
         match self {
             InputAtom::Negation { span, .. }
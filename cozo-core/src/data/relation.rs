@@ -21,6 +21,8 @@ use smartstring::{LazyCompact, SmartString};
 use thiserror::Error;
 
 use crate::data::expr::Expr;
+use crate::data::functions::{val2decimal, val2interval, val2point, val2ts};
+use crate::data::json::{interval2json, point2json};
 use crate::data::value::{DataValue, JsonData, UuidWrapper, Validity, ValidityTs, Vector};
 use crate::Num;
 
@@ -41,6 +43,10 @@ impl Display for NullableColType {
             ColType::Bytes => f.write_str("Bytes")?,
             ColType::Uuid => f.write_str("Uuid")?,
             ColType::Validity => f.write_str("Validity")?,
+            ColType::Timestamp => f.write_str("Timestamp")?,
+            ColType::Decimal => f.write_str("Decimal")?,
+            ColType::Interval => f.write_str("Interval")?,
+            ColType::Point => f.write_str("Point")?,
             ColType::List { eltype, len } => {
                 f.write_str("[")?;
                 write!(f, "{eltype}")?;
@@ -100,6 +106,10 @@ pub enum ColType {
     Tuple(Vec<NullableColType>),
     Validity,
     Json,
+    Timestamp,
+    Decimal,
+    Interval,
+    Point,
 }
 
 #[derive(
@@ -451,7 +461,31 @@ impl NullableColType {
                 DataValue::Bot => {
                     json!(null)
                 }
+                DataValue::Timestamp(ts) => {
+                    json!(ts.to_string())
+                }
+                DataValue::Decimal(d) => {
+                    json!(d.to_string())
+                }
+                DataValue::Interval(iv) => interval2json(&iv),
+                DataValue::Point(p) => point2json(&p),
             })),
+            ColType::Timestamp => match val2ts(&data) {
+                Some(ts) => DataValue::Timestamp(ts),
+                None => bail!(make_err()),
+            },
+            ColType::Decimal => match val2decimal(&data) {
+                Some(d) => DataValue::Decimal(d),
+                None => bail!(make_err()),
+            },
+            ColType::Interval => match val2interval(&data) {
+                Some(iv) => DataValue::Interval(iv),
+                None => bail!(make_err()),
+            },
+            ColType::Point => match val2point(&data) {
+                Some(p) => DataValue::Point(p),
+                None => bail!(make_err()),
+            },
         })
     }
 }
@@ -233,6 +233,51 @@ fn test_collect() {
     );
 }
 
+#[test]
+fn test_bounded_collect() {
+    let mut aggr = parse_aggr("collect").unwrap().clone();
+    aggr.normal_init(&[DataValue::from(2)]).unwrap();
+
+    let mut collect_aggr = aggr.normal_op.unwrap();
+    for i in 0..1000 {
+        collect_aggr.set(&DataValue::from(i)).unwrap();
+    }
+    assert_eq!(
+        collect_aggr.get().unwrap(),
+        DataValue::List(vec![DataValue::from(0), DataValue::from(1)])
+    );
+
+    let mut aggr = parse_aggr("collect").unwrap().clone();
+    assert!(aggr.normal_init(&[DataValue::from(0)]).is_err());
+    assert!(aggr.normal_init(&[DataValue::from(-1)]).is_err());
+    assert!(aggr.normal_init(&[DataValue::from("a")]).is_err());
+}
+
+#[test]
+fn test_group_concat() {
+    let mut aggr = parse_aggr("group_concat").unwrap().clone();
+    aggr.normal_init(&[DataValue::from(" | ")]).unwrap();
+
+    let mut group_concat_aggr = aggr.normal_op.unwrap();
+    assert_eq!(group_concat_aggr.get().unwrap(), DataValue::from(""));
+    group_concat_aggr.set(&DataValue::from("b")).unwrap();
+    group_concat_aggr.set(&DataValue::from("")).unwrap();
+    group_concat_aggr.set(&DataValue::from("a")).unwrap();
+    assert_eq!(
+        group_concat_aggr.get().unwrap(),
+        DataValue::from("b |  | a")
+    );
+    assert!(group_concat_aggr.set(&DataValue::from(1)).is_err());
+
+    let mut aggr = parse_aggr("group_concat").unwrap().clone();
+    assert!(aggr.normal_init(&[DataValue::from(1)]).is_err());
+    aggr.normal_init(&[]).unwrap();
+    let mut group_concat_aggr = aggr.normal_op.unwrap();
+    group_concat_aggr.set(&DataValue::from("x")).unwrap();
+    group_concat_aggr.set(&DataValue::from("y")).unwrap();
+    assert_eq!(group_concat_aggr.get().unwrap(), DataValue::from("x,y"));
+}
+
 #[test]
 fn test_count() {
     let mut aggr = parse_aggr("count").unwrap().clone();
@@ -271,6 +316,39 @@ fn test_std_dev() {
     assert!(v.abs_diff_eq(&(0.5_f64).sqrt(), 1e-10));
 }
 
+#[test]
+fn test_population_variance() {
+    let feed = |name: &str, values: &[f64]| {
+        let mut aggr = parse_aggr(name).unwrap().clone();
+        aggr.normal_init(&[]).unwrap();
+        let mut op = aggr.normal_op.unwrap();
+        for v in values {
+            op.set(&DataValue::from(*v)).unwrap();
+        }
+        op.get().unwrap()
+    };
+    let values = [2., 4., 4., 4., 5., 5., 7., 9.];
+    assert_eq!(feed("variance_pop", &values), DataValue::from(4.0));
+    assert_eq!(feed("std_dev_pop", &values), DataValue::from(2.0));
+    let v = feed("variance_samp", &values).get_float().unwrap();
+    assert!(v.abs_diff_eq(&(32. / 7.), 1e-10));
+    let v = feed("std_dev_samp", &values).get_float().unwrap();
+    assert!(v.abs_diff_eq(&(32_f64 / 7.).sqrt(), 1e-10));
+
+    // a large offset must not swamp the spread of the values
+    let shifted = values.map(|v| v + 1e9);
+    let v = feed("variance_pop", &shifted).get_float().unwrap();
+    assert!(v.abs_diff_eq(&4., 1e-6));
+
+    assert_eq!(feed("variance_pop", &[3.]), DataValue::from(0.0));
+    assert_eq!(feed("variance", &[3.]), DataValue::Null);
+    assert_eq!(feed("std_dev_pop", &[]), DataValue::Null);
+
+    let mut aggr = parse_aggr("std_dev_pop").unwrap().clone();
+    aggr.normal_init(&[]).unwrap();
+    assert!(aggr.normal_op.unwrap().set(&DataValue::from("a")).is_err());
+}
+
 #[test]
 fn test_mean() {
     let mut aggr = parse_aggr("mean").unwrap().clone();
@@ -572,3 +650,131 @@ fn test_bit_xor() {
     bit_xor_aggr.set(&DataValue::Bytes(vec![0b01011])).unwrap();
     assert_eq!(bit_xor_aggr.get().unwrap(), DataValue::Bytes(vec![0b10111]));
 }
+
+#[test]
+fn test_bitwise_ints() {
+    let feed = |name: &str| {
+        let mut aggr = parse_aggr(name).unwrap().clone();
+        aggr.normal_init(&[]).unwrap();
+        let mut op = aggr.normal_op.unwrap();
+        op.set(&DataValue::from(0b11100)).unwrap();
+        op.set(&DataValue::from(0b01011)).unwrap();
+        op.get().unwrap()
+    };
+    assert_eq!(feed("bit_and"), DataValue::from(0b01000));
+    assert_eq!(feed("bit_or"), DataValue::from(0b11111));
+    assert_eq!(feed("bit_xor"), DataValue::from(0b10111));
+
+    let mut aggr = parse_aggr("bit_or").unwrap().clone();
+    aggr.meet_init(&[]).unwrap();
+    let m_bit_or_aggr = aggr.meet_op.unwrap();
+    let mut v = m_bit_or_aggr.init_val();
+    assert!(m_bit_or_aggr
+        .update(&mut v, &DataValue::from(0b100))
+        .unwrap());
+    assert!(m_bit_or_aggr
+        .update(&mut v, &DataValue::from(0b001))
+        .unwrap());
+    assert!(!m_bit_or_aggr
+        .update(&mut v, &DataValue::from(0b100))
+        .unwrap());
+    assert_eq!(v, DataValue::from(0b101));
+
+    let mut aggr = parse_aggr("bit_or").unwrap().clone();
+    aggr.normal_init(&[]).unwrap();
+    let mut bit_or_aggr = aggr.normal_op.unwrap();
+    bit_or_aggr.set(&DataValue::from(1)).unwrap();
+    assert!(bit_or_aggr.set(&DataValue::Bytes(vec![1])).is_err());
+    assert!(bit_or_aggr.set(&DataValue::from(1.5)).is_err());
+}
+
+#[test]
+fn test_percentiles() {
+    let feed = |name: &str, args: &[DataValue], values: &[f64]| {
+        let mut aggr = parse_aggr(name).unwrap().clone();
+        aggr.normal_init(args).unwrap();
+        let mut op = aggr.normal_op.unwrap();
+        for v in values {
+            op.set(&DataValue::from(*v)).unwrap();
+        }
+        op.get().unwrap()
+    };
+    let values = [7., 1., 3., 5.];
+    assert_eq!(feed("median", &[], &values), DataValue::from(4.0));
+    assert_eq!(feed("median", &[], &values[..3]), DataValue::from(3.0));
+    assert_eq!(feed("median", &[], &[]), DataValue::Null);
+    assert_eq!(
+        feed("percentile", &[DataValue::from(0.25)], &values),
+        DataValue::from(2.5)
+    );
+    assert_eq!(
+        feed("percentile", &[DataValue::from(1)], &values),
+        DataValue::from(7.0)
+    );
+    assert_eq!(
+        feed("percentile_disc", &[DataValue::from(0.5)], &values),
+        DataValue::from(3.0)
+    );
+    assert_eq!(
+        feed("percentile_disc", &[DataValue::from(0)], &values),
+        DataValue::from(1.0)
+    );
+    assert_eq!(
+        feed("percentile_disc", &[DataValue::from(0.9)], &values),
+        DataValue::from(7.0)
+    );
+
+    let mut aggr = parse_aggr("percentile").unwrap().clone();
+    assert!(aggr.normal_init(&[]).is_err());
+    assert!(aggr.normal_init(&[DataValue::from(1.5)]).is_err());
+    aggr.normal_init(&[DataValue::from(0.5)]).unwrap();
+    assert!(aggr.normal_op.unwrap().set(&DataValue::from("a")).is_err());
+
+    let mut aggr = parse_aggr("percentile_disc").unwrap().clone();
+    aggr.normal_init(&[DataValue::from(0.5)]).unwrap();
+    let mut op = aggr.normal_op.unwrap();
+    for s in ["b", "c", "a"] {
+        op.set(&DataValue::from(s)).unwrap();
+    }
+    assert_eq!(op.get().unwrap(), DataValue::from("b"));
+}
+
+#[test]
+fn test_cost_k() {
+    let feed = |name: &str, k: i64| {
+        let mut aggr = parse_aggr(name).unwrap().clone();
+        aggr.normal_init(&[DataValue::from(k)]).unwrap();
+        let mut op = aggr.normal_op.unwrap();
+        for (payload, cost) in [("a", 3), ("b", 1), ("c", 4), ("d", 1), ("e", 5)] {
+            op.set(&DataValue::List(vec![
+                DataValue::from(payload),
+                DataValue::from(cost),
+            ]))
+            .unwrap();
+        }
+        op.get().unwrap()
+    };
+    let pairs = |ps: &[(&str, i64)]| {
+        DataValue::List(
+            ps.iter()
+                .map(|(p, c)| DataValue::List(vec![DataValue::from(*p), DataValue::from(*c)]))
+                .collect(),
+        )
+    };
+    assert_eq!(
+        feed("min_cost_k", 3),
+        pairs(&[("b", 1), ("d", 1), ("a", 3)])
+    );
+    assert_eq!(feed("max_cost_k", 2), pairs(&[("e", 5), ("c", 4)]));
+    assert_eq!(feed("max_cost_k", 10).get_slice().unwrap().len(), 5);
+    assert_eq!(
+        feed("min_cost_k", 100_000_000_000_000),
+        pairs(&[("b", 1), ("d", 1), ("a", 3), ("c", 4), ("e", 5)])
+    );
+
+    let mut aggr = parse_aggr("min_cost_k").unwrap().clone();
+    assert!(aggr.normal_init(&[]).is_err());
+    assert!(aggr.normal_init(&[DataValue::from(0)]).is_err());
+    aggr.normal_init(&[DataValue::from(1)]).unwrap();
+    assert!(aggr.normal_op.unwrap().set(&DataValue::from(1)).is_err());
+}
@@ -30,3 +30,44 @@ fn expression_eval() {
         .unwrap();
     assert_eq!(res.rows[0][0].get_bool().unwrap(), true);
 }
+
+#[test]
+fn short_circuiting_conditionals() {
+    let db = DbInstance::default();
+
+    // `get` out of bounds raises an error, so these only succeed if the branches are lazy
+    let res = db
+        .run_default(
+            r#"
+    ?[a, b, c, d, e, f] := x = 1, n = null,
+        a = if(x == 1, 'one', get([], 5)),
+        b = cond(x > 5, get([], 5), x > 0, 'positive', true, get([], 5)),
+        c = coalesce(n, x, get([], 5)),
+        d = n ~ x ~ get([], 5),
+        e = coalesce(n, null),
+        f = coalesce(n, if(x == 2, 'two'), 'other')
+    "#,
+        )
+        .unwrap();
+    assert_eq!(
+        res.rows[0],
+        vec![
+            DataValue::from("one"),
+            DataValue::from("positive"),
+            DataValue::from(1),
+            DataValue::from(1),
+            DataValue::Null,
+            DataValue::from("other"),
+        ]
+    );
+
+    assert!(db
+        .run_default("?[a] := x = null, a = coalesce(x, get([], 5))")
+        .is_err());
+
+    // constant folding of filters must not evaluate the unreachable arguments either
+    let res = db
+        .run_default("?[x] := x in [1, null], coalesce(x, 2, get([], 5)) > 1")
+        .unwrap();
+    assert_eq!(res.rows, vec![vec![DataValue::Null]]);
+}
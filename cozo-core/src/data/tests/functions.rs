@@ -12,7 +12,7 @@ use regex::Regex;
 use serde_json::json;
 
 use crate::data::functions::*;
-use crate::data::value::{DataValue, RegexWrapper};
+use crate::data::value::{DataValue, GeoPoint, RegexWrapper};
 use crate::DbInstance;
 
 #[test]
@@ -144,6 +144,31 @@ fn test_is_in() {
     );
 }
 
+#[test]
+fn test_null_propagation() {
+    let null = DataValue::Null;
+    let one = DataValue::from(1);
+    assert_eq!(op_lt(&[null.clone(), one.clone()]).unwrap(), null);
+    assert_eq!(op_le(&[one.clone(), null.clone()]).unwrap(), null);
+    assert_eq!(op_add(&[one.clone(), null.clone()]).unwrap(), null);
+    assert_eq!(op_sub(&[null.clone(), one.clone()]).unwrap(), null);
+    assert_eq!(op_mul(&[one.clone(), null.clone()]).unwrap(), null);
+    assert_eq!(op_div(&[one.clone(), null.clone()]).unwrap(), null);
+    assert_eq!(op_minus(std::slice::from_ref(&null)).unwrap(), null);
+    assert_eq!(op_negate(std::slice::from_ref(&null)).unwrap(), null);
+
+    let t = DataValue::from(true);
+    let f = DataValue::from(false);
+    assert_eq!(op_and(&[t.clone(), null.clone()]).unwrap(), null);
+    assert_eq!(op_and(&[null.clone(), f.clone()]).unwrap(), f);
+    assert_eq!(op_or(&[f.clone(), null.clone()]).unwrap(), null);
+    assert_eq!(op_or(&[null.clone(), t.clone()]).unwrap(), t);
+
+    // equality stays total
+    assert_eq!(op_eq(&[null.clone(), null.clone()]).unwrap(), t);
+    assert_eq!(op_eq(&[null, one]).unwrap(), f);
+}
+
 #[test]
 fn test_comparators() {
     assert_eq!(
@@ -171,7 +196,10 @@ fn test_comparators() {
         op_ge(&[DataValue::from(1), DataValue::from(2)]).unwrap(),
         DataValue::from(false)
     );
-    assert!(op_ge(&[DataValue::Null, DataValue::from(true)]).is_err());
+    assert_eq!(
+        op_ge(&[DataValue::Null, DataValue::from(true)]).unwrap(),
+        DataValue::Null
+    );
     assert_eq!(
         op_gt(&[DataValue::from(2), DataValue::from(1)]).unwrap(),
         DataValue::from(true)
@@ -196,7 +224,10 @@ fn test_comparators() {
         op_gt(&[DataValue::from(1), DataValue::from(2)]).unwrap(),
         DataValue::from(false)
     );
-    assert!(op_gt(&[DataValue::Null, DataValue::from(true)]).is_err());
+    assert_eq!(
+        op_gt(&[DataValue::Null, DataValue::from(true)]).unwrap(),
+        DataValue::Null
+    );
     assert_eq!(
         op_le(&[DataValue::from(2), DataValue::from(1)]).unwrap(),
         DataValue::from(false)
@@ -221,7 +252,10 @@ fn test_comparators() {
         op_le(&[DataValue::from(1), DataValue::from(2)]).unwrap(),
         DataValue::from(true)
     );
-    assert!(op_le(&[DataValue::Null, DataValue::from(true)]).is_err());
+    assert_eq!(
+        op_le(&[DataValue::Null, DataValue::from(true)]).unwrap(),
+        DataValue::Null
+    );
     assert_eq!(
         op_lt(&[DataValue::from(2), DataValue::from(1)]).unwrap(),
         DataValue::from(false)
@@ -246,7 +280,10 @@ fn test_comparators() {
         op_lt(&[DataValue::from(1), DataValue::from(2)]).unwrap(),
         DataValue::from(true)
     );
-    assert!(op_lt(&[DataValue::Null, DataValue::from(true)]).is_err());
+    assert_eq!(
+        op_lt(&[DataValue::Null, DataValue::from(true)]).unwrap(),
+        DataValue::Null
+    );
 }
 
 #[test]
@@ -407,6 +444,28 @@ fn test_floor_ceil() {
         op_ceil(&[DataValue::from(1.5)]).unwrap(),
         DataValue::from(2.0)
     );
+    assert_eq!(
+        op_floor(&[DataValue::from(1.25), DataValue::from(1)]).unwrap(),
+        DataValue::from(1.2)
+    );
+    assert_eq!(
+        op_ceil(&[DataValue::from(-1.25), DataValue::from(1)]).unwrap(),
+        DataValue::from(-1.2)
+    );
+    assert_eq!(
+        op_floor(&[DataValue::from(-1201), DataValue::from(-2)]).unwrap(),
+        DataValue::from(-1300)
+    );
+    assert_eq!(
+        op_ceil(&[DataValue::from(1201), DataValue::from(-2)]).unwrap(),
+        DataValue::from(1300)
+    );
+    assert_eq!(
+        op_floor(&[DataValue::from(7), DataValue::from(3)]).unwrap(),
+        DataValue::from(7)
+    );
+    assert!(op_floor(&[DataValue::from(7), DataValue::from(-19)]).is_err());
+    assert!(op_floor(&[DataValue::from(7.5), DataValue::from(0.5)]).is_err());
 }
 
 #[test]
@@ -435,6 +494,44 @@ fn test_round() {
         op_round(&[DataValue::from(-1.5)]).unwrap(),
         DataValue::from(-2.0)
     );
+    assert_eq!(
+        op_round(&[DataValue::from(1.23456), DataValue::from(2)]).unwrap(),
+        DataValue::from(1.23)
+    );
+    assert_eq!(
+        op_round(&[DataValue::from(1234.5), DataValue::from(-2)]).unwrap(),
+        DataValue::from(1200.0)
+    );
+    assert_eq!(
+        op_round(&[DataValue::from(-1250), DataValue::from(-2)]).unwrap(),
+        DataValue::from(-1300)
+    );
+    assert_eq!(
+        op_round(&[DataValue::from(1e300), DataValue::from(100)]).unwrap(),
+        DataValue::from(1e300)
+    );
+    assert_eq!(
+        op_round(&[DataValue::from(15), DataValue::from(i64::MIN)])
+            .unwrap_err()
+            .to_string(),
+        "number of digits out of range for 'round'"
+    );
+    assert_eq!(
+        op_round(&[DataValue::from(1.5), DataValue::from(i64::MAX)])
+            .unwrap_err()
+            .to_string(),
+        "number of digits out of range for 'round'"
+    );
+}
+
+#[test]
+fn test_clamp() {
+    let clamp = |x: DataValue| op_clamp(&[x, DataValue::from(0), DataValue::from(1.5)]).unwrap();
+    assert_eq!(clamp(DataValue::from(-3)), DataValue::from(0));
+    assert_eq!(clamp(DataValue::from(0.5)), DataValue::from(0.5));
+    assert_eq!(clamp(DataValue::from(2)), DataValue::from(1.5));
+    assert_eq!(clamp(DataValue::Null), DataValue::Null);
+    assert!(op_clamp(&[DataValue::from(1), DataValue::from(2), DataValue::from(1)]).is_err());
 }
 
 #[test]
@@ -795,6 +892,22 @@ fn test_regex() {
         .unwrap(),
         DataValue::Null
     );
+
+    assert_eq!(
+        op_regex_matches(&[DataValue::from("abcdef"), DataValue::from("^a.c")]).unwrap(),
+        DataValue::from(true)
+    );
+    assert_eq!(
+        op_regex_replace_all(&[
+            DataValue::from("abcdef"),
+            DataValue::from("[be]"),
+            DataValue::from("x")
+        ])
+        .unwrap(),
+        DataValue::from("axcdxf")
+    );
+    assert!(op_regex_matches(&[DataValue::from("abc"), DataValue::from("(")]).is_err());
+    assert!(op_regex_matches(&[DataValue::from("abc"), DataValue::from(1)]).is_err());
 }
 
 #[test]
@@ -1207,6 +1320,22 @@ fn test_slice() {
         .unwrap(),
         DataValue::List(vec![DataValue::from(2)])
     );
+
+    assert_eq!(
+        op_slice(&[
+            DataValue::Bytes([1, 2, 3, 4].into()),
+            DataValue::from(1),
+            DataValue::from(-1)
+        ])
+        .unwrap(),
+        DataValue::Bytes([2, 3].into())
+    );
+    assert!(op_slice(&[
+        DataValue::Bytes([1, 2, 3].into()),
+        DataValue::from(1),
+        DataValue::from(4)
+    ])
+    .is_err());
 }
 
 #[test]
@@ -1223,7 +1352,17 @@ fn test_encode_decode() {
         op_decode_base64(&[op_encode_base64(&[DataValue::Bytes([1, 2, 3].into())]).unwrap()])
             .unwrap(),
         DataValue::Bytes([1, 2, 3].into())
-    )
+    );
+    assert_eq!(
+        op_encode_hex(&[DataValue::Bytes([0, 10, 255].into())]).unwrap(),
+        DataValue::from("000aff")
+    );
+    assert_eq!(
+        op_decode_hex(&[DataValue::from("000AfF")]).unwrap(),
+        DataValue::Bytes([0, 10, 255].into())
+    );
+    assert!(op_decode_hex(&[DataValue::from("abc")]).is_err());
+    assert!(op_decode_hex(&[DataValue::from("zz")]).is_err());
 }
 
 #[test]
@@ -1321,6 +1460,49 @@ fn test_to_float() {
     );
 }
 
+#[test]
+fn test_try_casts() {
+    assert!(op_to_int(&[DataValue::Str("x1".into())]).is_err());
+    assert_eq!(
+        op_try_to_int(&[DataValue::Str("x1".into())]).unwrap(),
+        DataValue::Null
+    );
+    assert_eq!(
+        op_try_to_int(&[DataValue::Str("12".into())]).unwrap(),
+        DataValue::from(12)
+    );
+    assert_eq!(
+        op_try_to_float(&[DataValue::Str("1.5".into())]).unwrap(),
+        DataValue::from(1.5)
+    );
+    assert_eq!(
+        op_try_to_float(&[DataValue::List(vec![])]).unwrap(),
+        DataValue::Null
+    );
+    assert_eq!(
+        op_try_to_decimal(&[DataValue::Str("abc".into())]).unwrap(),
+        DataValue::Null
+    );
+    assert_eq!(
+        op_try_to_uuid(&[DataValue::Str("not-a-uuid".into())]).unwrap(),
+        DataValue::Null
+    );
+    assert!(op_try_to_uuid(&[DataValue::Str(
+        "9d6aa6d8-7d0d-4b3a-a5c4-0e5b7a9d7d3a".into()
+    )])
+    .unwrap()
+    .get_uuid()
+    .is_some());
+    assert_eq!(
+        op_try_to_bool(&[DataValue::from(0)]).unwrap(),
+        DataValue::from(false)
+    );
+    assert_eq!(
+        op_try_to_string(&[DataValue::from(1)]).unwrap(),
+        DataValue::Str("1".into())
+    );
+}
+
 #[test]
 fn test_rand() {
     let n = op_rand_float(&[]).unwrap().get_float().unwrap();
@@ -1400,6 +1582,16 @@ fn test_uuid() {
     assert!(op_uuid_timestamp(&[v1]).unwrap().get_float().is_some());
     assert!(op_to_uuid(&[DataValue::from("")]).is_err());
     assert!(op_to_uuid(&[DataValue::from("f3b4958c-52a1-11e7-802a-010203040506")]).is_ok());
+
+    let v7 = op_rand_uuid_v7(&[]).unwrap();
+    let now = op_now(&[]).unwrap().get_float().unwrap();
+    let ts = op_uuid_timestamp(&[v7]).unwrap().get_float().unwrap();
+    assert!((now - ts).abs() < 1.);
+    let v7 = op_to_uuid(&[DataValue::from("017f22e2-79b0-7cc3-98c4-dc0c0c07398f")]).unwrap();
+    assert_eq!(
+        op_uuid_timestamp(&[v7]).unwrap(),
+        DataValue::from(1645557742.)
+    );
 }
 
 #[test]
@@ -1410,6 +1602,232 @@ fn test_now() {
     let _dt = op_parse_timestamp(&[s]).unwrap();
 }
 
+#[test]
+fn test_timestamp() {
+    let now = op_now_ts(&[]).unwrap();
+    assert!(matches!(now, DataValue::Timestamp(_)));
+    let s = op_format_ts(std::slice::from_ref(&now)).unwrap();
+    assert_eq!(op_parse_ts(&[s]).unwrap(), now);
+
+    let ts = op_parse_ts(&[DataValue::from("2024-02-29T23:59:58.123456789+01:00")]).unwrap();
+    assert_eq!(
+        op_format_ts(std::slice::from_ref(&ts)).unwrap(),
+        DataValue::from("2024-02-29T22:59:58.123456789Z")
+    );
+    assert_eq!(
+        op_format_ts(&[ts.clone(), DataValue::from("%Y/%m/%d %H:%M")]).unwrap(),
+        DataValue::from("2024/02/29 22:59")
+    );
+    assert_eq!(
+        op_ts_year(std::slice::from_ref(&ts)).unwrap(),
+        DataValue::from(2024)
+    );
+    assert_eq!(
+        op_ts_month(std::slice::from_ref(&ts)).unwrap(),
+        DataValue::from(2)
+    );
+    assert_eq!(
+        op_ts_day(std::slice::from_ref(&ts)).unwrap(),
+        DataValue::from(29)
+    );
+    assert_eq!(
+        op_ts_hour(std::slice::from_ref(&ts)).unwrap(),
+        DataValue::from(22)
+    );
+    assert_eq!(
+        op_ts_minute(std::slice::from_ref(&ts)).unwrap(),
+        DataValue::from(59)
+    );
+    assert_eq!(
+        op_ts_second(std::slice::from_ref(&ts)).unwrap(),
+        DataValue::from(58)
+    );
+    assert!(op_ts_year(&[DataValue::from(0)]).is_err());
+
+    assert_eq!(
+        op_parse_ts(&[
+            DataValue::from("2024-03-01 00:00"),
+            DataValue::from("%Y-%m-%d %H:%M")
+        ])
+        .unwrap(),
+        op_parse_ts(&[DataValue::from("01/03/2024"), DataValue::from("%d/%m/%Y")]).unwrap()
+    );
+    assert!(op_parse_ts(&[DataValue::from("yesterday")]).is_err());
+
+    let later = op_add(&[ts.clone(), DataValue::from(3602)]).unwrap();
+    assert_eq!(
+        op_format_ts(std::slice::from_ref(&later)).unwrap(),
+        DataValue::from("2024-03-01T00:00:00.123456789Z")
+    );
+    assert_eq!(
+        op_sub(&[later.clone(), ts.clone()]).unwrap(),
+        DataValue::from(3602)
+    );
+    assert_eq!(
+        op_sub(&[later.clone(), DataValue::from(0.5)]).unwrap(),
+        op_add(&[DataValue::from(3601.5), ts.clone()]).unwrap()
+    );
+    assert_eq!(
+        op_sub(&[
+            ts.clone(),
+            op_add(&[ts.clone(), DataValue::from(0.25)]).unwrap()
+        ])
+        .unwrap(),
+        DataValue::from(-0.25)
+    );
+    assert!(op_add(&[ts.clone(), ts.clone()]).is_err());
+    assert_eq!(
+        op_lt(&[ts.clone(), later.clone()]).unwrap(),
+        DataValue::from(true)
+    );
+
+    assert_eq!(
+        op_to_ts(&[DataValue::from(1.5)]).unwrap(),
+        op_parse_ts(&[DataValue::from("1970-01-01T00:00:01.5Z")]).unwrap()
+    );
+    assert_eq!(
+        op_to_int(&[op_to_ts(&[DataValue::from(-1)]).unwrap()]).unwrap(),
+        DataValue::from(-1_000_000_000)
+    );
+    assert_eq!(op_is_timestamp(&[ts]).unwrap(), DataValue::from(true));
+}
+
+#[test]
+fn test_interval() {
+    let iv = op_interval(&[
+        DataValue::from("2024-01-01T00:00:00Z"),
+        DataValue::from("2024-02-01T00:00:00Z"),
+    ])
+    .unwrap();
+    assert_eq!(
+        op_interval_start(std::slice::from_ref(&iv)).unwrap(),
+        op_parse_ts(&[DataValue::from("2024-01-01T00:00:00Z")]).unwrap()
+    );
+    assert_eq!(
+        iv.to_string(),
+        r#"interval("2024-01-01T00:00:00Z", "2024-02-01T00:00:00Z")"#
+    );
+    assert!(op_interval(&[DataValue::from(10), DataValue::from(5)]).is_err());
+    assert!(op_interval(&[DataValue::from("soon"), DataValue::Null]).is_err());
+
+    // the start is included, the end is not
+    let contains = |iv: &DataValue, at: DataValue| {
+        op_interval_contains(&[iv.clone(), at]).unwrap() == DataValue::from(true)
+    };
+    assert!(contains(&iv, DataValue::from("2024-01-01T00:00:00Z")));
+    assert!(contains(&iv, DataValue::from("2024-01-31T23:59:59Z")));
+    assert!(!contains(&iv, DataValue::from("2024-02-01T00:00:00Z")));
+
+    let open = op_interval(&[DataValue::from("2024-01-15T00:00:00Z"), DataValue::Null]).unwrap();
+    assert_eq!(
+        op_interval_end(std::slice::from_ref(&open)).unwrap(),
+        DataValue::Null
+    );
+    assert!(contains(&open, DataValue::from("2200-01-01T00:00:00Z")));
+    assert!(!contains(&iv, open.clone()));
+    assert!(contains(
+        &op_interval(&[DataValue::Null, DataValue::Null]).unwrap(),
+        iv.clone()
+    ));
+    assert_eq!(
+        op_interval_overlaps(&[iv.clone(), open.clone()]).unwrap(),
+        DataValue::from(true)
+    );
+    let adjacent =
+        op_interval(&[DataValue::from("2024-02-01T00:00:00Z"), DataValue::Null]).unwrap();
+    assert_eq!(
+        op_interval_overlaps(&[iv.clone(), adjacent]).unwrap(),
+        DataValue::from(false)
+    );
+    assert!(op_interval_overlaps(&[iv.clone(), DataValue::from(1)]).is_err());
+    assert_eq!(op_is_interval(&[iv]).unwrap(), DataValue::from(true));
+}
+
+#[test]
+fn test_point() {
+    let london = op_point(&[DataValue::from(51.5074), DataValue::from(-0.1278)]).unwrap();
+    let paris = DataValue::List(vec![DataValue::from(48.8566), DataValue::from(2.3522)]);
+    assert_eq!(london.to_string(), "point(51.5074, -0.1278)");
+    assert_eq!(
+        op_point_lat(std::slice::from_ref(&london)).unwrap(),
+        DataValue::from(51.5074)
+    );
+    assert_eq!(
+        op_point_lon(std::slice::from_ref(&paris)).unwrap(),
+        DataValue::from(2.3522)
+    );
+    assert!(op_point(&[DataValue::from(91), DataValue::from(0)]).is_err());
+    assert!(op_point(&[DataValue::from(0), DataValue::from(-180.5)]).is_err());
+    assert!(op_point(&[DataValue::from("north"), DataValue::from(0)]).is_err());
+    assert_eq!(
+        op_is_point(std::slice::from_ref(&london)).unwrap(),
+        DataValue::from(true)
+    );
+    assert_eq!(
+        op_is_point(std::slice::from_ref(&paris)).unwrap(),
+        DataValue::from(false)
+    );
+
+    let distance = |a: &DataValue, b: &DataValue| {
+        op_geo_distance(&[a.clone(), b.clone()])
+            .unwrap()
+            .get_float()
+            .unwrap()
+    };
+    assert!((distance(&london, &paris) - 343_560.).abs() < 1000.);
+    let origin = op_point(&[DataValue::from(0), DataValue::from(0)]).unwrap();
+    let one_degree = op_point(&[DataValue::from(0), DataValue::from(1)]).unwrap();
+    assert!((distance(&origin, &one_degree) - 111_195.).abs() < 1.);
+    assert_eq!(distance(&london, &london), 0.);
+    assert!(op_geo_distance(&[london.clone(), DataValue::from(1)]).is_err());
+
+    let within = |p: &DataValue, meters: f64| {
+        op_geo_within(&[p.clone(), london.clone(), DataValue::from(meters)]).unwrap()
+            == DataValue::from(true)
+    };
+    assert!(within(&paris, 350_000.));
+    assert!(!within(&paris, 300_000.));
+
+    let in_box = |p: &DataValue, sw: (f64, f64), ne: (f64, f64)| {
+        let sw = op_point(&[DataValue::from(sw.0), DataValue::from(sw.1)]).unwrap();
+        let ne = op_point(&[DataValue::from(ne.0), DataValue::from(ne.1)]).unwrap();
+        op_geo_in_box(&[p.clone(), sw, ne]).unwrap() == DataValue::from(true)
+    };
+    assert!(in_box(&london, (50., -1.), (52., 1.)));
+    assert!(!in_box(&paris, (50., -1.), (52., 1.)));
+    // boxes crossing the antimeridian
+    let fiji = op_point(&[DataValue::from(-17.7), DataValue::from(178.)]).unwrap();
+    let samoa = op_point(&[DataValue::from(-13.8), DataValue::from(-172.1)]).unwrap();
+    assert!(in_box(&fiji, (-20., 170.), (-10., -170.)));
+    assert!(in_box(&samoa, (-20., 170.), (-10., -170.)));
+    assert!(!in_box(&london, (-20., 170.), (-10., -170.)));
+}
+
+#[test]
+fn test_point_bounding_box() {
+    // near the equator, at high latitudes, next to the antimeridian and around a pole
+    for (lat, lon, meters) in [
+        (0., 0., 100_000.),
+        (60., 10., 500_000.),
+        (-30., 179.5, 200_000.),
+        (89., 45., 300_000.),
+    ] {
+        let center = GeoPoint::new(lat, lon).unwrap();
+        let (sw, ne) = center.bounding_box(meters);
+        for i in -50..=50 {
+            for j in -50..=50 {
+                let p_lat = lat + i as f64 * 0.1;
+                let p_lon = lon + j as f64 * 0.2;
+                if let Some(p) = GeoPoint::new(p_lat, p_lon) {
+                    if p.distance(&center) <= meters {
+                        assert!(p.in_box(&sw, &ne), "{p} within {meters} of {center}");
+                    }
+                }
+            }
+        }
+    }
+}
+
 #[test]
 fn test_to_bool() {
     assert_eq!(
@@ -1487,3 +1905,373 @@ fn test_range() {
         .into_json();
     assert_eq!(res["rows"][0][0], json!([15, 13, 11, 9, 7, 5]));
 }
+
+#[test]
+fn test_decimal() {
+    let d = |s: &str| op_to_decimal(&[DataValue::from(s)]).unwrap();
+    assert_eq!(d("1.10").to_string(), "to_decimal(\"1.1\")");
+    assert_eq!(
+        op_add(&[d("0.1"), d("0.2")]).unwrap(),
+        op_to_decimal(&[DataValue::from("0.3")]).unwrap()
+    );
+    assert_eq!(op_add(&[d("0.1"), DataValue::from(2)]).unwrap(), d("2.1"));
+    assert_eq!(
+        op_add(&[d("0.5"), DataValue::from(0.25)]).unwrap(),
+        DataValue::from(0.75)
+    );
+    assert_eq!(op_sub(&[DataValue::from(1), d("0.01")]).unwrap(), d("0.99"));
+    assert_eq!(op_mul(&[d("1.5"), d("-1.5")]).unwrap(), d("-2.25"));
+    assert_eq!(op_div(&[d("1"), d("4")]).unwrap(), d("0.25"));
+    assert_eq!(
+        op_div(&[d("2"), DataValue::from(3)]).unwrap(),
+        d("0.6666666666666667")
+    );
+    assert!(op_div(&[d("1"), d("0")]).is_err());
+    assert_eq!(
+        op_add(&[
+            d("123456789012345678901234567890"),
+            d("0.000000000000000000001")
+        ])
+        .unwrap(),
+        d("123456789012345678901234567890.000000000000000000001")
+    );
+
+    assert_eq!(op_minus(&[d("1.5")]).unwrap(), d("-1.5"));
+    assert_eq!(op_abs(&[d("-1.5")]).unwrap(), d("1.5"));
+    assert_eq!(op_signum(&[d("-1.5")]).unwrap(), DataValue::from(-1));
+    assert_eq!(op_round(&[d("-2.5")]).unwrap(), d("-3"));
+    assert_eq!(op_floor(&[d("-2.5")]).unwrap(), d("-3"));
+    assert_eq!(op_ceil(&[d("-2.5")]).unwrap(), d("-2"));
+    assert_eq!(
+        op_floor(&[d("-2.555"), DataValue::from(2)]).unwrap(),
+        d("-2.56")
+    );
+    assert_eq!(
+        op_ceil(&[d("1234"), DataValue::from(-2)]).unwrap(),
+        d("1300")
+    );
+    assert_eq!(
+        op_round(&[d("1.25"), DataValue::from(i64::MAX)]).unwrap(),
+        d("1.25")
+    );
+    assert_eq!(
+        op_floor(&[d("1.25"), DataValue::from(i64::MIN)]).unwrap(),
+        d("0")
+    );
+    assert!(op_floor(&[d("-1.25"), DataValue::from(i64::MIN)]).is_err());
+    assert_eq!(
+        op_to_decimal(&[DataValue::from("3.14159"), DataValue::from(2)]).unwrap(),
+        d("3.14")
+    );
+
+    assert_eq!(
+        op_eq(&[d("2"), DataValue::from(2)]).unwrap(),
+        DataValue::from(true)
+    );
+    assert_eq!(
+        op_eq(&[d("0.5"), DataValue::from(0.5)]).unwrap(),
+        DataValue::from(true)
+    );
+    assert_eq!(
+        op_lt(&[d("1.99"), DataValue::from(2)]).unwrap(),
+        DataValue::from(true)
+    );
+    assert_eq!(
+        op_ge(&[DataValue::from(2), d("2.01")]).unwrap(),
+        DataValue::from(false)
+    );
+    assert_eq!(
+        op_gt(&[d("-0.1"), d("-0.2")]).unwrap(),
+        DataValue::from(true)
+    );
+
+    assert_eq!(op_to_int(&[d("-7.9")]).unwrap(), DataValue::from(-7));
+    assert_eq!(op_to_float(&[d("0.25")]).unwrap(), DataValue::from(0.25));
+    assert_eq!(op_to_decimal(&[DataValue::from(0.1)]).unwrap(), d("0.1"));
+    assert_eq!(op_to_decimal(&[DataValue::from(12)]).unwrap(), d("12"));
+    assert_eq!(d("1.2e-3"), d("0.0012"));
+    assert!(op_to_decimal(&[DataValue::from("1.2.3")]).is_err());
+    assert!(op_to_decimal(&[DataValue::from(f64::NAN)]).is_err());
+    assert_eq!(op_is_decimal(&[d("1")]).unwrap(), DataValue::from(true));
+    assert_eq!(
+        op_is_decimal(&[DataValue::from(1)]).unwrap(),
+        DataValue::from(false)
+    );
+}
+
+#[test]
+fn test_json_paths() {
+    let doc = op_parse_json(&[DataValue::from(
+        r#"{"a": {"b": [1, {"c": "x"}]}, "d": true}"#,
+    )])
+    .unwrap();
+    let path = |keys: Vec<DataValue>| DataValue::List(keys);
+    assert_eq!(
+        op_get_path(&[
+            doc.clone(),
+            path(vec![
+                DataValue::from("a"),
+                DataValue::from("b"),
+                DataValue::from(1),
+                DataValue::from("c")
+            ])
+        ])
+        .unwrap(),
+        DataValue::from("x")
+    );
+    assert_eq!(
+        op_get_path(&[doc.clone(), DataValue::from("d")]).unwrap(),
+        DataValue::from(true)
+    );
+    assert!(op_get_path(&[doc.clone(), DataValue::from("z")]).is_err());
+    assert_eq!(
+        op_get_path(&[doc.clone(), DataValue::from("z"), DataValue::Null]).unwrap(),
+        DataValue::Null
+    );
+
+    let updated = op_set_path(&[
+        doc.clone(),
+        path(vec![DataValue::from("a"), DataValue::from("e")]),
+        DataValue::from(2),
+    ])
+    .unwrap();
+    assert_eq!(
+        op_dump_json(&[updated]).unwrap(),
+        DataValue::from(r#"{"a":{"b":[1,{"c":"x"}],"e":2},"d":true}"#)
+    );
+
+    let patch =
+        op_parse_json(&[DataValue::from(r#"{"a": {"b": [3], "f": null}, "g": 1}"#)]).unwrap();
+    let merged = op_merge(&[doc, patch]).unwrap();
+    assert_eq!(
+        op_dump_json(&[merged]).unwrap(),
+        DataValue::from(r#"{"a":{"b":[3],"f":null},"d":true,"g":1}"#)
+    );
+}
+
+#[test]
+fn test_string_library() {
+    let s = DataValue::from("héllo wörld");
+    assert_eq!(
+        op_str_slice(&[s.clone(), DataValue::from(1), DataValue::from(4)]).unwrap(),
+        DataValue::from("éll")
+    );
+    assert_eq!(
+        op_str_slice(&[s.clone(), DataValue::from(-5)]).unwrap(),
+        DataValue::from("wörld")
+    );
+    assert_eq!(
+        op_str_slice(&[s.clone(), DataValue::from(6), DataValue::from(100)]).unwrap(),
+        DataValue::from("wörld")
+    );
+    assert_eq!(
+        op_str_slice(&[s.clone(), DataValue::from(4), DataValue::from(2)]).unwrap(),
+        DataValue::from("")
+    );
+    assert!(op_str_slice(&[DataValue::from(1), DataValue::from(0)]).is_err());
+
+    assert_eq!(
+        op_str_index_of(&[s.clone(), DataValue::from("wö")]).unwrap(),
+        DataValue::from(6)
+    );
+    assert_eq!(
+        op_str_index_of(&[s.clone(), DataValue::from("xyz")]).unwrap(),
+        DataValue::Null
+    );
+
+    assert_eq!(
+        op_split(&[DataValue::from("a,b,,c"), DataValue::from(",")]).unwrap(),
+        DataValue::List(vec![
+            DataValue::from("a"),
+            DataValue::from("b"),
+            DataValue::from(""),
+            DataValue::from("c")
+        ])
+    );
+    assert!(op_split(&[DataValue::from("abc"), DataValue::from("")]).is_err());
+
+    assert_eq!(
+        op_byte_length(std::slice::from_ref(&s)).unwrap(),
+        DataValue::from(13)
+    );
+    assert_eq!(
+        op_char_length(std::slice::from_ref(&s)).unwrap(),
+        DataValue::from(11)
+    );
+    assert_eq!(
+        op_byte_length(&[DataValue::Bytes(vec![1, 2])]).unwrap(),
+        DataValue::from(2)
+    );
+    assert!(op_char_length(&[DataValue::Bytes(vec![1, 2])]).is_err());
+}
+
+#[test]
+fn test_nfc_nfkc() {
+    let decomposed = DataValue::from("e\u{301}");
+    assert_eq!(
+        op_nfc(std::slice::from_ref(&decomposed)).unwrap(),
+        DataValue::from("\u{e9}")
+    );
+    assert_eq!(op_nfkc(&[decomposed]).unwrap(), DataValue::from("\u{e9}"));
+    assert_eq!(
+        op_nfc(&[DataValue::from("ﬁ")]).unwrap(),
+        DataValue::from("ﬁ")
+    );
+    assert_eq!(
+        op_nfkc(&[DataValue::from("ﬁ")]).unwrap(),
+        DataValue::from("fi")
+    );
+    assert!(op_nfc(&[DataValue::from(1)]).is_err());
+}
+
+#[cfg(feature = "collation")]
+#[test]
+fn test_collate_key() {
+    let key = |s: &str, rest: &[&str]| {
+        let mut args = vec![DataValue::from(s)];
+        args.extend(rest.iter().map(|r| DataValue::from(*r)));
+        op_collate_key(&args).unwrap()
+    };
+    // byte order would put all capitals first, and accented letters last
+    let mut words = vec!["zebra", "Apple", "élan", "apple", "Eagle", "banana"];
+    words.sort_by_key(|w| key(w, &[]));
+    assert_eq!(
+        words,
+        vec!["apple", "Apple", "banana", "Eagle", "élan", "zebra"]
+    );
+
+    // in Swedish, ö sorts after z
+    assert!(key("öl", &["en"]) < key("zoo", &["en"]));
+    assert!(key("öl", &["sv"]) > key("zoo", &["sv"]));
+    assert_eq!(key("öl", &["SV"]), key("öl", &["sv"]));
+
+    assert_eq!(
+        key("Apple", &["und", "primary"]),
+        key("apple", &["und", "primary"])
+    );
+    assert_ne!(key("Apple", &[]), key("apple", &[]));
+    assert!(op_collate_key(&[DataValue::from("a"), DataValue::from("not a locale!")]).is_err());
+    assert!(op_collate_key(&[
+        DataValue::from("a"),
+        DataValue::from("en"),
+        DataValue::from("loud")
+    ])
+    .is_err());
+}
+
+#[test]
+fn test_hashes() {
+    let hex = |v: DataValue| op_encode_hex(&[v]).unwrap();
+    assert_eq!(
+        hex(op_sha256(&[DataValue::from("abc")]).unwrap()),
+        DataValue::from("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+    );
+    assert_eq!(
+        hex(op_blake3(&[DataValue::from("abc")]).unwrap()),
+        DataValue::from("6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85")
+    );
+    assert_eq!(
+        hex(op_md5(&[DataValue::from("abc")]).unwrap()),
+        DataValue::from("900150983cd24fb0d6963f7d28e17f72")
+    );
+    assert_eq!(
+        op_sha256(&[DataValue::Bytes(b"abc".to_vec())]).unwrap(),
+        op_sha256(&[DataValue::from("abc")]).unwrap()
+    );
+    assert!(op_blake3(&[DataValue::from(1)]).is_err());
+}
+
+#[test]
+fn test_list_manipulation() {
+    let list = |xs: &[i64]| DataValue::List(xs.iter().map(|x| DataValue::from(*x)).collect());
+    let letters = DataValue::List(vec![DataValue::from("a"), DataValue::from("b")]);
+    assert_eq!(
+        op_zip(&[list(&[1, 2, 3]), letters.clone()]).unwrap(),
+        DataValue::List(vec![
+            DataValue::List(vec![DataValue::from(1), DataValue::from("a")]),
+            DataValue::List(vec![DataValue::from(2), DataValue::from("b")]),
+        ])
+    );
+    assert_eq!(
+        op_unzip(&[op_zip(&[list(&[1, 2]), letters.clone()]).unwrap()]).unwrap(),
+        DataValue::List(vec![list(&[1, 2]), letters])
+    );
+    assert_eq!(
+        op_unzip(&[DataValue::List(vec![])]).unwrap(),
+        DataValue::List(vec![])
+    );
+    assert!(op_unzip(&[DataValue::List(vec![list(&[1]), list(&[1, 2])])]).is_err());
+
+    assert_eq!(
+        op_index_of(&[list(&[5, 6, 6]), DataValue::from(6)]).unwrap(),
+        DataValue::from(1)
+    );
+    assert_eq!(
+        op_index_of(&[list(&[5, 6]), DataValue::from(7)]).unwrap(),
+        DataValue::Null
+    );
+
+    assert_eq!(
+        op_unique(&[list(&[3, 1, 3, 2, 1])]).unwrap(),
+        list(&[3, 1, 2])
+    );
+    assert_eq!(
+        op_list_union(&[list(&[3, 1]), list(&[2, 3, 0])]).unwrap(),
+        list(&[3, 1, 2, 0])
+    );
+    assert_eq!(
+        op_list_intersection(&[list(&[4, 3, 1, 3]), list(&[1, 3, 5]), list(&[3, 1])]).unwrap(),
+        list(&[3, 1])
+    );
+    assert!(op_list_union(&[list(&[1]), DataValue::from(1)]).is_err());
+}
+
+#[test]
+fn test_dot_and_cos_sim() {
+    let a = op_vec(&[DataValue::List(vec![
+        DataValue::from(1),
+        DataValue::from(2),
+        DataValue::from(2),
+    ])])
+    .unwrap();
+    let b = op_vec(&[DataValue::List(vec![
+        DataValue::from(2),
+        DataValue::from(0),
+        DataValue::from(0),
+    ])])
+    .unwrap();
+    assert_eq!(
+        op_dot(&[a.clone(), b.clone()]).unwrap(),
+        DataValue::from(2.0)
+    );
+    assert_eq!(
+        op_dot(&[a.clone(), a.clone()]).unwrap(),
+        DataValue::from(9.0)
+    );
+    let sim = op_cos_sim(&[a.clone(), b.clone()])
+        .unwrap()
+        .get_float()
+        .unwrap();
+    assert!(sim.abs_diff_eq(&(1. / 3.), 1E-6));
+    let dist = op_cos_dist(&[a.clone(), b.clone()])
+        .unwrap()
+        .get_float()
+        .unwrap();
+    assert!((sim + dist).abs_diff_eq(&1., 1E-6));
+
+    let c = op_vec(&[
+        DataValue::List(vec![DataValue::from(1), DataValue::from(2)]),
+        DataValue::from("F64"),
+    ])
+    .unwrap();
+    assert!(op_dot(&[a.clone(), c.clone()]).is_err());
+    assert!(op_cos_sim(&[
+        c.clone(),
+        op_vec(&[
+            DataValue::List(vec![DataValue::from(1)]),
+            DataValue::from("F64")
+        ])
+        .unwrap()
+    ])
+    .is_err());
+}
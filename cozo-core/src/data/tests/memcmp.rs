@@ -7,10 +7,13 @@
  *
  */
 
+use std::str::FromStr;
+
 use uuid::Uuid;
 
+use crate::data::decimal::Decimal;
 use crate::data::memcmp::{decode_bytes, MemCmpEncoder};
-use crate::data::value::{DataValue, Num, UuidWrapper};
+use crate::data::value::{DataValue, GeoPoint, Interval, Num, Timestamp, UuidWrapper};
 
 #[test]
 fn encode_decode_num() {
@@ -61,6 +64,161 @@ fn test_encode_decode_uuid() {
     assert!(remaining.is_empty());
 }
 
+#[test]
+fn encode_decode_timestamps() {
+    let mut timestamps = [i64::MIN, -1_000_000_001, -1, 0, 1, 999_999_999, i64::MAX]
+        .map(|nanos| DataValue::Timestamp(Timestamp(nanos)));
+    timestamps.reverse();
+    let mut encoded: Vec<_> = timestamps
+        .iter()
+        .map(|ts| {
+            let mut encoder = vec![];
+            encoder.encode_datavalue(ts);
+            let (decoded, remaining) = DataValue::decode_from_key(&encoder);
+            assert_eq!(&decoded, ts);
+            assert!(remaining.is_empty());
+            encoder
+        })
+        .collect();
+    encoded.sort();
+    let decoded: Vec<_> = encoded
+        .iter()
+        .map(|bs| DataValue::decode_from_key(bs).0)
+        .collect();
+    timestamps.sort();
+    assert_eq!(decoded, timestamps);
+}
+
+#[test]
+fn encode_decode_intervals() {
+    let mut intervals = [
+        (i64::MIN, -1),
+        (i64::MIN, i64::MAX),
+        (-1, 0),
+        (0, 0),
+        (0, 10),
+        (0, i64::MAX),
+        (5, 6),
+    ]
+    .map(|(start, end)| {
+        DataValue::Interval(Interval {
+            start: Timestamp(start),
+            end: Timestamp(end),
+        })
+    });
+    intervals.reverse();
+    let mut encoded: Vec<_> = intervals
+        .iter()
+        .map(|iv| {
+            let mut encoder = vec![];
+            encoder.encode_datavalue(iv);
+            let (decoded, remaining) = DataValue::decode_from_key(&encoder);
+            assert_eq!(&decoded, iv);
+            assert!(remaining.is_empty());
+            encoder
+        })
+        .collect();
+    encoded.sort();
+    let decoded: Vec<_> = encoded
+        .iter()
+        .map(|bs| DataValue::decode_from_key(bs).0)
+        .collect();
+    intervals.sort();
+    assert_eq!(decoded, intervals);
+}
+
+#[test]
+fn encode_decode_points() {
+    let mut points = [
+        (-90., -180.),
+        (-90., 180.),
+        (-0., -0.),
+        (0., 0.000001),
+        (0.000001, 0.),
+        (45.5, -73.6),
+        (-33.9, 151.2),
+        (90., 0.),
+    ]
+    .map(|(lat, lon)| DataValue::Point(GeoPoint::new(lat, lon).unwrap()));
+    points.reverse();
+    let encode = |v: &DataValue| {
+        let mut encoder = vec![];
+        encoder.encode_datavalue(v);
+        encoder
+    };
+    let mut encoded: Vec<_> = points
+        .iter()
+        .map(|p| {
+            let encoded = encode(p);
+            let (decoded, remaining) = DataValue::decode_from_key(&encoded);
+            assert_eq!(&decoded, p);
+            assert!(remaining.is_empty());
+            encoded
+        })
+        .collect();
+    encoded.sort();
+    let decoded: Vec<_> = encoded
+        .iter()
+        .map(|bs| DataValue::decode_from_key(bs).0)
+        .collect();
+    points.sort();
+    assert_eq!(decoded, points);
+
+    // the points inside a box are encoded between its corners
+    let sw = GeoPoint::new(10., 20.).unwrap();
+    let ne = GeoPoint::new(30.5, 40.25).unwrap();
+    let lower = encode(&DataValue::Point(sw));
+    let upper = encode(&DataValue::Point(ne));
+    for lat in -90..=90 {
+        for lon in -180..=180 {
+            let p = GeoPoint::new(lat as f64 / 2. + 10., lon as f64 / 4. + 20.).unwrap();
+            if p.in_box(&sw, &ne) {
+                let encoded = encode(&DataValue::Point(p));
+                assert!(lower <= encoded && encoded <= upper, "{p}");
+            }
+        }
+    }
+}
+
+#[test]
+fn encode_uuid_v7_in_time_order() {
+    let ids = [
+        "017f22e2-79b0-7cc3-98c4-dc0c0c07398f",
+        "017f22e2-79b1-7000-8000-000000000000",
+        "018f22e2-79b0-70c3-98c4-dc0c0c07398f",
+    ]
+    .map(|s| UuidWrapper(Uuid::parse_str(s).unwrap()));
+    let encoded = ids.clone().map(|id| {
+        let mut encoder = vec![];
+        encoder.encode_datavalue(&DataValue::Uuid(id));
+        encoder
+    });
+    for pair in ids.windows(2) {
+        assert!(pair[0] < pair[1]);
+    }
+    for pair in encoded.windows(2) {
+        assert!(pair[0] < pair[1]);
+    }
+    for (id, bs) in ids.into_iter().zip(encoded.iter()) {
+        let (decoded, remaining) = DataValue::decode_from_key(bs);
+        assert_eq!(decoded, DataValue::Uuid(id));
+        assert!(remaining.is_empty());
+    }
+
+    // other versions keep their layout, which puts the v1 timestamp first
+    let mut encoder = vec![];
+    encoder.encode_datavalue(&DataValue::Uuid(UuidWrapper(
+        Uuid::parse_str("dd85b19a-5fde-11ed-a88e-1774a7698039").unwrap(),
+    )));
+    assert_eq!(
+        encoder,
+        [
+            0x28, 0x11, 0xed, 0x5f, 0xde, 0xdd, 0x85, 0xb1, 0x9a, 0xa8, 0x8e, 0x17, 0x74, 0xa7,
+            0x69, 0x80, 0x39
+        ]
+    );
+}
+
 #[test]
 fn encode_decode_bytes() {
     let target = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit...";
@@ -93,6 +251,98 @@ fn encode_decode_bytes() {
     }
 }
 
+#[test]
+fn encode_bytes_in_order() {
+    let mut values: Vec<Vec<u8>> = vec![
+        vec![0xff],
+        vec![],
+        vec![0, 1],
+        vec![0],
+        vec![1, 2, 3, 4, 5, 6, 7, 8],
+        vec![1, 2, 3, 4, 5, 6, 7, 8, 9],
+        vec![1, 2, 3, 4, 5, 6, 7, 9],
+        vec![1, 2, 3, 4, 5, 6, 7, 8, 0],
+        vec![0x7f],
+    ];
+    let mut encoded: Vec<_> = values
+        .iter()
+        .map(|v| {
+            let mut encoder = vec![];
+            encoder.encode_datavalue(&DataValue::Bytes(v.clone()));
+            encoder
+        })
+        .collect();
+    encoded.sort();
+    values.sort();
+    let decoded: Vec<_> = encoded
+        .iter()
+        .map(|bs| DataValue::decode_from_key(bs).0)
+        .collect();
+    assert_eq!(
+        decoded,
+        values.into_iter().map(DataValue::Bytes).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn encode_decimals_in_order() {
+    let mut values: Vec<Decimal> = [
+        "0",
+        "1",
+        "-1",
+        "10",
+        "9.99",
+        "-9.99",
+        "0.001",
+        "-0.001",
+        "0.0011",
+        "-0.0011",
+        "123e100",
+        "-123e100",
+        "1e-300",
+        "-1e-300",
+        "12345678901234567890.123456789",
+        "12345678901234567890.12",
+        "100",
+        "99",
+        "-100",
+        "-99",
+        "0.1",
+        "0.10001",
+    ]
+    .into_iter()
+    .map(|s| Decimal::from_str(s).unwrap())
+    .collect();
+    let mut encoded: Vec<_> = values
+        .iter()
+        .map(|v| {
+            let mut encoder = vec![];
+            encoder.encode_datavalue(&DataValue::Decimal(v.clone()));
+            encoder
+        })
+        .collect();
+    encoded.sort();
+    values.sort();
+    for pair in values.windows(2) {
+        assert!(pair[0].to_f64() <= pair[1].to_f64());
+    }
+    let decoded: Vec<_> = encoded
+        .iter()
+        .map(|bs| {
+            let (v, rest) = DataValue::decode_from_key(bs);
+            assert!(rest.is_empty());
+            v
+        })
+        .collect();
+    assert_eq!(
+        decoded,
+        values
+            .into_iter()
+            .map(DataValue::Decimal)
+            .collect::<Vec<_>>()
+    );
+}
+
 #[test]
 fn specific_encode() {
     let mut encoder = vec![];
@@ -108,6 +358,78 @@ fn specific_encode() {
     assert_eq!(b, DataValue::from("MSS"));
 }
 
+#[test]
+fn encode_small_values_compactly() {
+    let encode = |v: DataValue| {
+        let mut encoder = vec![];
+        encoder.encode_datavalue(&v);
+        encoder
+    };
+    assert_eq!(encode(DataValue::from(0)), [0x25, 0x00, 0x00]);
+    assert_eq!(encode(DataValue::from(1)), [0x25, 0x3f, 0xf8, 0x00]);
+    assert_eq!(encode(DataValue::from(-1)), [0x24, 0xc0, 0x07, 0x00]);
+    assert_eq!(encode(DataValue::from(1.)), [0x25, 0x3f, 0xf8, 0x10]);
+    assert_eq!(encode(DataValue::from("")), [0x26, 0x00, 0x01]);
+    assert_eq!(
+        encode(DataValue::from("a\0b")),
+        [0x26, b'a', 0x00, 0xff, b'b', 0x00, 0x01]
+    );
+
+    let mut values: Vec<_> = [0., -0., 1., -1., 0.5, -0.5, 1e300, -1e300, 1e-300, -1e-300]
+        .into_iter()
+        .map(DataValue::from)
+        .chain(
+            [0, 1, -1, 2, -2, 1000, -1000]
+                .into_iter()
+                .map(DataValue::from),
+        )
+        .chain(["", "a", "a\0", "a\0\0", "a\x01", "ab", "b"].map(DataValue::from))
+        .collect();
+    let mut encoded: Vec<_> = values.iter().cloned().map(encode).collect();
+    encoded.sort();
+    values.sort();
+    let decoded: Vec<_> = encoded
+        .iter()
+        .map(|bs| {
+            let (v, rest) = DataValue::decode_from_key(bs);
+            assert!(rest.is_empty());
+            v
+        })
+        .collect();
+    assert_eq!(decoded, values);
+}
+
+#[test]
+fn decode_legacy_keys() {
+    let mut key = vec![0x01, 0x05];
+    key.extend((1f64.to_bits() | 0x8000000000000000).to_be_bytes());
+    key.push(0x00);
+    key.push(0x05);
+    key.extend((!(-2.5f64).to_bits()).to_be_bytes());
+    key.push(0x10);
+    key.push(0x06);
+    key.extend(b"Lorem ip\xffsum\x00\x00\x00\x00\x00\xfa");
+    key.extend([0x0A, 0x03, 0x07, 0, 0, 0, 0, 0, 0, 0, 0, 0xf7, 0x00]);
+
+    let mut remaining = &key[..];
+    let mut decoded = vec![];
+    while !remaining.is_empty() {
+        let (v, rest) = DataValue::decode_from_key(remaining);
+        decoded.push(v);
+        remaining = rest;
+    }
+    assert_eq!(
+        decoded,
+        vec![
+            DataValue::Null,
+            DataValue::from(1),
+            DataValue::from(-2.5),
+            DataValue::from("Lorem ipsum"),
+            DataValue::List(vec![DataValue::from(true), DataValue::Bytes(vec![])]),
+        ]
+    );
+}
+
 #[test]
 fn encode_decode_datavalues() {
     let mut dv = vec![
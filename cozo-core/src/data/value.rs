@@ -8,6 +8,7 @@
 
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
 use ndarray::Array1;
 use std::cmp::{Ordering, Reverse};
 use std::collections::BTreeSet;
@@ -15,6 +16,7 @@ use std::fmt::{Debug, Display, Formatter};
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 
+use crate::data::decimal::Decimal;
 use crate::data::json::JsonValue;
 use crate::data::relation::VecElementType;
 use ordered_float::OrderedFloat;
@@ -37,14 +39,27 @@ impl PartialOrd<Self> for UuidWrapper {
     }
 }
 
+impl UuidWrapper {
+    /// Version 7 UUIDs start with a millisecond UNIX timestamp and sort by their bytes
+    pub(crate) fn is_v7(&self) -> bool {
+        self.0.get_version_num() == 7
+    }
+}
+
 impl Ord for UuidWrapper {
     fn cmp(&self, other: &Self) -> Ordering {
-        let (s_l, s_m, s_h, s_rest) = self.0.as_fields();
-        let (o_l, o_m, o_h, o_rest) = other.0.as_fields();
-        s_h.cmp(&o_h)
-            .then_with(|| s_m.cmp(&o_m))
-            .then_with(|| s_l.cmp(&o_l))
-            .then_with(|| s_rest.cmp(o_rest))
+        match (self.is_v7(), other.is_v7()) {
+            (true, true) => self.0.as_bytes().cmp(other.0.as_bytes()),
+            (false, false) => {
+                let (s_l, s_m, s_h, s_rest) = self.0.as_fields();
+                let (o_l, o_m, o_h, o_rest) = other.0.as_fields();
+                s_h.cmp(&o_h)
+                    .then_with(|| s_m.cmp(&o_m))
+                    .then_with(|| s_l.cmp(&o_l))
+                    .then_with(|| s_rest.cmp(o_rest))
+            }
+            (s_v7, o_v7) => s_v7.cmp(&o_v7),
+        }
     }
 }
 
@@ -139,6 +154,218 @@ impl From<(i64, bool)> for Validity {
     }
 }
 
+/// Instant in time, as nanoseconds since the UNIX epoch in UTC
+#[derive(
+Copy,
+Clone,
+Eq,
+PartialEq,
+Ord,
+PartialOrd,
+serde_derive::Deserialize,
+serde_derive::Serialize,
+Hash,
+Debug,
+)]
+pub struct Timestamp(pub i64);
+
+impl Timestamp {
+    pub(crate) fn from_datetime<Tz: TimeZone>(dt: &DateTime<Tz>) -> Option<Self> {
+        dt.timestamp_nanos_opt().map(Timestamp)
+    }
+    pub(crate) fn to_datetime(self) -> DateTime<Utc> {
+        Utc.timestamp_nanos(self.0)
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = self
+            .to_datetime()
+            .to_rfc3339_opts(SecondsFormat::AutoSi, true);
+        f.write_str(&s)
+    }
+}
+
+/// Half-open interval of time `[start, end)`, for modelling the period in which a fact is valid
+#[derive(
+Copy,
+Clone,
+Eq,
+PartialEq,
+Ord,
+PartialOrd,
+serde_derive::Deserialize,
+serde_derive::Serialize,
+Hash,
+Debug,
+)]
+pub struct Interval {
+    /// Inclusive start, `Timestamp(i64::MIN)` if unbounded
+    pub start: Timestamp,
+    /// Exclusive end, `Timestamp(i64::MAX)` if unbounded
+    pub end: Timestamp,
+}
+
+impl Interval {
+    pub(crate) const UNBOUNDED_START: Timestamp = Timestamp(i64::MIN);
+    pub(crate) const UNBOUNDED_END: Timestamp = Timestamp(i64::MAX);
+
+    /// Bounds as optional timestamps, `None` if unbounded
+    pub fn bounds(&self) -> (Option<Timestamp>, Option<Timestamp>) {
+        (
+            (self.start != Self::UNBOUNDED_START).then_some(self.start),
+            (self.end != Self::UNBOUNDED_END).then_some(self.end),
+        )
+    }
+    pub(crate) fn contains_ts(&self, ts: Timestamp) -> bool {
+        self.start <= ts && ts < self.end
+    }
+    pub(crate) fn contains(&self, other: &Interval) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+    pub(crate) fn overlaps(&self, other: &Interval) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (start, end) = self.bounds();
+        f.write_str("interval(")?;
+        match start {
+            None => f.write_str("null")?,
+            Some(ts) => write!(f, "{:?}", ts.to_string())?,
+        }
+        f.write_str(", ")?;
+        match end {
+            None => f.write_str("null")?,
+            Some(ts) => write!(f, "{:?}", ts.to_string())?,
+        }
+        f.write_str(")")
+    }
+}
+
+/// Point on the surface of the earth, as latitude and longitude in degrees
+///
+/// Points are sorted along a Z-order curve first, so that the points inside a bounding box
+/// all sort between its south-west and north-east corners.
+#[derive(Copy, Clone, serde_derive::Deserialize, serde_derive::Serialize, Debug)]
+pub struct GeoPoint {
+    lat: f64,
+    lon: f64,
+}
+
+impl GeoPoint {
+    /// `None` unless the latitude is within `[-90, 90]` and the longitude within `[-180, 180]`
+    pub fn new(lat: f64, lon: f64) -> Option<Self> {
+        ((-90. ..=90.).contains(&lat) && (-180. ..=180.).contains(&lon)).then_some(Self {
+            // no negative zeros, so that equal coordinates are also equal in storage
+            lat: lat + 0.,
+            lon: lon + 0.,
+        })
+    }
+    /// Latitude in degrees
+    pub fn lat(&self) -> f64 {
+        self.lat
+    }
+    /// Longitude in degrees
+    pub fn lon(&self) -> f64 {
+        self.lon
+    }
+    /// Position on the Z-order curve, interleaving the bits of both coordinates.
+    /// Non-decreasing in each coordinate when the other one is fixed.
+    pub(crate) fn z_order(&self) -> u64 {
+        fn quantise(x: f64, lo: f64, hi: f64) -> u64 {
+            ((x - lo) / (hi - lo) * u32::MAX as f64) as u32 as u64
+        }
+        fn spread(x: u64) -> u64 {
+            let x = (x | (x << 16)) & 0x0000_FFFF_0000_FFFF;
+            let x = (x | (x << 8)) & 0x00FF_00FF_00FF_00FF;
+            let x = (x | (x << 4)) & 0x0F0F_0F0F_0F0F_0F0F;
+            let x = (x | (x << 2)) & 0x3333_3333_3333_3333;
+            (x | (x << 1)) & 0x5555_5555_5555_5555
+        }
+        spread(quantise(self.lat, -90., 90.)) << 1 | spread(quantise(self.lon, -180., 180.))
+    }
+    /// Mean radius of the earth in metres
+    pub(crate) const EARTH_RADIUS: f64 = 6_371_008.8;
+
+    /// Great-circle distance in metres
+    pub(crate) fn distance(&self, other: &GeoPoint) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.lon - self.lon).to_radians();
+        let h = (d_lat / 2.).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.).sin().powi(2);
+        2. * Self::EARTH_RADIUS * h.sqrt().min(1.).asin()
+    }
+    /// Whether the point is inside the box with the given south-west and north-east corners.
+    /// The box crosses the antimeridian if its western longitude is larger than its eastern one.
+    pub(crate) fn in_box(&self, sw: &GeoPoint, ne: &GeoPoint) -> bool {
+        let in_lon = if sw.lon <= ne.lon {
+            sw.lon <= self.lon && self.lon <= ne.lon
+        } else {
+            sw.lon <= self.lon || self.lon <= ne.lon
+        };
+        sw.lat <= self.lat && self.lat <= ne.lat && in_lon
+    }
+    /// South-west and north-east corners of a box containing every point within `meters`.
+    /// The box spans all longitudes if the circle reaches a pole or the antimeridian.
+    pub(crate) fn bounding_box(&self, meters: f64) -> (GeoPoint, GeoPoint) {
+        // slightly widened, so that rounding cannot leave out points at exactly that distance
+        let angle = (meters / Self::EARTH_RADIUS * (1. + 1e-9)).max(0.);
+        let min_lat = self.lat - angle.to_degrees();
+        let max_lat = self.lat + angle.to_degrees();
+        let mut lon_range = (-180., 180.);
+        if -90. < min_lat && max_lat < 90. {
+            let lon_delta = (angle.sin() / self.lat.to_radians().cos()).asin().to_degrees();
+            if -180. <= self.lon - lon_delta && self.lon + lon_delta <= 180. {
+                lon_range = (self.lon - lon_delta, self.lon + lon_delta);
+            }
+        }
+        (
+            GeoPoint::new(min_lat.max(-90.), lon_range.0).unwrap(),
+            GeoPoint::new(max_lat.min(90.), lon_range.1).unwrap(),
+        )
+    }
+}
+
+impl PartialEq for GeoPoint {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for GeoPoint {}
+
+impl Ord for GeoPoint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.z_order()
+            .cmp(&other.z_order())
+            .then_with(|| self.lat.total_cmp(&other.lat))
+            .then_with(|| self.lon.total_cmp(&other.lon))
+    }
+}
+
+impl PartialOrd for GeoPoint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hash for GeoPoint {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.lat.to_bits().hash(state);
+        self.lon.to_bits().hash(state);
+    }
+}
+
+impl Display for GeoPoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "point({}, {})", self.lat, self.lon)
+    }
+}
+
 /// A Value in the database
 #[derive(
 Clone, PartialEq, Eq, PartialOrd, Ord, serde_derive::Deserialize, serde_derive::Serialize, Hash,
@@ -169,6 +396,14 @@ pub enum DataValue {
     Json(JsonData),
     /// validity,
     Validity(Validity),
+    /// timestamp with nanosecond precision
+    Timestamp(Timestamp),
+    /// decimal number of arbitrary precision
+    Decimal(Decimal),
+    /// interval of time, for validity periods
+    Interval(Interval),
+    /// point on the earth, for geospatial queries
+    Point(GeoPoint),
     /// bottom type, used internally only
     Bot,
 }
@@ -644,6 +879,10 @@ impl Display for DataValue {
                     write!(f, "json({})", j.0)
                 }
             }
+            DataValue::Timestamp(ts) => write!(f, "parse_ts({:?})", ts.to_string()),
+            DataValue::Decimal(d) => write!(f, "to_decimal({:?})", d.to_string()),
+            DataValue::Interval(iv) => write!(f, "{iv}"),
+            DataValue::Point(p) => write!(f, "{p}"),
         }
     }
 }
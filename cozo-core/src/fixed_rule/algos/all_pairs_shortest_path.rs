@@ -10,17 +10,17 @@ use graph::prelude::{DirectedCsrGraph, DirectedNeighborsWithValues, Graph};
 use std::cmp::Reverse;
 use std::collections::BTreeMap;
 
-use itertools::Itertools;
 use miette::Result;
 use ordered_float::OrderedFloat;
 use priority_queue::PriorityQueue;
+use rand::seq::index;
+use rand::thread_rng;
 use rayon::prelude::*;
 use smartstring::{LazyCompact, SmartString};
 
 use crate::data::expr::Expr;
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
-use crate::fixed_rule::algos::shortest_path_dijkstra::dijkstra_keep_ties;
 use crate::fixed_rule::{FixedRule, FixedRulePayload};
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
@@ -37,48 +37,47 @@ impl FixedRule for BetweennessCentrality {
     ) -> Result<()> {
         let edges = payload.get_input(0)?;
         let undirected = payload.bool_option("undirected", Some(false))?;
+        let samples = if payload.manifest.options.contains_key("samples") {
+            Some(payload.pos_integer_option("samples", None)?)
+        } else {
+            None
+        };
 
-        let (graph, indices, _inv_indices) = edges.as_directed_weighted_graph(undirected, false)?;
+        let graph_data = edges.as_cached_directed_weighted_graph(undirected, false)?;
+        let (graph, indices, _inv_indices) = &*graph_data;
 
         let n = graph.node_count();
         if n == 0 {
             return Ok(());
         }
 
-        let it = (0..n).into_par_iter();
+        // With sampling, only a random subset of the nodes are used as sources,
+        // and the accumulated dependencies are scaled up to estimate the full sum.
+        let (sources, scale): (Vec<u32>, f64) = match samples {
+            Some(k) if k < n as usize => (
+                index::sample(&mut thread_rng(), n as usize, k)
+                    .into_iter()
+                    .map(|i| i as u32)
+                    .collect(),
+                n as f64 / k as f64,
+            ),
+            _ => ((0..n).collect(), 1.),
+        };
 
-        let centrality_segs: Vec<_> = it
-            .map(|start| -> Result<BTreeMap<u32, f32>> {
-                let res_for_start =
-                    dijkstra_keep_ties(&graph, start, &(), &(), &(), poison.clone())?;
-                let mut ret: BTreeMap<u32, f32> = Default::default();
-                let grouped = res_for_start.into_iter().group_by(|(n, _, _)| *n);
-                for (_, grp) in grouped.into_iter() {
-                    let grp = grp.collect_vec();
-                    let l = grp.len() as f32;
-                    for (_, _, path) in grp {
-                        if path.len() < 3 {
-                            continue;
-                        }
-                        for middle in path.iter().take(path.len() - 1).skip(1) {
-                            let entry = ret.entry(*middle).or_default();
-                            *entry += 1. / l;
-                        }
-                    }
-                }
-                Ok(ret)
-            })
+        let centrality_segs: Vec<_> = sources
+            .into_par_iter()
+            .map(|start| brandes_dependencies(graph, start, poison.clone()))
             .collect::<Result<_>>()?;
-        let mut centrality: Vec<f32> = vec![0.; n as usize];
-        for m in centrality_segs {
-            for (k, v) in m {
-                centrality[k as usize] += v;
+        let mut centrality: Vec<f64> = vec![0.; n as usize];
+        for seg in centrality_segs {
+            for (c, d) in centrality.iter_mut().zip(seg) {
+                *c += d;
             }
         }
 
         for (i, s) in centrality.into_iter().enumerate() {
             let node = indices[i].clone();
-            out.put(vec![node, (s as f64).into()]);
+            out.put(vec![node, (s * scale).into()]);
         }
 
         Ok(())
@@ -94,6 +93,60 @@ impl FixedRule for BetweennessCentrality {
     }
 }
 
+/// Single-source step of Brandes' algorithm: the dependency of `start` on every node,
+/// i.e. the sum over all targets of the fraction of shortest paths passing through the node.
+fn brandes_dependencies(
+    edges: &DirectedCsrGraph<u32, (), f32>,
+    start: u32,
+    poison: Poison,
+) -> Result<Vec<f64>> {
+    let n = edges.node_count() as usize;
+    let mut distance = vec![f32::INFINITY; n];
+    let mut sigma = vec![0f64; n];
+    let mut predecessors: Vec<Vec<u32>> = vec![vec![]; n];
+    let mut settled = vec![false; n];
+    let mut order = Vec::with_capacity(n);
+    let mut pq = PriorityQueue::new();
+
+    distance[start as usize] = 0.;
+    sigma[start as usize] = 1.;
+    pq.push(start, Reverse(OrderedFloat(0.)));
+
+    while let Some((node, Reverse(OrderedFloat(cost)))) = pq.pop() {
+        settled[node as usize] = true;
+        order.push(node);
+
+        for target in edges.out_neighbors_with_values(node) {
+            let nxt_node = target.target;
+            if settled[nxt_node as usize] {
+                continue;
+            }
+            let nxt_cost = cost + target.value;
+            let known_cost = distance[nxt_node as usize];
+            if nxt_cost < known_cost {
+                pq.push_increase(nxt_node, Reverse(OrderedFloat(nxt_cost)));
+                distance[nxt_node as usize] = nxt_cost;
+                sigma[nxt_node as usize] = sigma[node as usize];
+                predecessors[nxt_node as usize] = vec![node];
+            } else if nxt_cost == known_cost {
+                sigma[nxt_node as usize] += sigma[node as usize];
+                predecessors[nxt_node as usize].push(node);
+            }
+        }
+        poison.check()?;
+    }
+
+    let mut delta = vec![0f64; n];
+    for node in order.into_iter().rev() {
+        let coeff = (1. + delta[node as usize]) / sigma[node as usize];
+        for pred in &predecessors[node as usize] {
+            delta[*pred as usize] += sigma[*pred as usize] * coeff;
+        }
+    }
+    delta[start as usize] = 0.;
+    Ok(delta)
+}
+
 pub(crate) struct ClosenessCentrality;
 
 impl FixedRule for ClosenessCentrality {
@@ -106,7 +159,8 @@ impl FixedRule for ClosenessCentrality {
         let edges = payload.get_input(0)?;
         let undirected = payload.bool_option("undirected", Some(false))?;
 
-        let (graph, indices, _inv_indices) = edges.as_directed_weighted_graph(undirected, false)?;
+        let graph_data = edges.as_cached_directed_weighted_graph(undirected, false)?;
+        let (graph, indices, _inv_indices) = &*graph_data;
 
         let n = graph.node_count();
         if n == 0 {
@@ -116,9 +170,13 @@ impl FixedRule for ClosenessCentrality {
 
         let res: Vec<_> = it
             .map(|start| -> Result<f32> {
-                let distances = dijkstra_cost_only(&graph, start, poison.clone())?;
+                let distances = dijkstra_cost_only(graph, start, poison.clone())?;
                 let total_dist: f32 = distances.iter().filter(|d| d.is_finite()).cloned().sum();
-                let nc: f32 = distances.iter().filter(|d| d.is_finite()).count() as f32;
+                // the start node itself is not counted as reachable
+                let nc: f32 = distances.iter().filter(|d| d.is_finite()).count() as f32 - 1.;
+                if total_dist == 0. {
+                    return Ok(0.);
+                }
                 Ok(nc * nc / total_dist / (n - 1) as f32)
             })
             .collect::<Result<_>>()?;
@@ -142,6 +200,55 @@ impl FixedRule for ClosenessCentrality {
     }
 }
 
+pub(crate) struct HarmonicCentrality;
+
+impl FixedRule for HarmonicCentrality {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let undirected = payload.bool_option("undirected", Some(false))?;
+
+        let graph_data = edges.as_cached_directed_weighted_graph(undirected, false)?;
+        let (graph, indices, _inv_indices) = &*graph_data;
+
+        let n = graph.node_count();
+        if n == 0 {
+            return Ok(());
+        }
+        let it = (0..n).into_par_iter();
+
+        let res: Vec<_> = it
+            .map(|start| -> Result<f64> {
+                let distances = dijkstra_cost_only(graph, start, poison.clone())?;
+                Ok(distances
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, d)| *i != start as usize && d.is_finite())
+                    .map(|(_, d)| 1. / *d as f64)
+                    .sum())
+            })
+            .collect::<Result<_>>()?;
+        for (idx, centrality) in res.into_iter().enumerate() {
+            out.put(vec![indices[idx].clone(), DataValue::from(centrality)]);
+            poison.check()?;
+        }
+        Ok(())
+    }
+
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
 pub(crate) fn dijkstra_cost_only(
     edges: &DirectedCsrGraph<u32, (), f32>,
     start: u32,
@@ -174,3 +281,130 @@ pub(crate) fn dijkstra_cost_only(
 
     Ok(distance)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    #[test]
+    fn test_betweenness_centrality() {
+        let db = DbInstance::default();
+        let scores = |query: &str| {
+            db.run_default(query)
+                .unwrap()
+                .rows
+                .into_iter()
+                .map(|row| {
+                    (
+                        row[0].get_str().unwrap().to_string(),
+                        row[1].get_float().unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // tied shortest paths share the credit
+        let res = scores(
+            r#"
+            edges[] <- [['a', 'b'], ['a', 'c'], ['b', 'd'], ['c', 'd'], ['d', 'e']]
+            ?[node, score] <~ BetweennessCentrality(edges[])
+            "#,
+        );
+        assert_eq!(
+            res,
+            vec![
+                ("a".to_string(), 0.),
+                ("b".to_string(), 1.),
+                ("c".to_string(), 1.),
+                ("d".to_string(), 3.),
+                ("e".to_string(), 0.)
+            ]
+        );
+
+        // weights decide which paths are shortest
+        let res = scores(
+            r#"
+            edges[] <- [['a', 'b', 1], ['b', 'c', 1], ['a', 'c', 3]]
+            ?[node, score] <~ BetweennessCentrality(edges[], undirected: true)
+            "#,
+        );
+        assert_eq!(
+            res,
+            vec![
+                ("a".to_string(), 0.),
+                ("b".to_string(), 2.),
+                ("c".to_string(), 0.)
+            ]
+        );
+
+        // sampling every node is exact, sampling fewer still scores every node
+        let res = scores(
+            r#"
+            edges[] <- [['a', 'b'], ['b', 'c'], ['c', 'd']]
+            ?[node, score] <~ BetweennessCentrality(edges[], samples: 10)
+            "#,
+        );
+        assert_eq!(
+            res,
+            vec![
+                ("a".to_string(), 0.),
+                ("b".to_string(), 2.),
+                ("c".to_string(), 2.),
+                ("d".to_string(), 0.)
+            ]
+        );
+        let res = scores(
+            r#"
+            edges[] <- [['a', 'b'], ['b', 'c'], ['c', 'd']]
+            ?[node, score] <~ BetweennessCentrality(edges[], samples: 2)
+            "#,
+        );
+        assert_eq!(res.len(), 4);
+        assert!(res.iter().all(|(_, s)| *s == 0. || *s == 2. || *s == 4.));
+        assert!(db
+            .run_default(
+                r#"
+                edges[] <- [['a', 'b']]
+                ?[node, score] <~ BetweennessCentrality(edges[], samples: 0)
+                "#
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_closeness_and_harmonic_centrality() {
+        let db = DbInstance::default();
+        let scores = |query: &str| {
+            db.run_default(query)
+                .unwrap()
+                .rows
+                .into_iter()
+                .map(|row| row[1].get_float().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let res = scores(
+            r#"
+            edges[] <- [['a', 'b'], ['b', 'c']]
+            ?[node, score] <~ ClosenessCentrality(edges[])
+            "#,
+        );
+        assert_eq!(res, vec![(2f32 / 3.) as f64, 0.5, 0.]);
+        let res = scores(
+            r#"
+            edges[] <- [['a', 'b'], ['b', 'c']]
+            ?[node, score] <~ HarmonicCentrality(edges[])
+            "#,
+        );
+        assert_eq!(res, vec![1.5, 1., 0.]);
+
+        // weighted edges, and nodes in other components contribute nothing
+        let res = scores(
+            r#"
+            edges[] <- [['a', 'b', 2], ['b', 'c', 2], ['a', 'c', 1], ['x', 'y', 1]]
+            ?[node, score] <~ HarmonicCentrality(edges[], undirected: true)
+            "#,
+        );
+        assert_eq!(res, vec![1.5, 1., 1.5, 1., 1.]);
+    }
+}
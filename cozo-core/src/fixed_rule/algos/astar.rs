@@ -111,8 +111,10 @@ fn astar(
         PriorityQueue::new();
     open_set.push(start_node.clone(), (Reverse(OrderedFloat(0.)), 0));
     let mut sub_priority: usize = 0;
-    while let Some((node, (Reverse(OrderedFloat(cost)), _))) = open_set.pop() {
+    while let Some((node, _)) = open_set.pop() {
         if node == *goal_node {
+            // the priority includes the heuristic, the cost of the path does not
+            let cost = g_score[&node];
             let mut current = node;
             let mut ret = vec![];
             while current != *start_node {
@@ -178,3 +180,59 @@ fn astar(
     }
     Ok((f64::INFINITY, vec![]))
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::DbInstance;
+
+    #[test]
+    fn test_astar() {
+        let db = DbInstance::default();
+        db.run_default(
+            r#"
+            ?[name, x, y] <- [['a', 0, 0], ['b', 1, 0], ['c', 1, 1], ['d', 2, 1], ['e', 0, 5]]
+            :create place {name => x, y}
+            "#,
+        )
+        .unwrap();
+        db.run_default(
+            r#"
+            ?[fr, to, dist] <- [['a', 'b', 1.0], ['b', 'c', 1.0], ['c', 'd', 1.0],
+                                ['a', 'e', 5.0], ['e', 'd', 5.0], ['b', 'd', 3.0]]
+            :create link {fr, to => dist}
+            "#,
+        )
+        .unwrap();
+
+        let query = |heuristic: &str| {
+            db.run_default(&format!(
+                r#"
+                start[] <- [['a']]
+                goal[name, x, y] := *place{{name, x, y}}, name = 'd'
+                ?[fr, to, cost, path] <~ ShortestPathAStar(*link[], *place[node, x, y], start[],
+                                                           goal[g, gx, gy], heuristic: {heuristic})
+                "#
+            ))
+            .unwrap()
+            .into_json()["rows"]
+                .clone()
+        };
+        let expected = json!([["a", "d", 3.0, ["a", "b", "c", "d"]]]);
+        assert_eq!(query("sqrt((x - gx) ^ 2 + (y - gy) ^ 2)"), expected);
+        assert_eq!(query("0"), expected);
+        // a heuristic that does not vanish at the goal does not leak into the reported cost
+        assert_eq!(query("0.5"), expected);
+        assert!(db
+            .run_default(
+                r#"
+                start[] <- [['a']]
+                goal[] <- [['d']]
+                ?[fr, to, cost, path] <~ ShortestPathAStar(*link[], *place[node, x, y], start[],
+                                                           goal[g], heuristic: 'far')
+                "#
+            )
+            .is_err());
+    }
+}
@@ -14,7 +14,7 @@ use smartstring::{LazyCompact, SmartString};
 use crate::data::expr::{eval_bytecode_pred, Expr};
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
-use crate::fixed_rule::{FixedRule, FixedRulePayload, NodeNotFoundError};
+use crate::fixed_rule::{FixedRule, FixedRuleInputRelation, FixedRulePayload, NodeNotFoundError};
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
 use crate::runtime::temp_store::RegularTempStore;
@@ -31,8 +31,17 @@ impl FixedRule for Bfs {
         let edges = payload.get_input(0)?.ensure_min_len(2)?;
         let nodes = payload.get_input(1)?;
         let starting_nodes = payload.get_input(2).unwrap_or(nodes);
-        let limit = payload.pos_integer_option("limit", Some(1))?;
+        let max_depth = if payload.manifest.options.contains_key("max_depth") {
+            Some(payload.non_neg_integer_option("max_depth", None)?)
+        } else {
+            None
+        };
+        if !payload.manifest.options.contains_key("condition") {
+            let limit = payload.pos_integer_option("limit", Some(i64::MAX as usize))?;
+            return traverse(edges, starting_nodes, max_depth, limit, out, poison);
+        }
         let mut condition = payload.expr_option("condition", None)?;
+        let limit = payload.pos_integer_option("limit", Some(1))?;
         let binding_map = nodes.get_binding_map(0);
         condition.fill_binding_indices(&binding_map)?;
         let condition_bytecode = condition.compile()?;
@@ -53,10 +62,13 @@ impl FixedRule for Bfs {
             }
             visited.insert(starting_node.clone());
 
-            let mut queue: VecDeque<DataValue> = VecDeque::default();
-            queue.push_front(starting_node.clone());
+            let mut queue: VecDeque<(DataValue, usize)> = VecDeque::default();
+            queue.push_front((starting_node.clone(), 0));
 
-            while let Some(candidate) = queue.pop_back() {
+            while let Some((candidate, depth)) = queue.pop_back() {
+                if max_depth == Some(depth) {
+                    continue;
+                }
                 for edge in edges.prefix_iter(&candidate)? {
                     let edge = edge?;
                     let to_node = &edge[1];
@@ -91,7 +103,7 @@ impl FixedRule for Bfs {
                         }
                     }
 
-                    queue.push_front(to_node.clone());
+                    queue.push_front((to_node.clone(), depth + 1));
                     poison.check()?;
                 }
             }
@@ -121,3 +133,136 @@ impl FixedRule for Bfs {
         Ok(3)
     }
 }
+
+/// Without a condition, every node reachable from the starting nodes is returned
+/// together with its depth and its predecessor, which is null for the starting nodes.
+#[allow(clippy::mutable_key_type)]
+fn traverse(
+    edges: FixedRuleInputRelation<'_, '_>,
+    starting_nodes: FixedRuleInputRelation<'_, '_>,
+    max_depth: Option<usize>,
+    limit: usize,
+    out: &mut RegularTempStore,
+    poison: Poison,
+) -> Result<()> {
+    let mut visited: BTreeSet<DataValue> = Default::default();
+    let mut queue: VecDeque<(DataValue, usize)> = VecDeque::default();
+    let mut count = 0;
+
+    for node_tuple in starting_nodes.iter()? {
+        let starting_node = &node_tuple?[0];
+        if visited.insert(starting_node.clone()) {
+            out.put(vec![
+                starting_node.clone(),
+                DataValue::from(0),
+                DataValue::Null,
+            ]);
+            count += 1;
+            if count >= limit {
+                return Ok(());
+            }
+            queue.push_front((starting_node.clone(), 0));
+        }
+    }
+
+    while let Some((candidate, depth)) = queue.pop_back() {
+        if max_depth == Some(depth) {
+            continue;
+        }
+        for edge in edges.prefix_iter(&candidate)? {
+            let to_node = &edge?[1];
+            if visited.insert(to_node.clone()) {
+                out.put(vec![
+                    to_node.clone(),
+                    DataValue::from((depth + 1) as i64),
+                    candidate.clone(),
+                ]);
+                count += 1;
+                if count >= limit {
+                    return Ok(());
+                }
+                queue.push_front((to_node.clone(), depth + 1));
+            }
+            poison.check()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::DbInstance;
+
+    const EDGES: &str = r#"
+        edges[fr, to] <- [['a', 'b'], ['b', 'c'], ['c', 'd'], ['a', 'e'], ['e', 'c'], ['x', 'y']]
+        nodes[n] <- [['a'], ['b'], ['c'], ['d'], ['e'], ['x'], ['y']]
+        start[] <- [['a']]
+    "#;
+
+    #[test]
+    fn test_bfs_with_condition() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(&format!(
+                "{EDGES} ?[fr, to, path] <~ BFS(edges[], nodes[n], start[], condition: n == 'd')"
+            ))
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([["a", "d", ["a", "b", "c", "d"]]]));
+        let res = db
+            .run_default(&format!(
+                "{EDGES} ?[fr, to, path] <~ BFS(edges[], nodes[n], start[], condition: n == 'd', max_depth: 2)"
+            ))
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([]));
+    }
+
+    #[test]
+    fn test_bfs_traversal() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(&format!(
+                "{EDGES} ?[node, depth, pred] <~ BFS(edges[], start[]) :order depth, node"
+            ))
+            .unwrap()
+            .into_json();
+        assert_eq!(
+            res["rows"],
+            json!([
+                ["a", 0, null],
+                ["b", 1, "a"],
+                ["e", 1, "a"],
+                ["c", 2, "b"],
+                ["d", 3, "c"]
+            ])
+        );
+        let res = db
+            .run_default(&format!(
+                "{EDGES} ?[node, depth, pred] <~ BFS(edges[], nodes[], max_depth: 1) :order depth, node"
+            ))
+            .unwrap()
+            .into_json();
+        assert_eq!(
+            res["rows"],
+            json!([
+                ["a", 0, null],
+                ["b", 0, null],
+                ["c", 0, null],
+                ["d", 0, null],
+                ["e", 0, null],
+                ["x", 0, null],
+                ["y", 0, null]
+            ])
+        );
+        let res = db
+            .run_default(&format!(
+                "{EDGES} ?[node, depth, pred] <~ BFS(edges[], start[], max_depth: 1)"
+            ))
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"].as_array().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,221 @@
+/*
+ * Copyright 2022, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::{BTreeMap, VecDeque};
+
+use miette::Result;
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::Expr;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::{FixedRule, FixedRulePayload};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+pub(crate) struct MaximumBipartiteMatching;
+
+impl FixedRule for MaximumBipartiteMatching {
+    #[allow(clippy::mutable_key_type)]
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?.ensure_min_len(2)?;
+
+        // the two sides are indexed separately, so the same value may occur on both
+        let mut left_indices: Vec<DataValue> = vec![];
+        let mut left_inv_indices: BTreeMap<DataValue, usize> = Default::default();
+        let mut right_indices: Vec<DataValue> = vec![];
+        let mut right_inv_indices: BTreeMap<DataValue, usize> = Default::default();
+        let mut adj: Vec<Vec<usize>> = vec![];
+        for tuple in edges.iter()? {
+            let tuple = tuple?;
+            let left = *left_inv_indices.entry(tuple[0].clone()).or_insert_with(|| {
+                left_indices.push(tuple[0].clone());
+                adj.push(vec![]);
+                left_indices.len() - 1
+            });
+            let right = *right_inv_indices
+                .entry(tuple[1].clone())
+                .or_insert_with(|| {
+                    right_indices.push(tuple[1].clone());
+                    right_indices.len() - 1
+                });
+            adj[left].push(right);
+            poison.check()?;
+        }
+
+        let matching = hopcroft_karp(&adj, right_indices.len(), poison)?;
+        for (left, right) in matching.into_iter().enumerate() {
+            if let Some(right) = right {
+                out.put(vec![
+                    left_indices[left].clone(),
+                    right_indices[right].clone(),
+                ]);
+            }
+        }
+        Ok(())
+    }
+
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+/// Returns the partner of every left node in a maximum matching.
+fn hopcroft_karp(adj: &[Vec<usize>], n_right: usize, poison: Poison) -> Result<Vec<Option<usize>>> {
+    let n_left = adj.len();
+    let mut pair_left: Vec<Option<usize>> = vec![None; n_left];
+    let mut pair_right: Vec<Option<usize>> = vec![None; n_right];
+    let mut dist = vec![usize::MAX; n_left];
+
+    loop {
+        // layer the left nodes by their distance from a free left node along alternating paths
+        let mut queue = VecDeque::new();
+        for (left, pair) in pair_left.iter().enumerate() {
+            if pair.is_none() {
+                dist[left] = 0;
+                queue.push_back(left);
+            } else {
+                dist[left] = usize::MAX;
+            }
+        }
+        let mut found_free_right = false;
+        while let Some(left) = queue.pop_front() {
+            for right in &adj[left] {
+                match pair_right[*right] {
+                    None => found_free_right = true,
+                    Some(next) => {
+                        if dist[next] == usize::MAX {
+                            dist[next] = dist[left] + 1;
+                            queue.push_back(next);
+                        }
+                    }
+                }
+            }
+        }
+        if !found_free_right {
+            break;
+        }
+
+        // augment along vertex-disjoint shortest paths
+        let mut edge_pos = vec![0; n_left];
+        for root in 0..n_left {
+            if pair_left[root].is_none() {
+                augment(
+                    root,
+                    adj,
+                    &mut dist,
+                    &mut edge_pos,
+                    &mut pair_left,
+                    &mut pair_right,
+                );
+            }
+        }
+        poison.check()?;
+    }
+    Ok(pair_left)
+}
+
+// iterative, since augmenting paths can be as long as the number of nodes
+fn augment(
+    root: usize,
+    adj: &[Vec<usize>],
+    dist: &mut [usize],
+    edge_pos: &mut [usize],
+    pair_left: &mut [Option<usize>],
+    pair_right: &mut [Option<usize>],
+) {
+    let mut lefts = vec![root];
+    let mut rights: Vec<usize> = vec![];
+    while let Some(left) = lefts.last().copied() {
+        if edge_pos[left] < adj[left].len() {
+            let right = adj[left][edge_pos[left]];
+            edge_pos[left] += 1;
+            match pair_right[right] {
+                None => {
+                    rights.push(right);
+                    for (l, r) in lefts.iter().zip(rights.iter()) {
+                        pair_left[*l] = Some(*r);
+                        pair_right[*r] = Some(*l);
+                    }
+                    return;
+                }
+                Some(next) => {
+                    if dist[next] != usize::MAX && dist[next] == dist[left] + 1 {
+                        rights.push(right);
+                        lefts.push(next);
+                    }
+                }
+            }
+        } else {
+            // dead end, do not visit again in this phase
+            dist[left] = usize::MAX;
+            lefts.pop();
+            rights.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::DbInstance;
+
+    #[test]
+    fn test_bipartite_matching() {
+        let db = DbInstance::default();
+        // matching 'a' to 'x' first would leave 'b' unmatched
+        let res = db
+            .run_default(
+                r#"
+                edges[] <- [['a', 'x'], ['a', 'y'], ['b', 'x']]
+                ?[worker, job] <~ MaximumBipartiteMatching(edges[])
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([["a", "y"], ["b", "x"]]));
+
+        // the two sides are distinct even when their values coincide
+        let res = db
+            .run_default(
+                r#"
+                edges[] <- [[1, 1], [1, 2], [2, 1]]
+                ?[l, r] <~ MaximumBipartiteMatching(edges[])
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([[1, 2], [2, 1]]));
+
+        // a larger graph
+        let res = db
+            .run_default(
+                r#"
+                edges[l, r] := l in int_range(50000), r = l
+                edges[l, r] := l in int_range(1, 50000), r = l - 1
+                ?[count(l)] := m[l, r]
+                m[l, r] <~ MaximumBipartiteMatching(edges[])
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([[50000]]));
+    }
+}
@@ -0,0 +1,176 @@
+/*
+ * Copyright 2022, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::BTreeMap;
+
+use miette::Result;
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::Expr;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::{FixedRule, FixedRulePayload};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+pub(crate) struct ConnectedComponents;
+
+impl FixedRule for ConnectedComponents {
+    #[allow(clippy::mutable_key_type)]
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?.ensure_min_len(2)?;
+
+        let mut indices: Vec<DataValue> = vec![];
+        let mut inv_indices: BTreeMap<DataValue, u32> = Default::default();
+        let mut uf = UnionFind::default();
+        let mut get_idx = |v: &DataValue, uf: &mut UnionFind| -> u32 {
+            if let Some(idx) = inv_indices.get(v) {
+                *idx
+            } else {
+                let idx = uf.add();
+                inv_indices.insert(v.clone(), idx);
+                indices.push(v.clone());
+                idx
+            }
+        };
+
+        for tuple in edges.iter()? {
+            let tuple = tuple?;
+            let from = get_idx(&tuple[0], &mut uf);
+            let to = get_idx(&tuple[1], &mut uf);
+            uf.union(from, to);
+            poison.check()?;
+        }
+        if let Ok(nodes) = payload.get_input(1) {
+            for tuple in nodes.iter()? {
+                let tuple = tuple?;
+                get_idx(&tuple[0], &mut uf);
+                poison.check()?;
+            }
+        }
+
+        // components are numbered in the order their first node was seen
+        let mut component_ids: BTreeMap<u32, i64> = Default::default();
+        for (idx, node) in indices.into_iter().enumerate() {
+            let root = uf.find(idx as u32);
+            let next_id = component_ids.len() as i64;
+            let id = *component_ids.entry(root).or_insert(next_id);
+            out.put(vec![node, DataValue::from(id)]);
+        }
+
+        Ok(())
+    }
+
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+/// Disjoint sets over `0..n`, with union by size and path halving.
+#[derive(Default)]
+pub(crate) struct UnionFind {
+    parent: Vec<u32>,
+    size: Vec<u32>,
+}
+
+impl UnionFind {
+    pub(crate) fn new(n: u32) -> Self {
+        Self {
+            parent: (0..n).collect(),
+            size: vec![1; n as usize],
+        }
+    }
+    pub(crate) fn add(&mut self) -> u32 {
+        let idx = self.parent.len() as u32;
+        self.parent.push(idx);
+        self.size.push(1);
+        idx
+    }
+    pub(crate) fn find(&mut self, mut idx: u32) -> u32 {
+        while self.parent[idx as usize] != idx {
+            let grandparent = self.parent[self.parent[idx as usize] as usize];
+            self.parent[idx as usize] = grandparent;
+            idx = grandparent;
+        }
+        idx
+    }
+    /// Returns `false` if `a` and `b` were already in the same set.
+    pub(crate) fn union(&mut self, a: u32, b: u32) -> bool {
+        let mut a = self.find(a);
+        let mut b = self.find(b);
+        if a == b {
+            return false;
+        }
+        if self.size[a as usize] < self.size[b as usize] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b as usize] = a;
+        self.size[a as usize] += self.size[b as usize];
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::DbInstance;
+
+    #[test]
+    fn test_connected_components() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(
+                r#"
+                edges[] <- [['a', 'b'], ['c', 'b'], ['d', 'e'], ['f', 'f'], ['e', 'g']]
+                nodes[] <- [['a'], ['lonely']]
+                ?[node, comp] <~ ConnectedComponents(edges[], nodes[])
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        // edge direction is ignored
+        assert_eq!(
+            res["rows"],
+            json!([
+                ["a", 0],
+                ["b", 0],
+                ["c", 0],
+                ["d", 1],
+                ["e", 1],
+                ["f", 2],
+                ["g", 1],
+                ["lonely", 3]
+            ])
+        );
+
+        // a long chain, which would be deep recursion for a DFS-based approach
+        let res = db
+            .run_default(
+                r#"
+                edges[i, j] := i in int_range(200000), j = i + 1
+                ?[count(node), count_unique(comp)] := c[node, comp]
+                c[node, comp] <~ ConnectedComponents(edges[])
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([[200001, 1]]));
+    }
+}
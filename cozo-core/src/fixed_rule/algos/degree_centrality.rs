@@ -74,3 +74,38 @@ impl FixedRule for DegreeCentrality {
         Ok(4)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::DbInstance;
+
+    #[test]
+    fn test_degree_centrality() {
+        let db = DbInstance::default();
+        db.run_default(
+            r#"?[fr, to] <- [['a', 'b'], ['a', 'c'], ['b', 'c'], ['c', 'c']] :create e {fr, to}"#,
+        )
+        .unwrap();
+        let res = db
+            .run_default(
+                r#"
+                nodes[] <- [['a'], ['lonely']]
+                ?[node, total, out, in] <~ DegreeCentrality(*e[], nodes[])
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        // a self-loop counts as both an outgoing and an incoming edge
+        assert_eq!(
+            res["rows"],
+            json!([
+                ["a", 2, 2, 0],
+                ["b", 2, 1, 1],
+                ["c", 4, 1, 3],
+                ["lonely", 0, 0, 0]
+            ])
+        );
+    }
+}
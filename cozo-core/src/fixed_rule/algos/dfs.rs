@@ -14,7 +14,7 @@ use smartstring::{LazyCompact, SmartString};
 use crate::data::expr::{eval_bytecode_pred, Expr};
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
-use crate::fixed_rule::{FixedRule, FixedRulePayload, NodeNotFoundError};
+use crate::fixed_rule::{FixedRule, FixedRuleInputRelation, FixedRulePayload, NodeNotFoundError};
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
 use crate::runtime::temp_store::RegularTempStore;
@@ -31,8 +31,17 @@ impl FixedRule for Dfs {
         let edges = payload.get_input(0)?.ensure_min_len(2)?;
         let nodes = payload.get_input(1)?;
         let starting_nodes = payload.get_input(2).unwrap_or(nodes);
-        let limit = payload.pos_integer_option("limit", Some(1))?;
+        let max_depth = if payload.manifest.options.contains_key("max_depth") {
+            Some(payload.non_neg_integer_option("max_depth", None)?)
+        } else {
+            None
+        };
+        if !payload.manifest.options.contains_key("condition") {
+            let limit = payload.pos_integer_option("limit", Some(i64::MAX as usize))?;
+            return traverse(edges, starting_nodes, max_depth, limit, out, poison);
+        }
         let mut condition = payload.expr_option("condition", None)?;
+        let limit = payload.pos_integer_option("limit", Some(1))?;
         let binding_map = nodes.get_binding_map(0);
         condition.fill_binding_indices(&binding_map)?;
         let condition_bytecode = condition.compile()?;
@@ -52,10 +61,10 @@ impl FixedRule for Dfs {
                 continue;
             }
 
-            let mut to_visit_stack: Vec<DataValue> = vec![];
-            to_visit_stack.push(starting_node.clone());
+            let mut to_visit_stack: Vec<(DataValue, usize)> = vec![];
+            to_visit_stack.push((starting_node.clone(), 0));
 
-            while let Some(candidate) = to_visit_stack.pop() {
+            while let Some((candidate, depth)) = to_visit_stack.pop() {
                 if visited.contains(&candidate) {
                     continue;
                 }
@@ -81,6 +90,9 @@ impl FixedRule for Dfs {
                 }
 
                 visited.insert(candidate.clone());
+                if max_depth == Some(depth) {
+                    continue;
+                }
 
                 for edge in edges.prefix_iter(&candidate)? {
                     let edge = edge?;
@@ -89,7 +101,7 @@ impl FixedRule for Dfs {
                         continue;
                     }
                     backtrace.insert(to_node.clone(), candidate.clone());
-                    to_visit_stack.push(to_node.clone());
+                    to_visit_stack.push((to_node.clone(), depth + 1));
                     poison.check()?;
                 }
             }
@@ -113,10 +125,189 @@ impl FixedRule for Dfs {
 
     fn arity(
         &self,
-        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        options: &BTreeMap<SmartString<LazyCompact>, Expr>,
         _rule_head: &[Symbol],
         _span: SourceSpan,
     ) -> Result<usize> {
-        Ok(3)
+        Ok(if options.contains_key("condition") {
+            3
+        } else {
+            5
+        })
+    }
+}
+
+struct DfsFrame {
+    node: DataValue,
+    depth: usize,
+    predecessor: DataValue,
+    pre: usize,
+    successors: Vec<DataValue>,
+    next: usize,
+}
+
+/// Without a condition, every node reachable from the starting nodes is returned
+/// together with its depth in the search tree, its predecessor (null for the starting nodes),
+/// and the order in which it was first entered and finally left. An edge `u -> v` closes
+/// a cycle exactly when `pre(v) <= pre(u)` and `post(v) >= post(u)`.
+#[allow(clippy::mutable_key_type)]
+fn traverse(
+    edges: FixedRuleInputRelation<'_, '_>,
+    starting_nodes: FixedRuleInputRelation<'_, '_>,
+    max_depth: Option<usize>,
+    limit: usize,
+    out: &mut RegularTempStore,
+    poison: Poison,
+) -> Result<()> {
+    let mut visited: BTreeSet<DataValue> = Default::default();
+    let mut pre_counter = 0;
+    let mut post_counter = 0;
+
+    let enter =
+        |node: DataValue, depth: usize, predecessor: DataValue, pre: usize| -> Result<DfsFrame> {
+            let successors = if max_depth == Some(depth) {
+                vec![]
+            } else {
+                edges
+                    .prefix_iter(&node)?
+                    .map(|edge| -> Result<DataValue> { Ok(edge?[1].clone()) })
+                    .collect::<Result<_>>()?
+            };
+            Ok(DfsFrame {
+                node,
+                depth,
+                predecessor,
+                pre,
+                successors,
+                next: 0,
+            })
+        };
+
+    for node_tuple in starting_nodes.iter()? {
+        let starting_node = &node_tuple?[0];
+        if pre_counter >= limit {
+            break;
+        }
+        if !visited.insert(starting_node.clone()) {
+            continue;
+        }
+        let mut frames = vec![enter(
+            starting_node.clone(),
+            0,
+            DataValue::Null,
+            pre_counter,
+        )?];
+        pre_counter += 1;
+
+        while let Some(frame) = frames.last_mut() {
+            if frame.next < frame.successors.len() && pre_counter < limit {
+                let successor = frame.successors[frame.next].clone();
+                frame.next += 1;
+                if visited.insert(successor.clone()) {
+                    let (predecessor, depth) = (frame.node.clone(), frame.depth + 1);
+                    frames.push(enter(successor, depth, predecessor, pre_counter)?);
+                    pre_counter += 1;
+                }
+            } else {
+                let frame = frames.pop().unwrap();
+                out.put(vec![
+                    frame.node,
+                    DataValue::from(frame.depth as i64),
+                    frame.predecessor,
+                    DataValue::from(frame.pre as i64),
+                    DataValue::from(post_counter as i64),
+                ]);
+                post_counter += 1;
+            }
+            poison.check()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::DbInstance;
+
+    const EDGES: &str = r#"
+        edges[fr, to] <- [['a', 'b'], ['b', 'c'], ['c', 'a'], ['a', 'd'], ['d', 'e'], ['x', 'y']]
+        nodes[n] <- [['a'], ['b'], ['c'], ['d'], ['e'], ['x'], ['y']]
+        start[] <- [['a']]
+    "#;
+
+    #[test]
+    fn test_dfs_with_condition() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(&format!(
+                "{EDGES} ?[fr, to, path] <~ DFS(edges[], nodes[n], start[], condition: n == 'e')"
+            ))
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([["a", "e", ["a", "d", "e"]]]));
+        let res = db
+            .run_default(&format!(
+                "{EDGES} ?[fr, to, path] <~ DFS(edges[], nodes[n], start[], condition: n == 'e', max_depth: 1)"
+            ))
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([]));
+    }
+
+    #[test]
+    fn test_dfs_traversal() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(&format!(
+                "{EDGES} ?[node, depth, pred, pre, post] <~ DFS(edges[], start[]) :order pre"
+            ))
+            .unwrap()
+            .into_json();
+        assert_eq!(
+            res["rows"],
+            json!([
+                ["a", 0, null, 0, 4],
+                ["b", 1, "a", 1, 1],
+                ["c", 2, "b", 2, 0],
+                ["d", 1, "a", 3, 3],
+                ["e", 2, "d", 4, 2]
+            ])
+        );
+
+        // the back edge c -> a reveals the cycle
+        let res = db
+            .run_default(&format!(
+                r#"
+                {EDGES}
+                order[node, depth, pred, pre, post] <~ DFS(edges[], start[])
+                ?[fr, to] := edges[fr, to], order[fr, _, _, pre_fr, post_fr], order[to, _, _, pre_to, post_to],
+                             pre_to <= pre_fr, post_to >= post_fr
+                "#
+            ))
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([["c", "a"]]));
+
+        let res = db
+            .run_default(&format!(
+                "{EDGES} ?[node, depth, pred, pre, post] <~ DFS(edges[], start[], max_depth: 1) :order pre"
+            ))
+            .unwrap()
+            .into_json();
+        assert_eq!(
+            res["rows"],
+            json!([
+                ["a", 0, null, 0, 2],
+                ["b", 1, "a", 1, 0],
+                ["d", 1, "a", 2, 1]
+            ])
+        );
+        assert!(db
+            .run_default(&format!(
+                "{EDGES} ?[node, depth, pred] <~ DFS(edges[], start[])"
+            ))
+            .is_err());
     }
 }
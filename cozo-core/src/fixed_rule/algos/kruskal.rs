@@ -10,7 +10,6 @@ use graph::prelude::{DirectedCsrGraph, DirectedNeighborsWithValues, Graph};
 use std::cmp::Reverse;
 use std::collections::BTreeMap;
 
-use itertools::Itertools;
 use miette::Result;
 use ordered_float::OrderedFloat;
 use priority_queue::PriorityQueue;
@@ -19,6 +18,7 @@ use smartstring::{LazyCompact, SmartString};
 use crate::data::expr::Expr;
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
+use crate::fixed_rule::algos::connected_components::UnionFind;
 use crate::fixed_rule::{FixedRule, FixedRulePayload};
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
@@ -34,17 +34,28 @@ impl FixedRule for MinimumSpanningForestKruskal {
         poison: Poison,
     ) -> Result<()> {
         let edges = payload.get_input(0)?;
-        let (graph, indices, _) = edges.as_directed_weighted_graph(true, true)?;
+        let graph_data = edges.as_cached_directed_weighted_graph(true, true)?;
+        let (graph, indices, _) = &*graph_data;
         if graph.node_count() == 0 {
             return Ok(());
         }
-        let msp = kruskal(&graph, poison)?;
+        let with_component = payload.manifest.arity == 4;
+        let (msp, mut uf) = kruskal(graph, poison)?;
+        // trees are numbered in the order their first edge is output
+        let mut component_ids: BTreeMap<u32, i64> = Default::default();
         for (src, dst, cost) in msp {
-            out.put(vec![
+            let mut tuple = vec![
                 indices[src as usize].clone(),
                 indices[dst as usize].clone(),
                 DataValue::from(cost as f64),
-            ]);
+            ];
+            if with_component {
+                let root = uf.find(src);
+                let next_id = component_ids.len() as i64;
+                let id = *component_ids.entry(root).or_insert(next_id);
+                tuple.push(DataValue::from(id));
+            }
+            out.put(tuple);
         }
 
         Ok(())
@@ -53,14 +64,18 @@ impl FixedRule for MinimumSpanningForestKruskal {
     fn arity(
         &self,
         _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
-        _rule_head: &[Symbol],
+        rule_head: &[Symbol],
         _span: SourceSpan,
     ) -> Result<usize> {
-        Ok(3)
+        // an optional fourth column holds the id of the tree the edge belongs to
+        Ok(if rule_head.len() == 4 { 4 } else { 3 })
     }
 }
 
-fn kruskal(edges: &DirectedCsrGraph<u32, (), f32>, poison: Poison) -> Result<Vec<(u32, u32, f32)>> {
+fn kruskal(
+    edges: &DirectedCsrGraph<u32, (), f32>,
+    poison: Poison,
+) -> Result<(Vec<(u32, u32, f32)>, UnionFind)> {
     let mut pq = PriorityQueue::new();
     let mut uf = UnionFind::new(edges.node_count());
     let mut mst = Vec::with_capacity((edges.node_count() - 1) as usize);
@@ -72,58 +87,66 @@ fn kruskal(edges: &DirectedCsrGraph<u32, (), f32>, poison: Poison) -> Result<Vec
         }
     }
     while let Some(((from, to), Reverse(OrderedFloat(cost)))) = pq.pop() {
-        if uf.connected(from, to) {
+        if !uf.union(from, to) {
             continue;
         }
-        uf.union(from, to);
 
         mst.push((from, to, cost));
-        if uf.szs[0] == edges.node_count() {
+        if mst.len() + 1 == edges.node_count() as usize {
             break;
         }
         poison.check()?;
     }
-    Ok(mst)
+    Ok((mst, uf))
 }
 
-struct UnionFind {
-    ids: Vec<u32>,
-    szs: Vec<u32>,
-}
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
 
-impl UnionFind {
-    fn new(n: u32) -> Self {
-        Self {
-            ids: (0..n).collect_vec(),
-            szs: vec![1; n as usize],
-        }
-    }
-    fn union(&mut self, p: u32, q: u32) {
-        let root1 = self.find(p);
-        let root2 = self.find(q);
-        if root1 != root2 {
-            if self.szs[root1 as usize] < self.szs[root2 as usize] {
-                self.szs[root2 as usize] += self.szs[root1 as usize];
-                self.ids[root1 as usize] = root2;
-            } else {
-                self.szs[root1 as usize] += self.szs[root2 as usize];
-                self.ids[root2 as usize] = root1;
-            }
-        }
-    }
-    fn find(&mut self, mut p: u32) -> u32 {
-        let mut root = p;
-        while root != self.ids[root as usize] {
-            root = self.ids[root as usize];
-        }
-        while p != root {
-            let next = self.ids[p as usize];
-            self.ids[p as usize] = root;
-            p = next;
-        }
-        root
-    }
-    fn connected(&mut self, p: u32, q: u32) -> bool {
-        self.find(p) == self.find(q)
+    use crate::DbInstance;
+
+    #[test]
+    fn test_kruskal() {
+        let db = DbInstance::default();
+        let edges = r#"
+            edges[] <- [['a', 'b', 1], ['b', 'c', 2], ['a', 'c', 3], ['c', 'd', 1.5],
+                        ['x', 'y', 5], ['y', 'z', 4], ['z', 'x', 6]]
+        "#;
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[src, dst, cost] <~ MinimumSpanningForestKruskal(edges[])"
+            ))
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"].as_array().unwrap().len(), 5);
+        let total: f64 = res["rows"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| row[2].as_f64().unwrap())
+            .sum();
+        assert_eq!(total, 1. + 2. + 1.5 + 4. + 5.);
+
+        // edges are undirected, so either endpoint may come first
+        let res = db
+            .run_default(&format!(
+                "{edges} r[src, dst, cost, tree] <~ MinimumSpanningForestKruskal(edges[])
+                 ?[a, b, cost, tree] := r[src, dst, cost, tree],
+                                        s = sorted([src, dst]), a = first(s), b = last(s)
+                 :order cost"
+            ))
+            .unwrap()
+            .into_json();
+        assert_eq!(
+            res["rows"],
+            json!([
+                ["a", "b", 1.0, 0],
+                ["c", "d", 1.5, 0],
+                ["b", "c", 2.0, 0],
+                ["y", "z", 4.0, 1],
+                ["x", "y", 5.0, 1]
+            ])
+        );
     }
 }
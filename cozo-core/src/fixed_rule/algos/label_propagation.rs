@@ -34,8 +34,15 @@ impl FixedRule for LabelPropagation {
         let edges = payload.get_input(0)?;
         let undirected = payload.bool_option("undirected", Some(false))?;
         let max_iter = payload.pos_integer_option("max_iter", Some(10))?;
-        let (graph, indices, _inv_indices) = edges.as_directed_weighted_graph(undirected, true)?;
-        let labels = label_propagation(&graph, max_iter, poison)?;
+        // the visiting order and the tie-breaking are random, a fixed seed makes them reproducible
+        let mut rng = if payload.manifest.options.contains_key("seed") {
+            StdRng::seed_from_u64(payload.integer_option("seed", None)? as u64)
+        } else {
+            StdRng::from_entropy()
+        };
+        let graph_data = edges.as_cached_directed_weighted_graph(undirected, true)?;
+        let (graph, indices, _inv_indices) = &*graph_data;
+        let labels = label_propagation(graph, max_iter, &mut rng, poison)?;
         for (idx, label) in labels.into_iter().enumerate() {
             let node = indices[idx].clone();
             out.put(vec![DataValue::from(label as i64), node]);
@@ -56,14 +63,14 @@ impl FixedRule for LabelPropagation {
 fn label_propagation(
     graph: &DirectedCsrGraph<u32, (), f32>,
     max_iter: usize,
+    rng: &mut impl Rng,
     poison: Poison,
 ) -> Result<Vec<u32>> {
     let n_nodes = graph.node_count();
     let mut labels = (0..n_nodes).collect_vec();
-    let mut rng = thread_rng();
     let mut iter_order = (0..n_nodes).collect_vec();
     for _ in 0..max_iter {
-        iter_order.shuffle(&mut rng);
+        iter_order.shuffle(rng);
         let mut changed = false;
         for node in &iter_order {
             let mut labels_for_node: BTreeMap<u32, f32> = BTreeMap::new();
@@ -82,7 +89,7 @@ fn label_propagation(
                 .take_while(|(_, score)| *score == max_score)
                 .map(|(l, _)| l)
                 .collect_vec();
-            let new_label = *candidate_labels.choose(&mut rng).unwrap();
+            let new_label = *candidate_labels.choose(rng).unwrap();
             if new_label != labels[*node as usize] {
                 changed = true;
                 labels[*node as usize] = new_label;
@@ -95,3 +102,32 @@ fn label_propagation(
     }
     Ok(labels)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    #[test]
+    fn test_label_propagation_seed() {
+        let db = DbInstance::default();
+        // two triangles joined by a single edge, so that the label of the bridge is up to chance
+        let query = |seed: i64| {
+            db.run_default(&format!(
+                r#"
+                edges[] <- [['a', 'b'], ['b', 'c'], ['c', 'a'], ['c', 'x'],
+                            ['x', 'y'], ['y', 'z'], ['z', 'x']]
+                ?[label, node] <~ LabelPropagation(edges[], undirected: true, seed: {seed})
+                "#
+            ))
+            .unwrap()
+            .into_json()["rows"]
+                .clone()
+        };
+        for seed in 0..10 {
+            let res = query(seed);
+            assert_eq!(res, query(seed));
+            assert_eq!(res.as_array().unwrap().len(), 6);
+            assert_eq!(res[0][0], res[1][0]);
+        }
+    }
+}
@@ -13,10 +13,11 @@ use graph::prelude::{
 };
 use itertools::Itertools;
 use log::debug;
-use miette::Result;
+use miette::{ensure, Result};
 use smartstring::{LazyCompact, SmartString};
 
 use crate::data::expr::Expr;
+use crate::data::program::WrongFixedRuleOptionError;
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
 use crate::fixed_rule::{FixedRule, FixedRulePayload};
@@ -38,10 +39,32 @@ impl FixedRule for CommunityDetectionLouvain {
         let max_iter = payload.pos_integer_option("max_iter", Some(10))?;
         let delta = payload.unit_interval_option("delta", Some(0.0001))? as f32;
         let keep_depth = payload.non_neg_integer_option("keep_depth", None).ok();
+        let max_passes = if payload.manifest.options.contains_key("max_passes") {
+            payload.pos_integer_option("max_passes", None)?
+        } else {
+            usize::MAX
+        };
+        let resolution = payload.float_option("resolution", Some(1.))?;
+        ensure!(
+            resolution > 0.,
+            WrongFixedRuleOptionError {
+                name: "resolution".to_string(),
+                span: payload.option_span("resolution")?,
+                rule_name: payload.manifest.fixed_handle.name.to_string(),
+                help: "a positive number is required".to_string(),
+            }
+        );
+        let params = LouvainParams {
+            delta,
+            max_iter,
+            max_passes,
+            resolution: resolution as f32,
+        };
 
-        let (graph, indices, _inv_indices) = edges.as_directed_weighted_graph(undirected, false)?;
-        let result = louvain(&graph, delta, max_iter, poison)?;
-        for (idx, node) in indices.into_iter().enumerate() {
+        let graph_data = edges.as_cached_directed_weighted_graph(undirected, false)?;
+        let (graph, indices, _inv_indices) = &*graph_data;
+        let result = louvain(graph, &params, poison)?;
+        for (idx, node) in indices.iter().enumerate() {
             let mut labels = vec![];
             let mut cur_idx = idx as u32;
             for hierarchy in &result {
@@ -53,7 +76,7 @@ impl FixedRule for CommunityDetectionLouvain {
             if let Some(l) = keep_depth {
                 labels.truncate(l);
             }
-            out.put(vec![DataValue::List(labels), node]);
+            out.put(vec![DataValue::List(labels), node.clone()]);
         }
 
         Ok(())
@@ -69,16 +92,26 @@ impl FixedRule for CommunityDetectionLouvain {
     }
 }
 
-fn louvain(
-    graph: &DirectedCsrGraph<u32, (), f32>,
+struct LouvainParams {
+    /// minimum gain of modularity for another iteration within a pass
     delta: f32,
+    /// maximum number of iterations within a pass
     max_iter: usize,
+    /// maximum number of passes, each producing a coarser level of communities
+    max_passes: usize,
+    /// larger values favour smaller communities
+    resolution: f32,
+}
+
+fn louvain(
+    graph: &DirectedCsrGraph<u32, (), f32>,
+    params: &LouvainParams,
     poison: Poison,
 ) -> Result<Vec<Vec<u32>>> {
     let mut current = graph;
     let mut collected = vec![];
-    while current.node_count() > 2 {
-        let (node2comm, new_graph) = louvain_step(current, delta, max_iter, poison.clone())?;
+    while current.node_count() > 2 && collected.len() < params.max_passes {
+        let (node2comm, new_graph) = louvain_step(current, params, poison.clone())?;
         debug!(
             "before size: {}, after size: {}",
             current.node_count(),
@@ -101,6 +134,7 @@ fn calculate_delta(
     out_weights: &[f32],
     in_weights: &[f32],
     total_weight: f32,
+    resolution: f32,
 ) -> f32 {
     let mut sigma_out_total = 0.;
     let mut sigma_in_total = 0.;
@@ -126,15 +160,15 @@ fn calculate_delta(
         }
     }
     d2comm
-        - (sigma_out_total * in_weights[node as usize]
-            + sigma_in_total * out_weights[node as usize])
+        - resolution
+            * (sigma_out_total * in_weights[node as usize]
+                + sigma_in_total * out_weights[node as usize])
             / total_weight
 }
 
 fn louvain_step(
     graph: &DirectedCsrGraph<u32, (), f32>,
-    delta: f32,
-    max_iter: usize,
+    params: &LouvainParams,
     poison: Poison,
 ) -> Result<(Vec<u32>, DirectedCsrGraph<u32, (), f32>)> {
     let n_nodes = graph.node_count();
@@ -158,7 +192,7 @@ fn louvain_step(
 
     let mut last_modurality = f32::NEG_INFINITY;
 
-    for _ in 0..max_iter {
+    for _ in 0..params.max_iter {
         let modularity = {
             let mut modularity = 0.;
             for from in 0..n_nodes {
@@ -169,14 +203,15 @@ fn louvain_step(
                         }
                     }
                     modularity -=
-                        in_weights[from as usize] * out_weights[*to as usize] / total_weight;
+                        params.resolution * in_weights[from as usize] * out_weights[*to as usize]
+                            / total_weight;
                 }
             }
             modularity /= total_weight;
             debug!("modurality {}", modularity);
             modularity
         };
-        if modularity <= last_modurality + delta {
+        if modularity <= last_modurality + params.delta {
             break;
         } else {
             last_modurality = modularity;
@@ -194,6 +229,7 @@ fn louvain_step(
                 &out_weights,
                 &in_weights,
                 total_weight,
+                params.resolution,
             );
             let mut candidate_community = community_for_node;
             let mut best_improvement = 0.;
@@ -218,6 +254,7 @@ fn louvain_step(
                     &out_weights,
                     &in_weights,
                     total_weight,
+                    params.resolution,
                 );
                 if delta_q - original_delta_q > best_improvement {
                     best_improvement = delta_q - original_delta_q;
@@ -281,8 +318,11 @@ fn louvain_step(
 mod tests {
     use graph::prelude::{CsrLayout, GraphBuilder};
 
-    use crate::fixed_rule::algos::louvain::louvain;
+    use serde_json::json;
+
+    use crate::fixed_rule::algos::louvain::{louvain, LouvainParams};
     use crate::runtime::db::Poison;
+    use crate::DbInstance;
 
     #[test]
     fn sample() {
@@ -313,6 +353,75 @@ mod tests {
                     .flat_map(|(fr, tos)| tos.into_iter().map(move |to| (fr as u32, to, 1.))),
             )
             .build();
-        louvain(&graph, 0., 100, Poison::default()).unwrap();
+        let params = LouvainParams {
+            delta: 0.,
+            max_iter: 100,
+            max_passes: usize::MAX,
+            resolution: 1.,
+        };
+        louvain(&graph, &params, Poison::default()).unwrap();
+    }
+
+    #[test]
+    fn resolution_and_passes() {
+        let db = DbInstance::default();
+        // a ring of four triangles
+        let edges = r#"
+            edges[] <- [['a1', 'a2'], ['a2', 'a3'], ['a3', 'a1'], ['b1', 'b2'], ['b2', 'b3'], ['b3', 'b1'],
+                        ['c1', 'c2'], ['c2', 'c3'], ['c3', 'c1'], ['d1', 'd2'], ['d2', 'd3'], ['d3', 'd1'],
+                        ['a1', 'b1'], ['b1', 'c1'], ['c1', 'd1'], ['d1', 'a1']]
+        "#;
+        let rows = |opts: &str| {
+            db.run_default(&format!(
+                "{edges} ?[comm, node] <~ CommunityDetectionLouvain(edges[], undirected: true{opts})"
+            ))
+            .unwrap()
+            .into_json()["rows"]
+                .clone()
+        };
+        let labels = |opts: &str| {
+            rows(opts)
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|row| row[0].clone())
+                .collect::<Vec<_>>()
+        };
+        let triangles = [0, 0, 0, 1, 1, 1, 2, 2, 2, 3, 3, 3]
+            .map(|c| json!([c]))
+            .to_vec();
+        assert_eq!(labels(""), triangles);
+
+        // a lower resolution merges the triangles further in a second pass,
+        // unless only one pass is allowed
+        let pairs = [
+            [0, 0],
+            [0, 0],
+            [0, 0],
+            [0, 1],
+            [0, 1],
+            [0, 1],
+            [1, 2],
+            [1, 2],
+            [1, 2],
+            [1, 3],
+            [1, 3],
+            [1, 3],
+        ]
+        .map(|c| json!(c))
+        .to_vec();
+        assert_eq!(labels(", resolution: 0.2"), pairs);
+        assert_eq!(labels(", resolution: 0.2, max_passes: 1"), triangles);
+
+        // a high resolution leaves every node on its own
+        assert!(labels(", resolution: 100")
+            .iter()
+            .all(|l| l.as_array().unwrap().is_empty()));
+
+        assert!(db
+            .run_default(&format!(
+                "{edges} ?[comm, node] <~ CommunityDetectionLouvain(edges[], resolution: 0)"
+            ))
+            .is_err());
     }
 }
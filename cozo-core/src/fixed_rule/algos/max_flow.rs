@@ -0,0 +1,270 @@
+/*
+ * Copyright 2022, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::{BTreeMap, VecDeque};
+
+use graph::prelude::{DirectedNeighborsWithValues, Graph};
+use miette::{ensure, Result};
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::Expr;
+use crate::data::program::WrongFixedRuleOptionError;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::{FixedRule, FixedRulePayload, NodeNotFoundError};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+pub(crate) struct MaximumFlow;
+
+impl FixedRule for MaximumFlow {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let source = payload.expr_option("source", None)?.eval_to_const()?;
+        let sink = payload.expr_option("sink", None)?.eval_to_const()?;
+        ensure!(
+            source != sink,
+            WrongFixedRuleOptionError {
+                name: "sink".to_string(),
+                span: payload.option_span("sink")?,
+                rule_name: payload.manifest.fixed_handle.name.to_string(),
+                help: "the sink must be different from the source".to_string(),
+            }
+        );
+
+        let graph_data = edges.as_cached_directed_weighted_graph(false, false)?;
+        let (graph, indices, inv_indices) = &*graph_data;
+        let find_node = |node: &DataValue, option: &str| -> Result<usize> {
+            Ok(*inv_indices.get(node).ok_or_else(|| NodeNotFoundError {
+                missing: node.clone(),
+                span: payload.option_span(option).unwrap_or_else(|_| edges.span()),
+            })? as usize)
+        };
+        let source = find_node(&source, "source")?;
+        let sink = find_node(&sink, "sink")?;
+
+        let mut network = FlowNetwork::new(graph.node_count() as usize);
+        // parallel edges are merged, the input is a relation so they can only differ by capacity
+        let mut edge_ids: BTreeMap<(u32, u32), usize> = BTreeMap::new();
+        for from in 0..graph.node_count() {
+            for target in graph.out_neighbors_with_values(from) {
+                let to = target.target;
+                let capacity = target.value as f64;
+                match edge_ids.get(&(from, to)) {
+                    Some(e) => {
+                        network.capacity[*e] += capacity;
+                        network.residual[*e] += capacity;
+                    }
+                    None => {
+                        let e = network.add_edge(from as usize, to as usize, capacity);
+                        edge_ids.insert((from, to), e);
+                    }
+                }
+            }
+        }
+
+        network.dinic(source, sink, poison)?;
+        let source_side = network.reachable_from(source);
+
+        for ((from, to), e) in edge_ids {
+            let flow = network.capacity[e] - network.residual[e];
+            let in_cut = source_side[from as usize] && !source_side[to as usize];
+            out.put(vec![
+                indices[from as usize].clone(),
+                indices[to as usize].clone(),
+                DataValue::from(flow),
+                DataValue::from(in_cut),
+            ]);
+        }
+        Ok(())
+    }
+
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(4)
+    }
+}
+
+/// Residual network, the reverse of edge `e` is always `e ^ 1`.
+struct FlowNetwork {
+    adj: Vec<Vec<usize>>,
+    to: Vec<usize>,
+    capacity: Vec<f64>,
+    residual: Vec<f64>,
+}
+
+impl FlowNetwork {
+    fn new(n: usize) -> Self {
+        Self {
+            adj: vec![vec![]; n],
+            to: vec![],
+            capacity: vec![],
+            residual: vec![],
+        }
+    }
+    fn add_edge(&mut self, from: usize, to: usize, capacity: f64) -> usize {
+        let e = self.to.len();
+        self.adj[from].push(e);
+        self.to.push(to);
+        self.capacity.push(capacity);
+        self.residual.push(capacity);
+        self.adj[to].push(e + 1);
+        self.to.push(from);
+        self.capacity.push(0.);
+        self.residual.push(0.);
+        e
+    }
+    fn levels(&self, source: usize) -> Vec<usize> {
+        let mut level = vec![usize::MAX; self.adj.len()];
+        level[source] = 0;
+        let mut queue = VecDeque::from([source]);
+        while let Some(node) = queue.pop_front() {
+            for e in &self.adj[node] {
+                let to = self.to[*e];
+                if self.residual[*e] > 0. && level[to] == usize::MAX {
+                    level[to] = level[node] + 1;
+                    queue.push_back(to);
+                }
+            }
+        }
+        level
+    }
+    fn reachable_from(&self, source: usize) -> Vec<bool> {
+        self.levels(source)
+            .into_iter()
+            .map(|l| l != usize::MAX)
+            .collect()
+    }
+    fn dinic(&mut self, source: usize, sink: usize, poison: Poison) -> Result<f64> {
+        let mut total = 0.;
+        loop {
+            let level = self.levels(source);
+            if level[sink] == usize::MAX {
+                return Ok(total);
+            }
+            total += self.blocking_flow(source, sink, &level);
+            poison.check()?;
+        }
+    }
+    // iterative, since augmenting paths can be as long as the number of nodes
+    fn blocking_flow(&mut self, source: usize, sink: usize, level: &[usize]) -> f64 {
+        let mut total = 0.;
+        let mut next_edge = vec![0; self.adj.len()];
+        let mut path: Vec<usize> = vec![];
+        loop {
+            let node = path.last().map(|e| self.to[*e]).unwrap_or(source);
+            if node == sink {
+                let bottleneck = path
+                    .iter()
+                    .map(|e| self.residual[*e])
+                    .fold(f64::INFINITY, f64::min);
+                for e in &path {
+                    self.residual[*e] -= bottleneck;
+                    self.residual[*e ^ 1] += bottleneck;
+                }
+                total += bottleneck;
+                path.clear();
+                continue;
+            }
+            let mut advanced = false;
+            while next_edge[node] < self.adj[node].len() {
+                let e = self.adj[node][next_edge[node]];
+                let to = self.to[e];
+                if self.residual[e] > 0. && level[to] == level[node] + 1 {
+                    path.push(e);
+                    advanced = true;
+                    break;
+                }
+                next_edge[node] += 1;
+            }
+            if !advanced {
+                // dead end, retreat and skip the edge leading here
+                match path.pop() {
+                    None => return total,
+                    Some(e) => {
+                        let prev = self.to[e ^ 1];
+                        next_edge[prev] += 1;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::DbInstance;
+
+    #[test]
+    fn test_max_flow() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(
+                r#"
+                edges[] <- [['s', 'a', 10], ['s', 'b', 5], ['a', 'b', 15], ['a', 't', 5],
+                            ['b', 't', 10], ['t', 's', 100]]
+                ?[fr, to, flow, cut] <~ MaximumFlow(edges[], source: 's', sink: 't')
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(
+            res["rows"],
+            json!([
+                ["a", "b", 5.0, false],
+                ["a", "t", 5.0, false],
+                ["b", "t", 10.0, false],
+                ["s", "a", 10.0, true],
+                ["s", "b", 5.0, true],
+                ["t", "s", 0.0, false]
+            ])
+        );
+
+        // the total flow leaves the source, unit capacities by default
+        let res = db
+            .run_default(
+                r#"
+                edges[] <- [[1, 2], [1, 3], [2, 4], [3, 4], [2, 3], [3, 3]]
+                f[fr, to, flow, cut] <~ MaximumFlow(edges[], source: 1, sink: 4)
+                ?[sum(flow)] := f[1, _, flow, _]
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([[2.0]]));
+
+        assert!(db
+            .run_default(
+                r#"
+                edges[] <- [[1, 2]]
+                ?[fr, to, flow, cut] <~ MaximumFlow(edges[], source: 1, sink: 3)
+                "#,
+            )
+            .is_err());
+        assert!(db
+            .run_default(
+                r#"
+                edges[] <- [[1, 2]]
+                ?[fr, to, flow, cut] <~ MaximumFlow(edges[], source: 1, sink: 1)
+                "#,
+            )
+            .is_err());
+    }
+}
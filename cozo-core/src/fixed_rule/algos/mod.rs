@@ -9,14 +9,18 @@
 pub(crate) mod all_pairs_shortest_path;
 pub(crate) mod astar;
 pub(crate) mod bfs;
+pub(crate) mod bipartite_matching;
+pub(crate) mod connected_components;
 pub(crate) mod degree_centrality;
 pub(crate) mod dfs;
 pub(crate) mod kruskal;
 pub(crate) mod label_propagation;
 pub(crate) mod louvain;
+pub(crate) mod max_flow;
 pub(crate) mod pagerank;
 pub(crate) mod prim;
 pub(crate) mod random_walk;
+pub(crate) mod reconstruct_path;
 pub(crate) mod shortest_path_bfs;
 pub(crate) mod shortest_path_dijkstra;
 pub(crate) mod strongly_connected_components;
@@ -24,17 +28,23 @@ pub(crate) mod top_sort;
 pub(crate) mod triangles;
 pub(crate) mod yen;
 
-pub(crate) use all_pairs_shortest_path::{BetweennessCentrality, ClosenessCentrality};
+pub(crate) use all_pairs_shortest_path::{
+    BetweennessCentrality, ClosenessCentrality, HarmonicCentrality,
+};
 pub(crate) use astar::ShortestPathAStar;
 pub(crate) use bfs::Bfs;
+pub(crate) use bipartite_matching::MaximumBipartiteMatching;
+pub(crate) use connected_components::ConnectedComponents;
 pub(crate) use degree_centrality::DegreeCentrality;
 pub(crate) use dfs::Dfs;
 pub(crate) use kruskal::MinimumSpanningForestKruskal;
 pub(crate) use label_propagation::LabelPropagation;
 pub(crate) use louvain::CommunityDetectionLouvain;
-pub(crate) use pagerank::PageRank;
+pub(crate) use max_flow::MaximumFlow;
+pub(crate) use pagerank::{PageRank, PersonalizedPageRank};
 pub(crate) use prim::MinimumSpanningTreePrim;
 pub(crate) use random_walk::RandomWalk;
+pub(crate) use reconstruct_path::ReconstructPath;
 pub(crate) use shortest_path_bfs::ShortestPathBFS;
 pub(crate) use shortest_path_dijkstra::ShortestPathDijkstra;
 pub(crate) use strongly_connected_components::StronglyConnectedComponent;
@@ -10,8 +10,11 @@ use std::collections::BTreeMap;
 
 #[cfg(not(feature = "rayon"))]
 use approx::AbsDiffEq;
-use graph::prelude::{page_rank, PageRankConfig};
+use graph::prelude::{
+    page_rank, DirectedCsrGraph, DirectedNeighborsWithValues, Graph, PageRankConfig,
+};
 use miette::Result;
+use rayon::prelude::*;
 use smartstring::{LazyCompact, SmartString};
 
 use crate::data::expr::Expr;
@@ -38,14 +41,15 @@ impl FixedRule for PageRank {
         let epsilon = payload.unit_interval_option("epsilon", Some(0.0001))? as f32;
         let iterations = payload.pos_integer_option("iterations", Some(10))?;
 
-        let (graph, indices, _) = edges.as_directed_graph(undirected)?;
+        let graph_data = edges.as_cached_directed_graph(undirected)?;
+        let (graph, indices, _) = &*graph_data;
 
         if indices.is_empty() {
             return Ok(());
         }
 
         let (ranks, _n_run, _) = page_rank(
-            &graph,
+            graph,
             PageRankConfig::new(iterations, epsilon as f64, theta),
         );
 
@@ -65,6 +69,130 @@ impl FixedRule for PageRank {
     }
 }
 
+/// PageRank personalized to each node of the second input relation, as random walks
+/// that return to their source with probability `restart` at each step.
+pub(crate) struct PersonalizedPageRank;
+
+impl FixedRule for PersonalizedPageRank {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let sources = payload.get_input(1)?;
+        let undirected = payload.bool_option("undirected", Some(false))?;
+        let restart = payload.unit_interval_option("restart", Some(0.15))?;
+        let epsilon = payload.unit_interval_option("epsilon", Some(0.0001))?;
+        let iterations = payload.pos_integer_option("iterations", Some(20))?;
+
+        let graph_data = edges.as_cached_directed_weighted_graph(undirected, false)?;
+        let (graph, indices, inv_indices) = &*graph_data;
+        let out_weights: Vec<f64> = (0..graph.node_count())
+            .map(|u| {
+                graph
+                    .out_neighbors_with_values(u)
+                    .map(|t| t.value as f64)
+                    .sum()
+            })
+            .collect();
+
+        let mut source_nodes = vec![];
+        for tuple in sources.iter()? {
+            source_nodes.push(tuple?[0].clone());
+        }
+        source_nodes.sort();
+        source_nodes.dedup();
+
+        let all_scores: Vec<_> = source_nodes
+            .par_iter()
+            .map(|source| -> Result<Option<Vec<f64>>> {
+                match inv_indices.get(source) {
+                    None => Ok(None),
+                    Some(idx) => Ok(Some(random_walk_with_restart(
+                        graph,
+                        &out_weights,
+                        *idx,
+                        restart,
+                        epsilon,
+                        iterations,
+                        poison.clone(),
+                    )?)),
+                }
+            })
+            .collect::<Result<_>>()?;
+
+        for (source, scores) in source_nodes.into_iter().zip(all_scores) {
+            match scores {
+                // a source without edges keeps all of its walks
+                None => out.put(vec![source.clone(), source, DataValue::from(1.0)]),
+                Some(scores) => {
+                    for (idx, score) in scores.into_iter().enumerate() {
+                        if score > 0. {
+                            out.put(vec![
+                                source.clone(),
+                                indices[idx].clone(),
+                                DataValue::from(score),
+                            ]);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(3)
+    }
+}
+
+/// Power iteration of the walk distribution. Edge weights give the transition probabilities,
+/// and walks reaching a node without out-edges return to the source.
+fn random_walk_with_restart(
+    graph: &DirectedCsrGraph<u32, (), f32>,
+    out_weights: &[f64],
+    source: u32,
+    restart: f64,
+    epsilon: f64,
+    iterations: usize,
+    poison: Poison,
+) -> Result<Vec<f64>> {
+    let mut scores = vec![0.; out_weights.len()];
+    scores[source as usize] = 1.;
+    for _ in 0..iterations {
+        let mut next = vec![0.; scores.len()];
+        let mut returning = restart;
+        for (u, score) in scores.iter().enumerate() {
+            if *score == 0. {
+                continue;
+            }
+            if out_weights[u] > 0. {
+                let spread = (1. - restart) * score / out_weights[u];
+                for t in graph.out_neighbors_with_values(u as u32) {
+                    next[t.target as usize] += spread * t.value as f64;
+                }
+            } else {
+                returning += (1. - restart) * score;
+            }
+        }
+        next[source as usize] += returning;
+        let diff: f64 = next.iter().zip(&scores).map(|(a, b)| (a - b).abs()).sum();
+        scores = next;
+        if diff < epsilon {
+            break;
+        }
+        poison.check()?;
+    }
+    Ok(scores)
+}
+
 #[cfg(not(feature = "rayon"))]
 fn pagerank(
     edges: &[Vec<usize>],
@@ -107,3 +235,74 @@ fn pagerank(
     }
     Ok(pi_vec)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    #[test]
+    fn test_personalized_page_rank() {
+        let db = DbInstance::default();
+        let rows = db
+            .run_default(
+                r#"
+                edges[fr, to] <- [['a', 'b'], ['b', 'c'], ['c', 'a'], ['c', 'd'], ['x', 'y']]
+                sources[] <- [['a'], ['x'], ['lonely']]
+                ?[source, node, score] <~ PersonalizedPageRank(edges[], sources[], iterations: 100)
+                "#,
+            )
+            .unwrap()
+            .rows;
+        let score = |source: &str, node: &str| {
+            rows.iter()
+                .find(|row| row[0] == source.into() && row[1] == node.into())
+                .map(|row| row[2].get_float().unwrap())
+        };
+        for source in ["a", "x"] {
+            let total: f64 = rows
+                .iter()
+                .filter(|row| row[0] == source.into())
+                .map(|row| row[2].get_float().unwrap())
+                .sum();
+            assert!((total - 1.).abs() < 1e-3);
+        }
+        // relevance decreases with the distance from the source
+        assert!(score("a", "a").unwrap() > score("a", "b").unwrap());
+        assert!(score("a", "b").unwrap() > score("a", "c").unwrap());
+        assert!(score("a", "c").unwrap() > score("a", "d").unwrap());
+        // nodes that cannot be reached are left out
+        assert_eq!(score("a", "y"), None);
+        assert_eq!(score("x", "a"), None);
+        assert_eq!(score("lonely", "lonely"), Some(1.));
+
+        // walks that always restart never leave their source, otherwise edge weights are followed
+        let rows = db
+            .run_default(
+                r#"
+                edges[fr, to, w] <- [['s', 'p', 3.0], ['s', 'q', 1.0]]
+                ?[source, node, score] <~ PersonalizedPageRank(edges[], edges[s], restart: 1.0)
+                "#,
+            )
+            .unwrap()
+            .rows;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][2].get_float(), Some(1.));
+        let rows = db
+            .run_default(
+                r#"
+                edges[fr, to, w] <- [['s', 'p', 3.0], ['s', 'q', 1.0]]
+                ?[source, node, score] <~ PersonalizedPageRank(edges[], edges[s], restart: 0.5,
+                                                               iterations: 100)
+                "#,
+            )
+            .unwrap()
+            .rows;
+        let p = rows.iter().find(|row| row[1] == "p".into()).unwrap()[2]
+            .get_float()
+            .unwrap();
+        let q = rows.iter().find(|row| row[1] == "q".into()).unwrap()[2]
+            .get_float()
+            .unwrap();
+        assert!((p / q - 3.).abs() < 1e-6);
+    }
+}
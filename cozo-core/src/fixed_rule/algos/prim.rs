@@ -35,7 +35,8 @@ impl FixedRule for MinimumSpanningTreePrim {
         poison: Poison,
     ) -> Result<()> {
         let edges = payload.get_input(0)?;
-        let (graph, indices, inv_indices) = edges.as_directed_weighted_graph(true, true)?;
+        let graph_data = edges.as_cached_directed_weighted_graph(true, true)?;
+        let (graph, indices, inv_indices) = &*graph_data;
         if graph.node_count() == 0 {
             return Ok(());
         }
@@ -61,7 +62,7 @@ impl FixedRule for MinimumSpanningTreePrim {
                 })?
             }
         };
-        let msp = prim(&graph, starting, poison)?;
+        let msp = prim(graph, starting, poison)?;
         for (src, dst, cost) in msp {
             out.put(vec![
                 indices[src as usize].clone(),
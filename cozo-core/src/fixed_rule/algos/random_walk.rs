@@ -36,6 +36,7 @@ impl FixedRule for RandomWalk {
         let starting = payload.get_input(2)?;
         let iterations = payload.pos_integer_option("iterations", Some(1))?;
         let steps = payload.pos_integer_option("steps", None)?;
+        let flatten = payload.bool_option("flatten", Some(false))?;
 
         let mut maybe_weight = payload.expr_option("weight", None).ok();
         let mut maybe_weight_bytecode = None;
@@ -51,7 +52,11 @@ impl FixedRule for RandomWalk {
         let mut stack = vec![];
 
         let mut counter = 0i64;
-        let mut rng = thread_rng();
+        let mut rng = if payload.manifest.options.contains_key("seed") {
+            StdRng::seed_from_u64(payload.integer_option("seed", None)? as u64)
+        } else {
+            StdRng::from_entropy()
+        };
         for start_node in starting.iter()? {
             let start_node = start_node?;
             let start_node_key = &start_node[0];
@@ -102,8 +107,11 @@ impl FixedRule for RandomWalk {
                                 })
                             })
                             .try_collect()?;
-                        let dist = WeightedIndex::new(&weights).unwrap();
-                        &candidate_steps[dist.sample(&mut rng)]
+                        // fails only if all the weights are zero, in which case the walk is stuck
+                        match WeightedIndex::new(&weights) {
+                            Ok(dist) => &candidate_steps[dist.sample(&mut rng)],
+                            Err(_) => break,
+                        }
                     } else {
                         candidate_steps.choose(&mut rng).unwrap()
                     };
@@ -117,11 +125,21 @@ impl FixedRule for RandomWalk {
                     })??;
                     poison.check()?;
                 }
-                out.put(vec![
-                    DataValue::from(counter),
-                    start_node_key.clone(),
-                    DataValue::List(path),
-                ]);
+                if flatten {
+                    for (idx, node) in path.into_iter().enumerate() {
+                        out.put(vec![
+                            DataValue::from(counter),
+                            DataValue::from(idx as i64),
+                            node,
+                        ]);
+                    }
+                } else {
+                    out.put(vec![
+                        DataValue::from(counter),
+                        start_node_key.clone(),
+                        DataValue::List(path),
+                    ]);
+                }
             }
         }
         Ok(())
@@ -136,3 +154,58 @@ impl FixedRule for RandomWalk {
         Ok(3)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::DbInstance;
+
+    #[test]
+    fn test_random_walk() {
+        let db = DbInstance::default();
+        let graph = r#"
+            nodes[n] <- [['a'], ['b'], ['c'], ['d']]
+            edges[fr, to, w] <- [['a', 'b', 1.0], ['b', 'a', 1.0], ['b', 'c', 0.0], ['c', 'd', 0.0]]
+            start[] <- [['a'], ['c']]
+        "#;
+        let walk = |opts: &str| {
+            db.run_default(&format!(
+                "{graph}
+                 ?[id, start, path] <~ RandomWalk(edges[fr, to, w], nodes[n], start[],
+                                                  steps: 4{opts})"
+            ))
+            .unwrap()
+            .into_json()["rows"]
+                .clone()
+        };
+
+        // zero-weight edges are never taken, and a walk ends when no edge can be taken
+        assert_eq!(
+            walk(", weight: w, iterations: 2"),
+            json!([
+                [1, "a", ["a", "b", "a", "b", "a"]],
+                [2, "a", ["a", "b", "a", "b", "a"]],
+                [3, "c", ["c"]],
+                [4, "c", ["c"]]
+            ])
+        );
+        assert_eq!(
+            walk(", seed: 42, iterations: 10"),
+            walk(", seed: 42, iterations: 10")
+        );
+
+        let res = db
+            .run_default(&format!(
+                "{graph}
+                 ?[id, step, node] <~ RandomWalk(edges[fr, to, w], nodes[n], start[],
+                                                 steps: 2, weight: w, flatten: true)"
+            ))
+            .unwrap()
+            .into_json();
+        assert_eq!(
+            res["rows"],
+            json!([[1, 0, "a"], [1, 1, "b"], [1, 2, "a"], [2, 0, "c"]])
+        );
+    }
+}
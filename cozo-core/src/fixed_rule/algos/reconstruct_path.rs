@@ -0,0 +1,157 @@
+/*
+ * Copyright 2022, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use itertools::Itertools;
+use miette::{Diagnostic, Result};
+use smartstring::{LazyCompact, SmartString};
+use thiserror::Error;
+
+use crate::data::expr::Expr;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::{FixedRule, FixedRuleInputRelation, FixedRulePayload};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+/// Follows parent pointers `[node, parent]`, as computed by a recursive shortest-path rule,
+/// back to a root and returns the path from the root to each node.
+pub(crate) struct ReconstructPath;
+
+impl FixedRule for ReconstructPath {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let parents = payload.get_input(0)?.ensure_min_len(2)?;
+        let nodes: Vec<_> = match payload.get_input(1) {
+            Err(_) => parents
+                .iter()?
+                .map_ok(|t| t.into_iter().next().unwrap())
+                .try_collect::<_, BTreeSet<_>, _>()?
+                .into_iter()
+                .collect(),
+            Ok(rel) => rel
+                .ensure_min_len(1)?
+                .iter()?
+                .map_ok(|t| t.into_iter().next().unwrap())
+                .try_collect()?,
+        };
+
+        // an acyclic chain of parent pointers cannot be longer than this
+        let max_len = parents.iter()?.count() + 1;
+        for node in nodes {
+            let path = path_to(&parents, &node, max_len, &poison)?;
+            out.put(vec![node, DataValue::List(path)]);
+        }
+        Ok(())
+    }
+
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+/// A node is a root if it has no parent or is its own parent. If a node has several parents,
+/// the smallest one is followed.
+fn path_to(
+    parents: &FixedRuleInputRelation<'_, '_>,
+    node: &DataValue,
+    max_len: usize,
+    poison: &Poison,
+) -> Result<Vec<DataValue>> {
+    #[derive(Debug, Error, Diagnostic)]
+    #[error("The parent pointers starting from node {0:?} form a cycle")]
+    #[diagnostic(code(algo::parent_cycle))]
+    struct ParentCycle(DataValue, #[label] SourceSpan);
+
+    let mut path = vec![];
+    let mut current = node.clone();
+    loop {
+        if path.len() == max_len {
+            return Err(ParentCycle(node.clone(), parents.span()).into());
+        }
+        path.push(current.clone());
+        let parent = match parents.prefix_iter(&current)?.next() {
+            None => break,
+            Some(tuple) => tuple?.into_iter().nth(1).unwrap(),
+        };
+        if parent == current {
+            break;
+        }
+        current = parent;
+        poison.check()?;
+    }
+    path.reverse();
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::DbInstance;
+
+    #[test]
+    fn test_reconstruct_path() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(
+                r#"
+        edge[fr, to, w] <- [['a', 'b', 1], ['b', 'c', 1], ['a', 'c', 5], ['c', 'd', 1], ['x', 'y', 1]]
+        dist[n, min(d)] := n = 'a', d = 0
+        dist[to, min(d)] := dist[fr, d0], edge[fr, to, w], d = d0 + w
+        parent[to, fr] := dist[fr, d0], edge[fr, to, w], dist[to, d], d == d0 + w
+        ?[node, path] <~ ReconstructPath(parent[])
+        "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(
+            res["rows"],
+            json!([
+                ["b", ["a", "b"]],
+                ["c", ["a", "b", "c"]],
+                ["d", ["a", "b", "c", "d"]]
+            ])
+        );
+
+        let res = db
+            .run_default(
+                r#"
+        parent[node, parent] <- [['b', 'a'], ['c', 'b'], ['a', 'a']]
+        targets[n] <- [['c'], ['a'], ['z']]
+        ?[node, path] <~ ReconstructPath(parent[], targets[])
+        "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(
+            res["rows"],
+            json!([["a", ["a"]], ["c", ["a", "b", "c"]], ["z", ["z"]]])
+        );
+
+        assert!(db
+            .run_default(
+                r#"
+        parent[node, parent] <- [['b', 'a'], ['a', 'b']]
+        ?[node, path] <~ ReconstructPath(parent[])
+        "#,
+            )
+            .is_err());
+    }
+}
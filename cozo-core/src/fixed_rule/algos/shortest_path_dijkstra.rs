@@ -42,7 +42,8 @@ impl FixedRule for ShortestPathDijkstra {
         let undirected = payload.bool_option("undirected", Some(false))?;
         let keep_ties = payload.bool_option("keep_ties", Some(false))?;
 
-        let (graph, indices, inv_indices) = edges.as_directed_weighted_graph(undirected, false)?;
+        let graph_data = edges.as_cached_directed_weighted_graph(undirected, false)?;
+        let (graph, indices, inv_indices) = &*graph_data;
 
         let mut starting_nodes = BTreeSet::new();
         for tuple in starting.iter()? {
@@ -73,17 +74,17 @@ impl FixedRule for ShortestPathDijkstra {
                     if tn.len() == 1 {
                         let single = Some(*tn.iter().next().unwrap());
                         if keep_ties {
-                            dijkstra_keep_ties(&graph, start, &single, &(), &(), poison.clone())?
+                            dijkstra_keep_ties(graph, start, &single, &(), &(), poison.clone())?
                         } else {
-                            dijkstra(&graph, start, &single, &(), &())
+                            dijkstra(graph, start, &single, &(), &())
                         }
                     } else if keep_ties {
-                        dijkstra_keep_ties(&graph, start, tn, &(), &(), poison.clone())?
+                        dijkstra_keep_ties(graph, start, tn, &(), &(), poison.clone())?
                     } else {
-                        dijkstra(&graph, start, tn, &(), &())
+                        dijkstra(graph, start, tn, &(), &())
                     }
                 } else {
-                    dijkstra(&graph, start, &(), &(), &())
+                    dijkstra(graph, start, &(), &(), &())
                 };
                 for (target, cost, path) in res {
                     let t = vec![
@@ -111,7 +112,7 @@ impl FixedRule for ShortestPathDijkstra {
                                 let single = Some(*tn.iter().next().unwrap());
                                 if keep_ties {
                                     dijkstra_keep_ties(
-                                        &graph,
+                                        graph,
                                         start,
                                         &single,
                                         &(),
@@ -119,15 +120,15 @@ impl FixedRule for ShortestPathDijkstra {
                                         poison.clone(),
                                     )?
                                 } else {
-                                    dijkstra(&graph, start, &single, &(), &())
+                                    dijkstra(graph, start, &single, &(), &())
                                 }
                             } else if keep_ties {
-                                dijkstra_keep_ties(&graph, start, tn, &(), &(), poison.clone())?
+                                dijkstra_keep_ties(graph, start, tn, &(), &(), poison.clone())?
                             } else {
-                                dijkstra(&graph, start, tn, &(), &())
+                                dijkstra(graph, start, tn, &(), &())
                             }
                         } else {
-                            dijkstra(&graph, start, &(), &(), &())
+                            dijkstra(graph, start, &(), &(), &())
                         },
                     ))
                 })
@@ -430,3 +431,56 @@ pub(crate) fn dijkstra_keep_ties<FE: ForbiddenEdge, FN: ForbiddenNode, G: Goal +
 
     Ok(ret)
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::DbInstance;
+
+    #[test]
+    fn test_dijkstra_on_stored_and_derived_edges() {
+        let db = DbInstance::default();
+        db.run_default(
+            r#"
+            ?[fr, to, dist] <- [['a', 'b', 1.0], ['b', 'c', 1.0], ['a', 'c', 3.0],
+                                ['c', 'd', 0.5], ['x', 'y', 1.0]]
+            :create road {fr, to => dist}
+            "#,
+        )
+        .unwrap();
+
+        let res = db
+            .run_default(
+                r#"
+                start[] <- [['a']]
+                # unreachable goals get an infinite cost and an empty path
+                end[] <- [['d'], ['y']]
+                ?[fr, to, cost, path] <~ ShortestPathDijkstra(*road[], start[], end[])
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(
+            res["rows"],
+            json!([
+                ["a", "d", 2.5, ["a", "b", "c", "d"]],
+                ["a", "y", "INFINITY", []]
+            ])
+        );
+
+        // the edges can be computed by rules as well
+        let res = db
+            .run_default(
+                r#"
+                fast[fr, to, dist] := *road{fr, to, dist}, fr != 'b'
+                start[] <- [['a']]
+                end[] <- [['d']]
+                ?[fr, to, cost, path] <~ ShortestPathDijkstra(fast[], start[], end[])
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([["a", "d", 3.5, ["a", "c", "d"]]]));
+    }
+}
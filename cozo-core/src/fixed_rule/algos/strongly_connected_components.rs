@@ -5,40 +5,26 @@
  * If a copy of the MPL was not distributed with this file,
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
-#![allow(unused_imports)]
 
 use graph::prelude::{DirectedCsrGraph, DirectedNeighbors, Graph};
 use std::cmp::min;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
-use itertools::Itertools;
 use miette::Result;
 use smartstring::{LazyCompact, SmartString};
 
 use crate::data::expr::Expr;
-use crate::data::program::{MagicFixedRuleApply, MagicSymbol};
 use crate::data::symb::Symbol;
-use crate::data::tuple::Tuple;
 use crate::data::value::DataValue;
 use crate::fixed_rule::{FixedRule, FixedRulePayload};
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
-use crate::runtime::temp_store::{EpochStore, RegularTempStore};
-use crate::runtime::transact::SessionTx;
+use crate::runtime::temp_store::RegularTempStore;
 
-#[cfg(feature = "graph-algo")]
-pub(crate) struct StronglyConnectedComponent {
-    strong: bool,
-}
-#[cfg(feature = "graph-algo")]
-impl StronglyConnectedComponent {
-    pub(crate) fn new(strong: bool) -> Self {
-        Self { strong }
-    }
-}
+pub(crate) struct StronglyConnectedComponent;
 
-#[cfg(feature = "graph-algo")]
 impl FixedRule for StronglyConnectedComponent {
+    #[allow(clippy::mutable_key_type)]
     fn run(
         &self,
         payload: FixedRulePayload<'_, '_>,
@@ -47,25 +33,30 @@ impl FixedRule for StronglyConnectedComponent {
     ) -> Result<()> {
         let edges = payload.get_input(0)?;
 
-        let (graph, indices, mut inv_indices) = edges.as_directed_graph(!self.strong)?;
+        let graph_data = edges.as_cached_directed_graph(false)?;
+        let (graph, indices, inv_indices) = &*graph_data;
 
         let tarjan = TarjanSccG::new(graph).run(poison)?;
-        for (grp_id, cc) in tarjan.iter().enumerate() {
+        // Tarjan's algorithm completes components in reverse topological order,
+        // number them so that edges of the condensation go from smaller to larger ids
+        let n_components = tarjan.len();
+        for (i, cc) in tarjan.iter().enumerate() {
+            let grp_id = (n_components - 1 - i) as i64;
             for idx in cc {
                 let val = indices.get(*idx as usize).unwrap();
-                let tuple = vec![val.clone(), DataValue::from(grp_id as i64)];
+                let tuple = vec![val.clone(), DataValue::from(grp_id)];
                 out.put(tuple);
             }
         }
 
-        let mut counter = tarjan.len() as i64;
+        let mut counter = n_components as i64;
 
         if let Ok(nodes) = payload.get_input(1) {
+            let mut isolated = BTreeSet::new();
             for tuple in nodes.iter()? {
                 let tuple = tuple?;
                 let node = tuple.into_iter().next().unwrap();
-                if !inv_indices.contains_key(&node) {
-                    inv_indices.insert(node.clone(), u32::MAX);
+                if !inv_indices.contains_key(&node) && isolated.insert(node.clone()) {
                     let tuple = vec![node, DataValue::from(counter)];
                     out.put(tuple);
                     counter += 1;
@@ -86,8 +77,8 @@ impl FixedRule for StronglyConnectedComponent {
     }
 }
 
-pub(crate) struct TarjanSccG {
-    graph: DirectedCsrGraph<u32>,
+pub(crate) struct TarjanSccG<'a> {
+    graph: &'a DirectedCsrGraph<u32>,
     id: u32,
     ids: Vec<Option<u32>>,
     low: Vec<u32>,
@@ -95,8 +86,8 @@ pub(crate) struct TarjanSccG {
     stack: Vec<u32>,
 }
 
-impl TarjanSccG {
-    pub(crate) fn new(graph: DirectedCsrGraph<u32>) -> Self {
+impl<'a> TarjanSccG<'a> {
+    pub(crate) fn new(graph: &'a DirectedCsrGraph<u32>) -> Self {
         let graph_size = graph.node_count();
         Self {
             graph,
@@ -107,43 +98,112 @@ impl TarjanSccG {
             stack: vec![],
         }
     }
+    /// Returns the components in the order they are completed,
+    /// which is a reverse topological order of the condensation.
     pub(crate) fn run(mut self, poison: Poison) -> Result<Vec<Vec<u32>>> {
+        let mut components = vec![];
         for i in 0..self.graph.node_count() {
             if self.ids[i as usize].is_none() {
-                self.dfs(i);
-                poison.check()?;
+                self.dfs(i, &mut components, &poison)?;
             }
         }
-
-        let mut low_map: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
-        for (idx, grp) in self.low.into_iter().enumerate() {
-            low_map.entry(grp).or_default().push(idx as u32);
-        }
-
-        Ok(low_map.into_values().collect_vec())
+        Ok(components)
     }
-    fn dfs(&mut self, at: u32) {
+    fn visit(&mut self, at: u32) {
         self.stack.push(at);
         self.on_stack[at as usize] = true;
         self.id += 1;
         self.ids[at as usize] = Some(self.id);
         self.low[at as usize] = self.id;
-        for to in self.graph.out_neighbors(at).cloned().collect_vec() {
-            if self.ids[to as usize].is_none() {
-                self.dfs(to);
+    }
+    // iterative, since the recursion depth would be the length of the longest simple path
+    fn dfs(&mut self, root: u32, components: &mut Vec<Vec<u32>>, poison: &Poison) -> Result<()> {
+        self.visit(root);
+        let mut call_stack: Vec<(u32, usize)> = vec![(root, 0)];
+        while let Some((at, pos)) = call_stack.last().copied() {
+            if let Some(to) = self.graph.out_neighbors(at).nth(pos).copied() {
+                call_stack.last_mut().unwrap().1 += 1;
+                match self.ids[to as usize] {
+                    None => {
+                        self.visit(to);
+                        call_stack.push((to, 0));
+                    }
+                    Some(to_id) => {
+                        if self.on_stack[to as usize] {
+                            self.low[at as usize] = min(self.low[at as usize], to_id);
+                        }
+                    }
+                }
+                continue;
             }
-            if self.on_stack[to as usize] {
-                self.low[at as usize] = min(self.low[at as usize], self.low[to as usize]);
+
+            call_stack.pop();
+            if let Some((parent, _)) = call_stack.last() {
+                self.low[*parent as usize] = min(self.low[*parent as usize], self.low[at as usize]);
             }
-        }
-        if self.ids[at as usize].unwrap() == self.low[at as usize] {
-            while let Some(node) = self.stack.pop() {
-                self.on_stack[node as usize] = false;
-                self.low[node as usize] = self.ids[at as usize].unwrap();
-                if node == at {
-                    break;
+            if self.ids[at as usize].unwrap() == self.low[at as usize] {
+                let mut component = vec![];
+                while let Some(node) = self.stack.pop() {
+                    self.on_stack[node as usize] = false;
+                    component.push(node);
+                    if node == at {
+                        break;
+                    }
                 }
+                component.reverse();
+                components.push(component);
             }
+            poison.check()?;
         }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::DbInstance;
+
+    #[test]
+    fn test_strongly_connected_components() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(
+                r#"
+                edges[] <- [['a', 'b'], ['b', 'c'], ['c', 'a'], ['c', 'd'], ['d', 'e'], ['e', 'd'],
+                            ['x', 'a'], ['f', 'f']]
+                nodes[] <- [['a'], ['lonely']]
+                ?[node, scc] <~ StronglyConnectedComponents(edges[], nodes[])
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        // condensation edges always go from a smaller to a larger id: x -> {a, b, c} -> {d, e}
+        assert_eq!(
+            res["rows"],
+            json!([
+                ["a", 2],
+                ["b", 2],
+                ["c", 2],
+                ["d", 3],
+                ["e", 3],
+                ["f", 1],
+                ["lonely", 4],
+                ["x", 0]
+            ])
+        );
+
+        let res = db
+            .run_default(
+                r#"
+                edges[i, j] := i in int_range(200000), j = (i + 1) % 200000
+                ?[count(node), count_unique(scc)] := c[node, scc]
+                c[node, scc] <~ SCC(edges[])
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([[200000, 1]]));
     }
 }
@@ -10,7 +10,7 @@ use std::collections::{BTreeMap, BTreeSet};
 
 use graph::prelude::{DirectedCsrGraph, DirectedNeighborsWithValues};
 use itertools::Itertools;
-use miette::Result;
+use miette::{bail, Result};
 #[cfg(feature = "rayon")]
 use rayon::prelude::*;
 use smartstring::{LazyCompact, SmartString};
@@ -19,7 +19,7 @@ use crate::data::expr::Expr;
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
 use crate::fixed_rule::algos::shortest_path_dijkstra::dijkstra;
-use crate::fixed_rule::{FixedRule, FixedRulePayload};
+use crate::fixed_rule::{BadExprValueError, FixedRule, FixedRulePayload};
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
 use crate::runtime::temp_store::RegularTempStore;
@@ -36,10 +36,34 @@ impl FixedRule for KShortestPathYen {
         let edges = payload.get_input(0)?;
         let starting = payload.get_input(1)?;
         let termination = payload.get_input(2)?;
+        let pair_ks = payload.get_input(3).ok();
         let undirected = payload.bool_option("undirected", Some(false))?;
-        let k = payload.pos_integer_option("k", None)?;
+        let k = payload.pos_integer_option("k", pair_ks.map(|_| 1))?;
 
-        let (graph, indices, inv_indices) = edges.as_directed_weighted_graph(undirected, false)?;
+        let graph_data = edges.as_cached_directed_weighted_graph(undirected, false)?;
+        let (graph, indices, inv_indices) = &*graph_data;
+
+        // rows `[start, goal, k]` of the optional fourth relation override `k` for their pair
+        let mut k_for_pair: BTreeMap<(u32, u32), usize> = BTreeMap::new();
+        if let Some(pair_ks) = pair_ks {
+            for tuple in pair_ks.ensure_min_len(3)?.iter()? {
+                let tuple = tuple?;
+                let pair_k = match tuple[2].get_int() {
+                    Some(i) if i > 0 => i as usize,
+                    _ => bail!(BadExprValueError(
+                        tuple[2].clone(),
+                        pair_ks.span(),
+                        "k must be a positive integer".to_string()
+                    )),
+                };
+                if let (Some(start), Some(goal)) =
+                    (inv_indices.get(&tuple[0]), inv_indices.get(&tuple[1]))
+                {
+                    k_for_pair.insert((*start, *goal), pair_k);
+                }
+            }
+        }
+        let k_for = |start: u32, goal: u32| k_for_pair.get(&(start, goal)).cloned().unwrap_or(k);
 
         let mut starting_nodes = BTreeSet::new();
         for tuple in starting.iter()? {
@@ -60,9 +84,13 @@ impl FixedRule for KShortestPathYen {
         if starting_nodes.len() <= 1 && termination_nodes.len() <= 1 {
             for start in starting_nodes {
                 for goal in &termination_nodes {
-                    for (cost, path) in
-                        k_shortest_path_yen(k, &graph, start, *goal, poison.clone())?
-                    {
+                    for (cost, path) in k_shortest_path_yen(
+                        k_for(start, *goal),
+                        graph,
+                        start,
+                        *goal,
+                        poison.clone(),
+                    )? {
                         let t = vec![
                             indices[start as usize].clone(),
                             indices[*goal as usize].clone(),
@@ -90,7 +118,13 @@ impl FixedRule for KShortestPathYen {
                         Ok((
                             start,
                             goal,
-                            k_shortest_path_yen(k, &graph, start, goal, poison.clone())?,
+                            k_shortest_path_yen(
+                                k_for(start, goal),
+                                graph,
+                                start,
+                                goal,
+                                poison.clone(),
+                            )?,
                         ))
                     },
                 )
@@ -140,6 +174,8 @@ fn k_shortest_path_yen(
         .next()
     {
         None => return Ok(k_shortest),
+        // an unreachable goal is reported as by Dijkstra, there are no further paths to it
+        Some((_, cost, path)) if path.is_empty() => return Ok(vec![(cost, path)]),
         Some((_, cost, path)) => k_shortest.push((cost, path)),
     }
 
@@ -209,3 +245,71 @@ fn k_shortest_path_yen(
     }
     Ok(k_shortest)
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::DbInstance;
+
+    #[test]
+    fn test_yen() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(
+                r#"
+                edges[fr, to, dist] <- [['a', 'b', 1.0], ['b', 'd', 1.0], ['a', 'c', 1.0],
+                                        ['c', 'd', 2.0], ['a', 'd', 4.0], ['x', 'y', 1.0]]
+                start[] <- [['a']]
+                goal[] <- [['d'], ['y']]
+                ?[fr, to, cost, path] <~ KShortestPathYen(edges[], start[], goal[], k: 3)
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(
+            res["rows"],
+            json!([
+                ["a", "d", 2.0, ["a", "b", "d"]],
+                ["a", "d", 3.0, ["a", "c", "d"]],
+                ["a", "d", 4.0, ["a", "d"]],
+                ["a", "y", "INFINITY", []]
+            ])
+        );
+
+        // k can be given for each pair, and defaults to 1 for the others
+        let res = db
+            .run_default(
+                r#"
+                edges[fr, to, dist] <- [['a', 'b', 1.0], ['b', 'd', 1.0], ['a', 'c', 1.0],
+                                        ['c', 'd', 2.0], ['a', 'd', 4.0], ['b', 'c', 0.5]]
+                start[] <- [['a'], ['b']]
+                goal[] <- [['c'], ['d']]
+                ks[fr, to, k] <- [['a', 'd', 2], ['b', 'd', 5]]
+                ?[fr, to, cost, path] <~ KShortestPathYen(edges[], start[], goal[], ks[])
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(
+            res["rows"],
+            json!([
+                ["a", "c", 1.0, ["a", "c"]],
+                ["a", "d", 2.0, ["a", "b", "d"]],
+                ["a", "d", 3.0, ["a", "c", "d"]],
+                ["b", "c", 0.5, ["b", "c"]],
+                ["b", "d", 1.0, ["b", "d"]],
+                ["b", "d", 2.5, ["b", "c", "d"]]
+            ])
+        );
+        assert!(db
+            .run_default(
+                r#"
+                edges[fr, to] <- [['a', 'b']]
+                ks[fr, to, k] <- [['a', 'b', 0]]
+                ?[fr, to, cost, path] <~ KShortestPathYen(edges[], edges[fr], edges[_, to], ks[])
+                "#,
+            )
+            .is_err());
+    }
+}
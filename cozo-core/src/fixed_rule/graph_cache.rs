@@ -0,0 +1,185 @@
+/*
+ * Copyright 2022, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Graphs built from the input relations of fixed rules, shared by all fixed rules of a query.
+#![cfg_attr(not(feature = "graph-algo"), allow(dead_code))]
+
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use miette::Result;
+
+use crate::data::program::{MagicFixedRuleRuleArg, MagicSymbol};
+use crate::data::symb::Symbol;
+use crate::data::value::ValidityTs;
+
+/// The relation a graph is built from.
+/// Within a query, the content of both kinds of relations cannot change once they are readable
+/// by a fixed rule, so this identifies the graph.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) enum GraphSource {
+    InMem(MagicSymbol),
+    Stored(Symbol, Option<ValidityTs>),
+}
+
+impl From<&MagicFixedRuleRuleArg> for GraphSource {
+    fn from(arg: &MagicFixedRuleRuleArg) -> Self {
+        match arg {
+            MagicFixedRuleRuleArg::InMem { name, .. } => GraphSource::InMem(name.clone()),
+            MagicFixedRuleRuleArg::Stored { name, valid_at, .. } => {
+                GraphSource::Stored(name.clone(), *valid_at)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) struct GraphKey {
+    pub(crate) source: GraphSource,
+    pub(crate) weighted: bool,
+    pub(crate) undirected: bool,
+    pub(crate) allow_negative_weights: bool,
+}
+
+type GraphSlot = Arc<Mutex<Option<Arc<dyn Any + Send + Sync>>>>;
+
+/// Lives for the evaluation of a single query.
+#[derive(Default)]
+pub(crate) struct GraphCache {
+    slots: Mutex<BTreeMap<GraphKey, GraphSlot>>,
+}
+
+impl GraphCache {
+    /// Get the graph for `key`, building it with `build` on the first request.
+    /// Fixed rules in the same stratum run in parallel, concurrent requests for the same key
+    /// wait for the first one to finish building instead of building the graph again.
+    pub(crate) fn get_or_build<T: Any + Send + Sync>(
+        &self,
+        key: GraphKey,
+        build: impl FnOnce() -> Result<T>,
+    ) -> Result<Arc<T>> {
+        let slot = self.slots.lock().unwrap().entry(key).or_default().clone();
+        let mut slot = slot.lock().unwrap();
+        if let Some(graph) = &*slot {
+            // the key determines the type of the graph
+            return Ok(graph.clone().downcast::<T>().unwrap());
+        }
+        let graph = Arc::new(build()?);
+        *slot = Some(graph.clone());
+        Ok(graph)
+    }
+    /// Drop the graphs built from in-memory relations that have been dropped themselves.
+    pub(crate) fn retain_in_mem(&self, mut keep: impl FnMut(&MagicSymbol) -> bool) {
+        self.slots
+            .lock()
+            .unwrap()
+            .retain(|key, _| match &key.source {
+                GraphSource::InMem(name) => keep(name),
+                GraphSource::Stored(..) => true,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::parse::SourceSpan;
+    use crate::DbInstance;
+
+    fn in_mem_key(name: &str, undirected: bool) -> GraphKey {
+        GraphKey {
+            source: GraphSource::InMem(MagicSymbol::Muggle {
+                inner: Symbol::new(name, SourceSpan::default()),
+            }),
+            weighted: false,
+            undirected,
+            allow_negative_weights: false,
+        }
+    }
+
+    #[test]
+    fn test_graph_cache() {
+        let cache = GraphCache::default();
+        let builds = AtomicUsize::new(0);
+        let build = |n: usize| {
+            builds.fetch_add(1, Ordering::SeqCst);
+            Ok(n)
+        };
+
+        assert_eq!(
+            *cache
+                .get_or_build(in_mem_key("e", false), || build(1))
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            *cache
+                .get_or_build(in_mem_key("e", false), || build(2))
+                .unwrap(),
+            1
+        );
+        assert_eq!(builds.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            *cache
+                .get_or_build(in_mem_key("e", true), || build(3))
+                .unwrap(),
+            3
+        );
+        assert_eq!(builds.load(Ordering::SeqCst), 2);
+
+        // a failed build is retried on the next request
+        assert!(cache
+            .get_or_build::<usize>(in_mem_key("f", false), || miette::bail!("failed"))
+            .is_err());
+        assert_eq!(
+            *cache
+                .get_or_build(in_mem_key("f", false), || build(4))
+                .unwrap(),
+            4
+        );
+
+        cache.retain_in_mem(|name| name.symbol().name != "e");
+        assert_eq!(
+            *cache
+                .get_or_build(in_mem_key("e", false), || build(5))
+                .unwrap(),
+            5
+        );
+        assert_eq!(
+            *cache
+                .get_or_build(in_mem_key("f", false), || build(6))
+                .unwrap(),
+            4
+        );
+    }
+
+    #[test]
+    fn test_shared_graph() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(
+                r#"
+                edges[] <- [[1, 2], [2, 3], [3, 1], [3, 4]]
+                scc[n, c] <~ StronglyConnectedComponents(edges[])
+                ranks[n, r] <~ PageRank(edges[])
+                ?[n, c, ok] := scc[n, c], ranks[n, r], ok = r > 0
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(
+            res["rows"],
+            json!([[1, 0, true], [2, 0, true], [3, 0, true], [4, 1, true]])
+        );
+    }
+}
@@ -32,6 +32,9 @@ use crate::data::tuple::TupleIter;
 use crate::data::value::DataValue;
 #[cfg(feature = "graph-algo")]
 use crate::fixed_rule::algos::*;
+use crate::fixed_rule::graph_cache::GraphCache;
+#[cfg(feature = "graph-algo")]
+use crate::fixed_rule::graph_cache::GraphKey;
 use crate::fixed_rule::utilities::*;
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
@@ -41,6 +44,7 @@ use crate::NamedRows;
 
 #[cfg(feature = "graph-algo")]
 pub(crate) mod algos;
+pub(crate) mod graph_cache;
 pub(crate) mod utilities;
 
 /// Passed into implementation of fixed rule, can be used to obtain relation inputs and options
@@ -48,6 +52,7 @@ pub struct FixedRulePayload<'a, 'b> {
     pub(crate) manifest: &'a MagicFixedRuleApply,
     pub(crate) stores: &'a BTreeMap<MagicSymbol, EpochStore>,
     pub(crate) tx: &'a SessionTx<'b>,
+    pub(crate) graph_cache: &'a GraphCache,
 }
 
 /// Represents an input relation during the execution of a fixed rule
@@ -56,6 +61,7 @@ pub struct FixedRuleInputRelation<'a, 'b> {
     arg_manifest: &'a MagicFixedRuleRuleArg,
     stores: &'a BTreeMap<MagicSymbol, EpochStore>,
     tx: &'a SessionTx<'b>,
+    graph_cache: &'a GraphCache,
 }
 
 impl<'a, 'b> FixedRuleInputRelation<'a, 'b> {
@@ -326,6 +332,54 @@ impl<'a, 'b> FixedRuleInputRelation<'a, 'b> {
 
         Ok((graph, indices, inv_indices))
     }
+    /// Same as [Self::as_directed_graph], but the graph is built at most once per query
+    /// for each input relation, and shared by all fixed rules that request it.
+    #[cfg(feature = "graph-algo")]
+    #[allow(clippy::type_complexity)]
+    pub fn as_cached_directed_graph(
+        &self,
+        undirected: bool,
+    ) -> Result<
+        Arc<(
+            DirectedCsrGraph<u32>,
+            Vec<DataValue>,
+            BTreeMap<DataValue, u32>,
+        )>,
+    > {
+        let key = GraphKey {
+            source: self.arg_manifest.into(),
+            weighted: false,
+            undirected,
+            allow_negative_weights: false,
+        };
+        self.graph_cache
+            .get_or_build(key, || self.as_directed_graph(undirected))
+    }
+    /// Same as [Self::as_directed_weighted_graph], but the graph is built at most once per query
+    /// for each input relation, and shared by all fixed rules that request it.
+    #[cfg(feature = "graph-algo")]
+    #[allow(clippy::type_complexity)]
+    pub fn as_cached_directed_weighted_graph(
+        &self,
+        undirected: bool,
+        allow_negative_weights: bool,
+    ) -> Result<
+        Arc<(
+            DirectedCsrGraph<u32, (), f32>,
+            Vec<DataValue>,
+            BTreeMap<DataValue, u32>,
+        )>,
+    > {
+        let key = GraphKey {
+            source: self.arg_manifest.into(),
+            weighted: true,
+            undirected,
+            allow_negative_weights,
+        };
+        self.graph_cache.get_or_build(key, || {
+            self.as_directed_weighted_graph(undirected, allow_negative_weights)
+        })
+    }
 }
 
 impl<'a, 'b> FixedRulePayload<'a, 'b> {
@@ -340,6 +394,7 @@ impl<'a, 'b> FixedRulePayload<'a, 'b> {
             arg_manifest,
             stores: self.stores,
             tx: self.tx,
+            graph_cache: self.graph_cache,
         })
     }
     /// Get the name of the current fixed rule
@@ -721,6 +776,11 @@ lazy_static! {
                 Arc::<Box<dyn FixedRule>>::new(Box::new(ClosenessCentrality)),
             ),
             #[cfg(feature = "graph-algo")]
+            (
+                "HarmonicCentrality".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(HarmonicCentrality)),
+            ),
+            #[cfg(feature = "graph-algo")]
             (
                 "BetweennessCentrality".to_string(),
                 Arc::<Box<dyn FixedRule>>::new(Box::new(BetweennessCentrality)),
@@ -776,6 +836,21 @@ lazy_static! {
                 Arc::<Box<dyn FixedRule>>::new(Box::new(MinimumSpanningForestKruskal)),
             ),
             #[cfg(feature = "graph-algo")]
+            (
+                "MaximumBipartiteMatching".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(MaximumBipartiteMatching)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "MaximumFlow".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(MaximumFlow)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "ReconstructPath".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(ReconstructPath)),
+            ),
+            #[cfg(feature = "graph-algo")]
             (
                 "TopSort".to_string(),
                 Arc::<Box<dyn FixedRule>>::new(Box::new(TopSort)),
@@ -783,17 +858,17 @@ lazy_static! {
             #[cfg(feature = "graph-algo")]
             (
                 "ConnectedComponents".to_string(),
-                Arc::<Box<dyn FixedRule>>::new(Box::new(StronglyConnectedComponent::new(false))),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(ConnectedComponents)),
             ),
             #[cfg(feature = "graph-algo")]
             (
                 "StronglyConnectedComponents".to_string(),
-                Arc::<Box<dyn FixedRule>>::new(Box::new(StronglyConnectedComponent::new(true))),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(StronglyConnectedComponent)),
             ),
             #[cfg(feature = "graph-algo")]
             (
                 "SCC".to_string(),
-                Arc::<Box<dyn FixedRule>>::new(Box::new(StronglyConnectedComponent::new(true))),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(StronglyConnectedComponent)),
             ),
             #[cfg(feature = "graph-algo")]
             (
@@ -801,6 +876,11 @@ lazy_static! {
                 Arc::<Box<dyn FixedRule>>::new(Box::new(PageRank)),
             ),
             #[cfg(feature = "graph-algo")]
+            (
+                "PersonalizedPageRank".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(PersonalizedPageRank)),
+            ),
+            #[cfg(feature = "graph-algo")]
             (
                 "CommunityDetectionLouvain".to_string(),
                 Arc::<Box<dyn FixedRule>>::new(Box::new(CommunityDetectionLouvain)),
@@ -819,6 +899,14 @@ lazy_static! {
                 "ReorderSort".to_string(),
                 Arc::<Box<dyn FixedRule>>::new(Box::new(ReorderSort)),
             ),
+            (
+                "MinHashLsh".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(MinHashLsh)),
+            ),
+            (
+                "VectorSearch".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(VectorSearch)),
+            ),
             (
                 "JsonReader".to_string(),
                 Arc::<Box<dyn FixedRule>>::new(Box::new(JsonReader)),
@@ -0,0 +1,201 @@
+/*
+ * Copyright 2022, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use miette::{bail, ensure, Result};
+use rand::prelude::*;
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::Expr;
+use crate::data::program::WrongFixedRuleOptionError;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::{FixedRule, FixedRulePayload};
+use crate::fts::TokenizerConfig;
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::minhash_lsh::{HashPermutations, HashValues, LshParams, Weights};
+use crate::runtime::temp_store::RegularTempStore;
+
+/// Finds pairs of rows whose values are near-duplicates, without comparing all pairs:
+/// rows are only compared if their MinHash signatures agree on at least one band.
+pub(crate) struct MinHashLsh;
+
+impl FixedRule for MinHashLsh {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let in_rel = payload.get_input(0)?.ensure_min_len(2)?;
+        let threshold = payload.unit_interval_option("threshold", Some(0.9))?;
+        let n_perm = payload.pos_integer_option("n_perm", Some(200))?;
+        let n_gram = payload.pos_integer_option("n_gram", Some(1))?;
+        let false_positive_weight = payload.float_option("false_positive_weight", Some(1.))?;
+        let false_negative_weight = payload.float_option("false_negative_weight", Some(1.))?;
+        for (name, weight) in [
+            ("false_positive_weight", false_positive_weight),
+            ("false_negative_weight", false_negative_weight),
+        ] {
+            ensure!(
+                weight > 0.,
+                WrongFixedRuleOptionError {
+                    name: name.to_string(),
+                    span: payload.option_span(name)?,
+                    rule_name: payload.name().to_string(),
+                    help: "a positive number is required".to_string(),
+                }
+            );
+        }
+        let tokenizer = TokenizerConfig {
+            name: payload.string_option("tokenizer", Some("Simple"))?,
+            args: vec![],
+        };
+        let filters = if payload.bool_option("lowercase", Some(true))? {
+            vec![TokenizerConfig {
+                name: "Lowercase".into(),
+                args: vec![],
+            }]
+        } else {
+            vec![]
+        };
+        let tokenizer = tokenizer.build(&filters)?;
+        // the permutations are random, a fixed seed makes the candidates reproducible
+        let mut rng = if payload.manifest.options.contains_key("seed") {
+            StdRng::seed_from_u64(payload.integer_option("seed", None)? as u64)
+        } else {
+            StdRng::from_entropy()
+        };
+
+        let params = LshParams::find_optimal_params(
+            threshold,
+            n_perm,
+            &Weights(false_positive_weight, false_negative_weight),
+        );
+        let perms = HashPermutations::with_rng(params.b * params.r, &mut rng);
+
+        let mut ids = vec![];
+        let mut signatures = vec![];
+        for tuple in in_rel.iter()? {
+            let tuple = tuple?;
+            let signature = match &tuple[1] {
+                DataValue::Null => continue,
+                DataValue::List(l) => HashValues::new(l.iter(), &perms),
+                DataValue::Str(s) => {
+                    let n_grams = tokenizer.unique_ngrams(s, n_gram);
+                    HashValues::new(n_grams.iter(), &perms)
+                }
+                v => bail!(
+                    "MinHashLsh requires strings or lists to compare, got {:?}",
+                    v
+                ),
+            };
+            ids.push(tuple[0].clone());
+            signatures.push(signature);
+            poison.check()?;
+        }
+
+        let mut candidates: BTreeSet<(usize, usize)> = BTreeSet::new();
+        for band in 0..params.b {
+            let mut buckets: BTreeMap<&[u32], Vec<usize>> = BTreeMap::new();
+            for (i, signature) in signatures.iter().enumerate() {
+                let rows = &signature.0[band * params.r..(band + 1) * params.r];
+                buckets.entry(rows).or_default().push(i);
+            }
+            for bucket in buckets.values() {
+                for (pos, i) in bucket.iter().enumerate() {
+                    for j in &bucket[pos + 1..] {
+                        candidates.insert((*i, *j));
+                    }
+                }
+            }
+            poison.check()?;
+        }
+
+        for (i, j) in candidates {
+            if ids[i] == ids[j] {
+                continue;
+            }
+            let similarity = signatures[i].jaccard(&signatures[j]) as f64;
+            if similarity < threshold {
+                continue;
+            }
+            let (left, right) = if ids[i] < ids[j] {
+                (&ids[i], &ids[j])
+            } else {
+                (&ids[j], &ids[i])
+            };
+            out.put(vec![
+                left.clone(),
+                right.clone(),
+                DataValue::from(similarity),
+            ]);
+        }
+        Ok(())
+    }
+
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::DbInstance;
+
+    #[test]
+    fn test_minhash_lsh() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(
+                r#"
+                docs[] <- [[1, 'the quick brown fox jumps over the lazy dog'],
+                           [2, 'The quick brown fox jumps over the lazy dog!'],
+                           [3, 'the quick brown fox jumped over a lazy cat'],
+                           [4, 'lorem ipsum dolor sit amet'],
+                           [5, null],
+                           [6, ['a', 'b', 'c']],
+                           [7, ['c', 'b', 'a']]]
+                ?[l, r, s] <~ MinHashLsh(docs[], threshold: 0.8, seed: 42)
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([[1, 2, 1.0], [6, 7, 1.0]]));
+
+        // word pairs instead of single words
+        let res = db
+            .run_default(
+                r#"
+                docs[] <- [[1, 'a b c d e f g h'], [2, 'h g f e d c b a']]
+                ?[l, r, s] <~ MinHashLsh(docs[], threshold: 0.5, n_gram: 2, seed: 42)
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([]));
+
+        assert!(db
+            .run_default(
+                r#"
+                docs[] <- [[1, 2]]
+                ?[l, r, s] <~ MinHashLsh(docs[])
+                "#,
+            )
+            .is_err());
+    }
+}
@@ -9,9 +9,13 @@
 pub(crate) mod constant;
 pub(crate) mod csv;
 pub(crate) mod jlines;
+pub(crate) mod minhash_lsh;
 pub(crate) mod reorder_sort;
+pub(crate) mod vector_search;
 
 pub(crate) use self::csv::CsvReader;
 pub(crate) use constant::Constant;
 pub(crate) use jlines::JsonReader;
+pub(crate) use minhash_lsh::MinHashLsh;
 pub(crate) use reorder_sort::ReorderSort;
+pub(crate) use vector_search::VectorSearch;
@@ -6,6 +6,7 @@
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
 
 use itertools::Itertools;
@@ -55,58 +56,88 @@ impl FixedRule for ReorderSort {
             }
         };
 
-        let mut sort_by = payload.expr_option(
+        let sort_by = payload.expr_option(
             "sort_by",
             Some(Expr::Const {
                 val: DataValue::Null,
                 span: SourceSpan(0, 0),
             }),
         )?;
-        let sort_descending = payload.bool_option("descending", Some(false))?;
+        // a list of expressions gives several sort keys, each can have its own direction
+        let mut sort_keys = match sort_by {
+            Expr::Apply { op, args, .. } if *op == OP_LIST => args.to_vec(),
+            ex => vec![ex],
+        };
+        let descending = match payload.manifest.options.get("descending") {
+            None => vec![false; sort_keys.len()],
+            Some(ex) => match ex.clone().eval_to_const() {
+                Ok(DataValue::Bool(b)) => vec![b; sort_keys.len()],
+                Ok(DataValue::List(l))
+                    if l.len() == sort_keys.len()
+                        && l.iter().all(|v| matches!(v, DataValue::Bool(_))) =>
+                {
+                    l.iter().map(|v| *v == DataValue::Bool(true)).collect_vec()
+                }
+                _ => bail!(WrongFixedRuleOptionError {
+                    name: "descending".to_string(),
+                    span: ex.span(),
+                    rule_name: payload.name().to_string(),
+                    help: "a boolean, or a list of booleans, one for each sort key, is required"
+                        .to_string()
+                }),
+            },
+        };
         let break_ties = payload.bool_option("break_ties", Some(false))?;
         let skip = payload.non_neg_integer_option("skip", Some(0))?;
-        let take = payload.non_neg_integer_option("take", Some(0))?;
+        let take = if payload.manifest.options.contains_key("take") {
+            payload.non_neg_integer_option("take", None)?
+        } else {
+            usize::MAX
+        };
 
         let binding_map = in_rel.get_binding_map(0);
-        sort_by.fill_binding_indices(&binding_map)?;
+        for key in sort_keys.iter_mut() {
+            key.fill_binding_indices(&binding_map)?;
+        }
         for out in out_list.iter_mut() {
             out.fill_binding_indices(&binding_map)?;
         }
         let out_bytecods: Vec<_> = out_list.iter().map(|e| e.compile()).try_collect()?;
-        let sort_by_bytecodes = sort_by.compile()?;
+        let sort_by_bytecodes: Vec<_> = sort_keys.iter().map(|e| e.compile()).try_collect()?;
         let mut stack = vec![];
 
         let mut buffer = vec![];
         for tuple in in_rel.iter()? {
             let tuple = tuple?;
-            let sorter = eval_bytecode(&sort_by_bytecodes, &tuple, &mut stack)?;
-            let mut s_tuple: Vec<_> = out_bytecods
+            let sorter: Vec<_> = sort_by_bytecodes
+                .iter()
+                .map(|ex| eval_bytecode(ex, &tuple, &mut stack))
+                .try_collect()?;
+            let s_tuple: Vec<_> = out_bytecods
                 .iter()
                 .map(|ex| eval_bytecode(ex, &tuple, &mut stack))
                 .try_collect()?;
-            s_tuple.push(sorter);
-            buffer.push(s_tuple);
+            buffer.push((sorter, s_tuple));
             poison.check()?;
         }
-        if sort_descending {
-            buffer.sort_by(|l, r| r.last().cmp(&l.last()));
-        } else {
-            buffer.sort_by(|l, r| l.last().cmp(&r.last()));
-        }
+        buffer.sort_by(|(l, _), (r, _)| {
+            l.iter()
+                .zip(r.iter())
+                .zip(descending.iter())
+                .map(|((l, r), desc)| if *desc { r.cmp(l) } else { l.cmp(r) })
+                .find(|o| o.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
 
         let mut count = 0usize;
         let mut rank = 0usize;
-        let mut last = &DataValue::Bot;
+        let mut last = None;
         let take_plus_skip = take.saturating_add(skip);
-        for val in &buffer {
-            let sorter = val.last().unwrap();
-
-            if sorter == last {
-                count += 1;
-            } else {
-                count += 1;
+        for (sorter, val) in &buffer {
+            count += 1;
+            if last != Some(sorter) {
                 rank = count;
-                last = sorter;
+                last = Some(sorter);
             }
 
             if count > take_plus_skip {
@@ -117,7 +148,7 @@ impl FixedRule for ReorderSort {
                 continue;
             }
             let mut out_t = vec![DataValue::from(if break_ties { count } else { rank } as i64)];
-            out_t.extend_from_slice(&val[0..val.len() - 1]);
+            out_t.extend_from_slice(val);
             out.put(out_t);
             poison.check()?;
         }
@@ -151,3 +182,49 @@ impl FixedRule for ReorderSort {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::DbInstance;
+
+    #[test]
+    fn test_reorder_sort() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(
+                r#"
+                r[] <- [['a', 1, 3], ['b', 2, 1], ['c', 1, 2], ['d', 2, 1]]
+                ?[rank, name] <~ ReorderSort(r[name, x, y], out: [name], sort_by: [x, y],
+                                             descending: [true, false])
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([[1, "b"], [1, "d"], [3, "c"], [4, "a"]]));
+
+        let res = db
+            .run_default(
+                r#"
+                r[] <- [['a', 1, 3], ['b', 2, 1], ['c', 1, 2], ['d', 2, 1]]
+                ?[rank, name, s] <~ ReorderSort(r[name, x, y], out: [name, x + y], sort_by: x + y,
+                                                descending: true, break_ties: true, skip: 1,
+                                                take: 2)
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([[2, "b", 3], [3, "c", 3]]));
+
+        assert!(db
+            .run_default(
+                r#"
+                r[] <- [['a', 1, 3]]
+                ?[rank, name] <~ ReorderSort(r[name, x, y], out: [name], sort_by: [x, y],
+                                             descending: [true])
+                "#,
+            )
+            .is_err());
+    }
+}
@@ -0,0 +1,164 @@
+/*
+ * Copyright 2022, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::{BTreeMap, BinaryHeap};
+
+use miette::{bail, Result};
+use ordered_float::OrderedFloat;
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::Expr;
+use crate::data::functions::{op_cos_dist, op_ip_dist, op_l2_dist, op_vec};
+use crate::data::program::WrongFixedRuleOptionError;
+use crate::data::symb::Symbol;
+use crate::data::value::{DataValue, Vector};
+use crate::fixed_rule::{FixedRule, FixedRulePayload};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+/// Exact nearest neighbours by scanning every row, for collections too small to need an index.
+pub(crate) struct VectorSearch;
+
+impl FixedRule for VectorSearch {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let in_rel = payload.get_input(0)?.ensure_min_len(2)?;
+        let k = payload.pos_integer_option("k", None)?;
+        let distance = payload.string_option("distance", Some("L2"))?;
+        let dist_fn = match &distance as &str {
+            "L2" => op_l2_dist,
+            "Cosine" => op_cos_dist,
+            "IP" => op_ip_dist,
+            _ => bail!(WrongFixedRuleOptionError {
+                name: "distance".to_string(),
+                span: payload.option_span("distance")?,
+                rule_name: payload.name().to_string(),
+                help: "one of 'L2', 'Cosine' and 'IP' is required".to_string(),
+            }),
+        };
+        let query = match payload.expr_option("query", None)?.eval_to_const()? {
+            DataValue::Vec(v) => v,
+            DataValue::List(l) => match op_vec(&[DataValue::List(l), DataValue::from("F64")])? {
+                DataValue::Vec(v) => v,
+                _ => unreachable!(),
+            },
+            _ => bail!(WrongFixedRuleOptionError {
+                name: "query".to_string(),
+                span: payload.option_span("query")?,
+                rule_name: payload.name().to_string(),
+                help: "a vector or a list of numbers is required".to_string(),
+            }),
+        };
+        // the query is compared in the precision of each row
+        let (query_f32, query_f64) = match query {
+            Vector::F32(v) => (Vector::F32(v.clone()), Vector::F64(v.mapv(|x| x as f64))),
+            Vector::F64(v) => (Vector::F32(v.mapv(|x| x as f32)), Vector::F64(v)),
+        };
+        let query_f32 = DataValue::Vec(query_f32);
+        let query_f64 = DataValue::Vec(query_f64);
+
+        // the k closest rows seen so far with their ids, the farthest on top
+        let mut nearest: BinaryHeap<(OrderedFloat<f64>, DataValue)> = BinaryHeap::new();
+        for tuple in in_rel.iter()? {
+            let mut tuple = tuple?;
+            let query = match &tuple[1] {
+                DataValue::Null => continue,
+                DataValue::Vec(Vector::F32(_)) => &query_f32,
+                DataValue::Vec(Vector::F64(_)) => &query_f64,
+                v => bail!("VectorSearch requires vectors to compare, got {:?}", v),
+            };
+            let dist = dist_fn(&[tuple[1].clone(), query.clone()])?
+                .get_float()
+                .unwrap();
+            let candidate = (OrderedFloat(dist), tuple.swap_remove(0));
+            if nearest.len() < k {
+                nearest.push(candidate);
+            } else if candidate < *nearest.peek().unwrap() {
+                nearest.pop();
+                nearest.push(candidate);
+            }
+            poison.check()?;
+        }
+
+        for (dist, id) in nearest.into_sorted_vec() {
+            out.put(vec![id, DataValue::from(dist.0)]);
+        }
+        Ok(())
+    }
+
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::DbInstance;
+
+    #[test]
+    fn test_vector_search() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(
+                r#"
+                vecs[k, v] := k = 'a', v = vec([1, 0])
+                vecs[k, v] := k = 'b', v = vec([0, 3])
+                vecs[k, v] := k = 'c', v = vec([2, 2], 'F64')
+                vecs[k, v] := k = 'd', v = null
+                ?[k, d] <~ VectorSearch(vecs[], query: [1, 1], k: 2)
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([["a", 1.0], ["c", 2.0]]));
+
+        let res = db
+            .run_default(
+                r#"
+                vecs[k, v] := k = 'a', v = vec([1, 0])
+                vecs[k, v] := k = 'b', v = vec([0, 3])
+                vecs[k, v] := k = 'c', v = vec([2, 2])
+                ?[k, d] <~ VectorSearch(vecs[], query: vec([0, 1]), k: 5, distance: 'Cosine')
+                "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"][0], json!(["a", 1.0]));
+        assert_eq!(res["rows"][1], json!(["b", 0.0]));
+        assert_eq!(res["rows"].as_array().unwrap().len(), 3);
+
+        assert!(db
+            .run_default(
+                r#"
+                vecs[k, v] := k = 'a', v = vec([1, 0])
+                ?[k, d] <~ VectorSearch(vecs[], query: [1, 1, 1], k: 1)
+                "#,
+            )
+            .is_err());
+        assert!(db
+            .run_default(
+                r#"
+                vecs[k, v] := k = 'a', v = vec([1, 0])
+                ?[k, d] <~ VectorSearch(vecs[], query: [1, 1], k: 1, distance: 'Manhattan')
+                "#,
+            )
+            .is_err());
+    }
+}
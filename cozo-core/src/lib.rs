@@ -47,15 +47,25 @@ use miette::{
 };
 use serde_json::json;
 
-pub use data::value::{DataValue, Num, RegexWrapper, UuidWrapper, Validity, ValidityTs};
+pub use data::decimal::Decimal;
+pub use data::value::{
+    DataValue, GeoPoint, Interval, Num, RegexWrapper, Timestamp, UuidWrapper, Validity, ValidityTs,
+};
 pub use fixed_rule::{FixedRule, FixedRuleInputRelation, FixedRulePayload};
+pub use runtime::cursor::QueryCursor;
 pub use runtime::db::Db;
 pub use runtime::db::NamedRows;
 pub use runtime::relation::decode_tuple_from_kv;
 pub use runtime::temp_store::RegularTempStore;
+#[cfg(feature = "encryption")]
+pub use storage::encrypted::{new_cozo_encrypted, EncryptedStorage, EncryptedTx};
 pub use storage::mem::{new_cozo_mem, MemStorage};
+pub use storage::namespaced::{new_cozo_namespaced, NamespacedStorage, NamespacedTx};
 #[cfg(feature = "storage-rocksdb")]
-pub use storage::rocks::{new_cozo_rocksdb, RocksDbStorage};
+pub use storage::rocks::{
+    new_cozo_rocksdb, new_cozo_rocksdb_with_options, new_rocksdb_storage, RocksDbCompression,
+    RocksDbOptions, RocksDbStorage,
+};
 #[cfg(feature = "storage-sled")]
 pub use storage::sled::{new_cozo_sled, SledStorage};
 #[cfg(feature = "storage-sqlite")]
@@ -134,7 +144,8 @@ impl DbInstance {
     /// some of the engines are available. The `mem` engine is always available.
     ///
     /// `path` is ignored for `mem` and `tikv` engines.
-    /// `options` is ignored for every engine except `tikv`.
+    /// `options` is a JSON object and is ignored for every engine except `rocksdb`
+    /// (see [RocksDbOptions], e.g. `{"block_cache_size": 1073741824}`) and `tikv`.
     #[allow(unused_variables)]
     pub fn new(engine: &str, path: impl AsRef<Path>, options: &str) -> Result<Self> {
         let options = if options.is_empty() { "{}" } else { options };
@@ -143,7 +154,10 @@ impl DbInstance {
             #[cfg(feature = "storage-sqlite")]
             "sqlite" => Self::Sqlite(new_cozo_sqlite(path)?),
             #[cfg(feature = "storage-rocksdb")]
-            "rocksdb" => Self::RocksDb(new_cozo_rocksdb(path)?),
+            "rocksdb" => {
+                let opts: RocksDbOptions = serde_json::from_str(options).into_diagnostic()?;
+                Self::RocksDb(new_cozo_rocksdb_with_options(path, opts)?)
+            }
             #[cfg(feature = "storage-sled")]
             "sled" => Self::Sled(new_cozo_sled(path)?),
             #[cfg(feature = "storage-tikv")]
@@ -220,6 +234,25 @@ impl DbInstance {
             Err(err) => format_error_as_json(err, Some(payload)),
         }
     }
+    /// Dispatcher method. See [crate::Db::open_cursor].
+    pub fn open_cursor(
+        &self,
+        payload: &str,
+        params: BTreeMap<String, DataValue>,
+        batch_size: usize,
+    ) -> Result<QueryCursor> {
+        match self {
+            DbInstance::Mem(db) => db.open_cursor(payload, params, batch_size),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.open_cursor(payload, params, batch_size),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.open_cursor(payload, params, batch_size),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.open_cursor(payload, params, batch_size),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.open_cursor(payload, params, batch_size),
+        }
+    }
     /// Run the CozoScript passed in. The `params` argument is a map of parameters formatted as JSON.
     /// See [crate::Db::run_script].
     pub fn run_script_str(&self, payload: &str, params: &str, immutable: bool) -> String {
@@ -333,6 +366,76 @@ impl DbInstance {
             .collect::<Result<_>>()?;
         self.import_relations(mapping)
     }
+    /// Dispatcher method. See [crate::Db::set_memory_limit].
+    pub fn set_memory_limit(&self, limit: Option<usize>) {
+        match self {
+            DbInstance::Mem(db) => db.set_memory_limit(limit),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.set_memory_limit(limit),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.set_memory_limit(limit),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.set_memory_limit(limit),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.set_memory_limit(limit),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::set_timeout].
+    pub fn set_timeout(&self, secs: Option<f64>) {
+        match self {
+            DbInstance::Mem(db) => db.set_timeout(secs),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.set_timeout(secs),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.set_timeout(secs),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.set_timeout(secs),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.set_timeout(secs),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::set_bulk_load].
+    pub fn set_bulk_load(&self, enabled: bool, skip_insert_checks: bool) -> Result<()> {
+        match self {
+            DbInstance::Mem(db) => db.set_bulk_load(enabled, skip_insert_checks),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.set_bulk_load(enabled, skip_insert_checks),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.set_bulk_load(enabled, skip_insert_checks),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.set_bulk_load(enabled, skip_insert_checks),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.set_bulk_load(enabled, skip_insert_checks),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::compact_range].
+    pub fn compact_range(&self, relation: Option<&str>) -> Result<()> {
+        match self {
+            DbInstance::Mem(db) => db.compact_range(relation),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.compact_range(relation),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.compact_range(relation),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.compact_range(relation),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.compact_range(relation),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::backup].
+    pub fn backup(&self, path: impl AsRef<Path>) -> Result<()> {
+        match self {
+            DbInstance::Mem(db) => db.backup(path),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.backup(path),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.backup(path),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.backup(path),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.backup(path),
+        }
+    }
     /// Dispatcher method. See [crate::Db::backup_db].
     pub fn backup_db(&self, out_file: impl AsRef<Path>) -> Result<()> {
         match self {
@@ -369,6 +472,20 @@ impl DbInstance {
             DbInstance::TiKv(db) => db.restore_backup(in_file),
         }
     }
+    /// Dispatcher method. See [crate::Db::restore_backup_forced].
+    pub fn restore_backup_forced(&self, in_file: impl AsRef<Path>) -> Result<()> {
+        match self {
+            DbInstance::Mem(db) => db.restore_backup_forced(in_file),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.restore_backup_forced(in_file),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.restore_backup_forced(in_file),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.restore_backup_forced(in_file),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.restore_backup_forced(in_file),
+        }
+    }
     /// Restore from an Sqlite backup, with JSON string return value.
     /// See [crate::Db::restore_backup].
     pub fn restore_backup_str(&self, in_file: impl AsRef<Path>) -> String {
@@ -480,6 +597,37 @@ impl DbInstance {
             DbInstance::TiKv(db) => db.unregister_fixed_rule(name),
         }
     }
+    /// Dispatcher method. See [crate::Db::register_fn].
+    pub fn register_fn<F>(&self, name: &str, arity: usize, func: F) -> Result<()>
+    where
+        F: Fn(&[DataValue]) -> Result<DataValue> + Send + Sync + 'static,
+    {
+        match self {
+            DbInstance::Mem(db) => db.register_fn(name, arity, func),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.register_fn(name, arity, func),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.register_fn(name, arity, func),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.register_fn(name, arity, func),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.register_fn(name, arity, func),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::unregister_fn]
+    pub fn unregister_fn(&self, name: &str) -> Result<bool> {
+        match self {
+            DbInstance::Mem(db) => db.unregister_fn(name),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.unregister_fn(name),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.unregister_fn(name),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.unregister_fn(name),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.unregister_fn(name),
+        }
+    }
 
     /// Dispatcher method. See [crate::Db::run_multi_transaction]
     pub fn run_multi_transaction(
@@ -506,9 +654,9 @@ impl DbInstance {
         let (app2db_send, app2db_recv) = bounded(1);
         let (db2app_send, db2app_recv) = bounded(1);
         let db = self.clone();
-        #[cfg(target_arch = "wasm32")]
+        #[cfg(any(target_arch = "wasm32", not(feature = "rayon")))]
         std::thread::spawn(move || db.run_multi_transaction(write, app2db_recv, db2app_send));
-        #[cfg(not(target_arch = "wasm32"))]
+        #[cfg(all(not(target_arch = "wasm32"), feature = "rayon"))]
         rayon::spawn(move || db.run_multi_transaction(write, app2db_recv, db2app_send));
         MultiTransaction {
             sender: app2db_send,
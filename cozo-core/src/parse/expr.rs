@@ -6,8 +6,12 @@
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use std::cell::RefCell;
 use std::collections::BTreeMap;
+use std::mem;
 
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use miette::{bail, ensure, Diagnostic, Result};
@@ -15,16 +19,36 @@ use pest::pratt_parser::{Op, PrattParser};
 use smartstring::{LazyCompact, SmartString};
 use thiserror::Error;
 
-use crate::data::expr::{get_op, Bytecode, Expr, NoImplementationError};
+use crate::data::expr::{get_op, Bytecode, Expr, ListFnKind, NoImplementationError, UserFn};
 use crate::data::functions::{
-    OP_ADD, OP_AND, OP_COALESCE, OP_CONCAT, OP_DIV, OP_EQ, OP_GE, OP_GT, OP_JSON_OBJECT, OP_LE,
-    OP_LIST, OP_LT, OP_MAYBE_GET, OP_MINUS, OP_MOD, OP_MUL, OP_NEGATE, OP_NEQ, OP_OR, OP_POW,
-    OP_SUB,
+    hex_to_bytes, regex_pattern_pos, str2regex, OP_ADD, OP_AND, OP_COALESCE, OP_CONCAT, OP_DIV,
+    OP_EQ, OP_GE, OP_GT, OP_JSON_OBJECT, OP_LE, OP_LIST, OP_LT, OP_MAYBE_GET, OP_MINUS, OP_MOD,
+    OP_MUL, OP_NEGATE, OP_NEQ, OP_OR, OP_POW, OP_SUB,
 };
 use crate::data::symb::Symbol;
-use crate::data::value::DataValue;
+use crate::data::value::{DataValue, RegexWrapper};
+use crate::parse::query::scalar_subquery_binding;
 use crate::parse::{ExtractSpan, Pair, Rule, SourceSpan};
 
+thread_local! {
+    /// Functions registered on the database whose script is being parsed, see [with_user_fns]
+    static USER_FNS: RefCell<BTreeMap<String, UserFn>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+/// Makes the functions registered on a database callable from the expressions parsed in `f`
+pub(crate) fn with_user_fns<T>(user_fns: &BTreeMap<String, UserFn>, f: impl FnOnce() -> T) -> T {
+    struct Restore(BTreeMap<String, UserFn>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            USER_FNS.with(|fns| *fns.borrow_mut() = mem::take(&mut self.0));
+        }
+    }
+
+    let _restore = Restore(USER_FNS.with(|fns| fns.replace(user_fns.clone())));
+    f()
+}
+
 lazy_static! {
     static ref PRATT_PARSER: PrattParser<Rule> = {
         use pest::pratt_parser::Assoc::*;
@@ -55,6 +79,16 @@ lazy_static! {
 #[diagnostic(code(parser::invalid_expression))]
 pub(crate) struct InvalidExpression(#[label] pub(crate) SourceSpan);
 
+#[derive(Error, Diagnostic, Debug)]
+#[error("Wrong number of arguments for function '{0}'")]
+#[diagnostic(code(parser::func_wrong_num_args))]
+struct WrongNumArgsError(String, #[label] SourceSpan, #[help] String);
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("Invalid regular expression")]
+#[diagnostic(code(parser::bad_regex))]
+struct BadRegexError(#[label] SourceSpan, #[help] String);
+
 pub(crate) fn expr2bytecode(expr: &Expr, collector: &mut Vec<Bytecode>) -> Result<()> {
     match expr {
         Expr::Binding { var, tuple_pos } => collector.push(Bytecode::Binding {
@@ -65,10 +99,57 @@ pub(crate) fn expr2bytecode(expr: &Expr, collector: &mut Vec<Bytecode>) -> Resul
             val: val.clone(),
             span: *span,
         }),
+        Expr::Apply { op, args, span } if **op == OP_COALESCE => {
+            // each argument is evaluated only if all previous ones are null
+            let (last, init) = match args.split_last() {
+                Some(split) => split,
+                None => {
+                    collector.push(Bytecode::Const {
+                        val: DataValue::Null,
+                        span: *span,
+                    });
+                    return Ok(());
+                }
+            };
+            let mut jump_pos = vec![];
+            for arg in init {
+                // +1
+                expr2bytecode(arg, collector)?;
+                // -1 in the fallthrough branch
+                collector.push(Bytecode::JumpIfNotNull {
+                    jump_to: 0,
+                    span: *span,
+                });
+                jump_pos.push(collector.len() - 1);
+            }
+            expr2bytecode(last, collector)?;
+            let total_len = collector.len();
+            for pos in jump_pos {
+                collector[pos] = Bytecode::JumpIfNotNull {
+                    jump_to: total_len,
+                    span: *span,
+                }
+            }
+        }
         Expr::Apply { op, args, span } => {
             let arity = args.len();
-            for arg in args.iter() {
-                expr2bytecode(arg, collector)?;
+            let pattern_pos = regex_pattern_pos(op);
+            for (i, arg) in args.iter().enumerate() {
+                match arg {
+                    Expr::Const {
+                        val: DataValue::Str(s),
+                        span,
+                    } if pattern_pos == Some(i) => {
+                        // compiled here once instead of on every evaluation
+                        let re =
+                            str2regex(s).map_err(|err| BadRegexError(*span, err.to_string()))?;
+                        collector.push(Bytecode::Const {
+                            val: DataValue::Regex(RegexWrapper(re)),
+                            span: *span,
+                        })
+                    }
+                    arg => expr2bytecode(arg, collector)?,
+                }
             }
             collector.push(Bytecode::Apply {
                 op,
@@ -76,6 +157,34 @@ pub(crate) fn expr2bytecode(expr: &Expr, collector: &mut Vec<Bytecode>) -> Resul
                 span: *span,
             })
         }
+        Expr::ListFn {
+            kind,
+            args,
+            param_pos,
+            body,
+            span,
+            ..
+        } => {
+            for arg in args.iter() {
+                expr2bytecode(arg, collector)?;
+            }
+            collector.push(Bytecode::ListFn {
+                kind: *kind,
+                param_pos: *param_pos,
+                body: body.compile()?,
+                span: *span,
+            })
+        }
+        Expr::UserApply { func, args, span } => {
+            for arg in args.iter() {
+                expr2bytecode(arg, collector)?;
+            }
+            collector.push(Bytecode::UserApply {
+                func: func.clone(),
+                arity: args.len(),
+                span: *span,
+            })
+        }
         Expr::Cond { clauses, span } => {
             let mut return_jump_pos = vec![];
             for (cond, val) in clauses {
@@ -266,6 +375,38 @@ fn build_term(pair: Pair<'_>, param_pool: &BTreeMap<String, DataValue>) -> Resul
                 span,
             }
         }
+        Rule::uuid_literal => {
+            #[derive(Error, Diagnostic, Debug)]
+            #[error("Cannot parse UUID")]
+            #[diagnostic(code(parser::bad_uuid))]
+            struct BadUuidError(#[label] SourceSpan);
+
+            let s = parse_string(pair.into_inner().next().unwrap())?;
+            let id = uuid::Uuid::try_parse(&s).map_err(|_| BadUuidError(span))?;
+            Expr::Const {
+                val: DataValue::uuid(id),
+                span,
+            }
+        }
+        Rule::hex_bytes_literal | Rule::base64_bytes_literal => {
+            #[derive(Error, Diagnostic, Debug)]
+            #[error("Cannot decode bytes")]
+            #[diagnostic(code(parser::bad_bytes))]
+            #[diagnostic(help("Hex literals look like x'0aff', base64 ones like b64'Cv8='"))]
+            struct BadBytesError(#[label] SourceSpan);
+
+            let is_hex = pair.as_rule() == Rule::hex_bytes_literal;
+            let s = parse_string(pair.into_inner().next().unwrap())?;
+            let bytes = if is_hex {
+                hex_to_bytes(&s)
+            } else {
+                STANDARD.decode(s.as_bytes()).ok()
+            };
+            Expr::Const {
+                val: DataValue::Bytes(bytes.ok_or(BadBytesError(span))?),
+                span,
+            }
+        }
         Rule::list => {
             let mut collected = vec![];
             for p in pair.into_inner() {
@@ -298,9 +439,11 @@ fn build_term(pair: Pair<'_>, param_pool: &BTreeMap<String, DataValue>) -> Resul
             let mut p = pair.into_inner();
             let ident_p = p.next().unwrap();
             let ident = ident_p.as_str();
-            let mut args: Vec<_> = p
-                .next()
-                .unwrap()
+            let args_p = p.next().unwrap();
+            if let Some(kind) = ListFnKind::from_name(ident) {
+                return build_list_fn(kind, args_p, span, param_pool);
+            }
+            let mut args: Vec<_> = args_p
                 .into_inner()
                 .map(|v| build_expr(v, param_pool))
                 .try_collect()?;
@@ -379,18 +522,30 @@ fn build_term(pair: Pair<'_>, param_pool: &BTreeMap<String, DataValue>) -> Resul
                     Expr::Cond { clauses, span }
                 }
                 _ => match get_op(ident) {
-                    None => Expr::UnboundApply {
-                        op: ident.into(),
-                        args: args.into(),
-                        span,
+                    None => match USER_FNS.with(|fns| fns.borrow().get(ident).cloned()) {
+                        None => Expr::UnboundApply {
+                            op: ident.into(),
+                            args: args.into(),
+                            span,
+                        },
+                        Some(func) => {
+                            ensure!(
+                                func.arity == args.len(),
+                                WrongNumArgsError(
+                                    ident.to_string(),
+                                    span,
+                                    format!("Need exactly {} argument(s)", func.arity)
+                                )
+                            );
+                            Expr::UserApply {
+                                func,
+                                args: args.into(),
+                                span,
+                            }
+                        }
                     },
                     Some(op) => {
                         op.post_process_args(&mut args);
-                        #[derive(Error, Diagnostic, Debug)]
-                        #[error("Wrong number of arguments for function '{0}'")]
-                        #[diagnostic(code(parser::func_wrong_num_args))]
-                        struct WrongNumArgsError(String, #[label] SourceSpan, #[help] String);
-
                         if op.vararg {
                             ensure!(
                                 op.min_arity <= args.len(),
@@ -420,10 +575,67 @@ fn build_term(pair: Pair<'_>, param_pool: &BTreeMap<String, DataValue>) -> Resul
             }
         }
         Rule::grouping => build_expr(pair.into_inner().next().unwrap(), param_pool)?,
+        Rule::lambda => {
+            #[derive(Error, Diagnostic, Debug)]
+            #[error(
+                "A lambda can only be passed as the last argument to 'map', 'filter' or 'fold'"
+            )]
+            #[diagnostic(code(parser::misplaced_lambda))]
+            struct MisplacedLambdaError(#[label] SourceSpan);
+
+            bail!(MisplacedLambdaError(span))
+        }
+        Rule::scalar_subquery => Expr::Binding {
+            var: scalar_subquery_binding(span),
+            tuple_pos: None,
+        },
         r => unreachable!("Encountered unknown op {:?}", r),
     })
 }
 
+fn build_list_fn(
+    kind: ListFnKind,
+    args_p: Pair<'_>,
+    span: SourceSpan,
+    param_pool: &BTreeMap<String, DataValue>,
+) -> Result<Expr> {
+    #[derive(Error, Diagnostic, Debug)]
+    #[error("'{0}' requires {1} argument(s) followed by a lambda with {2} parameter(s)")]
+    #[diagnostic(code(parser::bad_list_fn_args))]
+    #[diagnostic(help(
+        "Examples: map(l, |x| x + 1), filter(l, |x| x > 0), fold(l, 0, |acc, x| acc + x)"
+    ))]
+    struct BadListFnArgsError(&'static str, usize, usize, #[label] SourceSpan);
+
+    let bad_args = || BadListFnArgsError(kind.name(), kind.arity(), kind.num_params(), span);
+    let mut arg_ps = args_p.into_inner().collect_vec();
+    ensure!(arg_ps.len() == kind.arity() + 1, bad_args());
+    let mut lambda_expr_p = arg_ps.pop().unwrap().into_inner();
+    let lambda_p = match (lambda_expr_p.next(), lambda_expr_p.next()) {
+        (Some(p), None) if p.as_rule() == Rule::lambda => p,
+        _ => bail!(bad_args()),
+    };
+    let mut lambda_ps = lambda_p.into_inner().collect_vec();
+    let body = build_expr(lambda_ps.pop().unwrap(), param_pool)?;
+    let params: Box<[Symbol]> = lambda_ps
+        .iter()
+        .map(|p| Symbol::new(p.as_str(), p.extract_span()))
+        .collect();
+    ensure!(params.len() == kind.num_params(), bad_args());
+    let args: Box<[Expr]> = arg_ps
+        .into_iter()
+        .map(|p| build_expr(p, param_pool))
+        .try_collect()?;
+    Ok(Expr::ListFn {
+        kind,
+        args,
+        params,
+        param_pos: None,
+        body: body.into(),
+        span,
+    })
+}
+
 pub(crate) fn parse_int(s: &str, radix: u32) -> i64 {
     i64::from_str_radix(&s[2..].replace('_', ""), radix).unwrap()
 }
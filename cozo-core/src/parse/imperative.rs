@@ -89,7 +89,7 @@ fn parse_imperative_stmt(
                         let rel = SmartString::from(p.as_str());
                         rets.push(Right(rel));
                     }
-                    Rule::query_script_inner => {
+                    Rule::imperative_clause => {
                         let mut src = p.into_inner();
                         let prog = parse_query(
                             src.next().unwrap().into_inner(),
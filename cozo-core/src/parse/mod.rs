@@ -18,10 +18,11 @@ use pest::Parser;
 use smartstring::{LazyCompact, SmartString};
 use thiserror::Error;
 
+use crate::data::expr::UserFn;
 use crate::data::program::InputProgram;
 use crate::data::relation::NullableColType;
 use crate::data::value::{DataValue, ValidityTs};
-use crate::parse::expr::build_expr;
+use crate::parse::expr::{build_expr, with_user_fns};
 use crate::parse::imperative::parse_imperative_block;
 use crate::parse::query::parse_query;
 use crate::parse::schema::parse_nullable_type;
@@ -161,7 +162,7 @@ impl ImperativeStmt {
                             collector.insert(new.name.clone());
                         }
                     }
-                    SysOp::CreateIndex(symb, subs, _) => {
+                    SysOp::CreateIndex(symb, subs, _, _) => {
                         collector.insert(symb.name.clone());
                         collector.insert(SmartString::from(format!("{}:{}", symb.name, subs.name)));
                     }
@@ -177,6 +178,9 @@ impl ImperativeStmt {
                         collector.insert(m.base_relation.clone());
                         collector.insert(SmartString::from(format!("{}:{}", m.base_relation, m.index_name)));
                     }
+                    SysOp::AlterRelation(rel, _) => {
+                        collector.insert(rel.name.clone());
+                    }
                     SysOp::RemoveIndex(rel, idx) => {
                         collector.insert(SmartString::from(format!("{}:{}", rel.name, idx.name)));
                     }
@@ -273,6 +277,18 @@ pub(crate) fn parse_expressions(
 }
 
 pub(crate) fn parse_script(
+    src: &str,
+    param_pool: &BTreeMap<String, DataValue>,
+    fixed_rules: &BTreeMap<String, Arc<Box<dyn FixedRule>>>,
+    user_fns: &BTreeMap<String, UserFn>,
+    cur_vld: ValidityTs,
+) -> Result<CozoScript> {
+    with_user_fns(user_fns, || {
+        do_parse_script(src, param_pool, fixed_rules, cur_vld)
+    })
+}
+
+fn do_parse_script(
     src: &str,
     param_pool: &BTreeMap<String, DataValue>,
     fixed_rules: &BTreeMap<String, Arc<Box<dyn FixedRule>>>,
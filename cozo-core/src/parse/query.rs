@@ -21,8 +21,8 @@ use smartstring::{LazyCompact, SmartString};
 use thiserror::Error;
 
 use crate::data::aggr::{parse_aggr, Aggregation};
-use crate::data::expr::Expr;
-use crate::data::functions::{str2vld, MAX_VALIDITY_TS};
+use crate::data::expr::{Expr, Op};
+use crate::data::functions::{str2vld, MAX_VALIDITY_TS, OP_ADD, OP_GE, OP_LT, OP_SUBQUERY_VALUE};
 use crate::data::program::{
     FixedRuleApply, FixedRuleArg, InputAtom, InputInlineRule, InputInlineRulesOrFixed,
     InputNamedFieldRelationApplyAtom, InputProgram, InputRelationApplyAtom, InputRuleApplyAtom,
@@ -115,11 +115,15 @@ pub(crate) fn parse_query(
 
     let mut stored_relation = None;
     let mut returning_mutation = ReturnMutation::NotReturning;
+    let mut aux = AuxRules::default();
 
     for pair in src {
         match pair.as_rule() {
             Rule::rule => {
-                let (name, rule) = parse_rule(pair, param_pool, cur_vld)?;
+                let (name, rule) = parse_rule(pair, param_pool, fixed_rules, cur_vld, &mut aux)?;
+                for (aux_name, aux_rule) in aux.rules.drain(..) {
+                    progs.insert(aux_name, aux_rule);
+                }
 
                 match progs.entry(name) {
                     Entry::Vacant(e) => {
@@ -271,6 +275,16 @@ pub(crate) fn parse_query(
                     out_opts.timeout = None;
                 }
             }
+            Rule::memory_limit_option => {
+                let pair = pair.into_inner().next().unwrap();
+                let span = pair.extract_span();
+                let limit = build_expr(pair, param_pool)?
+                    .eval_to_const()
+                    .map_err(|err| OptionNotConstantError("memory_limit", span, [err]))?
+                    .get_non_neg_int()
+                    .ok_or(OptionNotNonNegIntError("memory_limit", span))?;
+                out_opts.memory_limit = Some(limit as usize);
+            }
             Rule::sleep_option => {
                 #[cfg(target_arch = "wasm32")]
                 bail!(":sleep is not supported under WASM");
@@ -338,6 +352,7 @@ pub(crate) fn parse_query(
                     Rule::relation_replace => RelationOp::Replace,
                     Rule::relation_put => RelationOp::Put,
                     Rule::relation_insert => RelationOp::Insert,
+                    Rule::relation_insert_or_ignore => RelationOp::InsertOrIgnore,
                     Rule::relation_update => RelationOp::Update,
                     Rule::relation_rm => RelationOp::Rm,
                     Rule::relation_delete => RelationOp::Delete,
@@ -351,8 +366,16 @@ pub(crate) fn parse_query(
                 match args.next() {
                     None => stored_relation = Some(Left((name, span, op))),
                     Some(schema_p) => {
-                        let (mut metadata, mut key_bindings, mut dep_bindings) =
+                        let (mut metadata, mut key_bindings, mut dep_bindings, merges) =
                             parse_schema(schema_p)?;
+                        if !merges.is_empty() && op != RelationOp::Put {
+                            #[derive(Debug, Error, Diagnostic)]
+                            #[error("Merging columns is only supported by :put")]
+                            #[diagnostic(code(parser::merge_outside_put))]
+                            struct MergeOutsidePut(#[label] SourceSpan);
+
+                            bail!(MergeOutsidePut(span))
+                        }
                         if !matches!(op, RelationOp::Create | RelationOp::Replace) {
                             key_bindings.extend(dep_bindings);
                             dep_bindings = vec![];
@@ -366,6 +389,7 @@ pub(crate) fn parse_query(
                                 key_bindings,
                                 dep_bindings,
                                 span,
+                                merges,
                             },
                             op,
                         )))
@@ -455,6 +479,7 @@ pub(crate) fn parse_query(
                 key_bindings: head,
                 dep_bindings: vec![],
                 span,
+                merges: Default::default(),
             };
             prog.out_opts.store_relation = Some((handle, op, returning_mutation))
         }
@@ -531,10 +556,13 @@ pub(crate) fn parse_query(
     Ok(prog)
 }
 
+/// Parses a rule, leaving the auxiliary rules generated for the subqueries in its body in `aux`
 fn parse_rule(
     src: Pair<'_>,
     param_pool: &BTreeMap<String, DataValue>,
+    fixed_rules: &BTreeMap<String, Arc<Box<dyn FixedRule>>>,
     cur_vld: ValidityTs,
+    aux: &mut AuxRules,
 ) -> Result<(Symbol, InputInlineRule)> {
     let span = src.extract_span();
     let mut src = src.into_inner();
@@ -549,6 +577,7 @@ fn parse_rule(
 
     ensure!(!head.is_empty(), EmptyRuleHead(head_span));
     let body = src.next().unwrap();
+    let scalar_subqueries = outermost_scalar_subqueries(body.clone());
     let mut body_clauses = vec![];
     let mut ignored_counter = 0;
     for atom_src in body.into_inner() {
@@ -557,9 +586,46 @@ fn parse_rule(
             param_pool,
             cur_vld,
             &mut ignored_counter,
+            aux,
         )?)
     }
 
+    for subquery in scalar_subqueries {
+        let id = aux.next_id();
+        // a scalar subquery inside `exists(...)` is bound in the body of the innermost one
+        let sq_span = subquery.extract_span();
+        let target = aux
+            .exists
+            .iter_mut()
+            .filter(|(name, _)| {
+                name.span.0 <= sq_span.0 && sq_span.0 + sq_span.1 <= name.span.0 + name.span.1
+            })
+            .max_by_key(|(name, _)| name.span.0)
+            .map(|(_, body)| body)
+            .unwrap_or(&mut body_clauses);
+        hoist_scalar_subquery(
+            subquery,
+            param_pool,
+            fixed_rules,
+            cur_vld,
+            target,
+            id,
+            &mut aux.rules,
+        )?;
+    }
+    if !aux.exists.is_empty() {
+        let mut enclosing: BTreeSet<_> = head.iter().cloned().collect();
+        for atom in &body_clauses {
+            atom.collect_bindings(&mut enclosing)?;
+        }
+        resolve_exists_subqueries(
+            &mut body_clauses,
+            &enclosing,
+            &mut aux.exists,
+            &mut aux.rules,
+        )?;
+    }
+
     Ok((
         name,
         InputInlineRule {
@@ -571,18 +637,278 @@ fn parse_rule(
     ))
 }
 
+/// Auxiliary rules generated while parsing the bodies of the rules of a query
+#[derive(Default)]
+struct AuxRules {
+    /// Bodies of `exists(...)` subqueries, the heads of which are only known once the
+    /// enclosing rule has been parsed completely
+    exists: BTreeMap<Symbol, Vec<InputAtom>>,
+    rules: Vec<(Symbol, InputInlineRulesOrFixed)>,
+    /// Numbers the names of the auxiliary rules, which must be unique within the query
+    next_id: u32,
+}
+
+impl AuxRules {
+    fn next_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+}
+
+/// Name of the variable holding the value of the scalar subquery at `span`. Unlike the names of
+/// auxiliary rules, it is only visible in the rule containing the subquery, where the offset of
+/// the subquery is unique.
+pub(crate) fn scalar_subquery_binding(span: SourceSpan) -> Symbol {
+    Symbol::new(format!("*scalar*{}", span.0), span)
+}
+
+fn outermost_scalar_subqueries(src: Pair<'_>) -> Vec<Pair<'_>> {
+    let mut ret = vec![];
+    for p in src.into_inner() {
+        if p.as_rule() == Rule::scalar_subquery {
+            ret.push(p);
+        } else {
+            ret.extend(outermost_scalar_subqueries(p));
+        }
+    }
+    ret
+}
+
+/// A scalar subquery `{ ?[x] := ... }` becomes the auxiliary rules
+///
+/// ```text
+/// *subquery*N[x] := ...
+/// *subquery_rows*N[collect(x)] := *subquery*N[x]
+/// ```
+///
+/// and the enclosing rule body binds the value with `*subquery_rows*N[rows], *scalar*S = ...`,
+/// where `S` is the offset of the subquery given by [scalar_subquery_binding].
+/// Since an aggregation without grouping keys always produces exactly one row, the binding
+/// never restricts the enclosing rule: an empty subquery gives `null`.
+fn hoist_scalar_subquery(
+    src: Pair<'_>,
+    param_pool: &BTreeMap<String, DataValue>,
+    fixed_rules: &BTreeMap<String, Arc<Box<dyn FixedRule>>>,
+    cur_vld: ValidityTs,
+    body: &mut Vec<InputAtom>,
+    id: u32,
+    aux_rules: &mut Vec<(Symbol, InputInlineRulesOrFixed)>,
+) -> Result<()> {
+    #[derive(Debug, Error, Diagnostic)]
+    #[error("A scalar subquery must consist of an entry rule returning a single column")]
+    #[diagnostic(code(parser::bad_scalar_subquery))]
+    #[diagnostic(help("Helper rules used by the subquery can be defined in the enclosing query"))]
+    struct BadScalarSubquery(#[label] SourceSpan);
+
+    let span = src.extract_span();
+    let mut prog = parse_query(src.into_inner(), param_pool, fixed_rules, cur_vld)?;
+    let entry = Symbol::new(PROG_ENTRY, span);
+    ensure!(
+        prog.prog.len() == 1 && prog.prog.contains_key(&entry) && prog.get_entry_arity()? == 1,
+        BadScalarSubquery(span)
+    );
+    let query = prog.prog.remove(&entry).unwrap();
+
+    let query_name = Symbol::new(format!("*subquery*{id}"), span);
+    let rows_name = Symbol::new(format!("*subquery_rows*{id}"), span);
+    let row = Symbol::new("*row*", span);
+    let rows = Symbol::new(format!("*rows*{id}"), span);
+    aux_rules.push((query_name.clone(), query));
+    aux_rules.push((
+        rows_name.clone(),
+        InputInlineRulesOrFixed::Rules {
+            rules: vec![InputInlineRule {
+                head: vec![row.clone()],
+                aggr: vec![Some((parse_aggr("collect").unwrap().clone(), vec![]))],
+                body: vec![InputAtom::Rule {
+                    inner: InputRuleApplyAtom {
+                        name: query_name,
+                        args: vec![Expr::Binding {
+                            var: row,
+                            tuple_pos: None,
+                        }],
+                        span,
+                    },
+                }],
+                span,
+            }],
+        },
+    ));
+    body.push(InputAtom::Rule {
+        inner: InputRuleApplyAtom {
+            name: rows_name,
+            args: vec![Expr::Binding {
+                var: rows.clone(),
+                tuple_pos: None,
+            }],
+            span,
+        },
+    });
+    body.push(InputAtom::Unification {
+        inner: Unification {
+            binding: scalar_subquery_binding(span),
+            expr: Expr::Apply {
+                op: &OP_SUBQUERY_VALUE,
+                args: [Expr::Binding {
+                    var: rows,
+                    tuple_pos: None,
+                }]
+                .into(),
+                span,
+            },
+            one_many_unif: false,
+            span,
+        },
+    });
+    Ok(())
+}
+
+/// Turns each `exists(...)` placeholder among `atoms` into an application of an auxiliary rule
+/// whose head consists of the variables the subquery shares with its enclosing scope.
+fn resolve_exists_subqueries(
+    atoms: &mut [InputAtom],
+    enclosing: &BTreeSet<Symbol>,
+    pending: &mut BTreeMap<Symbol, Vec<InputAtom>>,
+    aux_rules: &mut Vec<(Symbol, InputInlineRulesOrFixed)>,
+) -> Result<()> {
+    for atom in atoms {
+        match atom {
+            InputAtom::Rule { inner } => {
+                let mut body = match pending.remove(&inner.name) {
+                    Some(body) => body,
+                    None => continue,
+                };
+                let mut used = BTreeSet::new();
+                collect_subquery_bindings(&body, pending, &mut used)?;
+                let mut head: Vec<_> = used.intersection(enclosing).cloned().collect();
+                let mut body_enclosing: BTreeSet<_> = head.iter().cloned().collect();
+                for a in &body {
+                    a.collect_bindings(&mut body_enclosing)?;
+                }
+                resolve_exists_subqueries(&mut body, &body_enclosing, pending, aux_rules)?;
+                let mut generated = BTreeSet::new();
+                for a in &body {
+                    collect_generated_bindings(a, &mut generated)?;
+                }
+                for var in &head {
+                    ensure!(
+                        generated.contains(var),
+                        ExistsOnlyFiltersOuterVariable(var.to_string(), var.span, inner.span)
+                    );
+                }
+
+                let span = inner.span;
+                inner.args = head
+                    .iter()
+                    .map(|s| Expr::Binding {
+                        var: s.clone(),
+                        tuple_pos: None,
+                    })
+                    .collect();
+                if head.is_empty() {
+                    // rule heads cannot be empty, so an uncorrelated subquery yields a marker
+                    let marker = Symbol::new("*exists*", span);
+                    let truth = Expr::Const {
+                        val: DataValue::from(true),
+                        span,
+                    };
+                    body.push(InputAtom::Unification {
+                        inner: Unification {
+                            binding: marker.clone(),
+                            expr: truth.clone(),
+                            one_many_unif: false,
+                            span,
+                        },
+                    });
+                    head.push(marker);
+                    inner.args.push(truth);
+                }
+                aux_rules.push((
+                    inner.name.clone(),
+                    InputInlineRulesOrFixed::Rules {
+                        rules: vec![InputInlineRule {
+                            aggr: vec![None; head.len()],
+                            head,
+                            body,
+                            span,
+                        }],
+                    },
+                ));
+            }
+            InputAtom::Negation { inner, .. } => resolve_exists_subqueries(
+                std::slice::from_mut(inner.as_mut()),
+                enclosing,
+                pending,
+                aux_rules,
+            )?,
+            InputAtom::Conjunction { inner, .. } | InputAtom::Disjunction { inner, .. } => {
+                resolve_exists_subqueries(inner, enclosing, pending, aux_rules)?
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("Variable '{0}' from the enclosing rule is only used in conditions inside 'exists'")]
+#[diagnostic(code(parser::exists_only_filters_outer_variable))]
+#[diagnostic(help(
+    "Variables shared with the enclosing rule must be bound by a relation or rule application \
+inside 'exists'; move the condition out of 'exists' instead"
+))]
+struct ExistsOnlyFiltersOuterVariable(String, #[label] SourceSpan, #[label] SourceSpan);
+
+/// Collects the variables that `atom` can produce values for, as opposed to merely filter on
+fn collect_generated_bindings(atom: &InputAtom, coll: &mut BTreeSet<Symbol>) -> Result<()> {
+    match atom {
+        InputAtom::Predicate { .. } | InputAtom::Negation { .. } => {}
+        InputAtom::Unification { inner } => {
+            coll.insert(inner.binding.clone());
+        }
+        InputAtom::Conjunction { inner, .. } | InputAtom::Disjunction { inner, .. } => {
+            for a in inner {
+                collect_generated_bindings(a, coll)?;
+            }
+        }
+        atom => atom.collect_bindings(coll)?,
+    }
+    Ok(())
+}
+
+/// Collects the variables used in `atoms`, including those in nested `exists(...)` subqueries
+fn collect_subquery_bindings(
+    atoms: &[InputAtom],
+    pending: &BTreeMap<Symbol, Vec<InputAtom>>,
+    coll: &mut BTreeSet<Symbol>,
+) -> Result<()> {
+    for atom in atoms {
+        atom.collect_bindings(coll)?;
+        let mut used_rules = BTreeSet::new();
+        atom.collect_used_rules(&mut used_rules);
+        for rule in used_rules {
+            if let Some(body) = pending.get(&rule) {
+                collect_subquery_bindings(body, pending, coll)?;
+            }
+        }
+    }
+    Ok(())
+}
+
 fn parse_disjunction(
     pair: Pair<'_>,
     param_pool: &BTreeMap<String, DataValue>,
     cur_vld: ValidityTs,
     ignored_counter: &mut u32,
+    aux: &mut AuxRules,
 ) -> Result<InputAtom> {
     let span = pair.extract_span();
     let res: Vec<_> = pair
         .into_inner()
         .filter_map(|v| match v.as_rule() {
             Rule::or_op => None,
-            _ => Some(parse_atom(v, param_pool, cur_vld, ignored_counter)),
+            _ => Some(parse_atom(v, param_pool, cur_vld, ignored_counter, aux)),
         })
         .try_collect()?;
     Ok(if res.len() == 1 {
@@ -597,30 +923,59 @@ fn parse_atom(
     param_pool: &BTreeMap<String, DataValue>,
     cur_vld: ValidityTs,
     ignored_counter: &mut u32,
+    aux: &mut AuxRules,
 ) -> Result<InputAtom> {
     Ok(match src.as_rule() {
         Rule::rule_body => {
             let span = src.extract_span();
             let grouped: Vec<_> = src
                 .into_inner()
-                .map(|v| parse_disjunction(v, param_pool, cur_vld, ignored_counter))
+                .map(|v| parse_disjunction(v, param_pool, cur_vld, ignored_counter, aux))
                 .try_collect()?;
             InputAtom::Conjunction {
                 inner: grouped,
                 span,
             }
         }
-        Rule::disjunction => parse_disjunction(src, param_pool, cur_vld, ignored_counter)?,
+        Rule::disjunction => parse_disjunction(src, param_pool, cur_vld, ignored_counter, aux)?,
         Rule::negation => {
             let span = src.extract_span();
             let mut src = src.into_inner();
             src.next().unwrap();
-            let inner = parse_atom(src.next().unwrap(), param_pool, cur_vld, ignored_counter)?;
+            let inner = parse_atom(
+                src.next().unwrap(),
+                param_pool,
+                cur_vld,
+                ignored_counter,
+                aux,
+            )?;
             InputAtom::Negation {
                 inner: inner.into(),
                 span,
             }
         }
+        Rule::exists_subquery => {
+            let span = src.extract_span();
+            let mut src = src.into_inner();
+            src.next().unwrap();
+            let body: Vec<_> = src
+                .next()
+                .unwrap()
+                .into_inner()
+                .map(|v| parse_disjunction(v, param_pool, cur_vld, ignored_counter, aux))
+                .try_collect()?;
+            // the arguments are filled in by `resolve_exists_subqueries` once the enclosing rule
+            // has been parsed completely
+            let name = Symbol::new(format!("*exists*{}", aux.next_id()), span);
+            aux.exists.insert(name.clone(), body);
+            InputAtom::Rule {
+                inner: InputRuleApplyAtom {
+                    name,
+                    args: vec![],
+                    span,
+                },
+            }
+        }
         Rule::expr => {
             let expr = build_expr(src, param_pool)?;
             InputAtom::Predicate { inner: expr }
@@ -668,27 +1023,57 @@ fn parse_atom(
             let span = src.extract_span();
             let mut src = src.into_inner();
             let name = src.next().unwrap();
-            let args: Vec<_> = src
-                .next()
-                .unwrap()
+            let mut name = Symbol::new(name.as_str(), name.extract_span());
+            let mut args_src = src.next().unwrap();
+            if args_src.as_rule() == Rule::hop_range {
+                let (min_hops, max_hops) = parse_hop_range(args_src)?;
+                args_src = src.next().unwrap();
+                ensure!(
+                    args_src.clone().into_inner().count() == 2,
+                    BadTraversalArity(span)
+                );
+                let edge = |args| InputAtom::Rule {
+                    inner: InputRuleApplyAtom {
+                        name: name.clone(),
+                        args,
+                        span,
+                    },
+                };
+                name = expand_traversal(
+                    edge,
+                    min_hops,
+                    max_hops,
+                    span,
+                    aux.next_id(),
+                    &mut aux.rules,
+                );
+            }
+            let args: Vec<_> = args_src
                 .into_inner()
                 .map(|v| build_expr(v, param_pool))
                 .try_collect()?;
             InputAtom::Rule {
-                inner: InputRuleApplyAtom {
-                    name: Symbol::new(name.as_str(), name.extract_span()),
-                    args,
-                    span,
-                },
+                inner: InputRuleApplyAtom { name, args, span },
             }
         }
         Rule::relation_apply => {
             let span = src.extract_span();
             let mut src = src.into_inner();
             let name = src.next().unwrap();
-            let args: Vec<_> = src
-                .next()
-                .unwrap()
+            let name = Symbol::new(&name.as_str()[1..], name.extract_span());
+            let mut args_src = src.next().unwrap();
+            let hop_range = if args_src.as_rule() == Rule::hop_range {
+                let hop_range = parse_hop_range(args_src)?;
+                args_src = src.next().unwrap();
+                ensure!(
+                    args_src.clone().into_inner().count() == 2,
+                    BadTraversalArity(span)
+                );
+                Some(hop_range)
+            } else {
+                None
+            };
+            let args: Vec<_> = args_src
                 .into_inner()
                 .map(|v| build_expr(v, param_pool))
                 .try_collect()?;
@@ -699,13 +1084,39 @@ fn parse_atom(
                     Some(expr2vld_spec(vld_expr, cur_vld)?)
                 }
             };
-            InputAtom::Relation {
-                inner: InputRelationApplyAtom {
-                    name: Symbol::new(&name.as_str()[1..], name.extract_span()),
-                    args,
-                    valid_at,
-                    span,
+            match hop_range {
+                None => InputAtom::Relation {
+                    inner: InputRelationApplyAtom {
+                        name,
+                        args,
+                        valid_at,
+                        span,
+                    },
                 },
+                Some((min_hops, max_hops)) => {
+                    let edge = |args| InputAtom::Relation {
+                        inner: InputRelationApplyAtom {
+                            name: name.clone(),
+                            args,
+                            valid_at,
+                            span,
+                        },
+                    };
+                    InputAtom::Rule {
+                        inner: InputRuleApplyAtom {
+                            name: expand_traversal(
+                                edge,
+                                min_hops,
+                                max_hops,
+                                span,
+                                aux.next_id(),
+                                &mut aux.rules,
+                            ),
+                            args,
+                            span,
+                        },
+                    }
+                }
             }
         }
         Rule::search_apply => {
@@ -776,6 +1187,131 @@ fn parse_atom(
     })
 }
 
+#[derive(Debug, Error, Diagnostic)]
+#[error("Variable-length traversal must be applied to exactly two arguments")]
+#[diagnostic(code(parser::bad_traversal_arity))]
+#[diagnostic(help(
+    "The traversed rule or relation relates its first argument to its second, \
+    as in 'edge*1..3[fr, to]'"
+))]
+struct BadTraversalArity(#[label] SourceSpan);
+
+fn parse_hop_range(src: Pair<'_>) -> Result<(i64, i64)> {
+    #[derive(Debug, Error, Diagnostic)]
+    #[error("Invalid range of hops for variable-length traversal")]
+    #[diagnostic(code(parser::bad_hop_range))]
+    #[diagnostic(help("The range 'min..max' must satisfy 1 <= min <= max"))]
+    struct BadHopRange(#[label] SourceSpan);
+
+    let span = src.extract_span();
+    let mut bounds = src.into_inner().map(|p| {
+        p.as_str()
+            .replace('_', "")
+            .parse::<i64>()
+            .map_err(|_| BadHopRange(span))
+    });
+    let min_hops = bounds.next().unwrap()?;
+    let max_hops = bounds.next().unwrap()?;
+    ensure!(1 <= min_hops && min_hops <= max_hops, BadHopRange(span));
+    Ok((min_hops, max_hops))
+}
+
+/// Expands `edge*min..max[fr, to]`, or `*edge*min..max[fr, to]` for a stored relation,
+/// into the auxiliary rules
+///
+/// ```text
+/// *hop_depth*N[a, b, k] := edge[a, b], k = 1
+/// *hop_depth*N[a, c, k] := *hop_depth*N[a, b, k0], k0 < max, edge[b, c], k = k0 + 1
+/// *hop*N[a, b] := *hop_depth*N[a, b, k], k >= min
+/// ```
+///
+/// and returns the name of the last one. `edge` builds the application of the traversed
+/// rule or stored relation to the given arguments. The recursion is left-linear, so when
+/// the starting node is bound the magic set rewrite only explores the nodes reachable from it.
+fn expand_traversal(
+    edge: impl Fn(Vec<Expr>) -> InputAtom,
+    min_hops: i64,
+    max_hops: i64,
+    span: SourceSpan,
+    id: u32,
+    aux_rules: &mut Vec<(Symbol, InputInlineRulesOrFixed)>,
+) -> Symbol {
+    let var = |name: &str| Expr::Binding {
+        var: Symbol::new(name, span),
+        tuple_pos: None,
+    };
+    let int = |i: i64| Expr::Const {
+        val: DataValue::from(i),
+        span,
+    };
+    let apply = |name: &Symbol, args: &[&str]| InputAtom::Rule {
+        inner: InputRuleApplyAtom {
+            name: name.clone(),
+            args: args.iter().map(|a| var(a)).collect(),
+            span,
+        },
+    };
+    let op = |op: &'static Op, args: Vec<Expr>| Expr::Apply {
+        op,
+        args: args.into(),
+        span,
+    };
+    let unify = |binding: &str, expr: Expr| InputAtom::Unification {
+        inner: Unification {
+            binding: Symbol::new(binding, span),
+            expr,
+            one_many_unif: false,
+            span,
+        },
+    };
+    let rule = |head: &[&str], body: Vec<InputAtom>| InputInlineRule {
+        head: head.iter().map(|h| Symbol::new(*h, span)).collect(),
+        aggr: vec![None; head.len()],
+        body,
+        span,
+    };
+
+    let depth_name = Symbol::new(format!("*hop_depth*{id}"), span);
+    let hop_name = Symbol::new(format!("*hop*{id}"), span);
+    aux_rules.push((
+        depth_name.clone(),
+        InputInlineRulesOrFixed::Rules {
+            rules: vec![
+                rule(
+                    &["a", "b", "k"],
+                    vec![edge(vec![var("a"), var("b")]), unify("k", int(1))],
+                ),
+                rule(
+                    &["a", "c", "k"],
+                    vec![
+                        apply(&depth_name, &["a", "b", "k0"]),
+                        InputAtom::Predicate {
+                            inner: op(&OP_LT, vec![var("k0"), int(max_hops)]),
+                        },
+                        edge(vec![var("b"), var("c")]),
+                        unify("k", op(&OP_ADD, vec![var("k0"), int(1)])),
+                    ],
+                ),
+            ],
+        },
+    ));
+    aux_rules.push((
+        hop_name.clone(),
+        InputInlineRulesOrFixed::Rules {
+            rules: vec![rule(
+                &["a", "b"],
+                vec![
+                    apply(&depth_name, &["a", "b", "k"]),
+                    InputAtom::Predicate {
+                        inner: op(&OP_GE, vec![var("k"), int(min_hops)]),
+                    },
+                ],
+            )],
+        },
+    ));
+    hop_name
+}
+
 fn extract_named_apply_arg(
     pair: Pair<'_>,
     param_pool: &BTreeMap<String, DataValue>,
@@ -6,23 +6,30 @@
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 use itertools::Itertools;
 use miette::{bail, ensure, Diagnostic, Result, IntoDiagnostic};
-use smartstring::SmartString;
+use smartstring::{LazyCompact, SmartString};
 use thiserror::Error;
 
 use crate::data::relation::{VecElementType, ColType, ColumnDef, NullableColType, StoredRelationMetadata};
 use crate::data::symb::Symbol;
+use crate::data::expr::Expr;
 use crate::data::value::DataValue;
-use crate::parse::expr::{build_expr};
+use crate::parse::expr::{build_expr, with_user_fns};
 use crate::parse::{ExtractSpan, Pair, Rule, SourceSpan};
 
 pub(crate) fn parse_schema(
     pair: Pair<'_>,
-) -> Result<(StoredRelationMetadata, Vec<Symbol>, Vec<Symbol>)> {
+) -> Result<(
+    StoredRelationMetadata,
+    Vec<Symbol>,
+    Vec<Symbol>,
+    BTreeMap<SmartString<LazyCompact>, Expr>,
+)> {
     let mut src = pair.into_inner();
+    let mut merges = BTreeMap::new();
     let mut keys = vec![];
     let mut dependents = vec![];
     let mut key_bindings = vec![];
@@ -35,20 +42,26 @@ pub(crate) fn parse_schema(
     struct DuplicateNameInCols(String, #[label] SourceSpan);
     for p in src.next().unwrap().into_inner() {
         let span = p.extract_span();
-        let (col, ident) = parse_col(p)?;
+        let (col, ident, merge) = parse_col(p)?;
         if !seen_names.insert(col.name.clone()) {
             bail!(DuplicateNameInCols(col.name.to_string(), span));
         }
+        if let Some(merge) = merge {
+            merges.insert(col.name.clone(), merge);
+        }
         keys.push(col);
         key_bindings.push(ident)
     }
     if let Some(ps) = src.next() {
         for p in ps.into_inner() {
             let span = p.extract_span();
-            let (col, ident) = parse_col(p)?;
+            let (col, ident, merge) = parse_col(p)?;
             if !seen_names.insert(col.name.clone()) {
                 bail!(DuplicateNameInCols(col.name.to_string(), span));
             }
+            if let Some(merge) = merge {
+                merges.insert(col.name.clone(), merge);
+            }
             dependents.push(col);
             dep_bindings.push(ident)
         }
@@ -61,10 +74,11 @@ pub(crate) fn parse_schema(
         },
         key_bindings,
         dep_bindings,
+        merges,
     ))
 }
 
-fn parse_col(pair: Pair<'_>) -> Result<(ColumnDef, Symbol)> {
+pub(crate) fn parse_col(pair: Pair<'_>) -> Result<(ColumnDef, Symbol, Option<Expr>)> {
     let mut src = pair.into_inner();
     let name_p = src.next().unwrap();
     let name = SmartString::from(name_p.as_str());
@@ -73,14 +87,26 @@ fn parse_col(pair: Pair<'_>) -> Result<(ColumnDef, Symbol)> {
         nullable: true,
     };
     let mut default_gen = None;
+    let mut merge = None;
     let mut binding_candidate = None;
     for nxt in src {
         match nxt.as_rule() {
             Rule::col_type => typing = parse_nullable_type(nxt)?,
-            Rule::expr => default_gen = Some(build_expr(nxt, &Default::default())?),
+            // defaults are persisted, so they cannot refer to functions registered at runtime
+            Rule::expr => {
+                default_gen = Some(with_user_fns(&Default::default(), || {
+                    build_expr(nxt, &Default::default())
+                })?)
+            }
             Rule::out_arg => {
                 binding_candidate = Some(Symbol::new(nxt.as_str(), nxt.extract_span()))
             }
+            Rule::col_merge => {
+                merge = Some(build_expr(
+                    nxt.into_inner().next().unwrap(),
+                    &Default::default(),
+                )?)
+            }
             r => unreachable!("{:?}", r),
         }
     }
@@ -93,6 +119,7 @@ fn parse_col(pair: Pair<'_>) -> Result<(ColumnDef, Symbol)> {
             default_gen,
         },
         binding,
+        merge,
     ))
 }
 
@@ -113,6 +140,10 @@ fn parse_type_inner(pair: Pair<'_>) -> Result<ColType> {
         Rule::uuid_type => ColType::Uuid,
         Rule::json_type => ColType::Json,
         Rule::validity_type => ColType::Validity,
+        Rule::timestamp_type => ColType::Timestamp,
+        Rule::decimal_type => ColType::Decimal,
+        Rule::interval_type => ColType::Interval,
+        Rule::point_type => ColType::Point,
         Rule::list_type => {
             let mut inner = pair.into_inner();
             let eltype = parse_nullable_type(inner.next().unwrap())?;
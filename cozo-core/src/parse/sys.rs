@@ -16,19 +16,30 @@ use smartstring::{LazyCompact, SmartString};
 use thiserror::Error;
 
 use crate::data::program::InputProgram;
-use crate::data::relation::VecElementType;
+use crate::data::relation::{ColumnDef, NullableColType, VecElementType};
 use crate::data::symb::Symbol;
 use crate::data::value::{DataValue, ValidityTs};
 use crate::fts::TokenizerConfig;
 use crate::parse::expr::{build_expr, parse_string};
 use crate::parse::query::parse_query;
+use crate::parse::schema::{parse_col, parse_nullable_type};
 use crate::parse::{ExtractSpan, Pairs, Rule, SourceSpan};
 use crate::runtime::relation::AccessLevel;
 use crate::{Expr, FixedRule};
 
 #[derive(Debug)]
 pub(crate) enum SysOp {
-    Compact,
+    Compact(Option<Symbol>),
+    SetTtl(Symbol, Option<Symbol>),
+    StorageStats(Option<Symbol>),
+    CreateView(Symbol, String),
+    RemoveView(Symbol),
+    ListViews,
+    CreateProcedure(Symbol, Vec<SmartString<LazyCompact>>, String),
+    RemoveProcedure(Symbol),
+    ListProcedures,
+    CallProcedure(Symbol, Vec<DataValue>),
+    AlterRelation(Symbol, RelationAlteration),
     ListColumns(Symbol),
     ListIndices(Symbol),
     ListRelations,
@@ -36,12 +47,13 @@ pub(crate) enum SysOp {
     ListFixedRules,
     KillRunning(u64),
     Explain(Box<InputProgram>),
+    ExplainAnalyze(Box<InputProgram>),
     RemoveRelation(Vec<Symbol>),
     RenameRelation(Vec<(Symbol, Symbol)>),
     ShowTrigger(Symbol),
     SetTriggers(Symbol, Vec<String>, Vec<String>, Vec<String>),
     SetAccessLevel(Vec<Symbol>, AccessLevel),
-    CreateIndex(Symbol, Symbol, Vec<Symbol>),
+    CreateIndex(Symbol, Symbol, Vec<Symbol>, bool),
     CreateVectorIndex(HnswIndexConfig),
     CreateFtsIndex(FtsIndexConfig),
     CreateMinHashLshIndex(MinHashLshConfig),
@@ -49,6 +61,14 @@ pub(crate) enum SysOp {
     DescribeRelation(Symbol, SmartString<LazyCompact>)
 }
 
+#[derive(Debug)]
+pub(crate) enum RelationAlteration {
+    AddColumn(ColumnDef),
+    DropColumn(Symbol),
+    SetColumnType(Symbol, NullableColType),
+    RenameColumn(Symbol, Symbol),
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct FtsIndexConfig {
     pub(crate) base_relation: SmartString<LazyCompact>,
@@ -109,7 +129,180 @@ pub(crate) fn parse_sys(
 ) -> Result<SysOp> {
     let inner = src.next().unwrap();
     Ok(match inner.as_rule() {
-        Rule::compact_op => SysOp::Compact,
+        Rule::compact_op => {
+            let rel = inner
+                .into_inner()
+                .next()
+                .map(|p| Symbol::new(p.as_str(), p.extract_span()));
+            SysOp::Compact(rel)
+        }
+        Rule::ttl_op => {
+            let mut ps = inner.into_inner();
+            let rel_p = ps.next().unwrap();
+            let rel = Symbol::new(rel_p.as_str(), rel_p.extract_span());
+            let col = ps.next().map(|p| Symbol::new(p.as_str(), p.extract_span()));
+            SysOp::SetTtl(rel, col)
+        }
+        Rule::alter_op => {
+            let mut ps = inner.into_inner();
+            let rel_p = ps.next().unwrap();
+            let rel = Symbol::new(rel_p.as_str(), rel_p.extract_span());
+            let op = ps.next().unwrap();
+            let alteration = match op.as_rule() {
+                Rule::alter_add => RelationAlteration::AddColumn(parse_col(op)?.0),
+                Rule::alter_drop => {
+                    let col_p = op.into_inner().next().unwrap();
+                    RelationAlteration::DropColumn(Symbol::new(
+                        col_p.as_str(),
+                        col_p.extract_span(),
+                    ))
+                }
+                Rule::alter_set => {
+                    let mut ps = op.into_inner();
+                    let col_p = ps.next().unwrap();
+                    let col = Symbol::new(col_p.as_str(), col_p.extract_span());
+                    let typing = parse_nullable_type(ps.next().unwrap())?;
+                    RelationAlteration::SetColumnType(col, typing)
+                }
+                Rule::alter_rename => {
+                    let mut ps = op.into_inner();
+                    let old_p = ps.next().unwrap();
+                    let new_p = ps.next().unwrap();
+                    RelationAlteration::RenameColumn(
+                        Symbol::new(old_p.as_str(), old_p.extract_span()),
+                        Symbol::new(new_p.as_str(), new_p.extract_span()),
+                    )
+                }
+                r => unreachable!("{:?}", r),
+            };
+            SysOp::AlterRelation(rel, alteration)
+        }
+        Rule::storage_stats_op => {
+            let rel = inner
+                .into_inner()
+                .next()
+                .map(|p| Symbol::new(p.as_str(), p.extract_span()));
+            SysOp::StorageStats(rel)
+        }
+        Rule::view_op => {
+            let inner = inner.into_inner().next().unwrap();
+            match inner.as_rule() {
+                Rule::view_create => {
+                    let mut ps = inner.into_inner();
+                    let name_p = ps.next().unwrap();
+                    let name = Symbol::new(name_p.as_str(), name_p.extract_span());
+                    let script = ps.next().unwrap();
+                    let script_str = script.as_str().to_string();
+
+                    #[derive(Debug, Diagnostic, Error)]
+                    #[error("Views can only contain rules named after the view '{0}'")]
+                    #[diagnostic(code(parser::bad_view_definition))]
+                    #[diagnostic(help("Query options are not allowed in views either"))]
+                    struct BadViewDefinition(String, #[label] SourceSpan);
+
+                    for p in script.clone().into_inner() {
+                        if !matches!(
+                            p.as_rule(),
+                            Rule::rule | Rule::const_rule | Rule::fixed_rule
+                        ) {
+                            bail!(BadViewDefinition(name.to_string(), p.extract_span()))
+                        }
+                    }
+                    let prog = parse_query(
+                        script.into_inner(),
+                        &Default::default(),
+                        algorithms,
+                        cur_vld,
+                    )?;
+                    for (rule_name, rules) in &prog.prog {
+                        ensure!(
+                            *rule_name == name,
+                            BadViewDefinition(name.to_string(), rules.first_span())
+                        );
+                    }
+                    SysOp::CreateView(name, script_str)
+                }
+                Rule::view_drop => {
+                    let name_p = inner.into_inner().next().unwrap();
+                    SysOp::RemoveView(Symbol::new(name_p.as_str(), name_p.extract_span()))
+                }
+                r => unreachable!("{:?}", r),
+            }
+        }
+        Rule::list_views_op => SysOp::ListViews,
+        Rule::procedure_op => {
+            let inner = inner.into_inner().next().unwrap();
+            match inner.as_rule() {
+                Rule::procedure_create => {
+                    #[derive(Debug, Diagnostic, Error)]
+                    #[error("Parameter {0} is declared more than once")]
+                    #[diagnostic(code(parser::dup_procedure_param))]
+                    struct DuplicateProcedureParam(String, #[label] SourceSpan);
+
+                    #[derive(Debug, Diagnostic, Error)]
+                    #[error("Parameter {0} is used in the body but not declared")]
+                    #[diagnostic(code(parser::undeclared_procedure_param))]
+                    #[diagnostic(help("Declare it in the parameter list of the procedure"))]
+                    struct UndeclaredProcedureParam(String, #[label] SourceSpan);
+
+                    let mut ps = inner.into_inner();
+                    let name_p = ps.next().unwrap();
+                    let name = Symbol::new(name_p.as_str(), name_p.extract_span());
+                    let mut params: Vec<SmartString<LazyCompact>> = vec![];
+                    let mut body = String::new();
+                    for p in ps {
+                        match p.as_rule() {
+                            Rule::param => {
+                                let param =
+                                    SmartString::from(p.as_str().strip_prefix('$').unwrap());
+                                ensure!(
+                                    !params.contains(&param),
+                                    DuplicateProcedureParam(
+                                        p.as_str().to_string(),
+                                        p.extract_span()
+                                    )
+                                );
+                                params.push(param);
+                            }
+                            Rule::procedure_body => {
+                                // the parameters come first, so they are all known here
+                                for used in p.clone().into_inner().flatten() {
+                                    if used.as_rule() != Rule::param {
+                                        continue;
+                                    }
+                                    let used_name = used.as_str().strip_prefix('$').unwrap();
+                                    ensure!(
+                                        params.iter().any(|param| param == used_name),
+                                        UndeclaredProcedureParam(
+                                            used.as_str().to_string(),
+                                            used.extract_span()
+                                        )
+                                    );
+                                }
+                                body = p.as_str().to_string()
+                            }
+                            r => unreachable!("{:?}", r),
+                        }
+                    }
+                    SysOp::CreateProcedure(name, params, body)
+                }
+                Rule::procedure_drop => {
+                    let name_p = inner.into_inner().next().unwrap();
+                    SysOp::RemoveProcedure(Symbol::new(name_p.as_str(), name_p.extract_span()))
+                }
+                r => unreachable!("{:?}", r),
+            }
+        }
+        Rule::list_procedures_op => SysOp::ListProcedures,
+        Rule::call_op => {
+            let mut ps = inner.into_inner();
+            let name_p = ps.next().unwrap();
+            let name = Symbol::new(name_p.as_str(), name_p.extract_span());
+            let args: Vec<_> = ps
+                .map(|p| build_expr(p, param_pool)?.eval_to_const())
+                .try_collect()?;
+            SysOp::CallProcedure(name, args)
+        }
         Rule::running_op => SysOp::ListRunning,
         Rule::kill_op => {
             let i_expr = inner.into_inner().next().unwrap();
@@ -121,13 +314,18 @@ pub(crate) fn parse_sys(
             SysOp::KillRunning(i_val as u64)
         }
         Rule::explain_op => {
-            let prog = parse_query(
-                inner.into_inner().next().unwrap().into_inner(),
-                param_pool,
-                algorithms,
-                cur_vld,
-            )?;
-            SysOp::Explain(Box::new(prog))
+            let mut ps = inner.into_inner();
+            let mut body = ps.next().unwrap();
+            let analyze = body.as_rule() == Rule::explain_analyze;
+            if analyze {
+                body = ps.next().unwrap();
+            }
+            let prog = parse_query(body.into_inner(), param_pool, algorithms, cur_vld)?;
+            if analyze {
+                SysOp::ExplainAnalyze(Box::new(prog))
+            } else {
+                SysOp::Explain(Box::new(prog))
+            }
         }
         Rule::describe_relation_op => {
             let mut inner = inner.into_inner();
@@ -633,7 +831,8 @@ pub(crate) fn parse_sys(
                 r => unreachable!("{:?}", r),
             }
         }
-        Rule::index_op => {
+        Rule::index_op | Rule::unique_idx_op => {
+            let unique = inner.as_rule() == Rule::unique_idx_op;
             let inner = inner.into_inner().next().unwrap();
             match inner.as_rule() {
                 Rule::index_create => {
@@ -655,6 +854,7 @@ pub(crate) fn parse_sys(
                         Symbol::new(rel.as_str(), rel.extract_span()),
                         Symbol::new(name.as_str(), name.extract_span()),
                         cols,
+                        unique,
                     )
                 }
                 Rule::index_drop => {
@@ -14,12 +14,14 @@ use thiserror::Error;
 
 use crate::data::aggr::Aggregation;
 use crate::data::expr::Expr;
+use crate::data::functions::OP_EQ;
 use crate::data::program::{
     MagicAtom, MagicFixedRuleApply, MagicInlineRule, MagicRulesOrFixed, MagicSymbol,
     StratifiedMagicProgram,
 };
+use crate::data::relation::{ColType, NullableColType};
 use crate::data::symb::Symbol;
-use crate::data::value::DataValue;
+use crate::data::value::{DataValue, Num};
 use crate::parse::SourceSpan;
 use crate::query::ra::RelAlgebra;
 use crate::runtime::relation::{AccessLevel, InsufficientAccessLevel};
@@ -176,6 +178,7 @@ impl<'a> SessionTx<'a> {
             serial_id += 1;
             ret
         };
+        let const_equalities = const_equalities(&rule.body);
         for atom in &rule.body {
             match atom {
                 MagicAtom::Rule(rule_app) => {
@@ -217,7 +220,7 @@ impl<'a> SessionTx<'a> {
                     ret = ret.join(right, prev_joiner_vars, right_joiner_vars, rule_app.span);
                 }
                 MagicAtom::Relation(rel_app) => {
-                    let store = self.get_relation(&rel_app.name, false)?;
+                    let store = self.get_relation_for_read(&rel_app.name)?;
                     if store.access_level < AccessLevel::ReadOnly {
                         bail!(InsufficientAccessLevel(
                             store.name.to_string(),
@@ -234,6 +237,34 @@ impl<'a> SessionTx<'a> {
                             rel_app.span
                         )
                     );
+                    // bind variables compared to constants later in the body now,
+                    // so that the scan can seek by them instead of filtering every row
+                    for (i, var) in rel_app.args.iter().enumerate() {
+                        if seen_variables.contains(var) {
+                            continue;
+                        }
+                        let seekable = i == 0
+                            || store.indices.values().any(|(_, mapper)| mapper[0] == i);
+                        if !seekable {
+                            continue;
+                        }
+                        if let Some((val, span)) = const_equalities.get(var) {
+                            let mut cols = store.metadata.keys.iter().chain(&store.metadata.non_keys);
+                            let typing = &cols.nth(i).unwrap().typing;
+                            if eq_agrees_with_storage(typing, val) {
+                                seen_variables.insert(var.clone());
+                                ret = ret.unify(
+                                    var.clone(),
+                                    Expr::Const {
+                                        val: (*val).clone(),
+                                        span: *span,
+                                    },
+                                    false,
+                                    *span,
+                                );
+                            }
+                        }
+                    }
                     // already existing vars
                     let mut prev_joiner_vars = vec![];
                     // vars introduced by right and joined
@@ -412,7 +443,7 @@ impl<'a> SessionTx<'a> {
                     ret = ret.neg_join(right, prev_joiner_vars, right_joiner_vars, rule_app.span);
                 }
                 MagicAtom::NegatedRelation(rel_app) => {
-                    let store = self.get_relation(&rel_app.name, false)?;
+                    let store = self.get_relation_for_read(&rel_app.name)?;
                     ensure!(
                         store.arity() == rel_app.args.len(),
                         ArityMismatch(
@@ -663,3 +694,35 @@ impl<'a> SessionTx<'a> {
         Ok(ret)
     }
 }
+
+/// Variables that a predicate of the body requires to equal a constant.
+fn const_equalities(body: &[MagicAtom]) -> BTreeMap<&Symbol, (&DataValue, SourceSpan)> {
+    let mut ret = BTreeMap::new();
+    for atom in body {
+        if let MagicAtom::Predicate(Expr::Apply { op, args, span }) = atom {
+            if **op != OP_EQ {
+                continue;
+            }
+            match &args[..] {
+                [Expr::Binding { var, .. }, Expr::Const { val, .. }]
+                | [Expr::Const { val, .. }, Expr::Binding { var, .. }] => {
+                    ret.insert(var, (val, *span));
+                }
+                _ => {}
+            }
+        }
+    }
+    ret
+}
+
+/// Whether `==` on a column of type `typing` and `val` holds exactly when the stored value
+/// is `val` itself. `==` considers `1` and `1.0` equal, so numbers only qualify if the column
+/// cannot hold the other kind of number.
+fn eq_agrees_with_storage(typing: &NullableColType, val: &DataValue) -> bool {
+    match val {
+        DataValue::Str(_) | DataValue::Bool(_) | DataValue::Bytes(_) | DataValue::Uuid(_) => true,
+        DataValue::Num(Num::Int(_)) => typing.coltype == ColType::Int,
+        DataValue::Num(Num::Float(_)) => typing.coltype == ColType::Float,
+        _ => false,
+    }
+}
@@ -12,24 +12,50 @@ use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 use itertools::Itertools;
 use log::{debug, trace};
-use miette::Result;
-#[cfg(not(target_arch = "wasm32"))]
+use miette::{bail, Diagnostic, Result};
+#[cfg(all(not(target_arch = "wasm32"), feature = "rayon"))]
 use rayon::prelude::*;
+use thiserror::Error;
 
 use crate::data::aggr::Aggregation;
 use crate::data::program::{MagicSymbol, NoEntryError};
 use crate::data::symb::{Symbol, PROG_ENTRY};
 use crate::data::tuple::Tuple;
 use crate::data::value::DataValue;
+use crate::fixed_rule::graph_cache::GraphCache;
 use crate::fixed_rule::FixedRulePayload;
 use crate::parse::SourceSpan;
 use crate::query::compile::{
     AggrKind, CompiledProgram, CompiledRule, CompiledRuleSet, ContainedRuleMultiplicity,
 };
-use crate::runtime::db::Poison;
+use crate::runtime::db::{seconds_since_the_epoch, Poison};
 use crate::runtime::temp_store::{EpochStore, MeetAggrStore, RegularTempStore};
 use crate::runtime::transact::SessionTx;
 
+#[derive(Debug, Error, Diagnostic)]
+#[error("Query exceeded its memory limit of {0} bytes")]
+#[diagnostic(code(eval::memory_limit_exceeded))]
+#[diagnostic(help(
+    "The limit can be raised with the ':memory_limit' option, \
+or for the whole database with 'set_memory_limit'"
+))]
+pub(crate) struct QueryMemoryExceeded(pub(crate) usize);
+
+/// Execution statistics of a query, collected for `::explain analyze`
+#[derive(Debug, Default)]
+pub(crate) struct EvalProfile {
+    pub(crate) strata: Vec<StratumProfile>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct StratumProfile {
+    pub(crate) elapsed: f64,
+    /// Number of new tuples derived for each rule, one map per epoch
+    pub(crate) deltas: Vec<BTreeMap<MagicSymbol, usize>>,
+    /// Number of tuples of each rule when the stratum is done
+    pub(crate) rows: BTreeMap<MagicSymbol, usize>,
+}
+
 pub(crate) struct QueryLimiter {
     total: Option<usize>,
     skip: Option<usize>,
@@ -68,9 +94,12 @@ impl<'a> SessionTx<'a> {
         store_lifetimes: BTreeMap<MagicSymbol, usize>,
         total_num_to_take: Option<usize>,
         num_to_skip: Option<usize>,
+        memory_limit: Option<usize>,
         poison: Poison,
+        mut profile: Option<&mut EvalProfile>,
     ) -> Result<(EpochStore, bool)> {
         let mut stores: BTreeMap<MagicSymbol, EpochStore> = BTreeMap::new();
+        let graph_cache = GraphCache::default();
         let mut early_return = false;
         for (stratum, cur_prog) in strata.iter().enumerate() {
             if stratum > 0 {
@@ -79,6 +108,7 @@ impl<'a> SessionTx<'a> {
                     None => false,
                     Some(n) => *n >= stratum,
                 });
+                graph_cache.retain_in_mem(|name| stores.contains_key(name));
                 trace!("{:?}", stores);
             }
             for (rule_name, rule_set) in cur_prog {
@@ -95,13 +125,31 @@ impl<'a> SessionTx<'a> {
                 stores.insert(rule_name.clone(), store);
             }
             debug!("stratum {}", stratum);
+            let mut stratum_profile = match profile {
+                None => None,
+                Some(_) => Some((seconds_since_the_epoch()?, StratumProfile::default())),
+            };
             early_return = self.semi_naive_magic_evaluate(
                 cur_prog,
                 &mut stores,
+                &graph_cache,
                 total_num_to_take,
                 num_to_skip,
+                memory_limit,
                 poison.clone(),
+                stratum_profile.as_mut().map(|(_, p)| p),
             )?;
+            if let (Some(profile), Some((started, mut stratum_profile))) =
+                (profile.as_deref_mut(), stratum_profile)
+            {
+                stratum_profile.elapsed = seconds_since_the_epoch()? - started;
+                for rule_name in cur_prog.keys() {
+                    stratum_profile
+                        .rows
+                        .insert(rule_name.clone(), stores[rule_name].len());
+                }
+                profile.strata.push(stratum_profile);
+            }
         }
         let entry_symbol = MagicSymbol::Muggle {
             inner: Symbol::new(PROG_ENTRY, SourceSpan(0, 0)),
@@ -114,9 +162,12 @@ impl<'a> SessionTx<'a> {
         &self,
         prog: &CompiledProgram,
         stores: &mut BTreeMap<MagicSymbol, EpochStore>,
+        graph_cache: &GraphCache,
         total_num_to_take: Option<usize>,
         num_to_skip: Option<usize>,
+        memory_limit: Option<usize>,
         poison: Poison,
+        mut profile: Option<&mut StratumProfile>,
     ) -> Result<bool> {
         let limiter = QueryLimiter {
             total: total_num_to_take,
@@ -174,6 +225,7 @@ impl<'a> SessionTx<'a> {
                                 manifest: &fixed,
                                 stores: borrowed_stores,
                                 tx: self,
+                                graph_cache,
                             };
                             fixed_impl.run(payload, &mut out, poison.clone())?;
                             out.wrap()
@@ -181,7 +233,7 @@ impl<'a> SessionTx<'a> {
                     };
                     Ok((k, new_store))
                 };
-                #[cfg(not(target_arch = "wasm32"))]
+                #[cfg(all(not(target_arch = "wasm32"), feature = "rayon"))]
                 {
                     let limiter_enabled = limiter.total.is_some();
                     for res in prog
@@ -206,7 +258,7 @@ impl<'a> SessionTx<'a> {
                         to_merge.insert(k, new_store);
                     }
                 }
-                #[cfg(target_arch = "wasm32")]
+                #[cfg(any(target_arch = "wasm32", not(feature = "rayon")))]
                 {
                     for res in prog.iter().map(execution) {
                         let (k, new_store) = res?;
@@ -255,7 +307,7 @@ impl<'a> SessionTx<'a> {
                     };
                     Ok((k, new_store))
                 };
-                #[cfg(not(target_arch = "wasm32"))]
+                #[cfg(all(not(target_arch = "wasm32"), feature = "rayon"))]
                 {
                     let limiter_enabled = limiter.total.is_some();
                     // entry rules with limiter must execute sequentially in order to get deterministic ordering
@@ -280,7 +332,7 @@ impl<'a> SessionTx<'a> {
                         to_merge.insert(k, new_store);
                     }
                 }
-                #[cfg(target_arch = "wasm32")]
+                #[cfg(any(target_arch = "wasm32", not(feature = "rayon")))]
                 {
                     for res in prog.iter().map(execution) {
                         let (k, new_store) = res?;
@@ -289,11 +341,25 @@ impl<'a> SessionTx<'a> {
                 }
             }
             let mut changed = false;
+            let mut deltas = BTreeMap::new();
             for (k, new_store) in to_merge {
                 let old_store = stores.get_mut(k).unwrap();
-                old_store.merge_in(new_store)?;
+                old_store.merge_in(new_store, memory_limit.is_some())?;
                 trace!("delta for {}: {}", k, old_store.has_delta());
                 changed |= old_store.has_delta();
+                if profile.is_some() {
+                    deltas.insert(k.clone(), old_store.delta_len());
+                }
+            }
+            if let Some(profile) = profile.as_deref_mut() {
+                profile.deltas.push(deltas);
+            }
+            if let Some(limit) = memory_limit {
+                // stores of earlier strata still alive count towards the limit as well
+                let used: usize = stores.values().map(|s| s.approx_size()).sum();
+                if used > limit {
+                    bail!(QueryMemoryExceeded(limit))
+                }
             }
             if !changed {
                 break;
@@ -334,7 +400,7 @@ impl<'a> SessionTx<'a> {
                     out_store.put(item);
                 }
             }
-            poison.check()?;
+            poison.check_in_rule(rule_symb)?;
         }
 
         Ok((should_check_limit, out_store))
@@ -359,7 +425,7 @@ impl<'a> SessionTx<'a> {
                 trace!("item for {:?}.{}: {:?} at {}", rule_symb, rule_n, item, 0);
                 out_store.meet_put(item)?;
             }
-            poison.check()?;
+            poison.check_in_rule(rule_symb)?;
         }
         if out_store.is_empty() && ruleset[0].aggr.iter().all(|a| a.is_some()) {
             let mut aggr = ruleset[0].aggr.clone();
@@ -443,7 +509,7 @@ impl<'a> SessionTx<'a> {
                     }
                 }
             }
-            poison.check()?;
+            poison.check_in_rule(rule_symb)?;
         }
 
         let mut inv_indices = Vec::with_capacity(ruleset[0].aggr.len());
@@ -566,7 +632,7 @@ impl<'a> SessionTx<'a> {
                         }
                     }
                 }
-                poison.check()?;
+                poison.check_in_rule(rule_symb)?;
             } else {
                 for (delta_key, _) in stores.iter() {
                     if !rule.contained_rules.contains_key(delta_key) {
@@ -606,7 +672,7 @@ impl<'a> SessionTx<'a> {
                             }
                         }
                     }
-                    poison.check()?;
+                    poison.check_in_rule(rule_symb)?;
                 }
             }
         }
@@ -648,7 +714,7 @@ impl<'a> SessionTx<'a> {
                 for item_res in rule.relation.iter(self, None, stores)? {
                     out_store.meet_put(item_res?)?;
                 }
-                poison.check()?;
+                poison.check_in_rule(rule_symb)?;
             } else {
                 for (delta_key, _) in stores.iter() {
                     if !rule.contained_rules.contains_key(delta_key) {
@@ -661,7 +727,7 @@ impl<'a> SessionTx<'a> {
                     for item_res in rule.relation.iter(self, Some(delta_key), stores)? {
                         out_store.meet_put(item_res?)?;
                     }
-                    poison.check()?;
+                    poison.check_in_rule(rule_symb)?;
                 }
             }
         }
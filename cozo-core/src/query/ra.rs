@@ -19,13 +19,13 @@ use thiserror::Error;
 
 use crate::data::expr::{compute_bounds, eval_bytecode, eval_bytecode_pred, Bytecode, Expr};
 use crate::data::program::{FtsSearch, HnswSearch, MagicSymbol};
-use crate::data::relation::{ColType, NullableColType};
+use crate::data::relation::{ColType, ColumnDef, NullableColType};
 use crate::data::symb::Symbol;
 use crate::data::tuple::{Tuple, TupleIter};
 use crate::data::value::{DataValue, ValidityTs};
 use crate::parse::SourceSpan;
 use crate::runtime::minhash_lsh::LshSearch;
-use crate::runtime::relation::RelationHandle;
+use crate::runtime::relation::{is_expired, RelationHandle};
 use crate::runtime::temp_store::EpochStore;
 use crate::runtime::transact::SessionTx;
 use crate::utils::swap_option_result;
@@ -1147,7 +1147,27 @@ impl StoredWithValidityRA {
         Ok(())
     }
     fn iter<'a>(&'a self, tx: &'a SessionTx<'_>) -> Result<TupleIter<'a>> {
-        let it = self.storage.skip_scan_all(tx, self.valid_at);
+        let ttl = self.storage.ttl_cutoff();
+        let keys = &self.storage.metadata.keys;
+        let it = match key_bounds(&self.filters, &self.bindings[..keys.len()], keys) {
+            Some((l_bound, u_bound)) => Left(self.storage.skip_scan_bounded_prefix(
+                tx,
+                &Tuple::default(),
+                &l_bound,
+                &u_bound,
+                self.valid_at,
+            )),
+            None => Right(self.storage.skip_scan_all(tx, self.valid_at)),
+        }
+        .map(move |t| -> Result<Option<Tuple>> {
+            let t = t?;
+            Ok(if is_expired(tx, &t, ttl)? {
+                None
+            } else {
+                Some(t)
+            })
+        })
+        .filter_map(swap_option_result);
         Ok(if self.filters.is_empty() {
             Box::new(it)
         } else {
@@ -1169,6 +1189,7 @@ impl StoredWithValidityRA {
             .collect_vec();
 
         let mut skip_range_check = false;
+        let ttl = self.storage.ttl_cutoff();
 
         let it = left_iter
             .map_ok(move |tuple| {
@@ -1178,11 +1199,15 @@ impl StoredWithValidityRA {
                     .collect_vec();
 
                 if !skip_range_check && !self.filters.is_empty() {
-                    let other_bindings = &self.bindings[right_join_indices.len()..];
-                    let (l_bound, u_bound) = match compute_bounds(&self.filters, other_bindings) {
-                        Ok(b) => b,
-                        _ => (vec![], vec![]),
-                    };
+                    // only key columns can bound the scan, the join may bind all of them
+                    let keys = &self.storage.metadata.keys;
+                    let start = right_join_indices.len().min(keys.len());
+                    let other_bindings = &self.bindings[start..keys.len()];
+                    let (l_bound, u_bound) =
+                        match compute_bounds(&self.filters, other_bindings, Some(&keys[start..])) {
+                            Ok(b) => b,
+                            _ => (vec![], vec![]),
+                        };
                     if !l_bound.iter().all(|v| *v == DataValue::Null)
                         || !u_bound.iter().all(|v| *v == DataValue::Bot)
                     {
@@ -1198,6 +1223,9 @@ impl StoredWithValidityRA {
                                 )
                                 .map(move |res_found| -> Result<Option<Tuple>> {
                                     let found = res_found?;
+                                    if is_expired(tx, &found, ttl)? {
+                                        return Ok(None);
+                                    }
                                     for (p, span) in self.filters_bytecodes.iter() {
                                         if !eval_bytecode_pred(p, &found, &mut stack, *span)? {
                                             return Ok(None);
@@ -1218,6 +1246,9 @@ impl StoredWithValidityRA {
                         .skip_scan_prefix(tx, &prefix, self.valid_at)
                         .map(move |res_found| -> Result<Option<Tuple>> {
                             let found = res_found?;
+                            if is_expired(tx, &found, ttl)? {
+                                return Ok(None);
+                            }
                             for (p, span) in self.filters_bytecodes.iter() {
                                 if !eval_bytecode_pred(p, &found, &mut stack, *span)? {
                                     return Ok(None);
@@ -1267,6 +1298,7 @@ impl StoredRA {
         right_join_indices: Vec<usize>,
     ) -> Result<TupleIter<'a>> {
         let mut stack = vec![];
+        let ttl = self.storage.ttl_cutoff();
 
         let it = left_iter
             .map_ok(move |tuple| -> Result<Option<Tuple>> {
@@ -1277,6 +1309,7 @@ impl StoredRA {
                 let key = &prefix[0..key_len];
                 match self.storage.get(tx, key)? {
                     None => Ok(None),
+                    Some(found) if is_expired(tx, &found, ttl)? => Ok(None),
                     Some(found) => {
                         for (lk, rk) in left_join_indices.iter().zip(right_join_indices.iter()) {
                             if tuple[*lk] != found[*rk] {
@@ -1331,6 +1364,7 @@ impl StoredRA {
         }
 
         let mut skip_range_check = false;
+        let ttl = self.storage.ttl_cutoff();
         // In some cases, maybe we can stop as soon as we get one result?
         let it = left_iter
             .map_ok(move |tuple| {
@@ -1341,8 +1375,14 @@ impl StoredRA {
                 let mut stack = vec![];
 
                 if !skip_range_check && !self.filters.is_empty() {
-                    let other_bindings = &self.bindings[right_join_indices.len()..];
-                    let (l_bound, u_bound) = match compute_bounds(&self.filters, other_bindings) {
+                    // only key columns can bound the scan
+                    let keys = &self.storage.metadata.keys;
+                    let other_bindings = &self.bindings[right_join_indices.len()..keys.len()];
+                    let (l_bound, u_bound) = match compute_bounds(
+                        &self.filters,
+                        other_bindings,
+                        Some(&keys[right_join_indices.len()..]),
+                    ) {
                         Ok(b) => b,
                         _ => (vec![], vec![]),
                     };
@@ -1354,6 +1394,9 @@ impl StoredRA {
                                 .scan_bounded_prefix(tx, &prefix, &l_bound, &u_bound)
                                 .map(move |res_found| -> Result<Option<Tuple>> {
                                     let found = res_found?;
+                                    if is_expired(tx, &found, ttl)? {
+                                        return Ok(None);
+                                    }
                                     for (p, span) in self.filters_bytecodes.iter() {
                                         if !eval_bytecode_pred(p, &found, &mut stack, *span)? {
                                             return Ok(None);
@@ -1373,6 +1416,9 @@ impl StoredRA {
                         .scan_prefix(tx, &prefix)
                         .map(move |res_found| -> Result<Option<Tuple>> {
                             let found = res_found?;
+                            if is_expired(tx, &found, ttl)? {
+                                return Ok(None);
+                            }
                             for (p, span) in self.filters_bytecodes.iter() {
                                 if !eval_bytecode_pred(p, &found, &mut stack, *span)? {
                                     return Ok(None);
@@ -1411,6 +1457,7 @@ impl StoredRA {
             }
             left_to_prefix_indices.push(left_join_indices[*idx]);
         }
+        let ttl = self.storage.ttl_cutoff();
 
         if join_is_prefix(&right_join_indices) {
             Ok(Box::new(
@@ -1423,6 +1470,9 @@ impl StoredRA {
 
                         'outer: for found in self.storage.scan_prefix(tx, &prefix) {
                             let found = found?;
+                            if is_expired(tx, &found, ttl)? {
+                                continue;
+                            }
                             for (left_idx, right_idx) in
                                 left_join_indices.iter().zip(right_join_indices.iter())
                             {
@@ -1457,6 +1507,9 @@ impl StoredRA {
 
             for tuple in self.storage.scan_all(tx) {
                 let tuple = tuple?;
+                if is_expired(tx, &tuple, ttl)? {
+                    continue;
+                }
                 let to_join: Box<[DataValue]> = right_join_indices
                     .iter()
                     .map(|i| tuple[*i].clone())
@@ -1497,7 +1550,21 @@ impl StoredRA {
     }
 
     fn iter<'a>(&'a self, tx: &'a SessionTx<'_>) -> Result<TupleIter<'a>> {
-        let it = self.storage.scan_all(tx);
+        let ttl = self.storage.ttl_cutoff();
+        let keys = &self.storage.metadata.keys;
+        let it = match key_bounds(&self.filters, &self.bindings[..keys.len()], keys) {
+            Some((lower, upper)) => Left(self.storage.scan_bounded_prefix(tx, &[], &lower, &upper)),
+            None => Right(self.storage.scan_all(tx)),
+        }
+        .map(move |t| -> Result<Option<Tuple>> {
+            let t = t?;
+            Ok(if is_expired(tx, &t, ttl)? {
+                None
+            } else {
+                Some(t)
+            })
+        })
+        .filter_map(swap_option_result);
         Ok(if self.filters.is_empty() {
             Box::new(it)
         } else {
@@ -1506,6 +1573,21 @@ impl StoredRA {
     }
 }
 
+/// Bounds of a scan of the whole relation, if the filters restrict any of the key columns
+fn key_bounds(
+    filters: &[Expr],
+    key_bindings: &[Symbol],
+    keys: &[ColumnDef],
+) -> Option<(Vec<DataValue>, Vec<DataValue>)> {
+    if filters.is_empty() {
+        return None;
+    }
+    let (l_bound, u_bound) = compute_bounds(filters, key_bindings, Some(keys)).ok()?;
+    (!l_bound.iter().all(|v| *v == DataValue::Null)
+        || !u_bound.iter().all(|v| *v == DataValue::Bot))
+    .then_some((l_bound, u_bound))
+}
+
 fn join_is_prefix(right_join_indices: &[usize]) -> bool {
     // We do not consider partial index match to be "prefix", e.g. [a, u => c]
     // with a, c bound and u unbound is not "prefix", as it is not clear that
@@ -1692,10 +1774,11 @@ impl TempStoreRA {
 
                 if !skip_range_check && !self.filters.is_empty() {
                     let other_bindings = &self.bindings[right_join_indices.len()..];
-                    let (l_bound, u_bound) = match compute_bounds(&self.filters, other_bindings) {
-                        Ok(b) => b,
-                        _ => (vec![], vec![]),
-                    };
+                    let (l_bound, u_bound) =
+                        match compute_bounds(&self.filters, other_bindings, None) {
+                            Ok(b) => b,
+                            _ => (vec![], vec![]),
+                        };
                     if !l_bound.iter().all(|v| *v == DataValue::Null)
                         || !u_bound.iter().all(|v| *v == DataValue::Bot)
                     {
@@ -19,11 +19,14 @@ use crate::runtime::temp_store::EpochStore;
 use crate::runtime::transact::SessionTx;
 
 impl<'a> SessionTx<'a> {
+    /// Sort the results. If `keep` is given, only that many tuples from the start of the
+    /// sorted results are returned, which avoids sorting the rest.
     pub(crate) fn sort_and_collect(
         &mut self,
         original: EpochStore,
         sorters: &[(Symbol, SortDir)],
         head: &[Symbol],
+        keep: Option<usize>,
     ) -> Result<Vec<Tuple>> {
         let head_indices: BTreeMap<_, _> = head.iter().enumerate().map(|(i, k)| (k, i)).collect();
         let idx_sorters = sorters
@@ -32,7 +35,7 @@ impl<'a> SessionTx<'a> {
             .collect_vec();
 
         let mut all_data: Vec<_> = original.all_iter().map(|v| v.into_tuple()).collect_vec();
-        all_data.sort_by(|a, b| {
+        let cmp = |a: &Tuple, b: &Tuple| {
             for (idx, dir) in &idx_sorters {
                 match a[*idx].cmp(&b[*idx]) {
                     Ordering::Equal => {}
@@ -44,8 +47,19 @@ impl<'a> SessionTx<'a> {
                     }
                 }
             }
-            Ordering::Equal
-        });
+            // the data come sorted, so this keeps ties in their original order,
+            // even for the unstable selection below
+            a.cmp(b)
+        };
+        if let Some(keep) = keep {
+            if keep == 0 {
+                all_data.clear();
+            } else if keep < all_data.len() {
+                all_data.select_nth_unstable_by(keep - 1, cmp);
+                all_data.truncate(keep);
+            }
+        }
+        all_data.sort_by(cmp);
 
         Ok(all_data)
     }
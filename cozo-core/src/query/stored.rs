@@ -7,6 +7,7 @@
  */
 
 use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use itertools::Itertools;
@@ -30,6 +31,7 @@ use crate::runtime::callback::{CallbackCollector, CallbackOp};
 use crate::runtime::minhash_lsh::HashPermutations;
 use crate::runtime::relation::{
     extend_tuple_from_v, AccessLevel, InputRelationHandle, InsufficientAccessLevel, RelationHandle,
+    UniqueConstraintViolation,
 };
 use crate::runtime::transact::SessionTx;
 use crate::storage::Storage;
@@ -53,8 +55,9 @@ impl<'a> SessionTx<'a> {
         callback_collector: &mut CallbackCollector,
         propagate_triggers: bool,
         force_collect: &str,
-    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    ) -> Result<(Vec<(Vec<u8>, Vec<u8>)>, usize)> {
         let mut to_clear = vec![];
+        let mut skipped = 0;
         let mut replaced_old_triggers = None;
         if op == RelationOp::Replace {
             if !propagate_triggers {
@@ -87,6 +90,7 @@ impl<'a> SessionTx<'a> {
                         trigger,
                         &Default::default(),
                         &db.fixed_rules.read().unwrap(),
+                        &db.user_fns.read().unwrap(),
                         cur_vld,
                     )?
                     .get_single_program()?;
@@ -129,6 +133,7 @@ impl<'a> SessionTx<'a> {
             key_bindings,
             dep_bindings,
             span,
+            merges,
             ..
         } = meta;
 
@@ -182,8 +187,12 @@ impl<'a> SessionTx<'a> {
                 force_collect,
                 *span,
             )?,
-            RelationOp::Create | RelationOp::Replace | RelationOp::Put | RelationOp::Insert => self
-                .put_into_relation(
+            RelationOp::Create
+            | RelationOp::Replace
+            | RelationOp::Put
+            | RelationOp::Insert
+            | RelationOp::InsertOrIgnore => {
+                skipped = self.put_into_relation(
                     db,
                     res_iter,
                     headers,
@@ -196,13 +205,16 @@ impl<'a> SessionTx<'a> {
                     metadata,
                     key_bindings,
                     dep_bindings,
+                    merges,
                     op == RelationOp::Insert,
+                    op == RelationOp::InsertOrIgnore,
                     force_collect,
                     *span,
-                )?,
+                )?
+            }
         };
 
-        Ok(to_clear)
+        Ok((to_clear, skipped))
     }
 
     fn put_into_relation<'s, S: Storage<'s>>(
@@ -219,10 +231,12 @@ impl<'a> SessionTx<'a> {
         metadata: &StoredRelationMetadata,
         key_bindings: &[Symbol],
         dep_bindings: &[Symbol],
+        merges: &BTreeMap<SmartString<LazyCompact>, Expr>,
         is_insert: bool,
+        ignore_existing: bool,
         force_collect: &str,
         span: SourceSpan,
-    ) -> Result<()> {
+    ) -> Result<usize> {
         let is_callback_target = callback_targets.contains(&relation_store.name)
             || force_collect == relation_store.name;
 
@@ -268,26 +282,57 @@ impl<'a> SessionTx<'a> {
             )?
         };
         key_extractors.extend(val_extractors);
+        let mergers = make_mergers(relation_store, merges)?;
         let mut stack = vec![];
         let hnsw_filters = Self::make_hnsw_filters(relation_store)?;
         let fts_lsh_processors = self.make_fts_lsh_processors(relation_store)?;
         let lsh_perms = self.make_lsh_hash_perms(relation_store);
+        let mut skipped = 0;
+        let check_existing = ignore_existing
+            || (is_insert
+                && (relation_store.is_temp || !db.skip_insert_checks.load(Ordering::Acquire)));
 
         for tuple in res_iter {
-            let extracted: Vec<DataValue> = key_extractors
+            let mut extracted: Vec<DataValue> = key_extractors
                 .iter()
                 .map(|ex| ex.extract_data(&tuple, cur_vld))
                 .try_collect()?;
 
             let key = relation_store.encode_key_for_store(&extracted, span)?;
 
-            if is_insert {
+            if !mergers.is_empty() {
+                let existing = if relation_store.is_temp {
+                    self.temp_store_tx.get(&key, false)?
+                } else {
+                    self.store_tx.get(&key, false)?
+                };
+                if let Some(existing) = existing {
+                    let mut old = extracted[0..relation_store.metadata.keys.len()].to_vec();
+                    extend_tuple_from_v(&mut old, &existing);
+                    for (i, expr, col) in &mergers {
+                        let merged = expr.eval([old[*i].clone(), extracted[*i].clone()])?;
+                        extracted[*i] = col.typing.coerce(merged, cur_vld).wrap_err_with(|| {
+                            ColumnTypeMismatch {
+                                column: col.name.to_string(),
+                                typing: col.typing.clone(),
+                                row: tuple.clone(),
+                            }
+                        })?;
+                    }
+                }
+            }
+
+            if check_existing {
                 let already_exists = if relation_store.is_temp {
                     self.temp_store_tx.exists(&key, true)?
                 } else {
                     self.store_tx.exists(&key, true)?
                 };
 
+                if already_exists && ignore_existing {
+                    skipped += 1;
+                    continue;
+                }
                 if already_exists {
                     bail!(TransactAssertionFailure {
                         relation: relation_store.name.to_string(),
@@ -328,6 +373,7 @@ impl<'a> SessionTx<'a> {
                         self.store_tx.put(&encoded_new, &[])?;
                     }
                 }
+                self.ensure_unique_in_indices(relation_store, &extracted)?;
 
                 self.update_in_hnsw(relation_store, &mut stack, &hnsw_filters, &extracted)?;
                 self.put_in_fts(relation_store, &mut stack, &fts_lsh_processors, &extracted)?;
@@ -365,7 +411,7 @@ impl<'a> SessionTx<'a> {
                 old_tuples,
             )?;
         }
-        Ok(())
+        Ok(skipped)
     }
 
     fn put_in_fts(
@@ -622,6 +668,7 @@ impl<'a> SessionTx<'a> {
                 self.del_in_fts(relation_store, &mut stack, &fts_lsh_processors, &old_kv)?;
                 self.del_in_lsh(relation_store, &old_kv)?;
                 self.update_in_index(relation_store, &new_kv, &old_kv)?;
+                self.ensure_unique_in_indices(relation_store, &new_kv)?;
 
                 if need_to_collect {
                     old_tuples.push(DataValue::List(old_kv));
@@ -699,6 +746,7 @@ impl<'a> SessionTx<'a> {
                     trigger,
                     &Default::default(),
                     &db.fixed_rules.read().unwrap(),
+                    &db.user_fns.read().unwrap(),
                     cur_vld,
                 )?
                 .get_single_program()?;
@@ -795,6 +843,33 @@ impl<'a> SessionTx<'a> {
         Ok(())
     }
 
+    /// Checks that no other row shares the values of `kv` in the unique indices,
+    /// which must already contain `kv`. Null values never conflict.
+    fn ensure_unique_in_indices(
+        &self,
+        relation_store: &RelationHandle,
+        kv: &[DataValue],
+    ) -> Result<()> {
+        for (idx_name, n_cols) in &relation_store.unique_indices {
+            let (idx_rel, idx_extractor) = &relation_store.indices[idx_name];
+            let idx_tup = idx_extractor.iter().map(|i| kv[*i].clone()).collect_vec();
+            let prefix = idx_tup[..*n_cols].to_vec();
+            if prefix.contains(&DataValue::Null) {
+                continue;
+            }
+            for found in idx_rel.scan_prefix(self, &prefix) {
+                if found? != idx_tup {
+                    bail!(UniqueConstraintViolation {
+                        relation: relation_store.name.to_string(),
+                        index: idx_name.to_string(),
+                        values: prefix,
+                    })
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn ensure_not_in_relation(
         &mut self,
         res_iter: impl Iterator<Item = Tuple>,
@@ -1036,6 +1111,7 @@ impl<'a> SessionTx<'a> {
                         trigger,
                         &Default::default(),
                         &db.fixed_rules.read().unwrap(),
+                        &db.user_fns.read().unwrap(),
                         cur_vld,
                     )?
                     .get_single_program()?;
@@ -1117,21 +1193,33 @@ struct TransactAssertionFailure {
     notice: String,
 }
 
+#[derive(Debug, Error, Diagnostic)]
+#[error("Cannot store value for column '{column}' of type {typing} in row {row:?}")]
+#[diagnostic(code(eval::column_type_mismatch))]
+pub(crate) struct ColumnTypeMismatch {
+    pub(crate) column: String,
+    pub(crate) typing: NullableColType,
+    pub(crate) row: Tuple,
+}
+
 enum DataExtractor {
-    DefaultExtractor(Expr, NullableColType),
-    IndexExtractor(usize, NullableColType),
+    DefaultExtractor(Expr, ColumnDef),
+    IndexExtractor(usize, ColumnDef),
 }
 
 impl DataExtractor {
     fn extract_data(&self, tuple: &Tuple, cur_vld: ValidityTs) -> Result<DataValue> {
-        Ok(match self {
-            DataExtractor::DefaultExtractor(expr, typ) => typ
-                .coerce(expr.clone().eval_to_const()?, cur_vld)
-                .wrap_err_with(|| format!("when processing tuple {tuple:?}"))?,
-            DataExtractor::IndexExtractor(i, typ) => typ
-                .coerce(tuple[*i].clone(), cur_vld)
-                .wrap_err_with(|| format!("when processing tuple {tuple:?}"))?,
-        })
+        let (val, col) = match self {
+            DataExtractor::DefaultExtractor(expr, col) => (expr.clone().eval_to_const()?, col),
+            DataExtractor::IndexExtractor(i, col) => (tuple[*i].clone(), col),
+        };
+        col.typing
+            .coerce(val, cur_vld)
+            .wrap_err_with(|| ColumnTypeMismatch {
+                column: col.name.to_string(),
+                typing: col.typing.clone(),
+                row: tuple.clone(),
+            })
     }
 }
 
@@ -1175,7 +1263,7 @@ fn make_extractor(
         if inp_col.name == stored.name {
             for (idx, tuple_head) in tuple_headers.iter().enumerate() {
                 if tuple_head == inp_binding {
-                    return Ok(DataExtractor::IndexExtractor(idx, stored.typing.clone()));
+                    return Ok(DataExtractor::IndexExtractor(idx, stored.clone()));
                 }
             }
         }
@@ -1183,7 +1271,7 @@ fn make_extractor(
     if let Some(expr) = &stored.default_gen {
         Ok(DataExtractor::DefaultExtractor(
             expr.clone(),
-            stored.typing.clone(),
+            stored.clone(),
         ))
     } else {
         #[derive(Debug, Error, Diagnostic)]
@@ -1194,6 +1282,41 @@ fn make_extractor(
     }
 }
 
+/// Position in the stored tuple, compiled merge expression and column of each merged column
+fn make_mergers(
+    relation_store: &RelationHandle,
+    merges: &BTreeMap<SmartString<LazyCompact>, Expr>,
+) -> Result<Vec<(usize, Expr, ColumnDef)>> {
+    #[derive(Debug, Error, Diagnostic)]
+    #[error("Cannot merge column '{0}' of {1}: only existing non-key columns can be merged")]
+    #[diagnostic(code(eval::bad_merge_column))]
+    struct BadMergeColumn(String, String);
+
+    let binding_map = BTreeMap::from([
+        (Symbol::new("_old", Default::default()), 0),
+        (Symbol::new("_new", Default::default()), 1),
+    ]);
+    let n_keys = relation_store.metadata.keys.len();
+    merges
+        .iter()
+        .map(|(name, expr)| {
+            let pos = relation_store
+                .metadata
+                .non_keys
+                .iter()
+                .position(|c| c.name == *name)
+                .ok_or_else(|| BadMergeColumn(name.to_string(), relation_store.name.to_string()))?;
+            let mut expr = expr.clone();
+            expr.fill_binding_indices(&binding_map)?;
+            Ok((
+                n_keys + pos,
+                expr,
+                relation_store.metadata.non_keys[pos].clone(),
+            ))
+        })
+        .try_collect()
+}
+
 fn make_const_rule(
     program: &mut InputProgram,
     rule_name: &str,
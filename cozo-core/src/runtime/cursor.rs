@@ -0,0 +1,68 @@
+/*
+ * Copyright 2022, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::vec::IntoIter;
+
+use crate::data::tuple::Tuple;
+use crate::NamedRows;
+
+/// A cursor paginating the result of a query, handing out the rows in batches.
+///
+/// The query is evaluated to completion, against a single read snapshot, when the cursor
+/// is opened, and the whole result is held in memory until the cursor is dropped.
+/// The cursor does not reduce the memory needed for the query, it only saves the caller
+/// from handling all rows at once. Consuming the cursor never re-runs the query,
+/// so all batches are consistent with each other regardless of writes committed in the meantime.
+pub struct QueryCursor {
+    headers: Vec<String>,
+    rows: IntoIter<Tuple>,
+    batch_size: usize,
+}
+
+impl QueryCursor {
+    pub(crate) fn new(res: NamedRows, batch_size: usize) -> Self {
+        Self {
+            headers: res.headers,
+            rows: res.rows.into_iter(),
+            batch_size,
+        }
+    }
+
+    /// The headers of the query result
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// The maximal number of rows returned by each call to [QueryCursor::next_batch]
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    /// Number of rows not yet handed out
+    pub fn remaining(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns the next batch of rows, or `None` if the cursor is exhausted.
+    /// Every batch except possibly the last one contains exactly `batch_size` rows.
+    pub fn next_batch(&mut self) -> Option<NamedRows> {
+        if self.rows.as_slice().is_empty() {
+            return None;
+        }
+        let rows = self.rows.by_ref().take(self.batch_size).collect();
+        Some(NamedRows::new(self.headers.clone(), rows))
+    }
+}
+
+impl Iterator for QueryCursor {
+    type Item = NamedRows;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_batch()
+    }
+}
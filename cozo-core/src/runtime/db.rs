@@ -13,18 +13,21 @@ use std::fmt::{Debug, Formatter};
 use std::iter;
 use std::path::Path;
 #[allow(unused_imports)]
-use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+#[allow(unused_imports)]
+use std::sync::{Arc, Condvar, Mutex, Weak};
 #[allow(unused_imports)]
 use std::thread;
 #[allow(unused_imports)]
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 #[allow(unused_imports)]
 use crossbeam::channel::{bounded, unbounded, Receiver, Sender};
 use crossbeam::sync::ShardedLock;
 use either::{Left, Right};
 use itertools::Itertools;
+#[allow(unused_imports)]
+use lazy_static::lazy_static;
 use miette::Report;
 #[allow(unused_imports)]
 use miette::{bail, ensure, miette, Diagnostic, IntoDiagnostic, Result, WrapErr};
@@ -32,9 +35,10 @@ use serde_json::json;
 use smartstring::{LazyCompact, SmartString};
 use thiserror::Error;
 
+use crate::data::expr::{get_op, ListFnKind, UserFn};
 use crate::data::functions::current_validity;
 use crate::data::json::JsonValue;
-use crate::data::program::{InputProgram, QueryAssertion, RelationOp, ReturnMutation};
+use crate::data::program::{InputProgram, MagicSymbol, QueryAssertion, RelationOp, ReturnMutation};
 use crate::data::relation::ColumnDef;
 use crate::data::tuple::{Tuple, TupleT};
 use crate::data::value::{DataValue, ValidityTs, LARGEST_UTF_CHAR};
@@ -43,6 +47,7 @@ use crate::fts::TokenizerCache;
 use crate::parse::sys::SysOp;
 use crate::parse::{parse_expressions, parse_script, CozoScript, SourceSpan};
 use crate::query::compile::{CompiledProgram, CompiledRule, CompiledRuleSet};
+use crate::query::eval::EvalProfile;
 use crate::query::ra::{
     FilteredRA, FtsSearchRA, HnswSearchRA, InnerJoin, LshSearchRA, NegJoin, RelAlgebra, ReorderRA,
     StoredRA, StoredWithValidityRA, TempStoreRA, UnificationRA,
@@ -51,10 +56,13 @@ use crate::query::ra::{
 use crate::runtime::callback::{
     CallbackCollector, CallbackDeclaration, CallbackOp, EventCallbackRegistry,
 };
+use crate::runtime::cursor::QueryCursor;
 use crate::runtime::relation::{
-    extend_tuple_from_v, AccessLevel, InsufficientAccessLevel, RelationHandle, RelationId,
+    extend_tuple_from_v, is_expired, AccessLevel, InsufficientAccessLevel, RelationHandle,
+    RelationId, StoredProcedure,
 };
 use crate::runtime::transact::SessionTx;
+use crate::storage::counting::{CountingTx, StorageCounters};
 use crate::storage::temp::TempStorage;
 use crate::storage::Storage;
 use crate::{decode_tuple_from_kv, FixedRule, Symbol};
@@ -101,7 +109,13 @@ pub struct Db<S> {
     pub(crate) queries_count: Arc<AtomicU64>,
     pub(crate) running_queries: Arc<Mutex<BTreeMap<u64, RunningQueryHandle>>>,
     pub(crate) fixed_rules: Arc<ShardedLock<BTreeMap<String, Arc<Box<dyn FixedRule>>>>>,
+    pub(crate) user_fns: Arc<ShardedLock<BTreeMap<String, UserFn>>>,
     pub(crate) tokenizers: Arc<TokenizerCache>,
+    memory_limit: Arc<AtomicUsize>,
+    /// Bits of the default timeout in seconds, zero for none
+    timeout: Arc<AtomicU64>,
+    /// Whether `:insert` skips checking for existing keys, only set while bulk loading
+    pub(crate) skip_insert_checks: Arc<AtomicBool>,
     #[cfg(not(target_arch = "wasm32"))]
     callback_count: Arc<AtomicU32>,
     #[cfg(not(target_arch = "wasm32"))]
@@ -244,6 +258,7 @@ impl NamedRows {
 
 const STATUS_STR: &str = "status";
 const OK_STR: &str = "OK";
+const STORAGE_STATS_SAMPLE_SIZE: usize = 1000;
 
 /// The query and parameters.
 pub type Payload = (String, BTreeMap<String, DataValue>);
@@ -271,7 +286,11 @@ impl<'s, S: Storage<'s>> Db<S> {
             queries_count: Default::default(),
             running_queries: Default::default(),
             fixed_rules: Arc::new(ShardedLock::new(DEFAULT_FIXED_RULES.clone())),
+            user_fns: Default::default(),
             tokenizers: Arc::new(Default::default()),
+            memory_limit: Default::default(),
+            timeout: Default::default(),
+            skip_insert_checks: Default::default(),
             #[cfg(not(target_arch = "wasm32"))]
             callback_count: Default::default(),
             // callback_receiver: Arc::new(receiver),
@@ -342,18 +361,22 @@ impl<'s, S: Storage<'s>> Db<S> {
                     break;
                 }
                 TransactionPayload::Query((script, params)) => {
-                    let p =
-                        match parse_script(&script, &params, &self.fixed_rules.read().unwrap(), ts)
-                        {
-                            Ok(p) => p,
-                            Err(err) => {
-                                if results.send(Err(err)).is_err() {
-                                    break;
-                                } else {
-                                    continue;
-                                }
+                    let p = match parse_script(
+                        &script,
+                        &params,
+                        &self.fixed_rules.read().unwrap(),
+                        &self.user_fns.read().unwrap(),
+                        ts,
+                    ) {
+                        Ok(p) => p,
+                        Err(err) => {
+                            if results.send(Err(err)).is_err() {
+                                break;
+                            } else {
+                                continue;
                             }
-                        };
+                        }
+                    };
 
                     let p = match p.get_single_program() {
                         Ok(p) => p,
@@ -420,6 +443,26 @@ impl<'s, S: Storage<'s>> Db<S> {
         self.do_run_script(payload, &params, cur_vld, true)
     }
 
+    /// Open a cursor over the result of the CozoScript passed in, handing out `batch_size` rows
+    /// at a time. The script is run read-only and evaluated exactly once, against a single
+    /// snapshot of the database. The whole result is computed before this returns and is
+    /// held by the cursor, which only paginates it.
+    pub fn open_cursor(
+        &'s self,
+        payload: &str,
+        params: BTreeMap<String, DataValue>,
+        batch_size: usize,
+    ) -> Result<QueryCursor> {
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("Cursor batch size must be positive")]
+        #[diagnostic(code(db::zero_cursor_batch))]
+        struct ZeroCursorBatchSize;
+
+        ensure!(batch_size > 0, ZeroCursorBatchSize);
+        let res = self.run_script_read_only(payload, params)?;
+        Ok(QueryCursor::new(res, batch_size))
+    }
+
     /// Export relations to JSON data.
     ///
     /// `relations` contains names of the stored relations to export.
@@ -617,6 +660,70 @@ impl<'s, S: Storage<'s>> Db<S> {
         tx.commit_tx()?;
         Ok(())
     }
+    /// Copy the running database into the directory `path`, which must not exist yet.
+    /// Queries and writes can continue while the copy is made. The copy is a consistent
+    /// snapshot made with a RocksDB checkpoint, which hard-links the data files where
+    /// possible, so it is cheap when `path` is on the same filesystem. The result can be
+    /// opened directly with the same storage engine.
+    ///
+    /// Only supported by the RocksDB engine. For other engines, use [Db::backup_db].
+    pub fn backup(&'s self, path: impl AsRef<Path>) -> Result<()> {
+        self.db.checkpoint(path.as_ref())
+    }
+    /// Set the default memory limit in bytes for evaluating a query, `None` for no limit.
+    /// Queries exceeding the limit are aborted with an error.
+    /// Individual queries can override this with the `:memory_limit` option,
+    /// where `:memory_limit 0` turns the limit off for the query.
+    ///
+    /// The memory is estimated from the intermediate results of the rules of the query,
+    /// and is only approximate.
+    pub fn set_memory_limit(&'s self, limit: Option<usize>) {
+        self.memory_limit
+            .store(limit.unwrap_or(0), Ordering::Release);
+    }
+    fn effective_memory_limit(&self, query_limit: Option<usize>) -> Option<usize> {
+        match query_limit.unwrap_or_else(|| self.memory_limit.load(Ordering::Acquire)) {
+            0 => None,
+            l => Some(l),
+        }
+    }
+    /// Set the default timeout in seconds for evaluating a query, `None` for no timeout.
+    /// Queries running longer are aborted with an error naming the rule being evaluated.
+    /// Individual queries can override this with the `:timeout` option.
+    /// Has no effect on WebAssembly, where timeouts are unavailable.
+    pub fn set_timeout(&'s self, secs: Option<f64>) {
+        let secs = secs.filter(|s| *s > 0.).unwrap_or(0.);
+        self.timeout.store(secs.to_bits(), Ordering::Release);
+    }
+    /// Turn bulk loading mode on or off. In bulk loading mode, the RocksDB engine does not
+    /// write its write-ahead log, so writes are faster but data written may be lost on a crash
+    /// until bulk loading is turned off again. Turning it off compacts the whole store,
+    /// which makes everything durable but may take a while for large databases.
+    /// Other engines ignore this setting.
+    ///
+    /// With `skip_insert_checks`, `:insert` into stored relations does not read the store
+    /// to check that the keys are new while bulk loading, so it overwrites existing rows
+    /// like `:put`. Only use it when the ingested keys are known to be unique.
+    pub fn set_bulk_load(&'s self, enabled: bool, skip_insert_checks: bool) -> Result<()> {
+        self.db.set_bulk_load(enabled)?;
+        self.skip_insert_checks.store(enabled && skip_insert_checks, Ordering::Release);
+        Ok(())
+    }
+    /// Manually compact the storage, either the whole store or only the key range of
+    /// `relation`, which may also name an index as `relation:index`.
+    /// This is a no-op for storage engines without compaction.
+    ///
+    /// Expired rows of the affected relations with a TTL column (set by `::set_ttl`)
+    /// are deleted before compacting.
+    pub fn compact_range(&'s self, relation: Option<&str>) -> Result<()> {
+        {
+            let mut tx = self.transact_write()?;
+            self.purge_expired(&mut tx, relation)?;
+            tx.commit_tx()?;
+        }
+        let tx = self.transact()?;
+        self.compact_relation(&tx, relation)
+    }
     /// Backup the running database into an Sqlite file
     #[allow(unused_variables)]
     pub fn backup_db(&'s self, out_file: impl AsRef<Path>) -> Result<()> {
@@ -635,28 +742,54 @@ impl<'s, S: Storage<'s>> Db<S> {
         #[cfg(not(feature = "storage-sqlite"))]
         bail!("backup requires the 'storage-sqlite' feature to be enabled")
     }
-    /// Restore from an Sqlite backup
-    #[allow(unused_variables)]
+    /// Restore from an Sqlite backup. The current database must be empty.
     pub fn restore_backup(&'s self, in_file: impl AsRef<Path>) -> Result<()> {
+        self.restore_backup_impl(in_file.as_ref(), false)
+    }
+    /// Restore from an Sqlite backup, replacing everything in the current database.
+    /// The replacement happens in a single write transaction.
+    pub fn restore_backup_forced(&'s self, in_file: impl AsRef<Path>) -> Result<()> {
+        self.restore_backup_impl(in_file.as_ref(), true)
+    }
+    #[allow(unused_variables)]
+    fn restore_backup_impl(&'s self, in_file: &Path, force: bool) -> Result<()> {
         #[cfg(feature = "storage-sqlite")]
         {
+            if !in_file.is_file() {
+                bail!(
+                    "Cannot restore backup: {} is not a file",
+                    in_file.to_string_lossy()
+                );
+            }
+            // opening the backup checks its storage version
             let sqlite_db = crate::new_cozo_sqlite(in_file)?;
             let mut s_tx = sqlite_db.transact()?;
-            {
-                let mut tx = self.transact()?;
-                let store_id = tx.relation_store_id.load(Ordering::SeqCst);
-                if store_id != 0 {
-                    bail!(
-                        "Cannot restore backup: data exists in the current database. \
-                You can only restore into a new database (store id: {}).",
-                        store_id
-                    );
+            if force {
+                let mut tx = self.transact_write()?;
+                tx.store_tx.del_range_from_persisted(&[], &[0xFF])?;
+                for pair in s_tx.store_tx.total_scan() {
+                    let (k, v) = pair?;
+                    tx.store_tx.put(&k, &v)?;
                 }
                 tx.commit_tx()?;
+            } else {
+                {
+                    let mut tx = self.transact()?;
+                    let store_id = tx.relation_store_id.load(Ordering::SeqCst);
+                    if store_id != 0 {
+                        bail!(
+                            "Cannot restore backup: data exists in the current database. \
+                You can only restore into a new database (store id: {}).",
+                            store_id
+                        );
+                    }
+                    tx.commit_tx()?;
+                }
+                let iter = s_tx.store_tx.total_scan();
+                self.db.batch_put(iter)?;
             }
-            let iter = s_tx.store_tx.total_scan();
-            self.db.batch_put(iter)?;
             s_tx.commit_tx()?;
+            self.load_last_ids()?;
             Ok(())
         }
         #[cfg(not(feature = "storage-sqlite"))]
@@ -761,6 +894,46 @@ impl<'s, S: Storage<'s>> Db<S> {
         Ok(self.fixed_rules.write().unwrap().remove(name).is_some())
     }
 
+    /// Register a scalar function taking exactly `arity` arguments, callable in expressions
+    /// of the scripts run afterwards. Errors returned by the function are raised as evaluation
+    /// errors of the expression calling it.
+    ///
+    /// Registered functions are not persisted: they cannot be used in the default values of
+    /// stored relations, and they must be registered again whenever the database is opened.
+    pub fn register_fn<F>(&self, name: &str, arity: usize, func: F) -> Result<()>
+    where
+        F: Fn(&[DataValue]) -> Result<DataValue> + Send + Sync + 'static,
+    {
+        if get_op(name).is_some() || ListFnKind::from_name(name).is_some() {
+            bail!(
+                "Cannot register function {}: it is a builtin function",
+                name
+            );
+        }
+        match self.user_fns.write().unwrap().entry(name.to_string()) {
+            Entry::Vacant(ent) => {
+                ent.insert(UserFn {
+                    name: name.into(),
+                    arity,
+                    inner: Arc::new(func),
+                });
+                Ok(())
+            }
+            Entry::Occupied(ent) => {
+                bail!(
+                    "A function with the name {} is already registered",
+                    ent.key()
+                )
+            }
+        }
+    }
+
+    /// Unregister a function registered with [`register_fn`](Self::register_fn).
+    /// Returns whether the function existed.
+    pub fn unregister_fn(&self, name: &str) -> Result<bool> {
+        Ok(self.user_fns.write().unwrap().remove(name).is_some())
+    }
+
     /// Register callback channel to receive changes when the requested relation are successfully committed.
     /// The returned ID can be used to unregister the callback channel.
     #[cfg(not(target_arch = "wasm32"))]
@@ -833,13 +1006,124 @@ impl<'s, S: Storage<'s>> Db<S> {
         collected
     }
 
-    fn compact_relation(&'s self) -> Result<()> {
-        let l = Tuple::default().encode_as_key(RelationId(0));
-        let u = vec![DataValue::Bot].encode_as_key(RelationId(u64::MAX));
+    fn compact_relation(&'s self, tx: &SessionTx<'_>, relation: Option<&str>) -> Result<()> {
+        let (l, u) = match relation {
+            None => (
+                Tuple::default().encode_as_key(RelationId(0)),
+                vec![DataValue::Bot].encode_as_key(RelationId(u64::MAX)),
+            ),
+            Some(name) => {
+                if name.starts_with('_') {
+                    bail!("Cannot compact temporary relation {}", name);
+                }
+                let handle = tx.get_relation(name, false)?;
+                (
+                    Tuple::default().encode_as_key(handle.id),
+                    Tuple::default().encode_as_key(handle.id.next()),
+                )
+            }
+        };
         self.db.range_compact(&l, &u)?;
         Ok(())
     }
 
+    /// Add the definitions of the stored views applied in the program but not defined by it.
+    /// Views may use other views, so this repeats until nothing new is added.
+    fn expand_views(
+        &self,
+        tx: &SessionTx<'_>,
+        prog: &mut InputProgram,
+        cur_vld: ValidityTs,
+    ) -> Result<()> {
+        loop {
+            let mut expanded = false;
+            for name in prog.undefined_rules() {
+                if let Some(definition) = tx.get_view(&name)? {
+                    let view = parse_script(
+                        &definition,
+                        &Default::default(),
+                        &self.fixed_rules.read().unwrap(),
+                        &self.user_fns.read().unwrap(),
+                        cur_vld,
+                    )?
+                    .get_single_program()?;
+                    prog.prog.extend(view.prog);
+                    expanded = true;
+                }
+            }
+            if !expanded {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Delete the expired rows of the relation, or of all relations, that have a TTL column.
+    /// Removal goes through `:rm` so that indices and triggers are maintained.
+    fn purge_expired(&'s self, tx: &mut SessionTx<'_>, relation: Option<&str>) -> Result<()> {
+        let handles = match relation {
+            Some(name) => vec![tx.get_relation(name, false)?],
+            None => {
+                let lower = vec![DataValue::from("")].encode_as_key(RelationId::SYSTEM);
+                let upper = vec![DataValue::from(String::from(LARGEST_UTF_CHAR))]
+                    .encode_as_key(RelationId::SYSTEM);
+                let mut handles = vec![];
+                for kv_res in tx.store_tx.range_scan(&lower, &upper) {
+                    let (_, v_slice) = kv_res?;
+                    let handle = RelationHandle::decode(&v_slice)?;
+                    if handle.ttl_column.is_some() {
+                        handles.push(handle);
+                    }
+                }
+                handles
+            }
+        };
+        for handle in handles {
+            let ttl = handle.ttl_cutoff();
+            // expired rows of read-only relations stay until the relation is writable again
+            if ttl.is_none() || handle.access_level < AccessLevel::Protected {
+                continue;
+            }
+            // queries cannot see expired rows, so collect them here
+            let n_keys = handle.metadata.keys.len();
+            let mut expired = vec![];
+            for tuple in handle.scan_all(tx) {
+                let mut tuple = tuple?;
+                if is_expired(tx, &tuple, ttl)? {
+                    tuple.truncate(n_keys);
+                    expired.push(DataValue::List(tuple));
+                }
+            }
+            if expired.is_empty() {
+                continue;
+            }
+            let keys = handle.metadata.keys.iter().map(|c| &c.name).join(", ");
+            let name = &handle.name;
+            let script = format!("?[{keys}] <- $expired :rm {name} {{{keys}}}");
+            let params = BTreeMap::from([("expired".to_string(), DataValue::List(expired))]);
+            let cur_vld = current_validity();
+            let prog = parse_script(
+                &script,
+                &params,
+                &self.fixed_rules.read().unwrap(),
+                &self.user_fns.read().unwrap(),
+                cur_vld,
+            )?
+            .get_single_program()?;
+            let mut cleanups = vec![];
+            self.execute_single_program(
+                prog,
+                tx,
+                &mut cleanups,
+                cur_vld,
+                &Default::default(),
+                &mut Default::default(),
+            )?;
+            for (lower, upper) in cleanups {
+                tx.store_tx.del_range_from_persisted(&lower, &upper)?;
+            }
+        }
+        Ok(())
+    }
     fn load_last_ids(&'s self) -> Result<()> {
         let mut tx = self.transact_write()?;
         self.relation_store_id
@@ -900,6 +1184,7 @@ impl<'s, S: Storage<'s>> Db<S> {
             payload,
             param_pool,
             &self.fixed_rules.read().unwrap(),
+            &self.user_fns.read().unwrap(),
             cur_vld,
         )? {
             CozoScript::Single(p) => self.execute_single(cur_vld, p, read_only),
@@ -1195,22 +1480,141 @@ impl<'s, S: Storage<'s>> Db<S> {
     ) -> Result<NamedRows> {
         match op {
             SysOp::Explain(prog) => {
-                let (normalized_program, _) = prog.clone().into_normalized_program(tx)?;
+                let mut prog = prog.as_ref().clone();
+                self.expand_views(tx, &mut prog, current_validity())?;
+                let (normalized_program, _) = prog.into_normalized_program(tx)?;
                 let (stratified_program, _) = normalized_program.into_stratified_program()?;
                 let program = stratified_program.magic_sets_rewrite(tx)?;
                 let compiled = tx.stratified_magic_compile(program)?;
                 self.explain_compiled(&compiled)
             }
-            SysOp::Compact => {
+            SysOp::ExplainAnalyze(_) => {
+                bail!("'::explain analyze' cannot be used inside another script")
+            }
+            SysOp::Compact(rel) => {
                 if read_only {
                     bail!("Cannot compact in read-only mode");
                 }
-                self.compact_relation()?;
+                let rel = rel.as_ref().map(|r| &r.name as &str);
+                self.purge_expired(tx, rel)?;
+                self.compact_relation(tx, rel)?;
+                Ok(NamedRows::new(
+                    vec![STATUS_STR.to_string()],
+                    vec![vec![DataValue::from(OK_STR)]],
+                ))
+            }
+            SysOp::SetTtl(rel, col) => {
+                if read_only {
+                    bail!("Cannot set TTL in read-only mode");
+                }
+                tx.set_ttl_column(rel, col.as_ref())?;
+                Ok(NamedRows::new(
+                    vec![STATUS_STR.to_string()],
+                    vec![vec![DataValue::from(OK_STR)]],
+                ))
+            }
+            SysOp::AlterRelation(rel_name, alteration) => {
+                if read_only {
+                    bail!("Cannot alter relations in read-only mode");
+                }
+                if skip_locking {
+                    tx.alter_relation(rel_name, alteration, current_validity())?;
+                } else {
+                    let lock = self
+                        .obtain_relation_locks(iter::once(&rel_name.name))
+                        .pop()
+                        .unwrap();
+                    let _guard = lock.write().unwrap();
+                    tx.alter_relation(rel_name, alteration, current_validity())?;
+                }
+                Ok(NamedRows::new(
+                    vec![STATUS_STR.to_string()],
+                    vec![vec![DataValue::from(OK_STR)]],
+                ))
+            }
+            SysOp::StorageStats(rel) => {
+                self.storage_stats(tx, rel.as_ref().map(|r| &r.name as &str))
+            }
+            SysOp::CreateView(name, definition) => {
+                if read_only {
+                    bail!("Cannot create views in read-only mode");
+                }
+                tx.create_view(name, definition)?;
+                Ok(NamedRows::new(
+                    vec![STATUS_STR.to_string()],
+                    vec![vec![DataValue::from(OK_STR)]],
+                ))
+            }
+            SysOp::RemoveView(name) => {
+                if read_only {
+                    bail!("Cannot remove views in read-only mode");
+                }
+                tx.remove_view(name)?;
+                Ok(NamedRows::new(
+                    vec![STATUS_STR.to_string()],
+                    vec![vec![DataValue::from(OK_STR)]],
+                ))
+            }
+            SysOp::CreateProcedure(name, params, body) => {
+                if read_only {
+                    bail!("Cannot create procedures in read-only mode");
+                }
+                tx.create_procedure(
+                    name,
+                    &StoredProcedure {
+                        params: params.clone(),
+                        body: body.clone(),
+                    },
+                )?;
+                Ok(NamedRows::new(
+                    vec![STATUS_STR.to_string()],
+                    vec![vec![DataValue::from(OK_STR)]],
+                ))
+            }
+            SysOp::RemoveProcedure(name) => {
+                if read_only {
+                    bail!("Cannot remove procedures in read-only mode");
+                }
+                tx.remove_procedure(name)?;
                 Ok(NamedRows::new(
                     vec![STATUS_STR.to_string()],
                     vec![vec![DataValue::from(OK_STR)]],
                 ))
             }
+            SysOp::ListProcedures => Ok(NamedRows::new(
+                vec!["name".to_string(), "params".to_string(), "body".to_string()],
+                tx.list_procedures()?
+                    .into_iter()
+                    .map(|(name, proc)| {
+                        vec![
+                            DataValue::from(name),
+                            DataValue::List(
+                                proc.params
+                                    .iter()
+                                    .map(|p| DataValue::from(format!("${p}")))
+                                    .collect_vec(),
+                            ),
+                            DataValue::from(proc.body),
+                        ]
+                    })
+                    .collect_vec(),
+            )),
+            SysOp::CallProcedure(name, _) => {
+                #[derive(Debug, Error, Diagnostic)]
+                #[error("Procedure {0} cannot be called from inside another script")]
+                #[diagnostic(code(eval::nested_procedure_call))]
+                #[diagnostic(help("Procedures run in a transaction of their own, so only a top-level '::call' can run them"))]
+                struct NestedProcedureCall(String, #[label] SourceSpan);
+
+                bail!(NestedProcedureCall(name.to_string(), name.span))
+            }
+            SysOp::ListViews => Ok(NamedRows::new(
+                vec!["name".to_string(), "definition".to_string()],
+                tx.list_views()?
+                    .into_iter()
+                    .map(|(name, def)| vec![DataValue::from(name), DataValue::from(def)])
+                    .collect_vec(),
+            )),
             SysOp::ListRelations => self.list_relations(tx),
             SysOp::ListFixedRules => {
                 let rules = self.fixed_rules.read().unwrap();
@@ -1255,19 +1659,19 @@ impl<'s, S: Storage<'s>> Db<S> {
                     vec![vec![DataValue::from(OK_STR)]],
                 ))
             }
-            SysOp::CreateIndex(rel_name, idx_name, cols) => {
+            SysOp::CreateIndex(rel_name, idx_name, cols, unique) => {
                 if read_only {
                     bail!("Cannot create index in read-only mode");
                 }
                 if skip_locking {
-                    tx.create_index(rel_name, idx_name, cols)?;
+                    tx.create_index(rel_name, idx_name, cols, *unique)?;
                 } else {
                     let lock = self
                         .obtain_relation_locks(iter::once(&rel_name.name))
                         .pop()
                         .unwrap();
                     let _guard = lock.write().unwrap();
-                    tx.create_index(rel_name, idx_name, cols)?;
+                    tx.create_index(rel_name, idx_name, cols, *unique)?;
                 }
                 Ok(NamedRows::new(
                     vec![STATUS_STR.to_string()],
@@ -1439,6 +1843,11 @@ impl<'s, S: Storage<'s>> Db<S> {
         }
     }
     fn run_sys_op(&'s self, op: SysOp, read_only: bool) -> Result<NamedRows> {
+        match &op {
+            SysOp::CallProcedure(name, args) => return self.call_procedure(name, args, read_only),
+            SysOp::ExplainAnalyze(prog) => return self.explain_analyze(prog),
+            _ => {}
+        }
         let mut tx = if read_only {
             self.transact()?
         } else {
@@ -1448,6 +1857,143 @@ impl<'s, S: Storage<'s>> Db<S> {
         tx.commit_tx()?;
         Ok(res)
     }
+    /// Run the query, discarding its results, and return the plan followed by execution statistics.
+    /// Reads are counted on a transaction of its own, which is why this cannot run inside other scripts.
+    fn explain_analyze(&'s self, prog: &InputProgram) -> Result<NamedRows> {
+        let counters = Arc::new(StorageCounters::default());
+        let mut tx = self.transact()?;
+        tx.store_tx = Box::new(CountingTx::new(tx.store_tx, counters.clone()));
+
+        let mut prog = prog.clone();
+        self.expand_views(&tx, &mut prog, current_validity())?;
+        let (normalized_program, out_opts) = prog.into_normalized_program(&tx)?;
+        let (stratified_program, store_lifetimes) = normalized_program.into_stratified_program()?;
+        let program = stratified_program.magic_sets_rewrite(&tx)?;
+        let compiled = tx.stratified_magic_compile(program)?;
+        let mut plan = self.explain_compiled(&compiled)?;
+
+        let memory_limit = self.effective_memory_limit(out_opts.memory_limit);
+        let poison = Poison::default();
+        if let Some(secs) = out_opts.timeout {
+            poison.set_timeout(secs)?;
+        }
+        counters.reset();
+        let mut profile = EvalProfile::default();
+        tx.stratified_magic_evaluate(
+            &compiled,
+            store_lifetimes,
+            None,
+            None,
+            memory_limit,
+            poison,
+            Some(&mut profile),
+        )?;
+
+        let mut rows = vec![];
+        for (stratum, stratum_profile) in profile.strata.iter().enumerate() {
+            for (epoch, deltas) in stratum_profile.deltas.iter().enumerate() {
+                for (rule, n) in deltas {
+                    rows.push(vec![
+                        DataValue::from(stratum as i64),
+                        DataValue::from(rule.to_string()),
+                        DataValue::from(epoch as i64),
+                        DataValue::from("delta_rows"),
+                        DataValue::from(*n as i64),
+                    ]);
+                }
+            }
+            for (rule, n) in &stratum_profile.rows {
+                rows.push(vec![
+                    DataValue::from(stratum as i64),
+                    DataValue::from(rule.to_string()),
+                    DataValue::Null,
+                    DataValue::from("rows"),
+                    DataValue::from(*n as i64),
+                ]);
+            }
+            rows.push(vec![
+                DataValue::from(stratum as i64),
+                DataValue::Null,
+                DataValue::Null,
+                DataValue::from("epochs"),
+                DataValue::from(stratum_profile.deltas.len() as i64),
+            ]);
+            rows.push(vec![
+                DataValue::from(stratum as i64),
+                DataValue::Null,
+                DataValue::Null,
+                DataValue::from("elapsed_secs"),
+                DataValue::from(stratum_profile.elapsed),
+            ]);
+        }
+        for (metric, n) in [
+            ("storage_gets", counters.gets()),
+            ("storage_scans", counters.scans()),
+        ] {
+            rows.push(vec![
+                DataValue::Null,
+                DataValue::Null,
+                DataValue::Null,
+                DataValue::from(metric),
+                DataValue::from(n as i64),
+            ]);
+        }
+        plan.next = Some(Box::new(NamedRows::new(
+            vec![
+                "stratum".to_string(),
+                "rule".to_string(),
+                "epoch".to_string(),
+                "metric".to_string(),
+                "value".to_string(),
+            ],
+            rows,
+        )));
+        Ok(plan)
+    }
+    /// Run a stored procedure in a transaction of its own, with its parameters bound to `args`.
+    fn call_procedure(
+        &'s self,
+        name: &Symbol,
+        args: &[DataValue],
+        read_only: bool,
+    ) -> Result<NamedRows> {
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("Procedure {name} takes {expected} arguments but {got} were given")]
+        #[diagnostic(code(eval::procedure_arity_mismatch))]
+        struct ProcedureArityMismatch {
+            name: String,
+            expected: usize,
+            got: usize,
+            #[label]
+            span: SourceSpan,
+        }
+
+        let procedure = self.transact()?.get_procedure(name)?;
+        ensure!(
+            procedure.params.len() == args.len(),
+            ProcedureArityMismatch {
+                name: name.to_string(),
+                expected: procedure.params.len(),
+                got: args.len(),
+                span: name.span,
+            }
+        );
+        let param_pool = procedure
+            .params
+            .iter()
+            .map(|p| p.to_string())
+            .zip(args.iter().cloned())
+            .collect();
+        self.do_run_script(&procedure.body, &param_pool, current_validity(), read_only)
+            .map_err(|err| {
+                if err.source_code().is_some() {
+                    err
+                } else {
+                    err.with_source_code(procedure.body.clone())
+                }
+            })
+            .wrap_err_with(|| format!("when calling procedure '{name}'"))
+    }
     /// This is the entry to query evaluation
     pub(crate) fn run_query(
         &self,
@@ -1458,6 +2004,9 @@ impl<'s, S: Storage<'s>> Db<S> {
         callback_collector: &mut CallbackCollector,
         top_level: bool,
     ) -> Result<(NamedRows, Vec<(Vec<u8>, Vec<u8>)>)> {
+        let mut input_program = input_program;
+        self.expand_views(tx, &mut input_program, cur_vld)?;
+
         // cleanups contain stored relations that should be deleted at the end of query
         let mut clean_ups = vec![];
 
@@ -1502,7 +2051,16 @@ impl<'s, S: Storage<'s>> Db<S> {
 
         // poison is used to terminate queries early
         let poison = Poison::default();
-        if let Some(secs) = out_opts.timeout {
+        let timeout = out_opts.timeout;
+        // timers need threads, so the default is not applied on wasm
+        #[cfg(not(target_arch = "wasm32"))]
+        let timeout = timeout.or_else(|| {
+            match f64::from_bits(self.timeout.load(Ordering::Acquire)) {
+                s if s > 0. => Some(s),
+                _ => None,
+            }
+        });
+        if let Some(secs) = timeout {
             poison.set_timeout(secs)?;
         }
         // give the query an ID and store it so that it can be queried and cancelled
@@ -1535,13 +2093,17 @@ impl<'s, S: Storage<'s>> Db<S> {
             None
         };
 
+        let memory_limit = self.effective_memory_limit(out_opts.memory_limit);
+
         // the real evaluation
         let (result_store, early_return) = tx.stratified_magic_evaluate(
             &compiled,
             store_lifetimes,
             total_num_to_take,
             num_to_skip,
+            memory_limit,
             poison,
+            None,
         )?;
 
         // deal with assertions
@@ -1572,8 +2134,15 @@ impl<'s, S: Storage<'s>> Db<S> {
 
         if !out_opts.sorters.is_empty() {
             // sort outputs if required
-            let sorted_result =
-                tx.sort_and_collect(result_store, &out_opts.sorters, &entry_head_or_default)?;
+            let keep = out_opts
+                .limit
+                .map(|limit| limit + out_opts.offset.unwrap_or(0));
+            let sorted_result = tx.sort_and_collect(
+                result_store,
+                &out_opts.sorters,
+                &entry_head_or_default,
+                keep,
+            )?;
             let sorted_iter = if let Some(offset) = out_opts.offset {
                 Left(sorted_result.into_iter().skip(offset))
             } else {
@@ -1585,7 +2154,7 @@ impl<'s, S: Storage<'s>> Db<S> {
                 Right(sorted_iter)
             };
             if let Some((meta, relation_op, returning)) = &out_opts.store_relation {
-                let to_clear = tx
+                let (to_clear, skipped) = tx
                     .execute_relation(
                         self,
                         sorted_iter,
@@ -1604,8 +2173,9 @@ impl<'s, S: Storage<'s>> Db<S> {
                     )
                     .wrap_err_with(|| format!("when executing against relation '{}'", meta.name))?;
                 clean_ups.extend(to_clear);
+                let skipped = (*relation_op == RelationOp::InsertOrIgnore).then_some(skipped);
                 let returned_rows =
-                    tx.get_returning_rows(callback_collector, &meta.name, returning)?;
+                    tx.get_returning_rows(callback_collector, &meta.name, returning, skipped)?;
                 Ok((returned_rows, clean_ups))
             } else {
                 // not sorting outputs
@@ -1641,7 +2211,7 @@ impl<'s, S: Storage<'s>> Db<S> {
             };
 
             if let Some((meta, relation_op, returning)) = &out_opts.store_relation {
-                let to_clear = tx
+                let (to_clear, skipped) = tx
                     .execute_relation(
                         self,
                         scan,
@@ -1660,8 +2230,9 @@ impl<'s, S: Storage<'s>> Db<S> {
                     )
                     .wrap_err_with(|| format!("when executing against relation '{}'", meta.name))?;
                 clean_ups.extend(to_clear);
+                let skipped = (*relation_op == RelationOp::InsertOrIgnore).then_some(skipped);
                 let returned_rows =
-                    tx.get_returning_rows(callback_collector, &meta.name, returning)?;
+                    tx.get_returning_rows(callback_collector, &meta.name, returning, skipped)?;
 
                 Ok((returned_rows, clean_ups))
             } else {
@@ -1702,9 +2273,14 @@ impl<'s, S: Storage<'s>> Db<S> {
         let handle = tx.get_relation(name, false)?;
         let mut rows = vec![];
         for (name, (rel, cols)) in &handle.indices {
+            let kind = if handle.unique_indices.contains_key(name) {
+                "unique"
+            } else {
+                "normal"
+            };
             rows.push(vec![
                 json!(name),
-                json!("normal"),
+                json!(kind),
                 json!([rel.name]),
                 json!({ "indices": cols }),
             ]);
@@ -1818,6 +2394,70 @@ impl<'s, S: Storage<'s>> Db<S> {
             rows,
         ))
     }
+    fn storage_stats(&'s self, tx: &SessionTx<'_>, relation: Option<&str>) -> Result<NamedRows> {
+        let handles = match relation {
+            Some(name) => {
+                if name.starts_with('_') {
+                    bail!("Temporary relation {} is not persisted", name);
+                }
+                vec![tx.get_relation(name, false)?]
+            }
+            None => {
+                let lower = vec![DataValue::from("")].encode_as_key(RelationId::SYSTEM);
+                let upper = vec![DataValue::from(String::from(LARGEST_UTF_CHAR))]
+                    .encode_as_key(RelationId::SYSTEM);
+                let mut handles = vec![];
+                for kv_res in tx.store_tx.range_scan(&lower, &upper) {
+                    let (_, v_slice) = kv_res?;
+                    handles.push(RelationHandle::decode(&v_slice)?);
+                }
+                handles
+            }
+        };
+        let mut rows = vec![];
+        for handle in handles {
+            let lower = Tuple::default().encode_as_key(handle.id);
+            let upper = Tuple::default().encode_as_key(handle.id.next());
+            let n_keys = tx.store_tx.range_count(&lower, &upper)?;
+            // engines that cannot estimate their usage report the raw size of the data,
+            // extrapolated from the first rows
+            let (size, tombstones) = match self.db.range_size_estimate(&lower, &upper)? {
+                Some((size, tombstones)) => (size, DataValue::from(tombstones as i64)),
+                None => {
+                    let mut n_sampled = 0u64;
+                    let mut n_bytes = 0u64;
+                    for kv_res in tx
+                        .store_tx
+                        .range_scan(&lower, &upper)
+                        .take(STORAGE_STATS_SAMPLE_SIZE)
+                    {
+                        let (k, v) = kv_res?;
+                        n_sampled += 1;
+                        n_bytes += (k.len() + v.len()) as u64;
+                    }
+                    let size = (n_bytes * n_keys as u64)
+                        .checked_div(n_sampled)
+                        .unwrap_or(0);
+                    (size, DataValue::Null)
+                }
+            };
+            rows.push(vec![
+                DataValue::Str(handle.name),
+                DataValue::from(n_keys as i64),
+                DataValue::from(size as i64),
+                tombstones,
+            ]);
+        }
+        Ok(NamedRows::new(
+            vec![
+                "name".to_string(),
+                "key_count".to_string(),
+                "approx_size".to_string(),
+                "tombstones".to_string(),
+            ],
+            rows,
+        ))
+    }
     fn list_relations(&'s self, tx: &SessionTx<'_>) -> Result<NamedRows> {
         let lower = vec![DataValue::from("")].encode_as_key(RelationId::SYSTEM);
         let upper =
@@ -1918,37 +2558,141 @@ fn _get_variables(src: &str, params: &BTreeMap<String, DataValue>) -> Result<BTr
     expr.get_variables()
 }
 
+#[derive(Debug, Error, Diagnostic)]
+#[error("Running query is killed before completion")]
+#[diagnostic(code(eval::killed))]
+#[diagnostic(help("A query may be killed by timeout, or explicit command"))]
+struct ProcessKilled;
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("Query timed out after {elapsed:.3} seconds{}", .rule.as_ref().map(|r| format!(" while evaluating rule {r}")).unwrap_or_default())]
+#[diagnostic(code(eval::timeout))]
+#[diagnostic(help("The time limit is set by the `:timeout` option or `Db::set_timeout`"))]
+pub(crate) struct QueryTimeout {
+    pub(crate) elapsed: f64,
+    pub(crate) rule: Option<String>,
+}
+
 /// Used for user-initiated termination of running queries
 #[derive(Clone, Default)]
-pub struct Poison(pub(crate) Arc<AtomicBool>);
+pub struct Poison(pub(crate) Arc<AtomicBool>, Arc<AtomicU64>);
 
 impl Poison {
     /// Will return `Err` if user has initiated termination.
     #[inline(always)]
     pub fn check(&self) -> Result<()> {
-        #[derive(Debug, Error, Diagnostic)]
-        #[error("Running query is killed before completion")]
-        #[diagnostic(code(eval::killed))]
-        #[diagnostic(help("A query may be killed by timeout, or explicit command"))]
-        struct ProcessKilled;
-
         if self.0.load(Ordering::Relaxed) {
-            bail!(ProcessKilled)
+            return Err(self.termination_error(None));
+        }
+        Ok(())
+    }
+    /// Same as [Poison::check], but a timeout error names the rule being evaluated.
+    #[inline(always)]
+    pub(crate) fn check_in_rule(&self, rule: &MagicSymbol) -> Result<()> {
+        if self.0.load(Ordering::Relaxed) {
+            return Err(self.termination_error(Some(rule)));
         }
         Ok(())
     }
+    #[cold]
+    fn termination_error(&self, rule: Option<&MagicSymbol>) -> Report {
+        // set by the timer, holding the bits of the time it was armed
+        let armed_at = self.1.load(Ordering::Acquire);
+        if armed_at == 0 {
+            return ProcessKilled.into();
+        }
+        let elapsed = seconds_since_the_epoch().unwrap_or_default() - f64::from_bits(armed_at);
+        QueryTimeout {
+            elapsed,
+            rule: rule.map(|r| r.to_string()),
+        }
+        .into()
+    }
     #[cfg(target_arch = "wasm32")]
     pub(crate) fn set_timeout(&self, _secs: f64) -> Result<()> {
         bail!("Cannot set timeout when threading is disallowed");
     }
     #[cfg(not(target_arch = "wasm32"))]
     pub(crate) fn set_timeout(&self, secs: f64) -> Result<()> {
-        let pill = self.clone();
+        let armed_at = seconds_since_the_epoch()?;
+        let deadline =
+            Instant::now().checked_add(Duration::from_micros((secs * 1000000.) as u64));
+        // a deadline too far away to represent never arrives
+        if let Some(deadline) = deadline {
+            TIMEOUT_TIMER.schedule(deadline, armed_at, self);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+lazy_static! {
+    static ref TIMEOUT_TIMER: TimeoutTimer = TimeoutTimer::start();
+}
+
+/// Poisons timed-out queries from a single thread shared by all databases.
+#[cfg(not(target_arch = "wasm32"))]
+struct TimeoutTimer(Arc<(Mutex<PendingTimeouts>, Condvar)>);
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+struct PendingTimeouts {
+    by_deadline: BTreeMap<(Instant, u64), (Weak<AtomicBool>, Weak<AtomicU64>, f64)>,
+    next_id: u64,
+    live_after_prune: usize,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl TimeoutTimer {
+    fn start() -> Self {
+        let shared: Arc<(Mutex<PendingTimeouts>, Condvar)> = Default::default();
+        let timer_shared = shared.clone();
         thread::spawn(move || {
-            thread::sleep(Duration::from_micros((secs * 1000000.) as u64));
-            pill.0.store(true, Ordering::Relaxed);
+            let (pending, wakeup) = &*timer_shared;
+            let mut pending = pending.lock().unwrap();
+            loop {
+                let now = Instant::now();
+                while let Some(entry) = pending.by_deadline.first_entry() {
+                    if entry.key().0 > now {
+                        break;
+                    }
+                    let (killed, armed_at_bits, armed_at) = entry.remove();
+                    if let (Some(killed), Some(armed_at_bits)) =
+                        (killed.upgrade(), armed_at_bits.upgrade())
+                    {
+                        armed_at_bits.store(armed_at.to_bits(), Ordering::Release);
+                        killed.store(true, Ordering::Release);
+                    }
+                }
+                pending = match pending.by_deadline.keys().next() {
+                    None => wakeup.wait(pending).unwrap(),
+                    Some((deadline, _)) => {
+                        let wait = deadline.saturating_duration_since(now);
+                        wakeup.wait_timeout(pending, wait).unwrap().0
+                    }
+                };
+            }
         });
-        Ok(())
+        Self(shared)
+    }
+    fn schedule(&self, deadline: Instant, armed_at: f64, poison: &Poison) {
+        let (pending, wakeup) = &*self.0;
+        let mut pending = pending.lock().unwrap();
+        // queries that finished early leave their timeouts behind, drop them
+        // whenever the queue has doubled since the last time
+        if pending.by_deadline.len() >= 2 * pending.live_after_prune.max(1024) {
+            pending
+                .by_deadline
+                .retain(|_, (killed, _, _)| killed.strong_count() > 0);
+            pending.live_after_prune = pending.by_deadline.len();
+        }
+        let id = pending.next_id;
+        pending.next_id += 1;
+        pending.by_deadline.insert(
+            (deadline, id),
+            (Arc::downgrade(&poison.0), Arc::downgrade(&poison.1), armed_at),
+        );
+        wakeup.notify_one();
     }
 }
 
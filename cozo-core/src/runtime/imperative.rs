@@ -246,7 +246,6 @@ impl<'s, S: Storage<'s>> Db<S> {
                         Symbol::new(right.clone(), Default::default()),
                     )?;
                     ret = NamedRows::default();
-                    break;
                 }
             }
         }
@@ -380,6 +379,7 @@ impl SessionTx<'_> {
             key_bindings,
             dep_bindings: vec![],
             span: Default::default(),
+            merges: Default::default(),
         };
         let headers = meta.key_bindings.clone();
         self.execute_relation(
@@ -290,7 +290,9 @@ impl LshParams {
 
 impl HashPermutations {
     pub(crate) fn new(n_perms: usize) -> Self {
-        let mut rng = thread_rng();
+        Self::with_rng(n_perms, &mut thread_rng())
+    }
+    pub(crate) fn with_rng(n_perms: usize, rng: &mut impl RngCore) -> Self {
         let mut perms = Vec::with_capacity(n_perms);
         for _ in 0..n_perms {
             perms.push(rng.next_u32());
@@ -339,7 +341,6 @@ impl HashValues {
             }
         }
     }
-    #[cfg(test)]
     pub(crate) fn jaccard(&self, other_minhash: &Self) -> f32 {
         let matches = self
             .0
@@ -347,8 +348,7 @@ impl HashValues {
             .zip_eq(&other_minhash.0)
             .filter(|(left, right)| left == right)
             .count();
-        let result = matches as f32 / self.0.len() as f32;
-        result
+        matches as f32 / self.0.len() as f32
     }
     pub(crate) fn get_bytes(&self) -> &[u8] {
         unsafe {
@@ -7,6 +7,7 @@
  */
 
 pub(crate) mod callback;
+pub(crate) mod cursor;
 pub(crate) mod db;
 pub(crate) mod imperative;
 pub(crate) mod relation;
@@ -12,13 +12,15 @@ use std::sync::atomic::Ordering;
 
 use itertools::Itertools;
 use log::error;
-use miette::{bail, ensure, Diagnostic, IntoDiagnostic, Result};
+use miette::{bail, ensure, miette, Diagnostic, IntoDiagnostic, Result, WrapErr};
 use pest::Parser;
 use rmp_serde::Serializer;
 use serde::Serialize;
 use smartstring::{LazyCompact, SmartString};
 use thiserror::Error;
 
+use crate::data::expr::Expr;
+use crate::data::functions::current_validity;
 use crate::data::memcmp::MemCmpEncoder;
 use crate::data::relation::{ColType, ColumnDef, NullableColType, StoredRelationMetadata};
 use crate::data::symb::Symbol;
@@ -26,9 +28,10 @@ use crate::data::tuple::{decode_tuple_from_key, Tuple, TupleT, ENCODED_KEY_MIN_L
 use crate::data::value::{DataValue, ValidityTs};
 use crate::fts::FtsIndexManifest;
 use crate::parse::expr::build_expr;
-use crate::parse::sys::{FtsIndexConfig, HnswIndexConfig, MinHashLshConfig};
+use crate::parse::sys::{FtsIndexConfig, HnswIndexConfig, MinHashLshConfig, RelationAlteration};
 use crate::parse::{CozoScriptParser, Rule, SourceSpan};
 use crate::query::compile::IndexPositionUse;
+use crate::query::stored::ColumnTypeMismatch;
 use crate::runtime::hnsw::HnswIndexManifest;
 use crate::runtime::minhash_lsh::{HashPermutations, LshParams, MinHashLshIndexManifest, Weights};
 use crate::runtime::transact::SessionTx;
@@ -90,6 +93,17 @@ pub(crate) struct RelationHandle {
         (RelationHandle, RelationHandle, MinHashLshIndexManifest),
     >,
     pub(crate) description: SmartString<LazyCompact>,
+    /// Column holding the expiry time of rows, as seconds since the UNIX epoch or a timestamp
+    #[serde(default)]
+    pub(crate) ttl_column: Option<SmartString<LazyCompact>>,
+    /// Indices in `indices` that enforce uniqueness, with the number of constrained columns,
+    /// which come first in the index keys
+    #[serde(default)]
+    pub(crate) unique_indices: BTreeMap<SmartString<LazyCompact>, usize>,
+    /// For an index read in a query, the indexed relation and the positions of the index
+    /// columns in it, so that rows expired in the relation are skipped. Never stored.
+    #[serde(skip)]
+    pub(crate) indexed: Option<Box<(RelationHandle, Vec<usize>)>>,
 }
 
 impl RelationHandle {
@@ -138,6 +152,15 @@ impl Display for AccessLevel {
     }
 }
 
+#[derive(Debug, Error, Diagnostic)]
+#[error("Values {values:?} of unique index {index} of {relation} are already taken")]
+#[diagnostic(code(eval::unique_constraint_violation))]
+pub(crate) struct UniqueConstraintViolation {
+    pub(crate) relation: String,
+    pub(crate) index: String,
+    pub(crate) values: Vec<DataValue>,
+}
+
 #[derive(Debug, Error, Diagnostic)]
 #[error("Arity mismatch for stored relation {name}: expect {expect_arity}, got {actual_arity}")]
 #[diagnostic(code(eval::stored_rel_arity_mismatch))]
@@ -201,10 +224,11 @@ impl RelationHandle {
         if self.indices.is_empty() {
             return None;
         }
-        if *arg_uses.first().unwrap() == IndexPositionUse::Join {
-            return None;
-        }
-        let mut max_prefix_len = 0;
+        // the primary key is an index too, a secondary index must bind a longer prefix
+        let primary_prefix_len = arg_uses[..self.metadata.keys.len()]
+            .iter()
+            .take_while(|pos_use| **pos_use == IndexPositionUse::Join)
+            .count();
         let required_positions = arg_uses
             .iter()
             .enumerate()
@@ -216,33 +240,40 @@ impl RelationHandle {
                 }
             })
             .collect_vec();
-        let mut chosen = None;
+        let mut max_prefix_len = primary_prefix_len;
+        let mut chosen: Option<(RelationHandle, Vec<usize>, bool)> = None;
         for (manifest, mapper) in self.indices.values() {
             if validity_query && *mapper.last().unwrap() != self.metadata.keys.len() - 1 {
                 continue;
             }
 
-            let mut cur_prefix_len = 0;
-            for i in mapper {
-                if arg_uses[*i] == IndexPositionUse::Join {
-                    cur_prefix_len += 1;
-                } else {
-                    break;
-                }
-            }
-            if cur_prefix_len > max_prefix_len {
+            let cur_prefix_len = mapper
+                .iter()
+                .take_while(|i| arg_uses[**i] == IndexPositionUse::Join)
+                .count();
+            let need_join = required_positions
+                .iter()
+                .any(|need_pos| !mapper.contains(need_pos));
+            // among indices binding the same prefix, prefer one that covers all the columns,
+            // it saves the lookups in the relation itself
+            let better = cur_prefix_len > max_prefix_len
+                || (cur_prefix_len == max_prefix_len
+                    && !need_join
+                    && matches!(chosen, Some((_, _, true))));
+            if better {
                 max_prefix_len = cur_prefix_len;
-                let mut need_join = false;
-                for need_pos in required_positions.iter() {
-                    if !mapper.contains(need_pos) {
-                        need_join = true;
-                        break;
-                    }
-                }
                 chosen = Some((manifest.clone(), mapper.clone(), need_join))
             }
         }
-        chosen
+        // with a join, the relation itself skips the expired rows
+        chosen.map(|(index, mapper, need_join)| {
+            let index = if need_join {
+                index
+            } else {
+                index.indexing(self, &mapper)
+            };
+            (index, mapper, need_join)
+        })
     }
     pub(crate) fn encode_key_for_store(
         &self,
@@ -324,6 +355,10 @@ pub(crate) struct InputRelationHandle {
     pub(crate) key_bindings: Vec<Symbol>,
     pub(crate) dep_bindings: Vec<Symbol>,
     pub(crate) span: SourceSpan,
+    /// Expressions combining the stored value `_old` and the incoming value `_new`
+    /// of columns when `:put` replaces an existing row
+    #[serde(default)]
+    pub(crate) merges: BTreeMap<SmartString<LazyCompact>, Expr>,
 }
 
 impl Debug for RelationHandle {
@@ -345,6 +380,35 @@ impl RelationHandle {
     pub(crate) fn arity(&self) -> usize {
         self.metadata.non_keys.len() + self.metadata.keys.len()
     }
+    /// How to tell expired rows apart, if rows of the relation can expire.
+    pub(crate) fn ttl_cutoff(&self) -> Option<TtlCutoff<'_>> {
+        let now = current_validity().0 .0 as f64 / 1_000_000.;
+        if let Some(col) = &self.ttl_column {
+            let idx = self
+                .metadata
+                .keys
+                .iter()
+                .chain(self.metadata.non_keys.iter())
+                .position(|c| c.name == *col)?;
+            return Some(TtlCutoff::Column(idx, now));
+        }
+        let (base, mapper) = self.indexed.as_deref()?;
+        match base.ttl_cutoff()? {
+            TtlCutoff::Column(col, now) => Some(match mapper.iter().position(|i| *i == col) {
+                Some(idx) => TtlCutoff::Column(idx, now),
+                None => TtlCutoff::Lookup(base, mapper, col, now),
+            }),
+            TtlCutoff::Lookup(..) => None,
+        }
+    }
+    /// Attach the indexed relation to the handle of one of its indices,
+    /// if rows of the relation can expire.
+    pub(crate) fn indexing(mut self, base: &RelationHandle, mapper: &[usize]) -> Self {
+        if base.ttl_column.is_some() {
+            self.indexed = Some(Box::new((base.clone(), mapper.to_vec())));
+        }
+        self
+    }
     pub(crate) fn decode(data: &[u8]) -> Result<Self> {
         Ok(rmp_serde::from_slice(data).map_err(|e| {
             error!(
@@ -512,6 +576,50 @@ impl RelationHandle {
     }
 }
 
+/// How expired rows of a relation are found
+#[derive(Clone, Copy)]
+pub(crate) enum TtlCutoff<'a> {
+    /// The position of the TTL column in the rows, and the current time
+    Column(usize, f64),
+    /// For an index without the TTL column: the indexed relation, the positions of the index
+    /// columns in it, the position of the TTL column in it, and the current time
+    Lookup(&'a RelationHandle, &'a [usize], usize, f64),
+}
+
+/// Rows whose TTL column holds a number or timestamp not later than the cutoff have expired.
+/// Rows with other expiry times, e.g. null, never expire.
+pub(crate) fn is_expired(
+    tx: &SessionTx<'_>,
+    tuple: &[DataValue],
+    ttl_cutoff: Option<TtlCutoff<'_>>,
+) -> Result<bool> {
+    Ok(match ttl_cutoff {
+        None => false,
+        Some(TtlCutoff::Column(idx, now)) => expiry_passed(tuple.get(idx), now),
+        Some(TtlCutoff::Lookup(base, mapper, idx, now)) => {
+            // indices hold all the keys of the relation
+            let mut key = vec![DataValue::Null; base.metadata.keys.len()];
+            for (val, i) in tuple.iter().zip(mapper.iter()) {
+                if *i < key.len() {
+                    key[*i] = val.clone();
+                }
+            }
+            match base.get(tx, &key)? {
+                None => false,
+                Some(found) => expiry_passed(found.get(idx), now),
+            }
+        }
+    })
+}
+
+fn expiry_passed(expiry: Option<&DataValue>, now: f64) -> bool {
+    match expiry {
+        Some(DataValue::Num(n)) => n.get_float() <= now,
+        Some(DataValue::Timestamp(ts)) => ts.0 as f64 / 1_000_000_000. <= now,
+        _ => false,
+    }
+}
+
 const DEFAULT_SIZE_HINT: usize = 16;
 
 /// Decode tuple from key-value pairs. Used for customizing storage
@@ -540,6 +648,30 @@ pub(crate) struct IndexAlreadyExists(String, String);
 #[diagnostic(code(eval::rel_name_conflict))]
 struct RelNameConflictError(String);
 
+const VIEW_KEY_TAG: &str = "VIEW";
+const PROCEDURE_KEY_TAG: &str = "PROCEDURE";
+
+/// Views and procedures are kept in the system relation, next to the storage version
+fn system_entry_key(tag: &str, name: &str) -> Vec<u8> {
+    vec![DataValue::Null, DataValue::from(tag), DataValue::from(name)]
+        .encode_as_key(RelationId::SYSTEM)
+}
+
+fn view_key(name: &str) -> Vec<u8> {
+    system_entry_key(VIEW_KEY_TAG, name)
+}
+
+fn procedure_key(name: &str) -> Vec<u8> {
+    system_entry_key(PROCEDURE_KEY_TAG, name)
+}
+
+/// A named script stored in the database, run by `::call` with its parameters bound
+#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]
+pub(crate) struct StoredProcedure {
+    pub(crate) params: Vec<SmartString<LazyCompact>>,
+    pub(crate) body: String,
+}
+
 impl<'a> SessionTx<'a> {
     pub(crate) fn relation_exists(&self, name: &str) -> Result<bool> {
         let key = DataValue::from(name);
@@ -620,6 +752,9 @@ impl<'a> SessionTx<'a> {
             fts_indices: Default::default(),
             lsh_indices: Default::default(),
             description: Default::default(),
+            ttl_column: None,
+            unique_indices: Default::default(),
+            indexed: None,
         };
 
         let name_key = vec![DataValue::Str(meta.name.clone())].encode_as_key(RelationId::SYSTEM);
@@ -662,6 +797,18 @@ impl<'a> SessionTx<'a> {
         let metadata = RelationHandle::decode(&found)?;
         Ok(metadata)
     }
+    /// Get a relation to read in a query: an index knows of the relation it indexes,
+    /// in case rows of the relation can expire.
+    pub(crate) fn get_relation_for_read(&self, name: &str) -> Result<RelationHandle> {
+        let handle = self.get_relation(name, false)?;
+        if let Some((base_name, idx_name)) = name.split_once(':') {
+            let base = self.get_relation(base_name, false)?;
+            if let Some((_, mapper)) = base.indices.get(idx_name) {
+                return Ok(handle.indexing(&base, mapper));
+            }
+        }
+        Ok(handle)
+    }
     pub(crate) fn describe_relation(&mut self, name: &str, description: &str) -> Result<()> {
         let mut meta = self.get_relation(name, true)?;
 
@@ -678,6 +825,402 @@ impl<'a> SessionTx<'a> {
 
         Ok(())
     }
+    pub(crate) fn set_ttl_column(&mut self, rel: &Symbol, col: Option<&Symbol>) -> Result<()> {
+        let mut meta = self.get_relation(rel, true)?;
+        if meta.access_level < AccessLevel::Normal {
+            bail!(InsufficientAccessLevel(
+                meta.name.to_string(),
+                "setting the TTL column".to_string(),
+                meta.access_level
+            ));
+        }
+        if let Some(col) = col {
+            let col_def = meta
+                .metadata
+                .keys
+                .iter()
+                .chain(meta.metadata.non_keys.iter())
+                .find(|c| c.name == col.name)
+                .ok_or_else(|| {
+                    miette!(
+                        "Column '{}' not found in stored relation '{}'",
+                        col.name,
+                        meta.name
+                    )
+                })?;
+            match col_def.typing.coltype {
+                ColType::Any | ColType::Int | ColType::Float | ColType::Timestamp => {}
+                _ => bail!(
+                    "The TTL column '{}' must hold numbers or timestamps, found type {}",
+                    col.name,
+                    col_def.typing
+                ),
+            }
+        }
+        meta.ttl_column = col.map(|c| c.name.clone());
+
+        let name_key = vec![DataValue::Str(meta.name.clone())].encode_as_key(RelationId::SYSTEM);
+        let mut meta_val = vec![];
+        meta.serialize(&mut Serializer::new(&mut meta_val).with_struct_map())
+            .unwrap();
+        if meta.is_temp {
+            self.temp_store_tx.put(&name_key, &meta_val)?;
+        } else {
+            self.store_tx.put(&name_key, &meta_val)?;
+        }
+
+        Ok(())
+    }
+    /// Change the columns of a stored relation. Existing rows are rewritten within the
+    /// transaction, so a failure leaves both the metadata and the data untouched.
+    pub(crate) fn alter_relation(
+        &mut self,
+        rel: &Symbol,
+        alteration: &RelationAlteration,
+        cur_vld: ValidityTs,
+    ) -> Result<()> {
+        let mut meta = self.get_relation(rel, true)?;
+        if meta.is_temp {
+            bail!("Cannot alter temporary relation '{}'", meta.name);
+        }
+        if meta.access_level < AccessLevel::Normal {
+            bail!(InsufficientAccessLevel(
+                meta.name.to_string(),
+                "altering relation".to_string(),
+                meta.access_level
+            ));
+        }
+
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("Column '{0}' cannot be altered as it is used by index '{1}'")]
+        #[diagnostic(code(tx::col_used_by_index))]
+        struct ColumnUsedByIndex(String, String, #[label] SourceSpan);
+
+        let n_keys = meta.metadata.keys.len();
+        // position of the altered non-key column in stored tuples
+        let find_col = |meta: &RelationHandle, col: &Symbol| -> Result<usize> {
+            if meta.metadata.keys.iter().any(|c| c.name == col.name) {
+                bail!(
+                    "Cannot alter key column '{}' of stored relation '{}'",
+                    col.name,
+                    meta.name
+                );
+            }
+            let pos = meta
+                .metadata
+                .non_keys
+                .iter()
+                .position(|c| c.name == col.name)
+                .ok_or_else(|| {
+                    miette!(
+                        "Column '{}' not found in stored relation '{}'",
+                        col.name,
+                        meta.name
+                    )
+                })?;
+            let pos = pos + n_keys;
+            for (idx_name, (_, mapping)) in meta.indices.iter() {
+                if mapping.contains(&pos) {
+                    bail!(ColumnUsedByIndex(
+                        col.name.to_string(),
+                        idx_name.to_string(),
+                        col.span
+                    ));
+                }
+            }
+            if let Some(idx_name) = meta
+                .hnsw_indices
+                .keys()
+                .chain(meta.fts_indices.keys())
+                .chain(meta.lsh_indices.keys())
+                .next()
+            {
+                bail!(ColumnUsedByIndex(
+                    col.name.to_string(),
+                    idx_name.to_string(),
+                    col.span
+                ));
+            }
+            Ok(pos)
+        };
+
+        let mut existing = TempCollector::default();
+        if !matches!(alteration, RelationAlteration::RenameColumn(..)) {
+            for tuple in meta.scan_all(self) {
+                existing.push(tuple?);
+            }
+        }
+
+        match alteration {
+            RelationAlteration::AddColumn(col) => {
+                if meta
+                    .metadata
+                    .keys
+                    .iter()
+                    .chain(meta.metadata.non_keys.iter())
+                    .any(|c| c.name == col.name)
+                {
+                    bail!(
+                        "Column '{}' already exists in stored relation '{}'",
+                        col.name,
+                        meta.name
+                    );
+                }
+                if col.default_gen.is_none() && !col.typing.nullable {
+                    bail!(
+                        "Column '{}' must either be nullable or have a default, as stored relation '{}' may already have rows",
+                        col.name,
+                        meta.name
+                    );
+                }
+                meta.metadata.non_keys.push(col.clone());
+                for mut tuple in existing.into_iter() {
+                    let val = match &col.default_gen {
+                        None => DataValue::Null,
+                        Some(expr) => expr.clone().eval_to_const()?,
+                    };
+                    let val =
+                        col.typing
+                            .coerce(val, cur_vld)
+                            .wrap_err_with(|| ColumnTypeMismatch {
+                                column: col.name.to_string(),
+                                typing: col.typing.clone(),
+                                row: tuple.clone(),
+                            })?;
+                    tuple.push(val);
+                    let key = meta.encode_key_for_store(&tuple, Default::default())?;
+                    let val = meta.encode_val_for_store(&tuple, Default::default())?;
+                    self.store_tx.put(&key, &val)?;
+                }
+            }
+            RelationAlteration::DropColumn(col) => {
+                let pos = find_col(&meta, col)?;
+                if meta.ttl_column.as_ref() == Some(&col.name) {
+                    bail!(
+                        "Cannot drop column '{}' as it is the TTL column of stored relation '{}'",
+                        col.name,
+                        meta.name
+                    );
+                }
+                meta.metadata.non_keys.remove(pos - n_keys);
+                for (_, mapping) in meta.indices.values_mut() {
+                    for i in mapping.iter_mut() {
+                        if *i > pos {
+                            *i -= 1;
+                        }
+                    }
+                }
+                for mut tuple in existing.into_iter() {
+                    tuple.remove(pos);
+                    let key = meta.encode_key_for_store(&tuple, Default::default())?;
+                    let val = meta.encode_val_for_store(&tuple, Default::default())?;
+                    self.store_tx.put(&key, &val)?;
+                }
+            }
+            RelationAlteration::SetColumnType(col, typing) => {
+                let pos = find_col(&meta, col)?;
+                if meta.ttl_column.as_ref() == Some(&col.name)
+                    && !matches!(
+                        typing.coltype,
+                        ColType::Any | ColType::Int | ColType::Float | ColType::Timestamp
+                    )
+                {
+                    bail!(
+                        "The TTL column '{}' must hold numbers or timestamps, found type {}",
+                        col.name,
+                        typing
+                    );
+                }
+                meta.metadata.non_keys[pos - n_keys].typing = typing.clone();
+                for mut tuple in existing.into_iter() {
+                    let val = tuple[pos].clone();
+                    tuple[pos] =
+                        typing
+                            .coerce(val, cur_vld)
+                            .wrap_err_with(|| ColumnTypeMismatch {
+                                column: col.name.to_string(),
+                                typing: typing.clone(),
+                                row: tuple.clone(),
+                            })?;
+                    let key = meta.encode_key_for_store(&tuple, Default::default())?;
+                    let val = meta.encode_val_for_store(&tuple, Default::default())?;
+                    self.store_tx.put(&key, &val)?;
+                }
+            }
+            RelationAlteration::RenameColumn(old, new) => {
+                let exists = |name: &str| {
+                    meta.metadata
+                        .keys
+                        .iter()
+                        .chain(meta.metadata.non_keys.iter())
+                        .any(|c| c.name == name)
+                };
+                if !exists(&old.name) {
+                    bail!(
+                        "Column '{}' not found in stored relation '{}'",
+                        old.name,
+                        meta.name
+                    );
+                }
+                if exists(&new.name) {
+                    bail!(
+                        "Column '{}' already exists in stored relation '{}'",
+                        new.name,
+                        meta.name
+                    );
+                }
+                // these indices keep expressions referring to columns by name
+                if let Some(idx_name) = meta
+                    .hnsw_indices
+                    .keys()
+                    .chain(meta.fts_indices.keys())
+                    .chain(meta.lsh_indices.keys())
+                    .next()
+                {
+                    bail!(ColumnUsedByIndex(
+                        old.name.to_string(),
+                        idx_name.to_string(),
+                        old.span
+                    ));
+                }
+                for col in meta
+                    .metadata
+                    .keys
+                    .iter_mut()
+                    .chain(meta.metadata.non_keys.iter_mut())
+                {
+                    if col.name == old.name {
+                        col.name = new.name.clone();
+                    }
+                }
+                for (idx_handle, _) in meta.indices.values_mut() {
+                    let mut stored = self.get_relation(&idx_handle.name, true)?;
+                    for handle in [&mut stored, idx_handle] {
+                        for col in handle.metadata.keys.iter_mut() {
+                            if col.name == old.name {
+                                col.name = new.name.clone();
+                            }
+                        }
+                    }
+                    let idx_key =
+                        vec![DataValue::Str(stored.name.clone())].encode_as_key(RelationId::SYSTEM);
+                    let mut idx_val = vec![];
+                    stored
+                        .serialize(&mut Serializer::new(&mut idx_val))
+                        .unwrap();
+                    self.store_tx.put(&idx_key, &idx_val)?;
+                }
+                if meta.ttl_column.as_ref() == Some(&old.name) {
+                    meta.ttl_column = Some(new.name.clone());
+                }
+            }
+        }
+
+        let name_key = vec![DataValue::Str(meta.name.clone())].encode_as_key(RelationId::SYSTEM);
+        let mut meta_val = vec![];
+        meta.serialize(&mut Serializer::new(&mut meta_val).with_struct_map())
+            .unwrap();
+        self.store_tx.put(&name_key, &meta_val)?;
+
+        Ok(())
+    }
+    pub(crate) fn get_view(&self, name: &str) -> Result<Option<String>> {
+        let key = view_key(name);
+        Ok(match self.store_tx.get(&key, false)? {
+            None => None,
+            Some(v) => Some(String::from_utf8(v).into_diagnostic()?),
+        })
+    }
+    pub(crate) fn create_view(&mut self, name: &Symbol, definition: &str) -> Result<()> {
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("View {0} already exists")]
+        #[diagnostic(code(tx::view_exists))]
+        struct ViewExists(String, #[label] SourceSpan);
+
+        let key = view_key(name);
+        ensure!(
+            !self.store_tx.exists(&key, true)?,
+            ViewExists(name.to_string(), name.span)
+        );
+        self.store_tx.put(&key, definition.as_bytes())
+    }
+    pub(crate) fn remove_view(&mut self, name: &Symbol) -> Result<()> {
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("View {0} not found")]
+        #[diagnostic(code(tx::view_not_found))]
+        struct ViewNotFound(String, #[label] SourceSpan);
+
+        let key = view_key(name);
+        ensure!(
+            self.store_tx.exists(&key, true)?,
+            ViewNotFound(name.to_string(), name.span)
+        );
+        self.store_tx.del(&key)
+    }
+    pub(crate) fn list_views(&self) -> Result<Vec<(String, String)>> {
+        self.list_system_entries(VIEW_KEY_TAG)?
+            .into_iter()
+            .map(|(name, v)| Ok((name, String::from_utf8(v).into_diagnostic()?)))
+            .try_collect()
+    }
+    fn list_system_entries(&self, tag: &str) -> Result<Vec<(String, Vec<u8>)>> {
+        let lower = vec![DataValue::Null, DataValue::from(tag)].encode_as_key(RelationId::SYSTEM);
+        let upper = vec![DataValue::Null, DataValue::from(tag), DataValue::Bot]
+            .encode_as_key(RelationId::SYSTEM);
+        let mut ret = vec![];
+        for kv_res in self.store_tx.range_scan(&lower, &upper) {
+            let (k, v) = kv_res?;
+            let tuple = decode_tuple_from_key(&k, 3);
+            let name = match tuple.get(2) {
+                Some(DataValue::Str(s)) => s.to_string(),
+                _ => continue,
+            };
+            ret.push((name, v));
+        }
+        Ok(ret)
+    }
+    pub(crate) fn get_procedure(&self, name: &Symbol) -> Result<StoredProcedure> {
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("Procedure {0} not found")]
+        #[diagnostic(code(tx::procedure_not_found))]
+        struct ProcedureNotFound(String, #[label] SourceSpan);
+
+        let found = self
+            .store_tx
+            .get(&procedure_key(name), false)?
+            .ok_or_else(|| ProcedureNotFound(name.to_string(), name.span))?;
+        rmp_serde::from_slice(&found).into_diagnostic()
+    }
+    pub(crate) fn create_procedure(
+        &mut self,
+        name: &Symbol,
+        procedure: &StoredProcedure,
+    ) -> Result<()> {
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("Procedure {0} already exists")]
+        #[diagnostic(code(tx::procedure_exists))]
+        #[diagnostic(help("Drop it first if you want to redefine it"))]
+        struct ProcedureExists(String, #[label] SourceSpan);
+
+        let key = procedure_key(name);
+        ensure!(
+            !self.store_tx.exists(&key, true)?,
+            ProcedureExists(name.to_string(), name.span)
+        );
+        let mut val = vec![];
+        procedure.serialize(&mut Serializer::new(&mut val)).unwrap();
+        self.store_tx.put(&key, &val)
+    }
+    pub(crate) fn remove_procedure(&mut self, name: &Symbol) -> Result<()> {
+        self.get_procedure(name)?;
+        self.store_tx.del(&procedure_key(name))
+    }
+    pub(crate) fn list_procedures(&self) -> Result<Vec<(String, StoredProcedure)>> {
+        self.list_system_entries(PROCEDURE_KEY_TAG)?
+            .into_iter()
+            .map(|(name, v)| Ok((name, rmp_serde::from_slice(&v).into_diagnostic()?)))
+            .try_collect()
+    }
     pub(crate) fn destroy_relation(&mut self, name: &str) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
         let is_temp = name.starts_with('_');
         let mut to_clean = vec![];
@@ -739,6 +1282,13 @@ impl<'a> SessionTx<'a> {
     pub(crate) fn create_minhash_lsh_index(&mut self, config: &MinHashLshConfig) -> Result<()> {
         // Get relation handle
         let mut rel_handle = self.get_relation(&config.base_relation, true)?;
+        if rel_handle.access_level < AccessLevel::Normal {
+            bail!(InsufficientAccessLevel(
+                rel_handle.name.to_string(),
+                "index creation".to_string(),
+                rel_handle.access_level
+            ));
+        }
 
         // Check if index already exists
         if rel_handle.has_index(&config.index_name) {
@@ -869,6 +1419,13 @@ impl<'a> SessionTx<'a> {
     pub(crate) fn create_fts_index(&mut self, config: &FtsIndexConfig) -> Result<()> {
         // Get relation handle
         let mut rel_handle = self.get_relation(&config.base_relation, true)?;
+        if rel_handle.access_level < AccessLevel::Normal {
+            bail!(InsufficientAccessLevel(
+                rel_handle.name.to_string(),
+                "index creation".to_string(),
+                rel_handle.access_level
+            ));
+        }
 
         // Check if index already exists
         if rel_handle.has_index(&config.index_name) {
@@ -1010,6 +1567,13 @@ impl<'a> SessionTx<'a> {
     pub(crate) fn create_hnsw_index(&mut self, config: &HnswIndexConfig) -> Result<()> {
         // Get relation handle
         let mut rel_handle = self.get_relation(&config.base_relation, true)?;
+        if rel_handle.access_level < AccessLevel::Normal {
+            bail!(InsufficientAccessLevel(
+                rel_handle.name.to_string(),
+                "index creation".to_string(),
+                rel_handle.access_level
+            ));
+        }
 
         // Check if index already exists
         if rel_handle.has_index(&config.index_name) {
@@ -1224,6 +1788,7 @@ impl<'a> SessionTx<'a> {
             key_bindings,
             dep_bindings,
             span: Default::default(),
+            merges: Default::default(),
         };
         let idx_handle = self.create_relation(idx_handle)?;
         Ok(idx_handle)
@@ -1234,9 +1799,17 @@ impl<'a> SessionTx<'a> {
         rel_name: &Symbol,
         idx_name: &Symbol,
         cols: &[Symbol],
+        unique: bool,
     ) -> Result<()> {
         // Get relation handle
         let mut rel_handle = self.get_relation(rel_name, true)?;
+        if rel_handle.access_level < AccessLevel::Normal {
+            bail!(InsufficientAccessLevel(
+                rel_handle.name.to_string(),
+                "index creation".to_string(),
+                rel_handle.access_level
+            ));
+        }
 
         // Check if index already exists
         if rel_handle.has_index(&idx_name.name) {
@@ -1301,6 +1874,7 @@ impl<'a> SessionTx<'a> {
             key_bindings,
             dep_bindings: vec![],
             span: Default::default(),
+            merges: Default::default(),
         };
 
         let idx_handle = self.create_relation(idx_handle)?;
@@ -1350,6 +1924,28 @@ impl<'a> SessionTx<'a> {
             }
         }
 
+        if unique {
+            // the index is sorted, so duplicates are next to each other
+            let mut prev: Option<Tuple> = None;
+            for tuple in idx_handle.scan_all(self) {
+                let mut tuple = tuple?;
+                tuple.truncate(cols.len());
+                if let Some(prev) = &prev {
+                    if *prev == tuple && !tuple.contains(&DataValue::Null) {
+                        bail!(UniqueConstraintViolation {
+                            relation: rel_name.name.to_string(),
+                            index: idx_name.name.to_string(),
+                            values: tuple,
+                        })
+                    }
+                }
+                prev = Some(tuple);
+            }
+            rel_handle
+                .unique_indices
+                .insert(idx_name.name.clone(), cols.len());
+        }
+
         // add index to relation
         rel_handle
             .indices
@@ -1373,12 +1969,20 @@ impl<'a> SessionTx<'a> {
         idx_name: &Symbol,
     ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
         let mut rel = self.get_relation(rel_name, true)?;
+        if rel.access_level < AccessLevel::Normal {
+            bail!(InsufficientAccessLevel(
+                rel.name.to_string(),
+                "index removal".to_string(),
+                rel.access_level
+            ));
+        }
         let is_lsh = rel.lsh_indices.contains_key(&idx_name.name);
         let is_fts = rel.fts_indices.contains_key(&idx_name.name);
         if is_lsh || is_fts {
             self.tokenizers.named_cache.write().unwrap().clear();
             self.tokenizers.hashed_cache.write().unwrap().clear();
         }
+        rel.unique_indices.remove(&idx_name.name);
         if rel.indices.remove(&idx_name.name).is_none()
             && rel.hnsw_indices.remove(&idx_name.name).is_none()
             && rel.lsh_indices.remove(&idx_name.name).is_none()
@@ -1433,6 +2037,24 @@ impl<'a> SessionTx<'a> {
         }
         rel.name = new.name.clone();
 
+        // index relations are named after the relation they belong to
+        for (idx_name, (idx_handle, _)) in rel.indices.iter_mut() {
+            self.rename_index_relation(idx_handle, &new.name, idx_name)?;
+        }
+        for (idx_name, (idx_handle, manifest)) in rel.hnsw_indices.iter_mut() {
+            self.rename_index_relation(idx_handle, &new.name, idx_name)?;
+            manifest.base_relation = new.name.clone();
+        }
+        for (idx_name, (idx_handle, manifest)) in rel.fts_indices.iter_mut() {
+            self.rename_index_relation(idx_handle, &new.name, idx_name)?;
+            manifest.base_relation = new.name.clone();
+        }
+        for (idx_name, (idx_handle, inv_handle, manifest)) in rel.lsh_indices.iter_mut() {
+            self.rename_index_relation(idx_handle, &new.name, idx_name)?;
+            self.rename_index_relation(inv_handle, &new.name, &format!("{idx_name}:inv"))?;
+            manifest.base_relation = new.name.clone();
+        }
+
         let mut meta_val = vec![];
         rel.serialize(&mut Serializer::new(&mut meta_val)).unwrap();
         self.store_tx.del(&old_encoded)?;
@@ -1440,6 +2062,28 @@ impl<'a> SessionTx<'a> {
 
         Ok(())
     }
+    fn rename_index_relation(
+        &mut self,
+        handle: &mut RelationHandle,
+        base_name: &str,
+        idx_name: &str,
+    ) -> Result<()> {
+        let old_encoded =
+            vec![DataValue::Str(handle.name.clone())].encode_as_key(RelationId::SYSTEM);
+        let mut stored = self.get_relation(&handle.name, true)?;
+        let new_name = SmartString::from(format!("{base_name}:{idx_name}"));
+        stored.name = new_name.clone();
+        handle.name = new_name.clone();
+
+        let new_encoded = vec![DataValue::Str(new_name)].encode_as_key(RelationId::SYSTEM);
+        let mut meta_val = vec![];
+        stored
+            .serialize(&mut Serializer::new(&mut meta_val))
+            .unwrap();
+        self.store_tx.del(&old_encoded)?;
+        self.store_tx.put(&new_encoded, &meta_val)?;
+        Ok(())
+    }
     pub(crate) fn rename_temp_relation(&mut self, old: Symbol, new: Symbol) -> Result<()> {
         let new_key = DataValue::Str(new.name.clone());
         let new_encoded = vec![new_key].encode_as_key(RelationId::SYSTEM);
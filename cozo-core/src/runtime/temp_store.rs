@@ -19,7 +19,7 @@ use miette::Result;
 
 use crate::data::aggr::Aggregation;
 use crate::data::tuple::Tuple;
-use crate::data::value::DataValue;
+use crate::data::value::{DataValue, Vector};
 
 /// A store holding temp data during evaluation of queries.
 /// The public interface is used in custom implementations of algorithms/utilities.
@@ -30,6 +30,29 @@ pub struct RegularTempStore {
 
 const EMPTY_TUPLE_REF: &Tuple = &vec![];
 
+/// Bookkeeping overhead of an entry in a `BTreeMap`, a rough guess.
+const MAP_ENTRY_OVERHEAD: usize = 32;
+
+fn approx_value_size(val: &DataValue) -> usize {
+    let heap = match val {
+        DataValue::Str(s) => s.len(),
+        DataValue::Bytes(b) => b.len(),
+        DataValue::List(l) => l.iter().map(approx_value_size).sum(),
+        DataValue::Set(s) => s
+            .iter()
+            .map(|v| approx_value_size(v) + MAP_ENTRY_OVERHEAD)
+            .sum(),
+        DataValue::Vec(Vector::F32(v)) => v.len() * 4,
+        DataValue::Vec(Vector::F64(v)) => v.len() * 8,
+        _ => 0,
+    };
+    mem::size_of::<DataValue>() + heap
+}
+
+fn approx_tuple_size(tuple: &[DataValue]) -> usize {
+    mem::size_of::<Tuple>() + tuple.iter().map(approx_value_size).sum::<usize>()
+}
+
 impl RegularTempStore {
     pub(crate) fn wrap(self) -> TempStore {
         TempStore::Normal(self)
@@ -243,6 +266,26 @@ impl TempStore {
             TempStore::MeetAggr(m) => m.inner.is_empty(),
         }
     }
+    fn len(&self) -> usize {
+        match self {
+            TempStore::Normal(n) => n.inner.len(),
+            TempStore::MeetAggr(m) => m.inner.len(),
+        }
+    }
+    fn approx_size(&self) -> usize {
+        match self {
+            TempStore::Normal(n) => n
+                .inner
+                .keys()
+                .map(|k| approx_tuple_size(k) + MAP_ENTRY_OVERHEAD)
+                .sum(),
+            TempStore::MeetAggr(m) => m
+                .inner
+                .iter()
+                .map(|(k, v)| approx_tuple_size(k) + approx_tuple_size(v) + MAP_ENTRY_OVERHEAD)
+                .sum(),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -250,6 +293,8 @@ pub(crate) struct EpochStore {
     total: TempStore,
     delta: TempStore,
     use_total_for_delta: bool,
+    /// Approximate memory used by the store, accumulated from the deltas
+    approx_size: usize,
     pub(crate) arity: usize,
 }
 
@@ -262,6 +307,7 @@ impl EpochStore {
             total: TempStore::Normal(RegularTempStore::default()),
             delta: TempStore::Normal(RegularTempStore::default()),
             use_total_for_delta: true,
+            approx_size: 0,
             arity,
         }
     }
@@ -270,10 +316,13 @@ impl EpochStore {
             total: TempStore::MeetAggr(MeetAggrStore::new(aggrs.to_vec())?),
             delta: TempStore::MeetAggr(MeetAggrStore::new(aggrs.to_vec())?),
             use_total_for_delta: true,
+            approx_size: 0,
             arity: aggrs.len(),
         })
     }
-    pub(crate) fn merge_in(&mut self, new: TempStore) -> Result<()> {
+    /// Merge in the results of an epoch. The approximate size is only tracked
+    /// if `track_size` is set, as computing it costs a pass over the delta.
+    pub(crate) fn merge_in(&mut self, new: TempStore, track_size: bool) -> Result<()> {
         match (&mut self.total, &mut self.delta, new) {
             (TempStore::Normal(total), TempStore::Normal(prev), TempStore::Normal(new)) => {
                 self.use_total_for_delta = total.merge_in(prev, new);
@@ -283,8 +332,32 @@ impl EpochStore {
             }
             _ => unreachable!(),
         }
+        if track_size {
+            // updated meet aggregations are counted again, so this may overestimate
+            self.approx_size += if self.use_total_for_delta {
+                self.total.approx_size()
+            } else {
+                self.delta.approx_size()
+            };
+        }
         Ok(())
     }
+    /// Approximate memory in bytes used by the tuples in the store
+    pub(crate) fn approx_size(&self) -> usize {
+        self.approx_size
+    }
+    /// Number of tuples in the store
+    pub(crate) fn len(&self) -> usize {
+        self.total.len()
+    }
+    /// Number of tuples added or updated by the last merge
+    pub(crate) fn delta_len(&self) -> usize {
+        if self.use_total_for_delta {
+            self.total.len()
+        } else {
+            self.delta.len()
+        }
+    }
     pub(crate) fn has_delta(&self) -> bool {
         if self.use_total_for_delta {
             !self.total.is_empty()
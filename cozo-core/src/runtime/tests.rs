@@ -14,16 +14,23 @@ use itertools::Itertools;
 use log::debug;
 use serde_json::json;
 use smartstring::{LazyCompact, SmartString};
+use uuid::Uuid;
 
 use crate::data::expr::Expr;
 use crate::data::symb::Symbol;
-use crate::data::value::DataValue;
+use crate::data::tuple::{decode_tuple_from_key, TupleT};
+use crate::data::value::{DataValue, Num, UuidWrapper};
 use crate::fixed_rule::FixedRulePayload;
 use crate::fts::{TokenizerCache, TokenizerConfig};
 use crate::parse::SourceSpan;
 use crate::runtime::callback::CallbackOp;
 use crate::runtime::db::Poison;
-use crate::{DbInstance, FixedRule, RegularTempStore, ScriptMutability};
+use crate::runtime::relation::RelationId;
+use crate::runtime::transact::CURRENT_STORAGE_VERSION;
+use crate::{
+    new_cozo_mem, DbInstance, FixedRule, NamedRows, RegularTempStore, ScriptMutability,
+    SimpleFixedRule,
+};
 
 #[test]
 fn test_limit_offset() {
@@ -514,6 +521,91 @@ fn test_index() {
     db.run_default("::index drop friends:rev").unwrap();
 }
 
+#[test]
+fn test_index_for_const_filter() {
+    let db = DbInstance::default();
+    db.run_default(":create friends {fr: Int, to: Int => data: Any}")
+        .unwrap();
+    db.run_default("::index create friends:rev {to}").unwrap();
+    db.run_default(r"?[fr, to, data] <- [[1,2,3],[4,5,6],[7,2,2.0]] :put friends {fr, to, data}")
+        .unwrap();
+    db.run_default("::index create friends:by_data {data}")
+        .unwrap();
+
+    // the values of a column of the plan
+    let explain_column = |query: &str, col: usize| {
+        let expl = db
+            .run_default(&format!("::explain {{ {query} }}"))
+            .unwrap();
+        expl.into_json()["rows"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| row.as_array().unwrap()[col].clone())
+            .collect_vec()
+    };
+    let relations_used = |query: &str| explain_column(query, 5);
+
+    let query = "?[fr, data] := *friends{fr, to, data}, to == 2";
+    let res = db.run_default(query).unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[1, 3], [7, 2.0]]));
+    assert!(relations_used(query).contains(&json!(":friends:rev")));
+
+    let query = "?[to] := *friends{fr, to}, fr == 4";
+    let res = db.run_default(query).unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[5]]));
+    assert!(explain_column(query, 4).contains(&json!("stored_prefix_join")));
+
+    // `==` does not tell 2 and 2.0 apart, so a column of any type must be scanned
+    let query = "?[fr] := *friends{fr, data}, data == 2";
+    let res = db.run_default(query).unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[7]]));
+    assert!(!relations_used(query).contains(&json!(":friends:by_data")));
+}
+
+#[test]
+fn test_index_selection() {
+    let db = DbInstance::default();
+    db.run_default(":create r {a: Int, b: Int => c: Int, d: Int}")
+        .unwrap();
+    db.run_default(r"?[a, b, c, d] <- [[1,1,1,1],[1,2,3,4],[2,1,3,5]] :put r {a, b => c, d}")
+        .unwrap();
+    db.run_default("::index create r:a_c {a, c}").unwrap();
+    db.run_default("::index create r:c {c}").unwrap();
+    db.run_default("::index create r:c_d {c, d}").unwrap();
+    let relations_used = |query: &str| {
+        let expl = db
+            .run_default(&format!("::explain {{ {query} }}"))
+            .unwrap();
+        expl.into_json()["rows"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|row| row.as_array().unwrap()[5].as_str().map(|s| s.to_string()))
+            .filter(|name| name.starts_with(':'))
+            .collect_vec()
+    };
+
+    // the index binds two columns, the primary key only one
+    let query = "?[b] := *r{a: 1, b, c: 3}";
+    let res = db.run_default(query).unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[2]]));
+    assert_eq!(relations_used(query), vec![":r:a_c"]);
+
+    // the primary key binds as many columns as any index
+    let query = "?[c] := *r{a: 1, b: 2, c}";
+    assert_eq!(relations_used(query), vec![":r"]);
+
+    // both indices bind `c`, only one of them also holds `d`
+    let query = "?[a, d] := *r{a, c: 3, d}";
+    let res = db.run_default(query).unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[1, 4], [2, 5]]));
+    assert_eq!(relations_used(query), vec![":r:c_d"]);
+
+    let query = "?[a] := *r{a, c: 3}";
+    assert_eq!(relations_used(query), vec![":r:c"]);
+}
+
 #[test]
 fn test_json_objects() {
     let db = DbInstance::default();
@@ -576,6 +668,56 @@ fn test_custom_rules() {
     assert_eq!(res.into_json()["rows"], json!([[1000], [2600]]));
 }
 
+#[test]
+fn test_simple_fixed_rules() {
+    let db = DbInstance::default();
+    db.run_default(r"?[a, b] <- [[1, 2], [3, 4]] :create pairs {a => b}")
+        .unwrap();
+    let rule = SimpleFixedRule::new(2, |inputs, options| {
+        let shift = options.get("shift").and_then(|v| v.get_int()).unwrap_or(0);
+        let rows = inputs[0]
+            .rows
+            .iter()
+            .map(|row| {
+                vec![
+                    row[1].clone(),
+                    DataValue::from(row[0].get_int().unwrap() + shift),
+                ]
+            })
+            .collect();
+        Ok(NamedRows::new(vec!["b".to_string(), "a".to_string()], rows))
+    });
+    db.register_fixed_rule("Flip".to_string(), rule).unwrap();
+    assert!(db
+        .register_fixed_rule(
+            "Flip".to_string(),
+            SimpleFixedRule::new(0, |_, _| unreachable!())
+        )
+        .is_err());
+
+    // stored and derived relations can both be given as inputs
+    let res = db
+        .run_default("?[x, y] <~ Flip(*pairs[], shift: 10)")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[2, 11], [4, 13]]));
+    let res = db
+        .run_default(
+            r"
+            r[a, b] := *pairs{a, b}, a > 1
+            ?[x, y] <~ Flip(r[])
+            ",
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[4, 3]]));
+
+    assert!(db.unregister_fixed_rule("Flip").unwrap());
+    assert!(!db.unregister_fixed_rule("Flip").unwrap());
+    assert!(db.run_default("?[x, y] <~ Flip(*pairs[])").is_err());
+    assert!(db.unregister_fixed_rule("PageRank").is_err());
+}
+
 #[test]
 fn test_index_short() {
     let db = DbInstance::default();
@@ -808,6 +950,37 @@ fn test_vec_index() {
     }
 }
 
+#[test]
+fn test_vec_index_ef() {
+    let db = DbInstance::new("mem", "", "").unwrap();
+    db.run_default(
+        r"
+        ?[k, v] <- [['a', [1,2]], ['b', [2,3]], ['c', [3,4]], ['d', [4,5]], ['e', [5,6]]]
+        :create a {k: String => v: <F32; 2>}
+    ",
+    )
+    .unwrap();
+    db.run_default(
+        r"::hnsw create a:vec {
+            dim: 2, m: 50, dtype: F32, fields: [v], distance: L2, ef_construction: 20
+        }",
+    )
+    .unwrap();
+
+    // `ef` defaults to `ef_construction`, and is raised to `k` if smaller
+    for ef in ["", ", ef: 1"] {
+        let res = db
+            .run_default(&format!(
+                "?[k] := ~a:vec{{k | query: q, k: 3{ef}}}, q = vec([0, 0])"
+            ))
+            .unwrap();
+        assert_eq!(res.into_json()["rows"], json!([["a"], ["b"], ["c"]]));
+    }
+    assert!(db
+        .run_default("?[k] := ~a:vec{k | query: q, k: 3, ef: 0}, q = vec([0, 0])")
+        .is_err());
+}
+
 #[test]
 fn test_fts_indexing() {
     let db = DbInstance::new("mem", "", "").unwrap();
@@ -1574,3 +1747,2863 @@ fn fts_drop() {
         ::fts drop entity:fts_index
     "#).unwrap();
 }
+
+#[test]
+#[cfg(feature = "storage-sqlite")]
+fn sqlite_persistence() {
+    let path = std::env::temp_dir().join(format!("_cozo_sqlite_test_{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    {
+        let db = DbInstance::new("sqlite", &path, "").unwrap();
+        db.run_default(":create kv {k: Int => v: String}").unwrap();
+        db.run_default("?[k, v] <- [[1, 'a'], [2, 'b'], [3, 'c']] :put kv {k => v}")
+            .unwrap();
+        db.run_default("?[k] <- [[2]] :rm kv {k}").unwrap();
+    }
+    {
+        let db = DbInstance::new("sqlite", &path, "").unwrap();
+        let res = db.run_default("?[k, v] := *kv[k, v]").unwrap().into_json();
+        assert_eq!(res["rows"], json!([[1, "a"], [3, "c"]]));
+        let res = db.run_default("?[v] := *kv[3, v]").unwrap().into_json();
+        assert_eq!(res["rows"], json!([["c"]]));
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+#[cfg(feature = "storage-sled")]
+fn sled_deletion() {
+    let path = std::env::temp_dir().join(format!("_cozo_sled_test_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    {
+        let db = DbInstance::new("sled", &path, "").unwrap();
+        db.run_default(":create kv {k: Int => v: String}").unwrap();
+        db.run_default("?[k, v] <- [[1, 'a'], [2, 'b'], [3, 'c']] :put kv {k => v}")
+            .unwrap();
+        db.run_default("?[k] <- [[2]] :rm kv {k}").unwrap();
+        let res = db.run_default("?[k, v] := *kv[k, v]").unwrap().into_json();
+        assert_eq!(res["rows"], json!([[1, "a"], [3, "c"]]));
+        let res = db.run_default("?[count(k)] := *kv[k, _]").unwrap().into_json();
+        assert_eq!(res["rows"], json!([[2]]));
+    }
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+#[cfg(feature = "storage-sled")]
+fn sled_time_travel() {
+    let path = std::env::temp_dir().join(format!("_cozo_sled_tt_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    {
+        let db = DbInstance::new("sled", &path, "").unwrap();
+        db.run_default(":create hist {k: Int, at: Validity => v: String}")
+            .unwrap();
+        db.run_default(
+            "?[k, at, v] <- [[1, [10, true], 'a'], [1, [20, true], 'b'], [2, [15, true], 'c']] \
+            :put hist {k, at => v}",
+        )
+        .unwrap();
+        let res = db
+            .run_default("?[k, v] := *hist{k, v @ 17}")
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([[1, "a"], [2, "c"]]));
+        let res = db
+            .run_default("?[k, v] := *hist{k, v @ 25}")
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], json!([[1, "b"], [2, "c"]]));
+    }
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn online_backup_unsupported() {
+    let db = DbInstance::default();
+    let path = std::env::temp_dir().join(format!("_cozo_mem_backup_{}", std::process::id()));
+    assert!(db.backup(&path).is_err());
+    assert!(!path.exists());
+}
+
+#[test]
+#[cfg(feature = "storage-rocksdb")]
+fn rocksdb_online_backup() {
+    let base = std::env::temp_dir().join(format!("_cozo_rocks_test_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&base);
+    let src_path = base.join("src");
+    let backup_path = base.join("backup");
+    {
+        let db = DbInstance::new("rocksdb", &src_path, "").unwrap();
+        db.run_default(":create kv {k: Int => v: String}").unwrap();
+        db.run_default("?[k, v] <- [[1, 'a'], [2, 'b']] :put kv {k => v}")
+            .unwrap();
+        db.backup(&backup_path).unwrap();
+        assert!(db.backup(&backup_path).is_err());
+        db.run_default("?[k, v] <- [[3, 'c']] :put kv {k => v}")
+            .unwrap();
+    }
+    {
+        let db = DbInstance::new("rocksdb", &backup_path, "").unwrap();
+        let res = db.run_default("?[k, v] := *kv[k, v]").unwrap().into_json();
+        assert_eq!(res["rows"], json!([[1, "a"], [2, "b"]]));
+    }
+    std::fs::remove_dir_all(&base).unwrap();
+}
+
+#[test]
+#[cfg(feature = "storage-rocksdb")]
+fn rocksdb_tuning_options() {
+    let path = std::env::temp_dir().join(format!("_cozo_rocks_opts_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    let opts = r#"{"block_cache_size": 8388608, "write_buffer_size": 4194304,
+        "max_background_jobs": 2, "compression": "zstd"}"#;
+    {
+        let db = DbInstance::new("rocksdb", &path, opts).unwrap();
+        db.run_default(":create kv {k: Int => v: String}").unwrap();
+        db.run_default("?[k, v] <- [[1, 'a'], [2, 'b']] :put kv {k => v}")
+            .unwrap();
+    }
+    {
+        let db = DbInstance::new("rocksdb", &path, r#"{"compression": "none"}"#).unwrap();
+        let res = db.run_default("?[k, v] := *kv[k, v]").unwrap().into_json();
+        assert_eq!(res["rows"], json!([[1, "a"], [2, "b"]]));
+    }
+    assert!(DbInstance::new("rocksdb", &path, r#"{"compression": "brotli"}"#).is_err());
+    std::fs::write(path.join("options"), "").unwrap();
+    assert!(DbInstance::new("rocksdb", &path, opts).is_err());
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+#[cfg(feature = "storage-sqlite")]
+fn restore_backup_checks() {
+    let path = std::env::temp_dir().join(format!("_cozo_restore_test_{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    assert!(DbInstance::default().restore_backup(&path).is_err());
+    assert!(!path.exists());
+
+    let src = DbInstance::default();
+    src.run_default(":create kv {k: Int => v: String}").unwrap();
+    src.run_default("?[k, v] <- [[1, 'a'], [2, 'b']] :put kv {k => v}")
+        .unwrap();
+    src.backup_db(&path).unwrap();
+
+    let dst = DbInstance::default();
+    dst.run_default(":create other {x: Int}").unwrap();
+    assert!(dst.restore_backup(&path).is_err());
+    dst.restore_backup_forced(&path).unwrap();
+    let res = dst.run_default("?[k, v] := *kv[k, v]").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1, "a"], [2, "b"]]));
+    assert!(dst.run_default("?[x] := *other[x]").is_err());
+
+    // new relations must not reuse the ids of the restored ones
+    let fresh = DbInstance::default();
+    fresh.restore_backup(&path).unwrap();
+    fresh.run_default(":create another {x: Int}").unwrap();
+    fresh.run_default("?[x] <- [[1]] :put another {x}").unwrap();
+    let res = fresh.run_default("?[k, v] := *kv[k, v]").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1, "a"], [2, "b"]]));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn compact_relation() {
+    let db = DbInstance::default();
+    db.run_default(":create kv {k: Int => v: String}").unwrap();
+    db.run_default("::index create kv:by_v {v}").unwrap();
+    db.run_default("?[k, v] <- [[1, 'a'], [2, 'b']] :put kv {k => v}")
+        .unwrap();
+    db.run_default("::compact").unwrap();
+    db.run_default("::compact kv").unwrap();
+    db.run_default("::compact kv:by_v").unwrap();
+    assert!(db.run_default("::compact not_there").is_err());
+    db.compact_range(Some("kv")).unwrap();
+    db.compact_range(None).unwrap();
+    let res = db.run_default("?[k, v] := *kv[k, v]").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1, "a"], [2, "b"]]));
+}
+
+#[test]
+#[cfg(feature = "encryption")]
+fn encrypted_storage() {
+    use crate::storage::{Storage, StoreTx};
+    use crate::{new_cozo_encrypted, MemStorage};
+
+    let key = [7u8; 32];
+    let storage = MemStorage::default();
+    let db = new_cozo_encrypted(storage.clone(), &key).unwrap();
+    let run = |script: &str| db.run_script(script, Default::default(), ScriptMutability::Mutable);
+    run(":create kv {k: Int => v: String}").unwrap();
+    run("?[k, v] <- [[1, 'secret_one'], [2, 'secret_two']] :put kv {k => v}")
+        .unwrap();
+    run(":create vld {a, v: Validity => d}").unwrap();
+    run(
+        r#"?[a, v, d] <- [[1, [0, true], 'old'], [1, [1, true], 'new']] :put vld {a, v => d}"#,
+    )
+    .unwrap();
+    let res = run("?[k, v] := *kv[k, v]").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1, "secret_one"], [2, "secret_two"]]));
+    let res = run(r#"?[d] := *vld{a: 1, d @ "NOW"}"#).unwrap().into_json();
+    assert_eq!(res["rows"], json!([["new"]]));
+
+    {
+        let tx = storage.transact(false).unwrap();
+        for pair in tx.total_scan() {
+            let (_, v) = pair.unwrap();
+            assert!(!v.windows(6).any(|w| w == b"secret"));
+        }
+    }
+
+    assert!(new_cozo_encrypted(storage.clone(), &[8u8; 32]).is_err());
+    assert!(new_cozo_encrypted(MemStorage::default(), &[8u8; 16]).is_err());
+}
+
+#[test]
+#[cfg(feature = "encryption")]
+fn encrypted_time_travel_join() {
+    use crate::{new_cozo_encrypted, MemStorage};
+
+    let db = new_cozo_encrypted(MemStorage::default(), &[7u8; 32]).unwrap();
+    check_filters_with_all_validity_keys_joined(|script| {
+        db.run_script(script, Default::default(), ScriptMutability::Mutable)
+    });
+}
+
+#[test]
+fn namespaces() {
+    use crate::{new_cozo_namespaced, MemStorage};
+
+    let storage = MemStorage::default();
+    let tenant_a = new_cozo_namespaced(storage.clone(), "tenant_a").unwrap();
+    let tenant_b = new_cozo_namespaced(storage.clone(), "tenant_b").unwrap();
+    let run_a = |script: &str| {
+        tenant_a.run_script(script, Default::default(), ScriptMutability::Mutable)
+    };
+    let run_b = |script: &str| {
+        tenant_b.run_script(script, Default::default(), ScriptMutability::Mutable)
+    };
+
+    run_a(":create kv {k: Int => v: String}").unwrap();
+    run_a("?[k, v] <- [[1, 'a'], [2, 'b']] :put kv {k => v}").unwrap();
+    run_b(":create kv {k: Int => v: Int}").unwrap();
+    run_b("?[k, v] <- [[1, 100]] :put kv {k => v}").unwrap();
+    run_b(":create vld {a, v: Validity => d}").unwrap();
+    run_b(r#"?[a, v, d] <- [[1, [0, true], 'old'], [1, [1, true], 'new']] :put vld {a, v => d}"#)
+        .unwrap();
+
+    let res = run_a("?[k, v] := *kv[k, v]").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1, "a"], [2, "b"]]));
+    let res = run_b("?[k, v] := *kv[k, v]").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1, 100]]));
+    let res = run_b(r#"?[d] := *vld{a: 1, d @ "NOW"}"#).unwrap().into_json();
+    assert_eq!(res["rows"], json!([["new"]]));
+    assert!(run_a("?[a] := *vld{a}").is_err());
+
+    run_a("::remove kv").unwrap();
+    let res = run_b("?[k, v] := *kv[k, v]").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1, 100]]));
+
+    let reopened = new_cozo_namespaced(storage, "tenant_b").unwrap();
+    let res = reopened
+        .run_script("::relations", Default::default(), ScriptMutability::Immutable)
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"].as_array().unwrap().len(), 2);
+    assert!(new_cozo_namespaced(MemStorage::default(), "").is_err());
+}
+
+#[test]
+fn namespaced_time_travel_join() {
+    use crate::{new_cozo_namespaced, MemStorage};
+
+    let db = new_cozo_namespaced(MemStorage::default(), "a").unwrap();
+    check_filters_with_all_validity_keys_joined(|script| {
+        db.run_script(script, Default::default(), ScriptMutability::Mutable)
+    });
+}
+
+#[test]
+fn namespaced_online_backup_unsupported() {
+    use crate::{new_cozo_namespaced, MemStorage};
+
+    let db = new_cozo_namespaced(MemStorage::default(), "a").unwrap();
+    let path = std::env::temp_dir().join(format!("_cozo_ns_backup_{}", std::process::id()));
+    let err = db.backup(&path).unwrap_err();
+    assert!(err.to_string().contains("namespace"));
+    assert!(!path.exists());
+}
+
+#[test]
+fn bulk_load_mode() {
+    let db = DbInstance::default();
+    db.run_default(":create kv {k: Int => v: Int}").unwrap();
+    db.set_bulk_load(true, false).unwrap();
+    db.run_default("?[k, v] := k in int_range(1000), v = k * 2 :put kv {k => v}")
+        .unwrap();
+    db.set_bulk_load(false, false).unwrap();
+    let res = db
+        .run_default("?[count(k), sum(v)] := *kv[k, v]")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[1000, 999000.0]]));
+}
+
+#[test]
+fn bulk_load_skipping_insert_checks() {
+    let db = DbInstance::default();
+    db.run_default(":create kv {k: Int => v: Int}").unwrap();
+    db.run_default("?[k, v] <- [[1, 1]] :insert kv {k => v}")
+        .unwrap();
+    let insert_existing = "?[k, v] <- [[1, 2]] :insert kv {k => v}";
+
+    db.set_bulk_load(true, false).unwrap();
+    assert!(db.run_default(insert_existing).is_err());
+    // without reading the existing key, the insertion overwrites it
+    db.set_bulk_load(true, true).unwrap();
+    db.run_default(insert_existing).unwrap();
+    let res = db.run_default("?[k, v] := *kv[k, v]").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1, 2]]));
+    // temp relations and `:insert_or_ignore` still check
+    db.run_default("{?[k] <- [[1]] :create _tmp {k}} {?[k] <- [[2]] :insert _tmp {k}}")
+        .unwrap();
+    assert!(db
+        .run_default("{?[k] <- [[1]] :create _tmp {k}} {?[k] <- [[1]] :insert _tmp {k}}")
+        .is_err());
+    db.run_default("?[k, v] <- [[1, 3]] :insert_or_ignore kv {k => v}")
+        .unwrap();
+    let res = db.run_default("?[k, v] := *kv[k, v]").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1, 2]]));
+
+    db.set_bulk_load(false, true).unwrap();
+    assert!(db.run_default(insert_existing).is_err());
+}
+
+#[test]
+fn query_memory_limit() {
+    let db = DbInstance::default();
+    let q = r#"
+        r[x] := x in int_range(10)
+        r[y] := r[x], y = x + 1, y < 5000
+        ?[count(x)] := r[x]
+    "#;
+    let err = db
+        .run_default(&format!("{q} :memory_limit 10000"))
+        .unwrap_err();
+    assert!(err.to_string().contains("memory limit"));
+    let res = db
+        .run_default(&format!("{q} :memory_limit 100000000"))
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[5000]]));
+
+    db.set_memory_limit(Some(10000));
+    assert!(db.run_default(q).is_err());
+    // the option of the query takes precedence
+    assert!(db
+        .run_default(&format!("{q} :memory_limit 100000000"))
+        .is_ok());
+    assert!(db.run_default(&format!("{q} :memory_limit 0")).is_ok());
+    db.set_memory_limit(None);
+    assert!(db.run_default(q).is_ok());
+}
+
+#[test]
+fn row_ttl() {
+    let db = DbInstance::default();
+    db.run_default(":create sess {id: Int => expires: Float?}")
+        .unwrap();
+    db.run_default(":create live {id: Int}").unwrap();
+    db.run_default(
+        r#"
+        ?[id, expires] := id in [1, 2, 3], expires = if(id == 1, now() - 10, now() + 3600)
+        :put sess {id => expires}
+        "#,
+    )
+    .unwrap();
+    db.run_default("?[id, expires] <- [[4, null]] :put sess {id => expires}")
+        .unwrap();
+    db.run_default("?[id] <- [[1], [2]] :put live {id}")
+        .unwrap();
+    assert!(db.run_default("::set_ttl sess id_not_there").is_err());
+    db.run_default("::set_ttl sess expires").unwrap();
+
+    let res = db.run_default("?[id] := *sess[id, _]").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[2], [3], [4]]));
+    let res = db
+        .run_default("?[id] := *live[id], *sess[id, _]")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[2]]));
+    let res = db
+        .run_default("?[id] := *live[id], not *sess[id, _]")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[1]]));
+
+    // expired rows are still stored until compaction
+    db.run_default("::set_ttl sess").unwrap();
+    let res = db
+        .run_default("?[count(id)] := *sess[id, _]")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[4]]));
+    db.run_default("::set_ttl sess expires").unwrap();
+    db.run_default("::compact sess").unwrap();
+    db.run_default("::set_ttl sess").unwrap();
+    let res = db.run_default("?[id] := *sess[id, _]").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[2], [3], [4]]));
+}
+
+#[test]
+fn row_ttl_through_indices() {
+    let db = DbInstance::default();
+    db.run_default(":create s {k: Int => exp: Float, tag: String}")
+        .unwrap();
+    db.run_default("::index create s:by_tag {tag}").unwrap();
+    db.run_default("::index create s:by_tag_exp {tag, exp}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[k, exp, tag] := k in [1, 2], exp = if(k == 1, now() - 10, now() + 3600), tag = 'a'
+        :put s {k => exp, tag}
+        "#,
+    )
+    .unwrap();
+    db.run_default("::set_ttl s exp").unwrap();
+
+    for q in [
+        "?[k] := *s{k, tag: 'a'}",
+        "?[k] := *s:by_tag{tag: 'a', k}",
+        "?[k] := *s:by_tag_exp{tag: 'a', k}",
+    ] {
+        let res = db.run_default(q).unwrap().into_json();
+        assert_eq!(res["rows"], json!([[2]]), "{q}");
+    }
+    let res = db
+        .run_default("?[k] := k in [1, 2, 3], not *s:by_tag{tag: 'a', k}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[1], [3]]));
+
+    db.run_default(":create t {k: Int => exp: Timestamp}")
+        .unwrap();
+    db.run_default(
+        "?[k, exp] := k in [1, 2], exp = if(k == 1, now_ts() - 10, now_ts() + 3600) :put t {k => exp}",
+    )
+    .unwrap();
+    db.run_default("::set_ttl t exp").unwrap();
+    let res = db.run_default("?[k] := *t{k}").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[2]]));
+}
+
+#[test]
+fn storage_stats() {
+    let db = DbInstance::default();
+    db.run_default(":create kv {k: Int => v: String}").unwrap();
+    db.run_default(":create empty {k: Int}").unwrap();
+    db.run_default("::index create kv:by_v {v}").unwrap();
+    db.run_default("?[k, v] := k in int_range(100), v = to_string(k) :put kv {k => v}")
+        .unwrap();
+    let res = db.run_default("::storage_stats").unwrap();
+    assert_eq!(
+        res.headers,
+        ["name", "key_count", "approx_size", "tombstones"]
+    );
+    let rows = res.into_json()["rows"].clone();
+    assert_eq!(rows[0][0], json!("empty"));
+    assert_eq!(rows[0][1], json!(0));
+    assert_eq!(rows[1][0], json!("kv"));
+    assert_eq!(rows[1][1], json!(100));
+    assert!(rows[1][2].as_i64().unwrap() > 0);
+    assert_eq!(rows[1][3], json!(null));
+    assert_eq!(rows[2][0], json!("kv:by_v"));
+    assert_eq!(rows[2][1], json!(100));
+    let res = db.run_default("::storage_stats kv").unwrap();
+    assert_eq!(res.rows.len(), 1);
+
+    // the size of large relations is extrapolated from the first rows
+    db.run_default(":create small {k: Int => v: String}").unwrap();
+    db.run_default(":create large {k: Int => v: String}").unwrap();
+    db.run_default("?[k, v] := k in int_range(1000), v = 'x' :put small {k => v}")
+        .unwrap();
+    db.run_default("?[k, v] := k in int_range(2500), v = 'x' :put large {k => v}")
+        .unwrap();
+    let small = db.run_default("::storage_stats small").unwrap().rows;
+    let large = db.run_default("::storage_stats large").unwrap().rows;
+    assert_eq!(large[0][1], DataValue::from(2500));
+    assert_eq!(
+        large[0][2].get_int().unwrap(),
+        small[0][2].get_int().unwrap() * 5 / 2
+    );
+}
+
+#[test]
+#[cfg(feature = "storage-rocksdb")]
+fn rocksdb_storage_stats() {
+    let path = std::env::temp_dir().join(format!("_cozo_rocks_stats_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    {
+        let db = DbInstance::new("rocksdb", &path, "").unwrap();
+        db.run_default(":create kv {k: Int => v: String}").unwrap();
+        db.run_default("?[k, v] := k in int_range(1000), v = to_string(k) :put kv {k => v}")
+            .unwrap();
+        db.run_default("?[k] := k in int_range(500) :rm kv {k}")
+            .unwrap();
+        db.run_default("::compact").unwrap();
+        let rows = db.run_default("::storage_stats kv").unwrap().rows;
+        assert_eq!(rows[0][1], DataValue::from(500));
+        // the estimates come from RocksDB
+        assert!(rows[0][2].get_int().unwrap() >= 0);
+        assert!(rows[0][3].get_int().is_some());
+    }
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn stratified_negation() {
+    let db = DbInstance::default();
+    let res = db
+        .run_default(
+            r#"
+            edge[a, b] <- [[1, 2], [2, 3], [4, 5]]
+            node[n] := edge[n, _]
+            node[n] := edge[_, n]
+            reach[n] := n = 1
+            reach[n] := reach[m], edge[m, n]
+            ?[n] := node[n], not reach[n]
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[4], [5]]));
+
+    // negation through recursion has no stratification
+    let err = db
+        .run_default(
+            r#"
+            edge[a, b] <- [[1, 2], [2, 3]]
+            win[n] := edge[n, m], not win[m]
+            ?[n] := win[n]
+            "#,
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("unstratifiable"));
+}
+
+#[test]
+fn recursive_meet_aggregation() {
+    let db = DbInstance::default();
+    let res = db
+        .run_default(
+            r#"
+            edge[a, b, w] <- [[1, 2, 1.0], [2, 3, 1.0], [1, 3, 5.0], [3, 4, 2.0]]
+            dist[n, min(d)] := n = 1, d = 0.0
+            dist[n, min(d)] := dist[m, d0], edge[m, n, w], d = d0 + w
+            ?[n, d] := dist[n, d]
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[1, 0.0], [2, 1.0], [3, 2.0], [4, 4.0]]));
+
+    // non-meet aggregations cannot be computed through recursion
+    assert!(db
+        .run_default(
+            r#"
+            edge[a, b] <- [[1, 2], [2, 3]]
+            cnt[n, count(m)] := edge[n, m]
+            cnt[n, count(m)] := cnt[n, m], edge[m, _]
+            ?[n, c] := cnt[n, c]
+            "#,
+        )
+        .is_err());
+}
+
+#[test]
+fn sorted_limit_offset() {
+    let db = DbInstance::default();
+    let res = db
+        .run_default("?[a, b] := a in int_range(100), b = a % 3 :order -b, a :limit 3 :offset 2")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[8, 2], [11, 2], [14, 2]]));
+    let res = db
+        .run_default("?[a] := a in int_range(10) :order -a :limit 0")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([]));
+    let res = db
+        .run_default("?[a] := a in int_range(5) :order -a :limit 10 :offset 3")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[1], [0]]));
+}
+
+#[test]
+fn named_params_are_data() {
+    let db = DbInstance::default();
+    db.run_default(":create users {name: String => note: String}")
+        .unwrap();
+    let tricky = "x'] :rm users {name} ?[\"";
+    let params = BTreeMap::from([
+        ("name".to_string(), DataValue::from(tricky)),
+        ("note".to_string(), DataValue::from("it's \"quoted\"")),
+    ]);
+    db.run_script(
+        "?[name, note] <- [[$name, $note]] :put users {name => note}",
+        params.clone(),
+        ScriptMutability::Mutable,
+    )
+    .unwrap();
+    let res = db
+        .run_script(
+            "?[note] := *users{name: $name, note}",
+            params,
+            ScriptMutability::Immutable,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["it's \"quoted\""]]));
+    assert!(db.run_default("?[x] := x = $missing").is_err());
+}
+
+#[test]
+fn multi_statement_atomicity() {
+    let db = DbInstance::default();
+    db.run_default(":create acc {id: Int => bal: Int}").unwrap();
+    db.run_default("?[id, bal] <- [[1, 100], [2, 0]] :put acc {id => bal}")
+        .unwrap();
+    let transfer = |amount: i64| {
+        db.run_script(
+            r#"
+            { ?[id, bal] := *acc[id, b], id = 1, bal = b - $amount :put acc {id => bal} }
+            { ?[id, bal] := *acc[id, b], id = 2, bal = b + $amount :put acc {id => bal} }
+            { ?[id] := *acc[id, bal], bal < 0 :assert none }
+            "#,
+            BTreeMap::from([("amount".to_string(), DataValue::from(amount))]),
+            ScriptMutability::Mutable,
+        )
+    };
+    transfer(30).unwrap();
+    // the failing assertion rolls back the earlier statements
+    assert!(transfer(500).is_err());
+    let res = db.run_default("?[id, bal] := *acc[id, bal]").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1, 70], [2, 30]]));
+
+    // results of earlier statements are available through temp relations
+    let res = db
+        .run_default(
+            r#"
+            { ?[id] := *acc[id, bal], bal > 50 :replace _rich {id} }
+            { ?[id, bal] := *_rich[id], *acc[id, b], bal = b - 10 :put acc {id => bal} }
+            { ?[bal] := *acc[1, bal] }
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[60]]));
+}
+
+#[test]
+fn stored_views() {
+    let db = DbInstance::default();
+    db.run_default(":create edge {fr: Int, to: Int}").unwrap();
+    db.run_default("?[fr, to] <- [[1, 2], [2, 3], [3, 4]] :put edge {fr, to}")
+        .unwrap();
+    db.run_default(
+        r#"::view create reach {
+            reach[a, b] := *edge[a, b]
+            reach[a, b] := reach[a, c], *edge[c, b]
+        }"#,
+    )
+    .unwrap();
+    db.run_default("::view create reach_from_1 { reach_from_1[b] := reach[1, b] }")
+        .unwrap();
+    assert!(db
+        .run_default("::view create reach { reach[a] := *edge[a, _] }")
+        .is_err());
+    assert!(db
+        .run_default("::view create bad { helper[a] := *edge[a, _] }")
+        .is_err());
+    assert!(db
+        .run_default("::view create bad { bad[a] := *edge[a, _] :limit 1 }")
+        .is_err());
+
+    let res = db
+        .run_default("?[b] := reach_from_1[b]")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[2], [3], [4]]));
+    // views are evaluated on the current data
+    db.run_default("?[fr, to] <- [[4, 5]] :put edge {fr, to}")
+        .unwrap();
+    let res = db
+        .run_default("?[count(b)] := reach_from_1[b]")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[4]]));
+    // rules defined in the query shadow views
+    let res = db
+        .run_default("reach[a, b] <- [[0, 0]] ?[a, b] := reach[a, b]")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[0, 0]]));
+
+    let res = db.run_default("::views").unwrap().into_json();
+    assert_eq!(res["rows"].as_array().unwrap().len(), 2);
+    db.run_default("::view drop reach").unwrap();
+    assert!(db.run_default("?[b] := reach_from_1[b]").is_err());
+    assert!(db.run_default("::view drop reach").is_err());
+}
+
+#[test]
+fn trigger_shares_transaction() {
+    let db = DbInstance::default();
+    db.run_default(":create item {id: Int => price: Int}")
+        .unwrap();
+    db.run_default(":create total {k: Int => sum: Int}")
+        .unwrap();
+    db.run_default("?[k, sum] <- [[0, 0]] :put total {k => sum}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ::set_triggers item
+
+        on put {
+            added[sum(p)] := _new[_, p]
+            ?[k, sum] := *total[k, s], k = 0, added[a], sum = s + a
+            :put total {k => sum}
+        }
+        on put {
+            ?[id] := _new[id, price], price < 0
+            :assert none
+        }
+        "#,
+    )
+    .unwrap();
+    db.run_default("?[id, price] <- [[1, 10], [2, 5]] :put item {id => price}")
+        .unwrap();
+    let res = db
+        .run_default("?[sum] := *total[0, sum]")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[15]]));
+    // the failing trigger aborts the mutation and the work of the other trigger
+    assert!(db
+        .run_default("?[id, price] <- [[3, 7], [4, -1]] :put item {id => price}")
+        .is_err());
+    let res = db
+        .run_default("?[count(id)] := *item[id, _]")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[2]]));
+    let res = db
+        .run_default("?[sum] := *total[0, sum]")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[15]]));
+}
+
+#[test]
+fn unique_index() {
+    let db = DbInstance::default();
+    db.run_default(":create user {id: Int => email: String?, name: String}")
+        .unwrap();
+    db.run_default(
+        r#"?[id, email, name] <- [[1, "a@x", "a"], [2, "b@x", "b"], [3, null, "c"]]
+        :put user {id => email, name}"#,
+    )
+    .unwrap();
+    db.run_default("::unique create user:email {email}")
+        .unwrap();
+    let res = db.run_default("::indices user").unwrap().into_json();
+    assert_eq!(res["rows"][0][1], json!("unique"));
+
+    // conflicts with another row, including rows of the same batch
+    assert!(db
+        .run_default(r#"?[id, email, name] <- [[4, "a@x", "d"]] :put user {id => email, name}"#)
+        .is_err());
+    assert!(db
+        .run_default(
+            r#"?[id, email, name] <- [[4, "d@x", "d"], [5, "d@x", "e"]]
+            :put user {id => email, name}"#
+        )
+        .is_err());
+    assert!(db
+        .run_default(r#"?[id, email] <- [[2, "a@x"]] :update user {id => email}"#)
+        .is_err());
+    let res = db
+        .run_default("?[count(id)] := *user{id}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[3]]));
+
+    // rewriting a row with its own values, moving values and nulls are fine
+    db.run_default(r#"?[id, email, name] <- [[1, "a@x", "aa"]] :put user {id => email, name}"#)
+        .unwrap();
+    db.run_default(r#"?[id, email] <- [[2, "c@x"]] :update user {id => email}"#)
+        .unwrap();
+    db.run_default(
+        r#"?[id, email, name] <- [[4, "b@x", "d"], [5, null, "e"]] :put user {id => email, name}"#,
+    )
+    .unwrap();
+
+    // existing duplicates prevent creating the index
+    db.run_default("::index drop user:email").unwrap();
+    db.run_default(r#"?[id, email, name] <- [[6, "b@x", "f"]] :put user {id => email, name}"#)
+        .unwrap();
+    assert!(db
+        .run_default("::unique create user:email {email}")
+        .is_err());
+}
+
+#[test]
+fn referential_integrity_with_triggers() {
+    let db = DbInstance::default();
+    db.run_default(":create node {id: Int}").unwrap();
+    db.run_default(":create edge {fr: Int, to: Int}").unwrap();
+    db.run_default(
+        r#"
+        ::set_triggers edge
+
+        on put {
+            ?[id] := _new[id, _] or _new[_, id]
+            :ensure node {id}
+        }
+        "#,
+    )
+    .unwrap();
+    db.run_default(
+        r#"
+        ::set_triggers node
+
+        on rm {
+            ?[fr, to] := _old[id], *edge[fr, to], fr = id or to = id
+            :rm edge {fr, to}
+        }
+        "#,
+    )
+    .unwrap();
+    db.run_default("?[id] <- [[1], [2], [3]] :put node {id}")
+        .unwrap();
+    db.run_default("?[fr, to] <- [[1, 2], [2, 3], [3, 1]] :put edge {fr, to}")
+        .unwrap();
+    // dangling endpoints are rejected
+    assert!(db
+        .run_default("?[fr, to] <- [[1, 4]] :put edge {fr, to}")
+        .is_err());
+    // removing a node cascades to its edges
+    db.run_default("?[id] <- [[2]] :rm node {id}").unwrap();
+    let res = db
+        .run_default("?[fr, to] := *edge[fr, to]")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[3, 1]]));
+}
+
+#[test]
+fn column_defaults() {
+    let db = DbInstance::default();
+    db.run_default(
+        ":create log {id: Uuid default rand_uuid_v4() => msg: String, level: Int default 1, at: Float default now()}",
+    )
+    .unwrap();
+    db.run_default(r#"?[msg] <- [["a"], ["b"], ["c"]] :put log {msg}"#)
+        .unwrap();
+    db.run_default(r#"?[msg, level] <- [["d", 3]] :put log {msg, level}"#)
+        .unwrap();
+    // non-constant defaults are evaluated for every row
+    let res = db
+        .run_default("?[count_unique(id), count(at)] := *log{id, at}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[4, 4]]));
+    let res = db
+        .run_default("?[msg, level] := *log{msg, level}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["a", 1], ["b", 1], ["c", 1], ["d", 3]]));
+    // columns without defaults must be given
+    assert!(db
+        .run_default("?[level] <- [[2]] :put log {level}")
+        .is_err());
+}
+
+#[test]
+fn filters_on_nullable_columns() {
+    let db = DbInstance::default();
+    db.run_default(":create t {k: Int? => v: Int?}").unwrap();
+    db.run_default("?[k, v] <- [[null, 1], [1, null], [2, 5], [3, 10]] :put t {k => v}")
+        .unwrap();
+    let res = db
+        .run_default("?[k] := *t[k, v], v > 3")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[2], [3]]));
+    let res = db
+        .run_default("?[k] := *t[k, v], !(v > 3)")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[null]]));
+    // range scans on keys skip nulls as well
+    let res = db
+        .run_default("?[k] := *t[k, _], k < 3")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[1], [2]]));
+    // bounds on non-key columns must not restrict the key range
+    let res = db
+        .run_default("?[k] := *t[k, v], k >= 3, v > 7")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[3]]));
+    let res = db
+        .run_default("?[k, w] := *t[k, v], w = v + 1")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[null, 2], [1, null], [2, 6], [3, 11]]));
+    let res = db
+        .run_default("?[k] := *t[k, v], is_null(v) or v < 2")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[null], [1]]));
+    let res = db
+        .run_default("?[k, w] := *t[k, v], k != null, w = coalesce(v, 0)")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[1, 0], [2, 5], [3, 10]]));
+    // declared non-nullable columns still reject nulls
+    db.run_default(":create u {k: Int => v: Int}").unwrap();
+    assert!(db
+        .run_default("?[k, v] <- [[1, null]] :put u {k => v}")
+        .is_err());
+}
+
+#[test]
+fn filters_with_all_validity_keys_joined() {
+    let db = DbInstance::default();
+    check_filters_with_all_validity_keys_joined(|script| db.run_default(script));
+}
+
+/// Runs a join that binds every key column of a relation with validity, so that the scan of
+/// the relation has no bounds and goes through the skip scan of the storage
+fn check_filters_with_all_validity_keys_joined(run: impl Fn(&str) -> miette::Result<NamedRows>) {
+    run(":create t {k: Int, at: Validity => v: Int, w: Int}").unwrap();
+    run(":create u {k: Int, at: Validity => v: Int}").unwrap();
+    run("?[k, at, v, w] <- [[1, [1000, true], 2, 5]] :put t {k, at => v, w}").unwrap();
+    run("?[k, at, v] <- [[1, [1000, true], 2]] :put u {k, at => v}").unwrap();
+    // the join binds every key column of `t`, leaving none for the filters to bound
+    for (filter, expected) in [("w > 3", json!([[1]])), ("w > 7", json!([]))] {
+        let res = run(&format!(
+            "?[k] := *u{{k, at, v}}, *t{{k, at, v, w @ 'NOW'}}, {filter}"
+        ))
+        .unwrap()
+        .into_json();
+        assert_eq!(res["rows"], expected);
+    }
+}
+
+#[test]
+fn insert_type_validation() {
+    let db = DbInstance::default();
+    db.run_default(":create t {k: Int => score: Float, name: String}")
+        .unwrap();
+    // integers widen to floats
+    db.run_default("?[k, score, name] <- [[1, 2, 'a']] :put t {k => score, name}")
+        .unwrap();
+    let res = db
+        .run_default("?[score] := *t{k: 1, score}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[2.0]]));
+    // floats with a fractional part do not narrow to integers
+    let err = db
+        .run_default("?[k, score, name] <- [[1.5, 2, 'a']] :put t {k => score, name}")
+        .unwrap_err();
+    assert!(format!("{err:?}").contains("column 'k' of type Int"));
+    let err = db
+        .run_default("?[k, score, name] <- [[2, 3.0, 4]] :put t {k => score, name}")
+        .unwrap_err();
+    let msg = format!("{err:?}");
+    assert!(msg.contains("column 'name' of type String"));
+    assert!(msg.contains("in row [2, 3, 4]"));
+    let err = db
+        .run_default("?[k, score] <- [[1, 'x']] :update t {k => score}")
+        .unwrap_err();
+    assert!(format!("{err:?}").contains("column 'score'"));
+    let res = db.run_default("?[k] := *t{k}").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1]]));
+}
+
+#[test]
+fn alter_relation_columns() {
+    let db = DbInstance::default();
+    db.run_default(":create t {k: Int => v: Any}").unwrap();
+    db.run_default("?[k, v] <- [[1, 10], [2, 20]] :put t {k => v}")
+        .unwrap();
+
+    db.run_default("::alter t add w: String default 'x'")
+        .unwrap();
+    db.run_default("::alter t add note: String?").unwrap();
+    assert!(db.run_default("::alter t add z: Int").is_err());
+    assert!(db.run_default("::alter t add v: Int default 0").is_err());
+    let res = db
+        .run_default("?[k, v, w, note] := *t{k, v, w, note}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[1, 10, "x", null], [2, 20, "x", null]]));
+    // new writes see the new schema, with the new default applied
+    db.run_default("?[k, v, note] <- [[3, 30, null]] :put t {k => v, note}")
+        .unwrap();
+    let res = db.run_default("?[w] := *t{k: 3, w}").unwrap().into_json();
+    assert_eq!(res["rows"], json!([["x"]]));
+
+    db.run_default("::alter t set v: Float").unwrap();
+    let res = db.run_default("?[k, v] := *t{k, v}").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1, 10.0], [2, 20.0], [3, 30.0]]));
+    let err = db.run_default("::alter t set w: Int").unwrap_err();
+    assert!(format!("{err:?}").contains("column 'w' of type Int"));
+    let res = db.run_default("::columns t").unwrap().into_json();
+    assert_eq!(res["rows"][2][3], json!("String"));
+
+    db.run_default("::index create t:by_w {w}").unwrap();
+    assert!(db.run_default("::alter t drop w").is_err());
+    assert!(db.run_default("::alter t drop k").is_err());
+    db.run_default("::alter t drop v").unwrap();
+    let res = db
+        .run_default("?[k, w, note] := *t{k, w, note}")
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([[1, "x", null], [2, "x", null], [3, "x", null]])
+    );
+    assert!(db.run_default("?[k, v] := *t{k, v}").is_err());
+    // the index keeps pointing at the right column after the drop
+    db.run_default("?[k, w, note] <- [[4, 'y', null]] :put t {k => w, note}")
+        .unwrap();
+    let res = db
+        .run_default("?[k] := *t:by_w{w: 'y', k}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[4]]));
+}
+
+#[test]
+fn rename_relations_and_columns() {
+    let db = DbInstance::default();
+    db.run_default(":create a {k: Int => v: Int}").unwrap();
+    db.run_default("?[k, v] <- [[1, 10]] :put a {k => v}")
+        .unwrap();
+    db.run_default("::index create a:by_v {v}").unwrap();
+    db.run_default("::rename a -> b").unwrap();
+    let res = db.run_default("::relations").unwrap().into_json();
+    assert_eq!(res["rows"][0][0], json!("b"));
+    assert_eq!(res["rows"][1][0], json!("b:by_v"));
+    db.run_default("?[k, v] <- [[2, 20]] :put b {k => v}")
+        .unwrap();
+    let res = db
+        .run_default("?[k] := *b:by_v{v: 20, k}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[2]]));
+
+    db.run_default("::alter b rename v -> w").unwrap();
+    assert!(db.run_default("::alter b rename k -> w").is_err());
+    assert!(db.run_default("::alter b rename x -> y").is_err());
+    let res = db.run_default("?[k, w] := *b{k, w}").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1, 10], [2, 20]]));
+    assert!(db.run_default("?[k, v] := *b{k, v}").is_err());
+    let res = db
+        .run_default("?[k] := *b:by_v{w: 10, k}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[1]]));
+
+    db.run_default("::index drop b:by_v").unwrap();
+    db.run_default("::remove b").unwrap();
+    let res = db.run_default("::relations").unwrap().into_json();
+    assert_eq!(res["rows"], json!([]));
+}
+
+#[test]
+fn disjunction_in_rule_bodies() {
+    let db = DbInstance::default();
+    db.run_default(":create person {name: String => age: Int, city: String}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[name, age, city] <- [['a', 10, 'x'], ['b', 30, 'y'], ['c', 50, 'x'], ['d', 70, 'z']]
+        :put person {name => age, city}
+        "#,
+    )
+    .unwrap();
+    let res = db
+        .run_default(
+            r#"
+            ?[name] := *person{name, age}, age < 20 or *person{name, city: 'z'}
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["a"], ["d"]]));
+    // groups of atoms on either side, with bindings shared by all branches
+    let res = db
+        .run_default(
+            r#"
+            ?[name, tag] := (*person{name, city: 'x'}, tag = 'east')
+                            or (*person{name, age}, age > 25, age < 60, tag = 'middle')
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([
+            ["a", "east"],
+            ["b", "middle"],
+            ["c", "east"],
+            ["c", "middle"]
+        ])
+    );
+    let res = db
+        .run_default(
+            r#"
+            ?[name] := *person{name, city}, not (city == 'x' or city == 'y')
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["d"]]));
+}
+
+#[test]
+fn query_timeout() {
+    let db = DbInstance::default();
+    let runaway = r#"
+        nat[n] := n = 0
+        nat[m] := nat[n], m = n + 1
+        ?[n] := nat[n], n < 0
+    "#;
+    let err = db
+        .run_default(&format!("{runaway} :timeout 0.1"))
+        .unwrap_err();
+    let msg = err.to_string();
+    assert!(msg.starts_with("Query timed out after"), "{msg}");
+    assert!(msg.contains("while evaluating rule"), "{msg}");
+
+    db.set_timeout(Some(0.1));
+    let err = db.run_default(runaway).unwrap_err();
+    assert!(err.to_string().starts_with("Query timed out after"));
+    // the option takes precedence over the default
+    let res = db
+        .run_default("?[n] := n = 1 :timeout 10")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[1]]));
+    db.set_timeout(None);
+    let res = db.run_default("?[n] := n = 1").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1]]));
+}
+
+#[test]
+fn query_timeouts_out_of_order() {
+    let db = DbInstance::default();
+    db.set_timeout(Some(60.));
+    for _ in 0..3000 {
+        db.run_default("?[n] := n = 1").unwrap();
+    }
+    // fires while the earlier, longer timeouts are still pending
+    let err = db
+        .run_default(
+            r#"
+            nat[n] := n = 0
+            nat[m] := nat[n], m = n + 1
+            ?[n] := nat[n], n < 0
+            :timeout 0.1
+            "#,
+        )
+        .unwrap_err();
+    assert!(err.to_string().starts_with("Query timed out after"));
+}
+
+#[test]
+fn rm_driven_by_query() {
+    let db = DbInstance::default();
+    db.run_default(":create item {id: Int => owner: String}")
+        .unwrap();
+    db.run_default(":create banned {owner: String}").unwrap();
+    db.run_default("::index create item:by_owner {owner}")
+        .unwrap();
+    db.run_default(
+        "?[id, owner] <- [[1, 'a'], [2, 'b'], [3, 'a'], [4, 'c']] :put item {id => owner}",
+    )
+    .unwrap();
+    db.run_default("?[owner] <- [['a'], ['c']] :put banned {owner}")
+        .unwrap();
+
+    let res = db
+        .run_default(
+            r#"
+            ?[id] := *banned{owner}, *item:by_owner{owner, id}
+            :returning
+            :rm item {id}
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([
+            ["requested", 1, null],
+            ["requested", 3, null],
+            ["requested", 4, null],
+            ["deleted", 1, "a"],
+            ["deleted", 3, "a"],
+            ["deleted", 4, "c"]
+        ])
+    );
+    let res = db.run_default("?[id] := *item{id}").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[2]]));
+    let res = db
+        .run_default("?[owner, id] := *item:by_owner{owner, id}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["b", 2]]));
+    // `:delete` insists that every key exists
+    assert!(db
+        .run_default("?[id] := id in [2, 3] :delete item {id}")
+        .is_err());
+    let res = db.run_default("?[id] := *item{id}").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[2]]));
+}
+
+#[test]
+fn partial_update() {
+    let db = DbInstance::default();
+    db.run_default(":create t {k: Int => a: Int, b: String default 'x', c: Float}")
+        .unwrap();
+    db.run_default("?[k, a, b, c] <- [[1, 10, 'p', 0.5], [2, 20, 'q', 1.5]] :put t {k => a, b, c}")
+        .unwrap();
+    // only the given columns change, the others keep their stored values instead of defaults
+    db.run_default("?[k, a] <- [[1, 11]] :update t {k => a}")
+        .unwrap();
+    db.run_default("?[k, c] := *t{k, c: old}, k = 2, c = old * 2 :update t {k => c}")
+        .unwrap();
+    let res = db
+        .run_default("?[k, a, b, c] := *t{k, a, b, c}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[1, 11, "p", 0.5], [2, 20, "q", 3.0]]));
+    // a missing key fails the whole statement
+    let err = db
+        .run_default("?[k, b] <- [[1, 'r'], [3, 's']] :update t {k => b}")
+        .unwrap_err();
+    assert!(format!("{err:?}").contains("key to update does not exist"));
+    let res = db.run_default("?[k, b] := *t{k, b}").unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1, "p"], [2, "q"]]));
+}
+
+#[test]
+fn put_with_merge() {
+    let db = DbInstance::default();
+    db.run_default(":create counts {word: String => n: Int, tags: Any, seen: Float}")
+        .unwrap();
+    let put = ":put counts {word => n merge _old + _new, tags merge union(_old, _new), seen}";
+    db.run_default(&format!(
+        "?[word, n, tags, seen] <- [['a', 1, ['x'], 1], ['b', 1, ['y'], 1]] {put}"
+    ))
+    .unwrap();
+    db.run_default(&format!(
+        "?[word, n, tags, seen] <- [['a', 2, ['z'], 2], ['c', 5, [], 2]] {put}"
+    ))
+    .unwrap();
+    let res = db
+        .run_default("?[word, n, tags, seen] := *counts{word, n, tags, seen}")
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([
+            ["a", 3, ["x", "z"], 2.0],
+            ["b", 1, ["y"], 1.0],
+            ["c", 5, [], 2.0]
+        ])
+    );
+    // merged values are still checked against the column type
+    let err = db
+        .run_default("?[word, n, tags, seen] <- [['a', 1, [], 0]] :put counts {word => n merge to_string(_old), tags, seen}")
+        .unwrap_err();
+    assert!(format!("{err:?}").contains("column 'n'"));
+    assert!(db
+        .run_default("?[word, n, tags, seen] <- [['a', 1, [], 0]] :put counts {word merge _new, n, tags, seen}")
+        .is_err());
+    assert!(db
+        .run_default("?[word, n] <- [['a', 1]] :update counts {word => n merge _old + _new}")
+        .is_err());
+}
+
+#[test]
+fn returning_mutated_rows() {
+    let db = DbInstance::default();
+    db.run_default(":create ctr {id: Int default 7 => n: Int, label: String default 'none'}")
+        .unwrap();
+    let res = db
+        .run_default("?[n] <- [[1]] :insert ctr {n} :returning")
+        .unwrap();
+    assert_eq!(res.headers, vec!["_kind", "id", "n", "label"]);
+    assert_eq!(res.into_json()["rows"], json!([["inserted", 7, 1, "none"]]));
+    // merged values are reported as written, along with the replaced row
+    let res = db
+        .run_default("?[id, n, label] <- [[7, 5, 'x']] :put ctr {id => n merge _old + _new, label} :returning")
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([["inserted", 7, 6, "x"], ["replaced", 7, 1, "none"]])
+    );
+    let res = db
+        .run_default("?[id] <- [[7], [8]] :rm ctr {id} :returning")
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([
+            ["requested", 7, null, null],
+            ["requested", 8, null, null],
+            ["deleted", 7, 6, "x"]
+        ])
+    );
+}
+
+#[test]
+fn insert_or_ignore() {
+    let db = DbInstance::default();
+    db.run_default(":create kv {k: Int => v: String}").unwrap();
+    db.run_default("?[k, v] <- [[1, 'a'], [2, 'b']] :insert kv {k => v}")
+        .unwrap();
+    let res = db
+        .run_default("?[k, v] <- [[1, 'x'], [2, 'y'], [3, 'z']] :insert_or_ignore kv {k => v}")
+        .unwrap();
+    assert_eq!(res.headers, vec!["status", "skipped"]);
+    assert_eq!(res.into_json()["rows"], json!([["OK", 2]]));
+    let res = db.run_default("?[k, v] := *kv[k, v]").unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([[1, "a"], [2, "b"], [3, "z"]])
+    );
+    // skipped rows do not show up among the returned ones
+    let res = db
+        .run_default("?[k, v] <- [[3, 'q'], [4, 'd']] :insert_or_ignore kv {k => v} :returning")
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["inserted", 4, "d"]]));
+    // plain inserts still fail on existing keys
+    assert!(db
+        .run_default("?[k, v] <- [[4, 'e']] :insert kv {k => v}")
+        .is_err());
+}
+
+#[test]
+fn stored_procedures() {
+    let db = DbInstance::default();
+    db.run_default(":create account {id: Int => balance: Int}")
+        .unwrap();
+    db.run_default("?[id, balance] <- [[1, 100], [2, 0]] :put account {id => balance}")
+        .unwrap();
+    db.run_default(
+        r"
+        ::procedure create transfer($from, $to, $amount) {
+            {?[id, balance] := *account{id, balance: b}, id = $from, balance = b - $amount
+             :assert some
+             :update account {id => balance}}
+            {?[id, balance] := *account{id, balance: b}, id = $to, balance = b + $amount
+             :update account {id => balance}}
+            {?[id, balance] := *account{id, balance}, balance < 0 :assert none}
+        }
+        ",
+    )
+    .unwrap();
+    db.run_default("::procedure create balance_of($id) { ?[b] := *account{id: $id, balance: b} }")
+        .unwrap();
+
+    db.run_default("::call transfer(1, 2, 30)").unwrap();
+    let res = db.run_default("::call balance_of(2)").unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[30]]));
+
+    // a failing procedure leaves no partial writes behind
+    assert!(db.run_default("::call transfer(1, 2, 500)").is_err());
+    let res = db
+        .run_default("?[id, b] := *account{id, balance: b}")
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[1, 70], [2, 30]]));
+
+    let err = db.run_default("::call balance_of(1, 2)").unwrap_err();
+    assert!(format!("{err:?}").contains("takes 1 arguments but 2 were given"));
+    assert!(db
+        .run_default("::procedure create balance_of($x) { ?[x] <- [[$x]] }")
+        .is_err());
+    // parameters used in the body must be declared
+    let err = db
+        .run_default("::procedure create get_it($x) { ?[y] <- [[$x, $y]] }")
+        .unwrap_err();
+    assert!(err.to_string().contains("$y"));
+
+    let res = db.run_default("::procedures").unwrap();
+    assert_eq!(res.headers, vec!["name", "params", "body"]);
+    let res = res.into_json();
+    assert_eq!(res["rows"][0][0], json!("balance_of"));
+    assert_eq!(res["rows"][1][1], json!(["$from", "$to", "$amount"]));
+
+    db.run_default("::procedure drop balance_of").unwrap();
+    assert!(db.run_default("::call balance_of(1)").is_err());
+    assert!(db.run_default("::procedure drop balance_of").is_err());
+}
+
+#[test]
+fn explain_query_plan() {
+    let db = DbInstance::default();
+    db.run_default(":create edge {fr: Int, to: Int}").unwrap();
+    db.run_default(":create blocked {node: Int}").unwrap();
+    let res = db
+        .run_default(
+            r"::explain {
+            reach[n] := n = 1
+            reach[to] := reach[fr], *edge{fr, to}
+            bad[n] := *blocked{node: n}, reach[n]
+            ?[n] := reach[n], not bad[n]
+        }",
+        )
+        .unwrap();
+    assert_eq!(
+        res.headers,
+        vec![
+            "stratum",
+            "rule_idx",
+            "rule",
+            "atom_idx",
+            "op",
+            "ref",
+            "joins_on",
+            "filters/expr",
+            "out_relation"
+        ]
+    );
+    let rows = res.into_json()["rows"].as_array().unwrap().clone();
+    let stratum_of = |rule: &str| {
+        rows.iter()
+            .find(|r| r[2] == json!(rule))
+            .map(|r| r[0].as_u64().unwrap())
+            .unwrap()
+    };
+    // the negated rule has to be fully computed before the entry is evaluated
+    assert!(stratum_of("bad") < stratum_of("?"));
+    assert_eq!(stratum_of("reach"), stratum_of("bad"));
+    // the recursive step scans the stored relation with its first key column bound
+    assert!(rows.contains(&json!([
+        0,
+        2,
+        "reach",
+        1,
+        "stored_prefix_join",
+        null,
+        {"fr": "**0"},
+        null,
+        ["to"]
+    ])));
+    assert!(rows
+        .iter()
+        .any(|r| r[2] == json!("?") && r[4] == json!("mem_neg_prefix_join")));
+}
+
+#[test]
+fn explain_analyze() {
+    let db = DbInstance::default();
+    db.run_default(":create edge {fr: Int, to: Int}").unwrap();
+    db.run_default("?[fr, to] <- [[1, 2], [2, 3], [3, 4], [10, 11]] :put edge {fr, to}")
+        .unwrap();
+    let res = db
+        .run_default(
+            r"::explain analyze {
+            reach[n] := n = 1
+            reach[to] := reach[fr], *edge{fr, to}
+            ?[n] := reach[n]
+        }",
+        )
+        .unwrap();
+    // the plan comes first, as with a plain ::explain
+    assert_eq!(res.headers[0], "stratum");
+    assert!(!res.rows.is_empty());
+    let stats = res.next.unwrap();
+    assert_eq!(
+        stats.headers,
+        vec!["stratum", "rule", "epoch", "metric", "value"]
+    );
+    let rows = stats.into_json()["rows"].as_array().unwrap().clone();
+    let metric = |rule: serde_json::Value, name: &str| {
+        rows.iter()
+            .filter(|r| r[1] == rule && r[3] == json!(name))
+            .map(|r| r[4].clone())
+            .collect_vec()
+    };
+    assert_eq!(
+        metric(json!("reach|Mf"), "delta_rows"),
+        vec![json!(1), json!(1), json!(1), json!(1), json!(0), json!(0)]
+    );
+    assert_eq!(metric(json!("reach|Mf"), "rows"), vec![json!(4)]);
+    assert_eq!(metric(json!("?"), "rows"), vec![json!(4)]);
+    assert_eq!(metric(json!(null), "epochs"), vec![json!(6)]);
+    assert!(metric(json!(null), "elapsed_secs")[0].as_f64().unwrap() >= 0.);
+    // one prefix scan of the stored relation for each node reached
+    assert_eq!(metric(json!(null), "storage_scans"), vec![json!(4)]);
+    assert_eq!(metric(json!(null), "storage_gets"), vec![json!(0)]);
+}
+
+#[test]
+fn schema_introspection() {
+    let db = DbInstance::default();
+    db.run_default(
+        ":create doc {id: Uuid, rev: Int default 0 => title: String, tags: [String]?, emb: <F32; 3>}",
+    )
+    .unwrap();
+    let res = db.run_default("::relations").unwrap();
+    assert_eq!(
+        res.headers,
+        vec![
+            "name",
+            "arity",
+            "access_level",
+            "n_keys",
+            "n_non_keys",
+            "n_put_triggers",
+            "n_rm_triggers",
+            "n_replace_triggers",
+            "description"
+        ]
+    );
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([["doc", 5, "normal", 2, 3, 0, 0, 0, ""]])
+    );
+    let res = db.run_default("::columns doc").unwrap();
+    assert_eq!(
+        res.headers,
+        vec![
+            "column",
+            "is_key",
+            "index",
+            "type",
+            "has_default",
+            "default_expr"
+        ]
+    );
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([
+            ["id", true, 0, "Uuid", false, null],
+            ["rev", true, 1, "Int", true, "0"],
+            ["title", false, 2, "String", false, null],
+            ["tags", false, 3, "[String]?", false, null],
+            ["emb", false, 4, "<F32;3>", false, null]
+        ])
+    );
+    assert!(db.run_default("::columns nope").is_err());
+}
+
+#[test]
+fn relation_access_levels() {
+    let db = DbInstance::default();
+    db.run_default(":create ref {k: Int => v: Int, exp: Float default 0.}")
+        .unwrap();
+    db.run_default("?[k, v] <- [[1, 10], [2, 20]] :put ref {k => v}")
+        .unwrap();
+
+    // protected: rows can change, the relation itself cannot
+    db.run_default("::access_level protected ref").unwrap();
+    db.run_default("?[k, v] <- [[3, 30]] :put ref {k => v}")
+        .unwrap();
+    db.run_default("?[k] <- [[3]] :rm ref {k}").unwrap();
+    for script in [
+        "::remove ref",
+        "::rename ref -> other",
+        "::alter ref add w: Int default 0",
+        "::index create ref:by_v {v}",
+        "::set_ttl ref exp",
+        "?[k, v] <- [[1, 1]] :replace ref {k => v}",
+    ] {
+        let err = db.run_default(script).unwrap_err();
+        assert!(
+            format!("{err:?}").contains("Insufficient access level protected"),
+            "{script}: {err:?}"
+        );
+    }
+
+    // read only: no writes at all, but reads are fine
+    db.run_default("::access_level read_only ref").unwrap();
+    assert!(db
+        .run_default("?[k, v] <- [[3, 30]] :put ref {k => v}")
+        .is_err());
+    assert!(db.run_default("?[k] <- [[1]] :rm ref {k}").is_err());
+    let res = db.run_default("?[k, v] := *ref{k, v}").unwrap();
+    assert_eq!(res.rows.len(), 2);
+
+    // hidden: not even reads
+    db.run_default("::access_level hidden ref").unwrap();
+    assert!(db.run_default("?[k, v] := *ref{k, v}").is_err());
+
+    db.run_default("::access_level normal ref").unwrap();
+    db.run_default("::index create ref:by_v {v}").unwrap();
+    db.run_default("::set_ttl ref exp").unwrap();
+    // compaction leaves the expired rows of read-only relations alone
+    db.run_default("::access_level read_only ref").unwrap();
+    db.run_default("::compact").unwrap();
+    db.run_default("::access_level normal ref").unwrap();
+    db.run_default("::index drop ref:by_v").unwrap();
+    db.run_default("::remove ref").unwrap();
+}
+
+#[test]
+fn time_travel_queries() {
+    let db = DbInstance::default();
+    db.run_default(":create price {item: String, at: Validity => amount: Int}")
+        .unwrap();
+    db.run_default(
+        r"?[item, at, amount] <- [
+            ['apple', [10, true], 1],
+            ['apple', [20, true], 2],
+            ['apple', [30, false], 0],
+            ['pear', [15, true], 5],
+            ['pear', [25, true], 6]
+        ] :put price {item, at => amount}",
+    )
+    .unwrap();
+    let as_of = |ts: &str| {
+        db.run_default(&format!("?[item, amount] := *price{{item, amount @ {ts}}}"))
+            .unwrap()
+            .into_json()["rows"]
+            .clone()
+    };
+    assert_eq!(as_of("5"), json!([]));
+    assert_eq!(as_of("10"), json!([["apple", 1]]));
+    assert_eq!(as_of("19"), json!([["apple", 1], ["pear", 5]]));
+    assert_eq!(as_of("20"), json!([["apple", 2], ["pear", 5]]));
+    // the retraction at 30 hides apple from then on
+    assert_eq!(as_of("30"), json!([["pear", 6]]));
+    assert_eq!(as_of("'NOW'"), json!([["pear", 6]]));
+    assert_eq!(as_of("'END'"), json!([["pear", 6]]));
+    // bound keys go through the same skip scan
+    let res = db
+        .run_default("?[amount] := *price{item: 'apple', amount @ 25}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[2]]));
+    // without a timestamp all versions are visible
+    let res = db.run_default("?[item, at] := *price{item, at}").unwrap();
+    assert_eq!(res.rows.len(), 5);
+    // only relations keyed by a validity can be queried as of a time
+    db.run_default(":create plain {k: Int => v: Int}").unwrap();
+    assert!(db.run_default("?[k] := *plain{k @ 'NOW'}").is_err());
+}
+
+#[test]
+fn imperative_control_flow() {
+    let db = DbInstance::default();
+    // iterate a transitive closure to a fixed point, one hop per round
+    db.run_default(":create edge {fr: Int, to: Int}").unwrap();
+    db.run_default("?[fr, to] <- [[1, 2], [2, 3], [3, 4]] :put edge {fr, to}")
+        .unwrap();
+    let res = db
+        .run_default(
+            r"
+        {?[n] <- [[1]] :replace _seen {n}}
+        %loop
+            {?[to] := *_seen{n: fr}, *edge{fr, to}, not *_seen{n: to}} as _new
+            %if_not _new %then %break %end
+            {?[n] := *_new[n] :put _seen {n}}
+        %end
+        %return _seen
+        ",
+        )
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[1], [2], [3], [4]]));
+
+    // conditions, labelled breaks and continues
+    let res = db
+        .run_default(
+            r"
+        {?[i] <- [[0]] :replace _i {i}}
+        {:create _evens {i}}
+        %mark outer
+        %loop
+            {?[i] := *_i[j], i = j + 1 :replace _i {i}}
+            %if {?[i] := *_i[i], i > 6}
+            %then %break outer
+            %end
+            %if {?[i] := *_i[i], i % 2 == 1}
+            %then %continue outer
+            %else {?[i] := *_i[i] :put _evens {i}}
+            %end
+        %end
+        %return _evens
+        ",
+        )
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[2], [4], [6]]));
+
+    // swapping temp relations, ignoring errors and returning several results
+    let res = db
+        .run_default(
+            r"
+        {?[a] <- [[1], [2]] :replace _x {a}}
+        {?[a] <- [[3]] :replace _y {a}}
+        %swap _x _y
+        %ignore_error {?[a] <- [[1]] :create _x {a}}
+        %return _x, {?[n] := n = 42}
+        ",
+        )
+        .unwrap();
+    assert_eq!(res.rows, vec![vec![DataValue::from(3)]]);
+    let next = res.next.unwrap();
+    assert_eq!(next.rows, vec![vec![DataValue::from(42)]]);
+}
+
+#[test]
+fn query_cursor() {
+    let db = DbInstance::default();
+    db.run_default(r"?[n] <~ Constant(data: [[0], [1], [2], [3], [4], [5], [6], [7], [8], [9]]) :create nums {n}")
+        .unwrap();
+
+    let mut cursor = db
+        .open_cursor(
+            "?[n] := *nums{n}, n >= $min",
+            BTreeMap::from([("min".to_string(), DataValue::from(0))]),
+            4,
+        )
+        .unwrap();
+    assert_eq!(cursor.headers(), ["n"]);
+    assert_eq!(cursor.remaining(), 10);
+
+    // writes made after opening the cursor are not seen by it
+    db.run_default("?[n] <- [[100]] :put nums {n}").unwrap();
+
+    let batch = cursor.next_batch().unwrap();
+    assert_eq!(batch.headers, ["n"]);
+    assert_eq!(batch.into_json()["rows"], json!([[0], [1], [2], [3]]));
+    assert_eq!(cursor.remaining(), 6);
+    let rest: Vec<_> = cursor.by_ref().map(|b| b.rows.len()).collect();
+    assert_eq!(rest, [4, 2]);
+    assert!(cursor.next_batch().is_none());
+
+    let mut empty = db
+        .open_cursor("?[n] := *nums{n}, n < 0", Default::default(), 4)
+        .unwrap();
+    assert!(empty.next_batch().is_none());
+
+    assert!(db
+        .open_cursor("?[n] := *nums{n}", Default::default(), 0)
+        .is_err());
+    assert!(db
+        .open_cursor("?[n] <- [[200]] :put nums {n}", Default::default(), 4)
+        .is_err());
+}
+
+#[test]
+fn subqueries() {
+    let db = DbInstance::default();
+    db.run_default(
+        r"?[fr, to] <- [['a', 'b'], ['a', 'c'], ['a', 'd'], ['b', 'c'], ['c', 'd'], ['d', 'a'], ['e', 'a']]
+          :create edge {fr, to}",
+    )
+    .unwrap();
+    let rows = |script: &str| db.run_default(script).unwrap().into_json()["rows"].clone();
+
+    // nodes whose out-degree exceeds the average
+    assert_eq!(
+        rows(
+            r"deg[n, count(m)] := *edge{fr: n, to: m}
+               ?[n, d] := deg[n, d], d > { ?[mean(d)] := deg[_, d] }"
+        ),
+        json!([["a", 3]])
+    );
+    // an empty scalar subquery gives null, more than one row is an error
+    assert_eq!(
+        rows("?[x] := x = { ?[t] := *edge{fr: 'z', to: t} }"),
+        json!([[null]])
+    );
+    assert!(db
+        .run_default("?[x] := x = { ?[t] := *edge{fr: 'a', to: t} }")
+        .is_err());
+    assert!(db
+        .run_default("?[x] := x = { ?[a, b] <- [[1, 2]] }")
+        .is_err());
+
+    // correlated existential subqueries, possibly negated or nested
+    assert_eq!(
+        rows("?[n] := *edge{fr: n}, exists(*edge{fr: n, to: 'c'})"),
+        json!([["a"], ["b"]])
+    );
+    assert_eq!(
+        rows("?[n] := *edge{fr: n}, not exists(*edge{fr: n, to: 'c'})"),
+        json!([["c"], ["d"], ["e"]])
+    );
+    assert_eq!(
+        rows(
+            "?[n] := *edge{fr: n, to: m}, exists(*edge{fr: m, to: k}, exists(*edge{fr: k, to: n}))"
+        ),
+        json!([["a"], ["c"], ["d"]])
+    );
+    // uncorrelated ones either keep or discard every row
+    assert_eq!(
+        rows("?[n] := *edge{fr: n}, exists(*edge{fr: 'z'})"),
+        json!([])
+    );
+    assert_eq!(
+        rows("?[n] := n = 'x', exists(*edge{fr: 'e'})"),
+        json!([["x"]])
+    );
+    // scalar subqueries may appear inside existential ones
+    assert_eq!(
+        rows("?[n] := *edge{fr: n}, exists(*edge{fr: n, to: t}, t == { ?[x] := x = 'd' })"),
+        json!([["a"], ["c"]])
+    );
+    assert!(db
+        .run_default("?[n] := *edge{fr: n}, exists(*edge{fr: m}, m > n)")
+        .is_err());
+}
+
+#[test]
+fn variable_length_traversal() {
+    let db = DbInstance::default();
+    let edges = "edge[a, b] <- [[1, 2], [2, 3], [3, 4], [4, 1], [4, 5]]";
+    let rows =
+        |q: &str| db.run_default(&format!("{edges} {q}")).unwrap().into_json()["rows"].clone();
+
+    assert_eq!(
+        rows("?[x, y] := edge*1..2[x, y], x <= 2"),
+        json!([[1, 2], [1, 3], [2, 3], [2, 4]])
+    );
+    assert_eq!(rows("?[y] := edge*2..3[1, y]"), json!([[3], [4]]));
+    assert_eq!(rows("?[y] := x = 1, edge*4..4[x, y]"), json!([[1], [5]]));
+    assert_eq!(
+        rows("?[y] := y in [1, 2, 3, 4, 5], not edge*1..2[1, y]"),
+        json!([[1], [4], [5]])
+    );
+
+    // with a bound starting node, the expansion goes through the magic set rewrite
+    let plan = db
+        .run_default(&format!("::explain {{ {edges} ?[y] := edge*2..3[1, y] }}"))
+        .unwrap();
+    assert!(plan.rows.iter().any(|row| {
+        let rule = row[2].get_str().unwrap();
+        rule.starts_with("*hop_depth*") && rule.ends_with("|Mbff")
+    }));
+
+    assert!(db
+        .run_default(&format!("{edges} ?[y] := edge*0..3[1, y]"))
+        .is_err());
+    assert!(db
+        .run_default(&format!("{edges} ?[y] := edge*3..2[1, y]"))
+        .is_err());
+    assert!(db
+        .run_default(&format!("{edges} ?[y, z] := edge*1..3[1, y, z]"))
+        .is_err());
+
+    // stored relations can be traversed as well
+    db.run_default(":create road {fr: Int, to: Int}").unwrap();
+    db.run_default("?[fr, to] <- [[1, 2], [2, 3], [3, 4], [4, 1], [4, 5]] :put road {fr, to}")
+        .unwrap();
+    let res = db
+        .run_default("?[y] := *road*2..3[1, y]")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[3], [4]]));
+    db.run_default(":create weighted {fr: Int, to: Int => w: Float}")
+        .unwrap();
+    assert!(db.run_default("?[y] := *weighted*1..2[1, y]").is_err());
+}
+
+#[test]
+fn timestamp_columns() {
+    let db = DbInstance::default();
+    db.run_default(":create events {at: Timestamp => name: String}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[at, name] <- [['2023-12-31T23:59:59.999999999Z', 'eve'],
+                        [0, 'epoch'],
+                        [-0.5, 'before'],
+                        [parse_ts('2024-01-01T00:00:00+00:00'), 'new year']]
+        :put events {at => name}
+        "#,
+    )
+    .unwrap();
+    assert!(db
+        .run_default("?[at, name] <- [['tomorrow', 'x']] :put events {at => name}")
+        .is_err());
+
+    // keys come back in chronological order, also across the epoch
+    let res = db
+        .run_default("?[at, name] := *events{at, name}")
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([
+            ["1969-12-31T23:59:59.500Z", "before"],
+            ["1970-01-01T00:00:00Z", "epoch"],
+            ["2023-12-31T23:59:59.999999999Z", "eve"],
+            ["2024-01-01T00:00:00Z", "new year"]
+        ])
+    );
+
+    let res = db
+        .run_default(
+            r#"
+            ?[name, y, gap] := *events{at, name},
+                               at >= to_ts('2000-01-01T00:00:00Z'),
+                               y = ts_year(at),
+                               gap = parse_ts('2024-01-01T00:00:00Z') - at
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([["eve", 2023, 1e-9], ["new year", 2024, 0]])
+    );
+}
+
+#[test]
+fn uuid_literals_and_v7_keys() {
+    let db = DbInstance::default();
+    db.run_default(":create items {id: Uuid => n: Int}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[id, n] <- [[u'018f22e2-79b0-70c3-98c4-dc0c0c07398f', 3],
+                     [u"017f22e2-79b0-7cc3-98c4-dc0c0c07398f", 1],
+                     [to_uuid('017f22e2-79b1-7000-8000-000000000000'), 2]]
+        :put items {id => n}
+        "#,
+    )
+    .unwrap();
+    for _ in 0..20 {
+        db.run_default("?[id, n] := id = rand_uuid_v7(), n = 4 :put items {id => n}")
+            .unwrap();
+    }
+
+    // version 7 UUIDs are stored in the order of their timestamps
+    let res = db.run_default("?[id, n] := *items{id, n}").unwrap();
+    let ns: Vec<_> = res
+        .rows
+        .iter()
+        .map(|row| row[1].get_int().unwrap())
+        .collect();
+    let mut sorted = ns.clone();
+    sorted.sort();
+    assert_eq!(ns, sorted);
+
+    let res = db
+        .run_default("?[n] := *items{id: u'017f22e2-79b0-7cc3-98c4-dc0c0c07398f', n}")
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[1]]));
+    let res = db.run_default("?[u] := u = 1").unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[1]]));
+    assert!(db.run_default("?[u] := u = u'not-a-uuid'").is_err());
+}
+
+#[test]
+fn upgrade_uuid_v7_keys() {
+    let db = new_cozo_mem().unwrap();
+    let run = |script: &str| {
+        db.run_script(script, Default::default(), ScriptMutability::Mutable)
+            .unwrap()
+            .into_json()["rows"]
+            .clone()
+    };
+    run(":create items {id: Uuid => n: Int}");
+    run("?[id, n] <- [[u'017f22e2-79b0-7cc3-98c4-dc0c0c07398f', 1]] :put items {id => n}");
+
+    // write the key and the storage version as the previous version did
+    let version_key = vec![DataValue::Null, DataValue::from("STORAGE_VERSION")]
+        .encode_as_key(RelationId::SYSTEM);
+    {
+        let mut tx = db.transact_write().unwrap();
+        let rel_id = tx.get_relation("items", false).unwrap().id;
+        let id = Uuid::parse_str("017f22e2-79b0-7cc3-98c4-dc0c0c07398f").unwrap();
+        let key = vec![DataValue::Uuid(UuidWrapper(id))].encode_as_key(rel_id);
+        let val = tx.store_tx.get(&key, false).unwrap().unwrap();
+        tx.store_tx.del(&key).unwrap();
+        let mut old_key = rel_id.raw_encode().to_vec();
+        old_key.extend([0x08, 0x7c, 0xc3, 0x79, 0xb0, 0x01, 0x7f, 0x22, 0xe2]);
+        old_key.extend(&id.as_bytes()[8..]);
+        tx.store_tx.put(&old_key, &val).unwrap();
+        tx.store_tx.put(&version_key, &[0x00]).unwrap();
+        tx.commit_tx().unwrap();
+    }
+
+    let q = "?[n] := *items{id: u'017f22e2-79b0-7cc3-98c4-dc0c0c07398f', n}";
+    assert_eq!(run(q), json!([]));
+    db.initialize().unwrap();
+    assert_eq!(run(q), json!([[1]]));
+    let tx = db.transact().unwrap();
+    let version = tx.store_tx.get(&version_key, false).unwrap().unwrap();
+    assert_eq!(version, CURRENT_STORAGE_VERSION);
+}
+
+#[test]
+fn upgrade_uuid_v7_keys_in_batches() {
+    let db = new_cozo_mem().unwrap();
+    let run = |script: &str, params: BTreeMap<String, DataValue>| {
+        db.run_script(script, params, ScriptMutability::Mutable)
+            .unwrap()
+            .into_json()["rows"]
+            .clone()
+    };
+    let ids = (0..2500u64)
+        .map(|i| {
+            let mut bytes = *Uuid::parse_str("017f22e2-79b0-7cc3-98c4-dc0c0c07398f")
+                .unwrap()
+                .as_bytes();
+            bytes[8..].copy_from_slice(&i.to_be_bytes());
+            Uuid::from_bytes(bytes)
+        })
+        .collect_vec();
+    let rows = DataValue::List(
+        ids.iter()
+            .enumerate()
+            .map(|(i, id)| {
+                DataValue::List(vec![
+                    DataValue::Uuid(UuidWrapper(*id)),
+                    DataValue::from(i as i64),
+                ])
+            })
+            .collect(),
+    );
+    let params = BTreeMap::from([("rows".to_string(), rows)]);
+    run(":create items {id: Uuid => n: Int}", Default::default());
+    run(":create others {id: Uuid => n: Int}", Default::default());
+    run("?[id, n] <- $rows :put items {id => n}", params.clone());
+    run(
+        "?[id, n] := r in $rows, id = r->0, n = r->1, n < 10 :put others {id => n}",
+        params,
+    );
+
+    // rewrite the keys and the storage version as the previous version did
+    let version_key = vec![DataValue::Null, DataValue::from("STORAGE_VERSION")]
+        .encode_as_key(RelationId::SYSTEM);
+    {
+        let mut tx = db.transact_write().unwrap();
+        for name in ["items", "others"] {
+            let rel_id = tx.get_relation(name, false).unwrap().id;
+            for id in &ids {
+                let key = vec![DataValue::Uuid(UuidWrapper(*id))].encode_as_key(rel_id);
+                if let Some(val) = tx.store_tx.get(&key, false).unwrap() {
+                    tx.store_tx.del(&key).unwrap();
+                    let mut old_key = rel_id.raw_encode().to_vec();
+                    old_key.extend([0x08, 0x7c, 0xc3, 0x79, 0xb0, 0x01, 0x7f, 0x22, 0xe2]);
+                    old_key.extend(&id.as_bytes()[8..]);
+                    tx.store_tx.put(&old_key, &val).unwrap();
+                }
+            }
+        }
+        tx.store_tx.put(&version_key, &[0x00]).unwrap();
+        tx.commit_tx().unwrap();
+    }
+
+    db.initialize().unwrap();
+    assert_eq!(
+        run("?[count(id), sum(n)] := *items{id, n}", Default::default()),
+        json!([[2500, 3123750.0]])
+    );
+    assert_eq!(
+        run("?[count(id)] := *others{id}", Default::default()),
+        json!([[10]])
+    );
+    let q = format!("?[n] := *items{{id: u'{}', n}}", ids[1234]);
+    assert_eq!(run(&q, Default::default()), json!([[1234]]));
+}
+
+/// Encodes a key as storage version 1 did, for the few types used in the test below
+fn encode_legacy_key(key: &[u8]) -> Vec<u8> {
+    let mut ret = key[..8].to_vec();
+    for v in decode_tuple_from_key(key, 0) {
+        match v {
+            DataValue::Null => ret.push(0x01),
+            DataValue::Num(Num::Int(i)) => {
+                let f = i as f64;
+                let u = if f.is_sign_positive() {
+                    f.to_bits() | 0x8000000000000000
+                } else {
+                    !f.to_bits()
+                };
+                ret.push(0x05);
+                ret.extend(u.to_be_bytes());
+                ret.push(0x00);
+            }
+            DataValue::Str(s) => {
+                ret.push(0x06);
+                let bytes = s.as_bytes();
+                for start in (0..=bytes.len()).step_by(8) {
+                    let group = &bytes[start..bytes.len().min(start + 8)];
+                    ret.extend(group);
+                    ret.extend(&[0u8; 8][group.len()..]);
+                    ret.push(0xFF - (8 - group.len()) as u8);
+                }
+            }
+            v => panic!("cannot encode {v:?}"),
+        }
+    }
+    ret
+}
+
+#[test]
+fn upgrade_legacy_key_encoding() {
+    let db = new_cozo_mem().unwrap();
+    let run = |script: &str| {
+        db.run_script(script, Default::default(), ScriptMutability::Mutable)
+            .unwrap()
+            .into_json()["rows"]
+            .clone()
+    };
+    run(":create kv {k: String, n: Int => v: String}");
+    run(r#"
+        ?[k, n, v] <- [['a', 1, 'x'], ['a', -1, 'w'], ['long enough key', 300, 'y'], ['', 0, 'z']]
+        :put kv {k, n => v}
+    "#);
+
+    // write all keys and the storage version as the previous version did
+    let version_key = vec![DataValue::Null, DataValue::from("STORAGE_VERSION")]
+        .encode_as_key(RelationId::SYSTEM);
+    {
+        let mut tx = db.transact_write().unwrap();
+        let all: Vec<_> = tx.store_tx.range_scan(&[], &[0xFF]).try_collect().unwrap();
+        for (key, val) in all {
+            tx.store_tx.del(&key).unwrap();
+            tx.store_tx.put(&encode_legacy_key(&key), &val).unwrap();
+        }
+        tx.store_tx
+            .put(&encode_legacy_key(&version_key), &[0x01])
+            .unwrap();
+        tx.commit_tx().unwrap();
+    }
+
+    db.initialize().unwrap();
+    assert_eq!(
+        run("?[k, n, v] := *kv{k, n, v}"),
+        json!([["", 0, "z"], ["a", -1, "w"], ["a", 1, "x"], ["long enough key", 300, "y"]])
+    );
+    assert_eq!(run("?[v] := *kv{k: 'a', n: 1, v}"), json!([["x"]]));
+    let tx = db.transact().unwrap();
+    let version = tx.store_tx.get(&version_key, false).unwrap().unwrap();
+    assert_eq!(version, CURRENT_STORAGE_VERSION);
+    for kv in tx.store_tx.range_scan(&[], &[0xFF]) {
+        let (key, _) = kv.unwrap();
+        let tuple = decode_tuple_from_key(&key, 0);
+        assert_eq!(tuple.encode_as_key(RelationId::raw_decode(&key)), key);
+    }
+}
+
+#[test]
+fn bytes_literals_and_keys() {
+    let db = DbInstance::default();
+    let res = db
+        .run_default(
+            r#"
+            ?[a, b, same, len, part] := a = x'00ff10', b = b64"AP8Q",
+                                        same = a == b, len = length(a),
+                                        part = encode_hex(slice(a, 1, 3))
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["AP8Q", "AP8Q", true, 3, "ff10"]]));
+    assert!(db.run_default("?[a] := a = x'0g'").is_err());
+    assert!(db.run_default("?[a] := a = x'abc'").is_err());
+    assert!(db.run_default("?[a] := a = b64'@@'").is_err());
+
+    db.run_default(":create blobs {k: Bytes => v: Int}")
+        .unwrap();
+    db.run_default("?[k, v] <- [[x'ff00', 1], [x'', 2]] :put blobs {k => v}")
+        .unwrap();
+    let res = db
+        .run_default("?[v] := *blobs{k: x'FF00', v}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[1]]));
+}
+
+#[test]
+fn decimal_columns() {
+    let db = DbInstance::default();
+    db.run_default(":create prices {p: Decimal => label: String}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[p, label] <- [['10.50', 'a'], [2, 'b'], [-0.5, 'c'], ['1e3', 'd']]
+        :put prices {p => label}
+        "#,
+    )
+    .unwrap();
+    let res = db
+        .run_default("?[p, label] := *prices{p, label}")
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([["-0.5", "c"], ["2", "b"], ["10.5", "a"], ["1000", "d"]])
+    );
+    let res = db
+        .run_default("?[label] := *prices{p: to_decimal('10.500'), label}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["a"]]));
+    let res = db
+        .run_default("?[sum(p)] := *prices{p}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["1012"]]));
+    let res = db
+        .run_default("?[x] := x = to_decimal('0.1') + to_decimal('0.2')")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["0.3"]]));
+    assert!(db
+        .run_default("?[p, label] <- [['abc', 'e']] :put prices {p => label}")
+        .is_err());
+}
+
+#[test]
+fn decimal_key_ranges() {
+    let db = DbInstance::default();
+    db.run_default(":create p {amount: Decimal => id: Int}")
+        .unwrap();
+    db.run_default(":create q {v => id: Int}").unwrap();
+    db.run_default(":create r {k: Int => id: Int}").unwrap();
+    db.run_default("?[amount, id] <- [['1.5', 1], [3, 2]] :put p {amount => id}")
+        .unwrap();
+    db.run_default("?[v, id] <- [[1, 1], [to_decimal('2.5'), 2], [4.0, 3]] :put q {v => id}")
+        .unwrap();
+    db.run_default("?[k, id] <- [[1, 1], [2, 2], [3, 3]] :put r {k => id}")
+        .unwrap();
+    // numbers and decimals compare by value, although all numbers sort before all decimals
+    for (query, expected) in [
+        ("*p{amount, id}, amount < 100", json!([[1], [2]])),
+        ("*p{amount, id}, amount > 2", json!([[2]])),
+        ("*p{amount, id}, amount <= 2.5", json!([[1]])),
+        ("*p{amount, id}, amount >= 1.5", json!([[1], [2]])),
+        ("*q{v, id}, v < 3", json!([[1], [2]])),
+        ("*q{v, id}, v > to_decimal(2)", json!([[2], [3]])),
+        ("*q{v, id}, v >= 1, v <= to_decimal('2.5')", json!([[1], [2]])),
+        ("*r{k, id}, k > to_decimal('1.5')", json!([[2], [3]])),
+        ("*r{k, id}, k < to_decimal('2.5')", json!([[1], [2]])),
+    ] {
+        let res = db
+            .run_default(&format!("?[id] := {query}"))
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], expected, "{query}");
+    }
+}
+
+#[test]
+fn key_filters_bound_scans_of_whole_relations() {
+    let db = DbInstance::default();
+    db.run_default(":create q {v => id: Int}").unwrap();
+    db.run_default(":create p {amount: Decimal => id: Int}")
+        .unwrap();
+    db.run_default(":create h {v, at: Validity => id: Int}")
+        .unwrap();
+    db.run_default(
+        "?[v, id] <- [[1, 1], [1.5, 2], [to_decimal('1.7'), 3], [2, 4], [2.5, 5]] :put q {v => id}",
+    )
+    .unwrap();
+    db.run_default("?[amount, id] <- [['1.5', 1], [3, 2]] :put p {amount => id}")
+        .unwrap();
+    db.run_default(
+        "?[v, at, id] := *q{v, id}, at = [1000, true] :put h {v, at => id}",
+    )
+    .unwrap();
+    // the relations are not joined on their keys, so they are scanned as a whole
+    for (query, expected) in [
+        ("*q{v, id}, v > 1.5", json!([[3], [4], [5]])),
+        ("*q{v, id}, v < to_decimal(2)", json!([[1], [2], [3]])),
+        ("*q{v, id}, v >= 1.5, v <= 2", json!([[2], [3], [4]])),
+        ("*p{amount, id}, amount < 100", json!([[1], [2]])),
+        ("*p{amount, id}, amount > 2", json!([[2]])),
+        ("*h{v, id @ 2000}, v > 1.5", json!([[3], [4], [5]])),
+        ("*h{v, id @ 2000}, v <= to_decimal('1.7')", json!([[1], [2], [3]])),
+    ] {
+        let res = db
+            .run_default(&format!("ids[id] := id in [1, 2, 3, 4, 5] ?[id] := ids[id], {query}"))
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], expected, "{query}");
+    }
+}
+
+#[test]
+fn interval_columns() {
+    let db = DbInstance::default();
+    db.run_default(":create prices {item: String, valid: Interval => price: Int}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[item, valid, price] <- [
+            ['tea', ['2024-01-01T00:00:00Z', '2024-02-01T00:00:00Z'], 3],
+            ['tea', ['2024-02-01T00:00:00Z', null], 4],
+            ['milk', interval('2023-06-01T00:00:00Z', '2024-01-10T00:00:00Z'), 1],
+            ['milk', [null, '2023-06-01T00:00:00Z'], 2],
+        ]
+        :put prices {item, valid => price}
+        "#,
+    )
+    .unwrap();
+    assert!(db
+        .run_default(
+            "?[item, valid, price] <- [['x', ['2024-02-01T00:00:00Z', 0], 1]] :put prices {item, valid => price}"
+        )
+        .is_err());
+
+    let res = db
+        .run_default("?[valid] := *prices{item: 'tea', valid}")
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([
+            [["2024-01-01T00:00:00Z", "2024-02-01T00:00:00Z"]],
+            [["2024-02-01T00:00:00Z", null]]
+        ])
+    );
+
+    // valid at a point in time, the end of an interval is excluded
+    for (at, expected) in [
+        ("2024-01-05T00:00:00Z", json!([["milk", 1], ["tea", 3]])),
+        ("2024-02-01T00:00:00Z", json!([["tea", 4]])),
+        ("2000-01-01T00:00:00Z", json!([["milk", 2]])),
+    ] {
+        let res = db
+            .run_default(&format!(
+                "?[item, price] := *prices{{item, valid, price}}, interval_contains(valid, '{at}')"
+            ))
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], expected);
+    }
+
+    let res = db
+        .run_default(
+            r#"
+            ?[item, price] := *prices{item, valid, price},
+                              interval_overlaps(valid, interval('2024-01-09T00:00:00Z', '2024-01-11T00:00:00Z'))
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["milk", 1], ["tea", 3]]));
+}
+
+#[test]
+fn point_columns() {
+    let db = DbInstance::default();
+    db.run_default(":create places {loc: Point, name: String}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[loc, name] <- [
+            [[51.5074, -0.1278], 'London'],
+            [[48.8566, 2.3522], 'Paris'],
+            [point(52.52, 13.405), 'Berlin'],
+            [[40.7128, -74.006], 'New York'],
+            [[-17.7134, 178.065], 'Fiji'],
+            [[-13.759, -172.1046], 'Samoa'],
+        ]
+        :put places {loc, name}
+        "#,
+    )
+    .unwrap();
+    assert!(db
+        .run_default("?[loc, name] <- [[[100, 0], 'Nowhere']] :put places {loc, name}")
+        .is_err());
+
+    let res = db
+        .run_default("?[loc] := *places{loc, name: 'Paris'}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[[48.8566, 2.3522]]]));
+
+    // the bounds of the key scan must not leave out any point
+    for (query, expected) in [
+        (
+            "geo_within(loc, point(51.5, -0.1), 400000)",
+            json!([["London"], ["Paris"]]),
+        ),
+        (
+            "geo_within(loc, [51.5, -0.1], 1000000)",
+            json!([["Berlin"], ["London"], ["Paris"]]),
+        ),
+        ("geo_within(loc, [51.5, -0.1], 1)", json!([])),
+        (
+            "geo_in_box(loc, [45, -5], [55, 5])",
+            json!([["London"], ["Paris"]]),
+        ),
+        (
+            "geo_in_box(loc, [-20, 170], [-10, -170])",
+            json!([["Fiji"], ["Samoa"]]),
+        ),
+        (
+            "geo_within(loc, [-15, 180], 1000000)",
+            json!([["Fiji"], ["Samoa"]]),
+        ),
+    ] {
+        let res = db
+            .run_default(&format!("?[name] := *places{{loc, name}}, {query}"))
+            .unwrap()
+            .into_json();
+        assert_eq!(res["rows"], expected, "{query}");
+    }
+
+    // joined on a column other than the point, the whole relation is scanned within the bounds
+    let res = db
+        .run_default(
+            r#"
+            capitals[name] <- [['London'], ['Berlin'], ['Washington']]
+            ?[name] := capitals[name], *places{loc, name}, geo_within(loc, [51.5, -0.1], 1000000)
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["Berlin"], ["London"]]));
+
+    let res = db
+        .run_default(
+            r#"
+            ?[name, km] := *places{loc, name}, *places{loc: paris, name: 'Paris'},
+                           loc != paris, km = round(geo_distance(loc, paris) / 1000)
+            :order km
+            :limit 2
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["London", 344.0], ["Berlin", 877.0]]));
+}
+
+#[test]
+fn json_path_functions() {
+    let db = DbInstance::default();
+    db.run_default(":create events {id: Int => payload: Json}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[id, payload] <- [[1, parse_json('{"kind": "click", "pos": {"x": 1, "y": 2}}')],
+                           [2, parse_json('{"kind": "scroll", "pos": {"x": 5}}')]]
+        :put events {id => payload}
+        "#,
+    )
+    .unwrap();
+    let res = db
+        .run_default(
+            r#"
+            ?[id, y] := *events{id, payload}, get_path(payload, 'kind') == 'click',
+                        y = get_path(payload, ['pos', 'y'], 0)
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[1, 2]]));
+    let res = db
+        .run_default(
+            r#"
+            ?[out] := *events{id: 2, payload},
+                      p = set_path(payload, ['pos', 'y'], 7),
+                      out = dump_json(merge(p, {'kind': 'wheel'}))
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([[r#"{"kind":"wheel","pos":{"x":5,"y":7}}"#]])
+    );
+}
+
+#[test]
+fn regex_string_patterns() {
+    let db = DbInstance::default();
+    let res = db
+        .run_default(
+            r#"
+            data[s] <- [['order-17'], ['order-4'], ['refund-9']]
+            ?[s, num, all, first, masked] := data[s], regex_matches(s, '^order-'),
+                                             num = to_int(regex_extract_first(s, '[0-9]+')),
+                                             all = regex_extract_all(s, '[a-z]+'),
+                                             first = regex_extract(s, 'r'),
+                                             masked = regex_replace_all(s, '[0-9]', '#')
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([
+            ["order-17", 17, ["order"], ["r", "r"], "order-##"],
+            ["order-4", 4, ["order"], ["r", "r"], "order-#"]
+        ])
+    );
+    let res = db
+        .run_default(
+            r#"
+            data[s, p] <- [['abc', 'b'], ['abc', 'x']]
+            ?[p, m] := data[s, p], m = regex_matches(s, p)
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["b", true], ["x", false]]));
+    assert!(db
+        .run_default("?[x] := x = regex_matches('abc', '(')")
+        .is_err());
+    // constant patterns are compiled with the query, even if no row is ever matched
+    assert!(db
+        .run_default("data[s] <- [] ?[s] := data[s], regex_matches(s, '(')")
+        .is_err());
+}
+
+#[test]
+fn string_functions_in_queries() {
+    let db = DbInstance::default();
+    let res = db
+        .run_default(
+            r#"
+            data[s] <- [['  Alice,Smith '], ['bob,jones'], ['Carol']]
+            ?[first, last, upper] := data[s], t = trim(s), starts_with(lowercase(t), 'a')
+                                     or str_index_of(t, ',') != null,
+                                     parts = split(t, ','), first = get(parts, 0),
+                                     last = get(parts, 1), upper = uppercase(str_slice(last, 0, 1))
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([["Alice", "Smith", "S"], ["bob", "jones", "J"]])
+    );
+}
+
+#[cfg(feature = "collation")]
+#[test]
+fn collation_keys() {
+    let db = DbInstance::default();
+    db.run_default(":create words {w: String}").unwrap();
+    db.run_default(
+        r#"
+        ?[w] <- [['zebra'], ['Apple'], ['élan'], ['apple'], ['Eagle']]
+        :put words {w}
+        "#,
+    )
+    .unwrap();
+    let res = db
+        .run_default("?[w, k] := *words{w}, k = collate_key(w, 'en') :order k")
+        .unwrap()
+        .into_json();
+    let sorted = res["rows"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|row| row[0].as_str().unwrap())
+        .collect_vec();
+    assert_eq!(sorted, vec!["apple", "Apple", "Eagle", "élan", "zebra"]);
+
+    db.run_default(":create words_sorted {k: Bytes, w: String}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[k, w] := *words{w}, k = collate_key(w, 'en')
+        :put words_sorted {k, w}
+        "#,
+    )
+    .unwrap();
+    let res = db
+        .run_default(
+            r#"
+            lower[k] := k = collate_key('b', 'en')
+            ?[w] := lower[lo], *words_sorted{k, w}, k >= lo
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["Eagle"], ["zebra"], ["élan"]]));
+}
+
+#[test]
+fn math_in_scoring_expressions() {
+    let db = DbInstance::default();
+    let res = db
+        .run_default(
+            r#"
+            ?[score, dist] := raw = ln(100) / ln(10) + sqrt(16) * pow(2, 3),
+                              score = clamp(round(raw, 1), 0, 30),
+                              d = haversine_deg_input(0, 0, 0, 10),
+                              dist = round(d, 4)
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[30, 0.1745]]));
+}
+
+#[test]
+fn content_addressed_keys() {
+    let db = DbInstance::default();
+    db.run_default(":create blobs {hash: Bytes => content: String}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[hash, content] := content in ['hello', 'world', 'hello'], hash = blake3(content)
+        :put blobs {hash => content}
+        "#,
+    )
+    .unwrap();
+    let res = db
+        .run_default("?[count(hash)] := *blobs{hash}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[2]]));
+    let res = db
+        .run_default("?[content] := *blobs{hash: blake3('world'), content}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["world"]]));
+}
+
+#[test]
+fn post_process_collected_lists() {
+    let db = DbInstance::default();
+    let res = db
+        .run_default(
+            r#"
+            tags[item, tag] <- [[1, 'b'], [1, 'a'], [1, 'b'], [2, 'c'], [2, 'a']]
+            per_item[item, collect(tag)] := tags[item, tag]
+            ?[item, tags, pos] := per_item[item, ts], tags = sorted(unique(ts)),
+                                  pos = index_of(tags, 'a')
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[1, ["a", "b"], 0], [2, ["a", "c"], 0]]));
+}
+
+#[test]
+fn vector_similarity_functions() {
+    let db = DbInstance::default();
+    db.run_default(":create docs {k: String => v: <F32; 3>}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[k, v] <- [['x', [1, 0, 0]], ['xy', [1, 1, 0]], ['z', [0, 0, 1]]]
+        :put docs {k => v}
+        "#,
+    )
+    .unwrap();
+    let res = db
+        .run_default(
+            r#"
+            ?[k, sim, d] := *docs{k, v}, q = vec([1, 0, 0]), sim = round(cos_sim(v, q), 3),
+                            d = dot(v + v, q)
+            :order -sim
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([["x", 1.0, 2.0], ["xy", 0.707, 2.0], ["z", 0.0, 0.0]])
+    );
+}
+
+#[test]
+fn percentile_aggregations() {
+    let db = DbInstance::default();
+    db.run_default(":create latency {service: String, at: Int => ms: Float}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[service, at, ms] <- [['a', 1, 10], ['a', 2, 20], ['a', 3, 30], ['a', 4, 100],
+                               ['b', 1, 5]]
+        :put latency {service, at => ms}
+        "#,
+    )
+    .unwrap();
+    let res = db
+        .run_default(
+            r#"
+            ?[service, median(ms), percentile(ms, 0.75), percentile_disc(ms, 0.75)] :=
+                *latency{service, ms}
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([["a", 25.0, 47.5, 30.0], ["b", 5.0, 5.0, 5.0]])
+    );
+}
+
+#[test]
+fn top_k_by_cost() {
+    let db = DbInstance::default();
+    let res = db
+        .run_default(
+            r#"
+            edge[fr, to, w] <- [['a', 'b', 3], ['a', 'c', 1], ['a', 'd', 2], ['b', 'a', 3],
+                                ['b', 'c', 7]]
+            ?[fr, min_cost_k(nb, 2), max_cost_k(nb, 1)] := edge[fr, to, w], nb = [to, w]
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([
+            ["a", [["c", 1], ["d", 2]], [["b", 3]]],
+            ["b", [["a", 3], ["c", 7]], [["c", 7]]]
+        ])
+    );
+}
+
+#[test]
+fn bitwise_int_aggregations() {
+    let db = DbInstance::default();
+    let res = db
+        .run_default(
+            r#"
+            member[user, group] <- [['u1', 'g1'], ['u2', 'g2']]
+            parent[group, parent] <- [['g1', 'g2'], ['g2', 'g3']]
+            grant[group, mask] <- [['g1', 1], ['g2', 2], ['g3', 8]]
+            effective[group, bit_or(mask)] := grant[group, mask]
+            effective[group, bit_or(mask)] := parent[group, p], effective[p, mask]
+            common[bit_and(mask)] := effective[_, mask]
+            flipped[bit_xor(mask)] := grant[_, mask]
+            ?[user, mask, c, f] := member[user, group], effective[group, mask],
+                common[c], flipped[f]
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["u1", 11, 8, 11], ["u2", 10, 8, 11]]));
+}
+
+#[test]
+fn group_concat_aggregation() {
+    let db = DbInstance::default();
+    db.run_default(":create tag {item: String, tag: String}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[item, tag] <- [['b', 'y'], ['a', 'z'], ['a', 'x'], ['b', 'w'], ['a', 'y']]
+        :put tag {item, tag}
+        "#,
+    )
+    .unwrap();
+    let res = db
+        .run_default("?[item, group_concat(tag, '; ')] := *tag{item, tag}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["a", "x; y; z"], ["b", "w; y"]]));
+}
+
+#[test]
+fn user_defined_functions() {
+    let db = DbInstance::default();
+    db.register_fn("scale", 2, |args| {
+        let x = args[0]
+            .get_float()
+            .ok_or_else(|| miette::miette!("'scale' requires numbers"))?;
+        let by = args[1]
+            .get_float()
+            .ok_or_else(|| miette::miette!("'scale' requires numbers"))?;
+        Ok(DataValue::from(x * by))
+    })
+    .unwrap();
+    assert!(db.register_fn("scale", 1, |_| Ok(DataValue::Null)).is_err());
+    assert!(db.register_fn("length", 1, |_| Ok(DataValue::Null)).is_err());
+
+    let res = db
+        .run_default(
+            r#"
+            ?[x, y] := x in [1, 2, 3], y = scale(x, 1.5), scale(x, 2) > 3
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[2, 3.0], [3, 4.5]]));
+
+    let err = db.run_default("?[y] := y = scale(1)").unwrap_err();
+    assert!(format!("{err:?}").contains("func_wrong_num_args"));
+    let err = db.run_default("?[y] := y = scale('a', 1)").unwrap_err();
+    assert!(format!("{err:?}").contains("'scale' requires numbers"));
+
+    db.run_default(":create scaled {k: Int => v: Float default scale(1, 2)}")
+        .unwrap();
+    assert!(db.run_default("?[k] <- [[1]] :put scaled {k}").is_err());
+
+    assert!(db.unregister_fn("scale").unwrap());
+    assert!(!db.unregister_fn("scale").unwrap());
+    assert!(db.run_default("?[y] := y = scale(1, 2)").is_err());
+}
+
+#[test]
+fn lambdas_over_lists() {
+    let db = DbInstance::default();
+    let res = db
+        .run_default(
+            r#"
+            ?[doubled, big, total, shifted, nested] :=
+                l = [1, 2, 3, 4],
+                k = 10,
+                doubled = map(l, |x| x * 2),
+                big = filter(l, |x| x > 2),
+                total = fold(l, 0, |acc, x| acc + x),
+                shifted = map(l, |x| x + k),
+                nested = map([[1, 2], [3]], |l| fold(l, k, |acc, x| acc - x))
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([[[2, 4, 6, 8], [3, 4], 10, [11, 12, 13, 14], [7, 7]]])
+    );
+
+    db.run_default(":create order {id: Int => prices: [Float]}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[id, prices] <- [[1, [10.0, 25.0, 5.0]], [2, [1.0]]]
+        :put order {id => prices}
+        "#,
+    )
+    .unwrap();
+    let res = db
+        .run_default(
+            r#"
+            ?[id, total] := *order{id, prices},
+                length(filter(prices, |p| p >= 10)) > 0,
+                total = fold(prices, 0.0, |acc, p| acc + p)
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[1, 40.0]]));
+
+    assert!(db.run_default("?[x] := x = |y| y").is_err());
+    assert!(db.run_default("?[x] := x = map([1], |a, b| a)").is_err());
+    assert!(db.run_default("?[x] := x = fold([1], |a, b| a)").is_err());
+    assert!(db.run_default("?[x] := x = map(1, |a| a)").is_err());
+    assert!(db
+        .run_default("?[x] := x = filter([1], |a| a + 1)")
+        .is_err());
+}
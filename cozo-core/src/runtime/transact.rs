@@ -12,7 +12,7 @@ use std::sync::Arc;
 use miette::{bail, Result};
 use crate::data::program::ReturnMutation;
 
-use crate::data::tuple::TupleT;
+use crate::data::tuple::{decode_tuple_from_key, TupleT};
 use crate::data::value::DataValue;
 use crate::fts::TokenizerCache;
 use crate::{CallbackOp, NamedRows};
@@ -29,25 +29,51 @@ pub struct SessionTx<'a> {
     pub(crate) tokenizers: Arc<TokenizerCache>,
 }
 
-pub const CURRENT_STORAGE_VERSION: [u8; 1] = [0x00];
+pub const CURRENT_STORAGE_VERSION: [u8; 1] = [0x02];
+
+/// Storage written before version 7 UUIDs had their own key encoding,
+/// upgraded by re-encoding the keys when opened
+const STORAGE_VERSION_UNORDERED_UUID_V7: [u8; 1] = [0x00];
+
+/// Storage written before the compact key encoding, upgraded in the same way
+const STORAGE_VERSION_LEGACY_KEYS: [u8; 1] = [0x01];
+
+const REENCODE_BATCH_SIZE: usize = 1000;
 
 fn storage_version_key() -> Vec<u8> {
     let storage_version_tuple = vec![DataValue::Null, DataValue::from("STORAGE_VERSION")];
     storage_version_tuple.encode_as_key(RelationId::SYSTEM)
 }
 
+/// The keys of the relation counter and of the storage version
+/// in the key encoding used before storage version 2
+fn legacy_system_keys() -> (Vec<u8>, Vec<u8>) {
+    let mut counter_key = RelationId::SYSTEM.raw_encode().to_vec();
+    counter_key.push(0x01);
+    let mut version_key = counter_key.clone();
+    version_key.push(0x06);
+    version_key.extend(b"STORAGE_\xFFVERSION\x00\xFE");
+    (counter_key, version_key)
+}
+
 const STATUS_STR: &str = "status";
 const OK_STR: &str = "OK";
 
 impl<'a> SessionTx<'a> {
-    pub(crate) fn get_returning_rows(&self, callback_collector: &mut CallbackCollector, rel: &str, returning: &ReturnMutation) -> Result<NamedRows> {
+    pub(crate) fn get_returning_rows(&self, callback_collector: &mut CallbackCollector, rel: &str, returning: &ReturnMutation, skipped: Option<usize>) -> Result<NamedRows> {
         let returned_rows = {
             match returning {
                 ReturnMutation::NotReturning => {
-                    NamedRows::new(
-                        vec![STATUS_STR.to_string()],
-                        vec![vec![DataValue::from(OK_STR)]],
-                    )
+                    match skipped {
+                        None => NamedRows::new(
+                            vec![STATUS_STR.to_string()],
+                            vec![vec![DataValue::from(OK_STR)]],
+                        ),
+                        Some(n) => NamedRows::new(
+                            vec![STATUS_STR.to_string(), "skipped".to_string()],
+                            vec![vec![DataValue::from(OK_STR), DataValue::from(n as i64)]],
+                        ),
+                    }
                 }
                 ReturnMutation::Returning => {
                     let meta = self.get_relation(rel, false)?;
@@ -97,8 +123,14 @@ impl<'a> SessionTx<'a> {
     pub(crate) fn init_storage(&mut self) -> Result<RelationId> {
         let tuple = vec![DataValue::Null];
         let t_encoded = tuple.encode_as_key(RelationId::SYSTEM);
-        let found = self.store_tx.get(&t_encoded, false)?;
+        let mut found = self.store_tx.get(&t_encoded, false)?;
         let storage_version_key = storage_version_key();
+        let mut found_version_key = storage_version_key.clone();
+        if found.is_none() {
+            let (legacy_counter_key, legacy_version_key) = legacy_system_keys();
+            found = self.store_tx.get(&legacy_counter_key, false)?;
+            found_version_key = legacy_version_key;
+        }
         let ret = match found {
             None => {
                 self.store_tx
@@ -108,11 +140,19 @@ impl<'a> SessionTx<'a> {
                 RelationId::SYSTEM
             }
             Some(slice) => {
-                let version_found = self.store_tx.get(&storage_version_key, false)?;
+                let version_found = self.store_tx.get(&found_version_key, false)?;
                 match version_found {
                     None => {
                         bail!("Storage is used but un-versioned, probably created by an ancient version of Cozo.")
                     }
+                    Some(v)
+                        if v == STORAGE_VERSION_UNORDERED_UUID_V7
+                            || v == STORAGE_VERSION_LEGACY_KEYS =>
+                    {
+                        self.reencode_keys()?;
+                        self.store_tx
+                            .put(&storage_version_key, &CURRENT_STORAGE_VERSION)?;
+                    }
                     Some(v) => {
                         if v != CURRENT_STORAGE_VERSION {
                             bail!(
@@ -129,6 +169,52 @@ impl<'a> SessionTx<'a> {
         Ok(ret)
     }
 
+    /// Rewrite the keys of all relations whose encoding has changed, including the
+    /// system relation, one relation at a time and at most `REENCODE_BATCH_SIZE` keys at a time
+    fn reencode_keys(&mut self) -> Result<()> {
+        let mut next_rel = RelationId::SYSTEM;
+        loop {
+            let rel_id = match self
+                .store_tx
+                .range_scan(&next_rel.raw_encode(), &[0xFF])
+                .next()
+            {
+                None => break,
+                Some(kv) => RelationId::raw_decode(&kv?.0),
+            };
+            next_rel = rel_id.next();
+            self.reencode_relation_keys(rel_id, &next_rel.raw_encode())?;
+        }
+        Ok(())
+    }
+
+    fn reencode_relation_keys(&mut self, rel_id: RelationId, upper: &[u8]) -> Result<()> {
+        let mut lower = rel_id.raw_encode().to_vec();
+        loop {
+            let mut changed = Vec::with_capacity(REENCODE_BATCH_SIZE);
+            for kv in self.store_tx.range_scan(&lower, upper) {
+                let (key, val) = kv?;
+                let tuple = decode_tuple_from_key(&key, 0);
+                let new_key = tuple.encode_as_key(rel_id);
+                if new_key != key {
+                    changed.push((key, new_key, val));
+                    if changed.len() >= REENCODE_BATCH_SIZE {
+                        break;
+                    }
+                }
+            }
+            // rewritten keys seen again by a later scan are already unchanged
+            match changed.last() {
+                None => return Ok(()),
+                Some((last_key, _, _)) => lower = last_key.clone(),
+            }
+            for (key, new_key, val) in changed {
+                self.store_tx.del(&key)?;
+                self.store_tx.put(&new_key, &val)?;
+            }
+        }
+    }
+
     pub fn commit_tx(&mut self) -> Result<()> {
         self.store_tx.commit()?;
         Ok(())
@@ -0,0 +1,149 @@
+/*
+ * Copyright 2022, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use miette::Result;
+
+use crate::data::tuple::Tuple;
+use crate::data::value::ValidityTs;
+use crate::storage::StoreTx;
+
+/// Number of point lookups and range scans issued against a storage transaction
+#[derive(Debug, Default)]
+pub(crate) struct StorageCounters {
+    gets: AtomicUsize,
+    scans: AtomicUsize,
+}
+
+impl StorageCounters {
+    pub(crate) fn gets(&self) -> usize {
+        self.gets.load(Ordering::Acquire)
+    }
+    pub(crate) fn scans(&self) -> usize {
+        self.scans.load(Ordering::Acquire)
+    }
+    pub(crate) fn reset(&self) {
+        self.gets.store(0, Ordering::Release);
+        self.scans.store(0, Ordering::Release);
+    }
+    fn count_gets(&self, n: usize) {
+        self.gets.fetch_add(n, Ordering::Relaxed);
+    }
+    fn count_scan(&self) {
+        self.scans.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Transaction wrapper counting the reads going to the wrapped transaction, used by `::explain analyze`
+pub(crate) struct CountingTx<'s> {
+    inner: Box<dyn StoreTx<'s> + 's>,
+    counters: Arc<StorageCounters>,
+}
+
+impl<'s> CountingTx<'s> {
+    pub(crate) fn new(inner: Box<dyn StoreTx<'s> + 's>, counters: Arc<StorageCounters>) -> Self {
+        Self { inner, counters }
+    }
+}
+
+impl<'s> StoreTx<'s> for CountingTx<'s> {
+    fn get(&self, key: &[u8], for_update: bool) -> Result<Option<Vec<u8>>> {
+        self.counters.count_gets(1);
+        self.inner.get(key, for_update)
+    }
+
+    fn multi_get(&self, keys: &[Vec<u8>], for_update: bool) -> Result<Vec<Option<Vec<u8>>>> {
+        self.counters.count_gets(keys.len());
+        self.inner.multi_get(keys, for_update)
+    }
+
+    fn put(&mut self, key: &[u8], val: &[u8]) -> Result<()> {
+        self.inner.put(key, val)
+    }
+
+    fn supports_par_put(&self) -> bool {
+        self.inner.supports_par_put()
+    }
+
+    fn par_put(&self, key: &[u8], val: &[u8]) -> Result<()> {
+        self.inner.par_put(key, val)
+    }
+
+    fn del(&mut self, key: &[u8]) -> Result<()> {
+        self.inner.del(key)
+    }
+
+    fn par_del(&self, key: &[u8]) -> Result<()> {
+        self.inner.par_del(key)
+    }
+
+    fn del_range_from_persisted(&mut self, lower: &[u8], upper: &[u8]) -> Result<()> {
+        self.inner.del_range_from_persisted(lower, upper)
+    }
+
+    fn exists(&self, key: &[u8], for_update: bool) -> Result<bool> {
+        self.counters.count_gets(1);
+        self.inner.exists(key, for_update)
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        self.inner.commit()
+    }
+
+    fn range_scan_tuple<'a>(
+        &'a self,
+        lower: &[u8],
+        upper: &[u8],
+    ) -> Box<dyn Iterator<Item = Result<Tuple>> + 'a>
+    where
+        's: 'a,
+    {
+        self.counters.count_scan();
+        self.inner.range_scan_tuple(lower, upper)
+    }
+
+    fn range_skip_scan_tuple<'a>(
+        &'a self,
+        lower: &[u8],
+        upper: &[u8],
+        valid_at: ValidityTs,
+    ) -> Box<dyn Iterator<Item = Result<Tuple>> + 'a> {
+        self.counters.count_scan();
+        self.inner.range_skip_scan_tuple(lower, upper, valid_at)
+    }
+
+    fn range_scan<'a>(
+        &'a self,
+        lower: &[u8],
+        upper: &[u8],
+    ) -> Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a>
+    where
+        's: 'a,
+    {
+        self.counters.count_scan();
+        self.inner.range_scan(lower, upper)
+    }
+
+    fn range_count<'a>(&'a self, lower: &[u8], upper: &[u8]) -> Result<usize>
+    where
+        's: 'a,
+    {
+        self.counters.count_scan();
+        self.inner.range_count(lower, upper)
+    }
+
+    fn total_scan<'a>(&'a self) -> Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a>
+    where
+        's: 'a,
+    {
+        self.counters.count_scan();
+        self.inner.total_scan()
+    }
+}
@@ -0,0 +1,247 @@
+/*
+ * Copyright 2022, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::path::Path;
+use std::sync::Arc;
+
+use miette::{bail, miette, Diagnostic, Result};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use thiserror::Error;
+
+use crate::data::tuple::Tuple;
+use crate::data::value::ValidityTs;
+use crate::storage::{SeekSkipIter, Storage, StoreTx};
+use crate::Db;
+
+/// Create a database on top of another storage engine, with all stored values
+/// encrypted by AES-256-GCM. `key` must be 32 bytes long, and the same key must be
+/// supplied every time the underlying storage is opened.
+/// For RocksDB, obtain the storage with [new_rocksdb_storage](crate::new_rocksdb_storage).
+///
+/// # Limitations
+///
+/// Only values are encrypted. Keys are stored in plaintext, as every engine relies on
+/// their byte order for range scans. Anyone with access to the files can read:
+///
+/// * the key columns of every stored relation,
+/// * every column covered by an index, since index rows keep the indexed columns in their keys,
+/// * the tokens of full-text indices and the hashes of MinHash-LSH indices,
+/// * the names of all relations.
+///
+/// Only data in non-key columns that are not indexed is protected. Backups made with
+/// [Db::backup_db] are not encrypted at all.
+pub fn new_cozo_encrypted<S>(storage: S, key: &[u8]) -> Result<Db<EncryptedStorage<S>>>
+where
+    S: for<'s> Storage<'s>,
+{
+    let ret = Db::new(EncryptedStorage {
+        inner: storage,
+        cipher: Arc::new(Cipher::new(key)?),
+    })?;
+    ret.initialize()?;
+    Ok(ret)
+}
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("Cannot decrypt stored value, the encryption key is probably wrong")]
+#[diagnostic(code(storage::decryption_failed))]
+struct DecryptionFailed;
+
+struct Cipher {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl Cipher {
+    fn new(key: &[u8]) -> Result<Self> {
+        let key = UnboundKey::new(&AES_256_GCM, key)
+            .map_err(|_| miette!("the encryption key must be 32 bytes long"))?;
+        Ok(Self {
+            key: LessSafeKey::new(key),
+            rng: SystemRandom::new(),
+        })
+    }
+    /// The stored layout is nonce + ciphertext + tag. The key is used as associated data,
+    /// so that values cannot be moved between keys.
+    fn encrypt(&self, key: &[u8], val: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| miette!("cannot generate nonce for encryption"))?;
+        let mut ret = Vec::with_capacity(NONCE_LEN + val.len() + AES_256_GCM.tag_len());
+        ret.extend_from_slice(&nonce);
+        ret.extend_from_slice(val);
+        let tag = self
+            .key
+            .seal_in_place_separate_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(key),
+                &mut ret[NONCE_LEN..],
+            )
+            .map_err(|_| miette!("encryption failed"))?;
+        ret.extend_from_slice(tag.as_ref());
+        Ok(ret)
+    }
+    fn decrypt(&self, key: &[u8], mut data: Vec<u8>) -> Result<Vec<u8>> {
+        if data.len() < NONCE_LEN + AES_256_GCM.tag_len() {
+            bail!(DecryptionFailed)
+        }
+        let nonce =
+            Nonce::try_assume_unique_for_key(&data[..NONCE_LEN]).map_err(|_| DecryptionFailed)?;
+        let plain_len = self
+            .key
+            .open_in_place(nonce, Aad::from(key), &mut data[NONCE_LEN..])
+            .map_err(|_| DecryptionFailed)?
+            .len();
+        data.drain(..NONCE_LEN);
+        data.truncate(plain_len);
+        Ok(data)
+    }
+}
+
+/// Storage engine wrapper encrypting all values but not the keys, see [new_cozo_encrypted]
+#[derive(Clone)]
+pub struct EncryptedStorage<S> {
+    inner: S,
+    cipher: Arc<Cipher>,
+}
+
+impl<'s, S: Storage<'s>> Storage<'s> for EncryptedStorage<S> {
+    type Tx = EncryptedTx<'s, S::Tx>;
+
+    fn storage_kind(&self) -> &'static str {
+        self.inner.storage_kind()
+    }
+
+    fn transact(&'s self, write: bool) -> Result<Self::Tx> {
+        Ok(EncryptedTx {
+            inner: self.inner.transact(write)?,
+            cipher: &self.cipher,
+        })
+    }
+
+    fn range_compact(&'s self, lower: &[u8], upper: &[u8]) -> Result<()> {
+        self.inner.range_compact(lower, upper)
+    }
+
+    fn range_size_estimate(&'s self, lower: &[u8], upper: &[u8]) -> Result<Option<(u64, u64)>> {
+        self.inner.range_size_estimate(lower, upper)
+    }
+
+    fn set_bulk_load(&'s self, enabled: bool) -> Result<()> {
+        self.inner.set_bulk_load(enabled)
+    }
+
+    fn checkpoint(&'s self, path: &Path) -> Result<()> {
+        self.inner.checkpoint(path)
+    }
+
+    fn batch_put<'a>(
+        &'a self,
+        data: Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a>,
+    ) -> Result<()> {
+        let cipher = &self.cipher;
+        self.inner.batch_put(Box::new(data.map(move |pair| {
+            let (k, v) = pair?;
+            let v = cipher.encrypt(&k, &v)?;
+            Ok((k, v))
+        })))
+    }
+}
+
+/// Transaction of [EncryptedStorage]
+pub struct EncryptedTx<'s, T> {
+    inner: T,
+    cipher: &'s Cipher,
+}
+
+impl<'s, T: StoreTx<'s>> StoreTx<'s> for EncryptedTx<'s, T> {
+    fn get(&self, key: &[u8], for_update: bool) -> Result<Option<Vec<u8>>> {
+        self.inner
+            .get(key, for_update)?
+            .map(|v| self.cipher.decrypt(key, v))
+            .transpose()
+    }
+
+    fn multi_get(&self, keys: &[Vec<u8>], for_update: bool) -> Result<Vec<Option<Vec<u8>>>> {
+        self.inner
+            .multi_get(keys, for_update)?
+            .into_iter()
+            .zip(keys)
+            .map(|(v, k)| v.map(|v| self.cipher.decrypt(k, v)).transpose())
+            .collect()
+    }
+
+    fn put(&mut self, key: &[u8], val: &[u8]) -> Result<()> {
+        let val = self.cipher.encrypt(key, val)?;
+        self.inner.put(key, &val)
+    }
+
+    fn supports_par_put(&self) -> bool {
+        self.inner.supports_par_put()
+    }
+
+    fn par_put(&self, key: &[u8], val: &[u8]) -> Result<()> {
+        let val = self.cipher.encrypt(key, val)?;
+        self.inner.par_put(key, &val)
+    }
+
+    fn del(&mut self, key: &[u8]) -> Result<()> {
+        self.inner.del(key)
+    }
+
+    fn par_del(&self, key: &[u8]) -> Result<()> {
+        self.inner.par_del(key)
+    }
+
+    fn del_range_from_persisted(&mut self, lower: &[u8], upper: &[u8]) -> Result<()> {
+        self.inner.del_range_from_persisted(lower, upper)
+    }
+
+    fn exists(&self, key: &[u8], for_update: bool) -> Result<bool> {
+        self.inner.exists(key, for_update)
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        self.inner.commit()
+    }
+
+    fn range_skip_scan_tuple<'a>(
+        &'a self,
+        lower: &[u8],
+        upper: &[u8],
+        valid_at: ValidityTs,
+    ) -> Box<dyn Iterator<Item = Result<Tuple>> + 'a> {
+        // the inner skip scan cannot be used as it needs the decrypted values
+        Box::new(SeekSkipIter::new(self, lower, upper, valid_at))
+    }
+
+    fn range_scan<'a>(
+        &'a self,
+        lower: &[u8],
+        upper: &[u8],
+    ) -> Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a>
+    where
+        's: 'a,
+    {
+        let cipher = self.cipher;
+        Box::new(self.inner.range_scan(lower, upper).map(move |pair| {
+            let (k, v) = pair?;
+            let v = cipher.decrypt(&k, v)?;
+            Ok((k, v))
+        }))
+    }
+
+    fn range_count<'a>(&'a self, lower: &[u8], upper: &[u8]) -> Result<usize>
+    where
+        's: 'a,
+    {
+        self.inner.range_count(lower, upper)
+    }
+}
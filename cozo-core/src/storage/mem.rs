@@ -58,10 +58,6 @@ impl<'s> Storage<'s> for MemStorage {
         })
     }
 
-    fn range_compact(&'s self, _lower: &[u8], _upper: &[u8]) -> Result<()> {
-        Ok(())
-    }
-
     fn batch_put<'a>(
         &'a self,
         data: Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a>,
@@ -454,6 +450,10 @@ impl<'a> Iterator for SkipIterator<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
+            // the seek past the last version of a key may already be out of range
+            if self.next_bound >= self.upper {
+                return None;
+            }
             let nxt = self
                 .inner
                 .range::<Vec<u8>, (Bound<&Vec<u8>>, Bound<&Vec<u8>>)>((
@@ -490,6 +490,9 @@ impl<'a> Iterator for SkipDualIterator<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
+            if self.next_bound >= self.upper {
+                return None;
+            }
             let stored_nxt = self
                 .stored
                 .range::<Vec<u8>, (Bound<&Vec<u8>>, Bound<&Vec<u8>>)>((
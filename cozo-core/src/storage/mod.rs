@@ -6,14 +6,22 @@
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use std::path::Path;
+
 use itertools::Itertools;
-use miette::Result;
+use miette::{bail, Result};
 
-use crate::data::tuple::Tuple;
+use crate::data::tuple::{check_key_for_validity, Tuple};
 use crate::data::value::ValidityTs;
 use crate::decode_tuple_from_kv;
+use crate::runtime::relation::extend_tuple_from_v;
+use crate::utils::swap_option_result;
 
+pub(crate) mod counting;
+#[cfg(feature = "encryption")]
+pub(crate) mod encrypted;
 pub(crate) mod mem;
+pub(crate) mod namespaced;
 #[cfg(feature = "storage-rocksdb")]
 pub(crate) mod rocks;
 #[cfg(feature = "storage-sled")]
@@ -37,8 +45,35 @@ pub trait Storage<'s>: Send + Sync + Clone {
     fn transact(&'s self, write: bool) -> Result<Self::Tx>;
 
     /// Compact the key range. Can be a no-op if the storage engine does not
-    /// have the concept of compaction.
-    fn range_compact(&'s self, lower: &[u8], upper: &[u8]) -> Result<()>;
+    /// have the concept of compaction, which is what the default implementation does.
+    fn range_compact(&'s self, _lower: &[u8], _upper: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Estimate the storage used by the key range, returning the approximate number of bytes
+    /// on disk and the number of deletion markers not yet removed by compaction.
+    /// The default implementation returns `None`, meaning that the engine cannot tell.
+    fn range_size_estimate(&'s self, _lower: &[u8], _upper: &[u8]) -> Result<Option<(u64, u64)>> {
+        Ok(None)
+    }
+
+    /// Turn bulk loading on or off. While bulk loading, the engine may trade durability
+    /// for write speed, e.g. by not writing a write-ahead log. When bulk loading is
+    /// turned off, everything written so far must be made durable.
+    /// The default implementation does nothing.
+    fn set_bulk_load(&'s self, _enabled: bool) -> Result<()> {
+        Ok(())
+    }
+
+    /// Copy a consistent snapshot of the whole store to `path` while other transactions
+    /// keep running. The copy must be openable directly by the same engine.
+    /// The default implementation returns an error.
+    fn checkpoint(&'s self, _path: &Path) -> Result<()> {
+        bail!(
+            "the {} engine does not support online backups",
+            self.storage_kind()
+        )
+    }
 
     /// Put multiple key-value pairs into the database.
     /// No duplicate data will be sent, and the order data come in is strictly ascending.
@@ -152,12 +187,97 @@ pub trait StoreTx<'s>: Sync {
         's: 'a;
 
     /// Return the number of rows in the range.
+    /// The default implementation counts the results of [`range_scan`](Self::range_scan),
+    /// engines that can count without materializing the rows should override it.
     fn range_count<'a>(&'a self, lower: &[u8], upper: &[u8]) -> Result<usize>
     where
-        's: 'a;
+        's: 'a,
+    {
+        let mut count = 0;
+        for pair in self.range_scan(lower, upper) {
+            pair?;
+            count += 1;
+        }
+        Ok(count)
+    }
 
     /// Scan for all rows. The rows are required to be in ascending order.
+    /// The default implementation calls [`range_scan`](Self::range_scan) over the whole key space.
     fn total_scan<'a>(&'a self) -> Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a>
     where
-        's: 'a;
+        's: 'a,
+    {
+        self.range_scan(&[], &[u8::MAX])
+    }
+}
+
+/// Skip scan implemented by seeking with repeated [`range_scan`](StoreTx::range_scan) calls,
+/// for engines without a native skip scan and for storage wrappers that cannot use the
+/// skip scan of the wrapped engine.
+pub(crate) struct SeekSkipIter<'a, T> {
+    tx: &'a T,
+    seek: Option<SeekFn<T>>,
+    upper: Vec<u8>,
+    valid_at: ValidityTs,
+    next_bound: Vec<u8>,
+}
+
+/// Fetches the first pair in the range from `lower` to `upper`
+pub(crate) type SeekFn<T> = fn(&T, &[u8], &[u8]) -> Result<Option<(Vec<u8>, Vec<u8>)>>;
+
+impl<'a, T> SeekSkipIter<'a, T> {
+    pub(crate) fn new(tx: &'a T, lower: &[u8], upper: &[u8], valid_at: ValidityTs) -> Self {
+        Self {
+            tx,
+            seek: None,
+            upper: upper.to_vec(),
+            valid_at,
+            next_bound: lower.to_vec(),
+        }
+    }
+    /// Seek with `seek` instead of taking the first result of `range_scan`,
+    /// for engines where the latter fetches more than needed.
+    #[cfg(feature = "storage-tikv")]
+    pub(crate) fn with_seek(mut self, seek: SeekFn<T>) -> Self {
+        self.seek = Some(seek);
+        self
+    }
+}
+
+impl<'a, 's: 'a, T: StoreTx<'s>> SeekSkipIter<'a, T> {
+    fn next_inner(&mut self) -> Result<Option<Tuple>> {
+        loop {
+            // the seek past the last version of a key may already be out of range
+            if self.next_bound >= self.upper {
+                return Ok(None);
+            }
+            let candidate = match self.seek {
+                Some(seek) => seek(self.tx, &self.next_bound, &self.upper)?,
+                None => self
+                    .tx
+                    .range_scan(&self.next_bound, &self.upper)
+                    .next()
+                    .transpose()?,
+            };
+            match candidate {
+                None => return Ok(None),
+                Some((k, v)) => {
+                    let (ret, nxt_bound) = check_key_for_validity(&k, self.valid_at, None);
+                    self.next_bound = nxt_bound;
+                    if let Some(mut tup) = ret {
+                        extend_tuple_from_v(&mut tup, &v);
+                        return Ok(Some(tup));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a, 's: 'a, T: StoreTx<'s>> Iterator for SeekSkipIter<'a, T> {
+    type Item = Result<Tuple>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        swap_option_result(self.next_inner())
+    }
 }
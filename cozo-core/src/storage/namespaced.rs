@@ -0,0 +1,206 @@
+/*
+ * Copyright 2022, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::path::Path;
+
+use miette::{bail, Result};
+
+use crate::data::tuple::Tuple;
+use crate::data::value::ValidityTs;
+use crate::storage::{SeekSkipIter, Storage, StoreTx};
+use crate::Db;
+
+/// Marks the start of namespaced keys. Keys of a database without namespace start
+/// with the big-endian relation id, so they never begin with this byte in practice.
+const NAMESPACE_MARKER: u8 = 0xFE;
+
+/// Create a database living in the namespace `namespace` of `storage`.
+///
+/// Any number of namespaces can share one storage (e.g. by cloning the storage object),
+/// each with its own relations, indices, triggers and access levels. The storage must
+/// not be used directly as a database at the same time.
+///
+/// Namespace names must be non-empty and must not contain the NUL character.
+/// [Db::backup] is not supported for namespaces, use [Db::backup_db] instead.
+pub fn new_cozo_namespaced<S>(storage: S, namespace: &str) -> Result<Db<NamespacedStorage<S>>>
+where
+    S: for<'s> Storage<'s>,
+{
+    if namespace.is_empty() || namespace.contains('\0') {
+        bail!("invalid namespace name {:?}", namespace)
+    }
+    let mut prefix = Vec::with_capacity(namespace.len() + 2);
+    prefix.push(NAMESPACE_MARKER);
+    prefix.extend_from_slice(namespace.as_bytes());
+    // terminating the name keeps namespaces from being prefixes of each other
+    prefix.push(0);
+    let ret = Db::new(NamespacedStorage {
+        inner: storage,
+        prefix,
+    })?;
+    ret.initialize()?;
+    Ok(ret)
+}
+
+/// Storage engine wrapper confining a database to a namespace, see [new_cozo_namespaced]
+#[derive(Clone)]
+pub struct NamespacedStorage<S> {
+    inner: S,
+    prefix: Vec<u8>,
+}
+
+fn with_prefix(prefix: &[u8], key: &[u8]) -> Vec<u8> {
+    let mut ret = Vec::with_capacity(prefix.len() + key.len());
+    ret.extend_from_slice(prefix);
+    ret.extend_from_slice(key);
+    ret
+}
+
+impl<'s, S: Storage<'s>> Storage<'s> for NamespacedStorage<S> {
+    type Tx = NamespacedTx<'s, S::Tx>;
+
+    fn storage_kind(&self) -> &'static str {
+        self.inner.storage_kind()
+    }
+
+    fn transact(&'s self, write: bool) -> Result<Self::Tx> {
+        Ok(NamespacedTx {
+            inner: self.inner.transact(write)?,
+            prefix: &self.prefix,
+        })
+    }
+
+    fn range_compact(&'s self, lower: &[u8], upper: &[u8]) -> Result<()> {
+        self.inner.range_compact(
+            &with_prefix(&self.prefix, lower),
+            &with_prefix(&self.prefix, upper),
+        )
+    }
+
+    fn range_size_estimate(&'s self, lower: &[u8], upper: &[u8]) -> Result<Option<(u64, u64)>> {
+        self.inner.range_size_estimate(
+            &with_prefix(&self.prefix, lower),
+            &with_prefix(&self.prefix, upper),
+        )
+    }
+
+    fn set_bulk_load(&'s self, enabled: bool) -> Result<()> {
+        self.inner.set_bulk_load(enabled)
+    }
+
+    fn checkpoint(&'s self, _path: &Path) -> Result<()> {
+        // a checkpoint of the inner storage would contain every namespace
+        bail!("online backups of a namespace are not supported, use `backup_db` instead")
+    }
+
+    fn batch_put<'a>(
+        &'a self,
+        data: Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a>,
+    ) -> Result<()> {
+        let prefix = &self.prefix;
+        self.inner.batch_put(Box::new(data.map(move |pair| {
+            let (k, v) = pair?;
+            Ok((with_prefix(prefix, &k), v))
+        })))
+    }
+}
+
+/// Transaction of [NamespacedStorage]
+pub struct NamespacedTx<'s, T> {
+    inner: T,
+    prefix: &'s [u8],
+}
+
+impl<'s, T: StoreTx<'s>> StoreTx<'s> for NamespacedTx<'s, T> {
+    fn get(&self, key: &[u8], for_update: bool) -> Result<Option<Vec<u8>>> {
+        self.inner.get(&with_prefix(self.prefix, key), for_update)
+    }
+
+    fn multi_get(&self, keys: &[Vec<u8>], for_update: bool) -> Result<Vec<Option<Vec<u8>>>> {
+        let keys: Vec<_> = keys.iter().map(|k| with_prefix(self.prefix, k)).collect();
+        self.inner.multi_get(&keys, for_update)
+    }
+
+    fn put(&mut self, key: &[u8], val: &[u8]) -> Result<()> {
+        self.inner.put(&with_prefix(self.prefix, key), val)
+    }
+
+    fn supports_par_put(&self) -> bool {
+        self.inner.supports_par_put()
+    }
+
+    fn par_put(&self, key: &[u8], val: &[u8]) -> Result<()> {
+        self.inner.par_put(&with_prefix(self.prefix, key), val)
+    }
+
+    fn del(&mut self, key: &[u8]) -> Result<()> {
+        self.inner.del(&with_prefix(self.prefix, key))
+    }
+
+    fn par_del(&self, key: &[u8]) -> Result<()> {
+        self.inner.par_del(&with_prefix(self.prefix, key))
+    }
+
+    fn del_range_from_persisted(&mut self, lower: &[u8], upper: &[u8]) -> Result<()> {
+        self.inner.del_range_from_persisted(
+            &with_prefix(self.prefix, lower),
+            &with_prefix(self.prefix, upper),
+        )
+    }
+
+    fn exists(&self, key: &[u8], for_update: bool) -> Result<bool> {
+        self.inner.exists(&with_prefix(self.prefix, key), for_update)
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        self.inner.commit()
+    }
+
+    fn range_skip_scan_tuple<'a>(
+        &'a self,
+        lower: &[u8],
+        upper: &[u8],
+        valid_at: ValidityTs,
+    ) -> Box<dyn Iterator<Item = Result<Tuple>> + 'a> {
+        // the inner skip scan would decode the prefixed keys
+        Box::new(SeekSkipIter::new(self, lower, upper, valid_at))
+    }
+
+    fn range_scan<'a>(
+        &'a self,
+        lower: &[u8],
+        upper: &[u8],
+    ) -> Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a>
+    where
+        's: 'a,
+    {
+        let prefix_len = self.prefix.len();
+        Box::new(
+            self.inner
+                .range_scan(
+                    &with_prefix(self.prefix, lower),
+                    &with_prefix(self.prefix, upper),
+                )
+                .map(move |pair| {
+                    let (mut k, v) = pair?;
+                    k.drain(..prefix_len);
+                    Ok((k, v))
+                }),
+        )
+    }
+
+    fn range_count<'a>(&'a self, lower: &[u8], upper: &[u8]) -> Result<usize>
+    where
+        's: 'a,
+    {
+        self.inner.range_count(
+            &with_prefix(self.prefix, lower),
+            &with_prefix(self.prefix, upper),
+        )
+    }
+}
@@ -6,13 +6,17 @@
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use std::fmt::Write;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use log::info;
-use miette::{miette, IntoDiagnostic, Result, WrapErr};
+use miette::{bail, miette, IntoDiagnostic, Result, WrapErr};
 
 use cozorocks::{DbBuilder, DbIter, RocksDb, Tx};
+use serde_derive::{Deserialize, Serialize};
 
 use crate::data::tuple::{check_key_for_validity, Tuple};
 use crate::data::value::ValidityTs;
@@ -24,21 +28,121 @@ use crate::Db;
 
 const KEY_PREFIX_LEN: usize = 9;
 const CURRENT_STORAGE_VERSION: u64 = 3;
+const BLOOM_FILTER_BITS_PER_KEY: f64 = 9.9;
+
+/// Compression algorithm for the RocksDB engine
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RocksDbCompression {
+    /// LZ4, with ZSTD for the bottommost level
+    #[default]
+    Default,
+    /// No compression
+    None,
+    /// Snappy for all levels
+    Snappy,
+    /// LZ4 for all levels
+    Lz4,
+    /// ZSTD for all levels
+    Zstd,
+}
+
+/// Tuning options for the RocksDB engine. Zero values keep the defaults.
+/// They cannot be combined with an `options` file in the database directory.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RocksDbOptions {
+    /// Size of the block cache in bytes
+    pub block_cache_size: usize,
+    /// Size of a single memtable in bytes
+    pub write_buffer_size: usize,
+    /// Maximum number of concurrent background flushes and compactions
+    pub max_background_jobs: usize,
+    /// Compression algorithm
+    pub compression: RocksDbCompression,
+}
+
+impl RocksDbOptions {
+    /// Renders the options in the format of RocksDB options files. A loaded options file
+    /// replaces the defaults of the engine, so these are written out as well.
+    fn to_options_file(&self) -> String {
+        let (compression, bottommost_compression) = match self.compression {
+            RocksDbCompression::Default => ("kLZ4Compression", "kZSTD"),
+            RocksDbCompression::None => ("kNoCompression", "kDisableCompressionOption"),
+            RocksDbCompression::Snappy => ("kSnappyCompression", "kDisableCompressionOption"),
+            RocksDbCompression::Lz4 => ("kLZ4Compression", "kDisableCompressionOption"),
+            RocksDbCompression::Zstd => ("kZSTD", "kDisableCompressionOption"),
+        };
+        let max_background_jobs = match self.max_background_jobs {
+            0 => 6,
+            n => n,
+        };
+        let mut ret = String::new();
+        // writing to a string cannot fail
+        let _ = writeln!(ret, "[Version]");
+        let _ = writeln!(ret, "  rocksdb_version=6.29.0");
+        let _ = writeln!(ret, "  options_file_version=1.1");
+        let _ = writeln!(ret, "\n[DBOptions]");
+        let _ = writeln!(ret, "  max_background_jobs={max_background_jobs}");
+        let _ = writeln!(ret, "  bytes_per_sync=1048576");
+        let _ = writeln!(ret, "\n[CFOptions \"default\"]");
+        let _ = writeln!(ret, "  compression={compression}");
+        let _ = writeln!(ret, "  bottommost_compression={bottommost_compression}");
+        let _ = writeln!(ret, "  level_compaction_dynamic_level_bytes=true");
+        let _ = writeln!(ret, "  compaction_pri=kMinOverlappingRatio");
+        if self.write_buffer_size > 0 {
+            let _ = writeln!(ret, "  write_buffer_size={}", self.write_buffer_size);
+        }
+        // the bloom filter is set here, since setting it when opening replaces the table options
+        let _ = writeln!(ret, "\n[TableOptions/BlockBasedTable \"default\"]");
+        let _ = writeln!(
+            ret,
+            "  filter_policy=bloomfilter:{BLOOM_FILTER_BITS_PER_KEY}:false"
+        );
+        let _ = writeln!(ret, "  whole_key_filtering=true");
+        if self.block_cache_size > 0 {
+            let _ = writeln!(ret, "  block_cache={}", self.block_cache_size);
+        }
+        ret
+    }
+}
 
 /// Creates a RocksDB database object.
 /// This is currently the fastest persistent storage and it can
 /// sustain huge concurrency.
 /// Supports concurrent readers and writers.
+/// The engine can be tuned by putting a RocksDB `options` file into the directory,
+/// or with [new_cozo_rocksdb_with_options].
 pub fn new_cozo_rocksdb(path: impl AsRef<Path>) -> Result<Db<RocksDbStorage>> {
-    let builder = DbBuilder::default().path(path.as_ref());
-    fs::create_dir_all(path.as_ref()).map_err(|err| {
+    new_cozo_rocksdb_with_options(path, RocksDbOptions::default())
+}
+
+/// Same as [new_cozo_rocksdb], but with tuning options.
+pub fn new_cozo_rocksdb_with_options(
+    path: impl AsRef<Path>,
+    options: RocksDbOptions,
+) -> Result<Db<RocksDbStorage>> {
+    let ret = Db::new(open_rocksdb_storage(path.as_ref(), &options)?)?;
+    ret.initialize()?;
+    Ok(ret)
+}
+
+/// Open the RocksDB storage without creating a database on top of it,
+/// e.g. for wrapping it in another storage.
+pub fn new_rocksdb_storage(path: impl AsRef<Path>) -> Result<RocksDbStorage> {
+    open_rocksdb_storage(path.as_ref(), &RocksDbOptions::default())
+}
+
+fn open_rocksdb_storage(path: &Path, options: &RocksDbOptions) -> Result<RocksDbStorage> {
+    let builder = DbBuilder::default().path(path);
+    fs::create_dir_all(path).map_err(|err| {
         BadDbInit(format!(
             "cannot create directory {}: {}",
-            path.as_ref().to_string_lossy(),
+            path.to_string_lossy(),
             err
         ))
     })?;
-    let path_buf = PathBuf::from(path.as_ref());
+    let path_buf = PathBuf::from(path);
 
     let is_new = {
         let mut manifest_path = path_buf.clone();
@@ -60,16 +164,7 @@ pub fn new_cozo_rocksdb(path: impl AsRef<Path>) -> Result<Db<RocksDbStorage>> {
 
             false
         } else {
-            fs::write(
-                manifest_path,
-                rmp_serde::to_vec_named(&DbManifest {
-                    storage_version: CURRENT_STORAGE_VERSION,
-                })
-                .into_diagnostic()
-                .wrap_err_with(|| "when serializing manifest")?,
-            )
-            .into_diagnostic()
-            .wrap_err_with(|| "when serializing manifest")?;
+            write_manifest(&manifest_path)?;
             true
         }
     };
@@ -84,41 +179,70 @@ pub fn new_cozo_rocksdb(path: impl AsRef<Path>) -> Result<Db<RocksDbStorage>> {
     let mut options_path = path_buf.clone();
     options_path.push("options");
 
+    let tuned = *options != RocksDbOptions::default();
     let options_path = if Path::exists(&options_path) {
+        if tuned {
+            bail!(
+                "tuning options cannot be combined with the options file {}",
+                options_path.to_string_lossy()
+            );
+        }
         info!(
             "RockDB storage engine will use options file {}",
             options_path.to_string_lossy()
         );
         options_path
-            .to_str()
-            .ok_or_else(|| miette!("bad path name"))?
+    } else if tuned {
+        let generated_path = path_buf.join("generated_options");
+        fs::write(&generated_path, options.to_options_file())
+            .into_diagnostic()
+            .wrap_err_with(|| "when writing options file")?;
+        generated_path
     } else {
-        ""
+        PathBuf::new()
     };
+    let options_path = options_path
+        .to_str()
+        .ok_or_else(|| miette!("bad path name"))?;
 
     let db_builder = builder
         .create_if_missing(is_new)
         .use_capped_prefix_extractor(true, KEY_PREFIX_LEN)
-        .use_bloom_filter(true, 9.9, true)
+        .use_bloom_filter(!tuned, BLOOM_FILTER_BITS_PER_KEY, true)
         .path(store_path)
         .options_path(options_path);
 
     let db = db_builder.build()?;
 
-    let ret = Db::new(RocksDbStorage::new(db))?;
-    ret.initialize()?;
-    Ok(ret)
+    Ok(RocksDbStorage::new(db))
+}
+
+fn write_manifest(manifest_path: &Path) -> Result<()> {
+    fs::write(
+        manifest_path,
+        rmp_serde::to_vec_named(&DbManifest {
+            storage_version: CURRENT_STORAGE_VERSION,
+        })
+        .into_diagnostic()
+        .wrap_err_with(|| "when serializing manifest")?,
+    )
+    .into_diagnostic()
+    .wrap_err_with(|| "when serializing manifest")
 }
 
 /// RocksDB storage engine
 #[derive(Clone)]
 pub struct RocksDbStorage {
     db: RocksDb,
+    bulk_load: Arc<AtomicBool>,
 }
 
 impl RocksDbStorage {
     pub(crate) fn new(db: RocksDb) -> Self {
-        Self { db }
+        Self {
+            db,
+            bulk_load: Default::default(),
+        }
     }
 }
 
@@ -130,7 +254,12 @@ impl Storage<'_> for RocksDbStorage {
     }
 
     fn transact(&self, _write: bool) -> Result<Self::Tx> {
-        let db_tx = self.db.transact().set_snapshot(true).start();
+        let db_tx = self
+            .db
+            .transact()
+            .set_snapshot(true)
+            .disable_wal(self.bulk_load.load(Ordering::Acquire))
+            .start();
         Ok(RocksDbTx { db_tx })
     }
 
@@ -138,6 +267,53 @@ impl Storage<'_> for RocksDbStorage {
         self.db.range_compact(lower, upper).into_diagnostic()
     }
 
+    fn range_size_estimate(&self, lower: &[u8], upper: &[u8]) -> Result<Option<(u64, u64)>> {
+        let (size, deletions) = self.db.approximate_size(lower, upper).into_diagnostic()?;
+        Ok(Some((size, deletions)))
+    }
+
+    fn set_bulk_load(&self, enabled: bool) -> Result<()> {
+        let was_enabled = self.bulk_load.swap(enabled, Ordering::AcqRel);
+        if was_enabled && !enabled {
+            // compacting flushes the memtables first, which persists the writes done without WAL
+            self.db.range_compact(&[], &[0xFF]).into_diagnostic()?;
+        }
+        Ok(())
+    }
+
+    fn checkpoint(&self, path: &Path) -> Result<()> {
+        if path.exists() {
+            bail!(
+                "cannot create backup: {} already exists",
+                path.to_string_lossy()
+            );
+        }
+        fs::create_dir_all(path).map_err(|err| {
+            BadDbInit(format!(
+                "cannot create directory {}: {}",
+                path.to_string_lossy(),
+                err
+            ))
+        })?;
+        write_manifest(&path.join("manifest"))?;
+
+        // carry over the user-supplied options file, if the source has one
+        let source_dir = PathBuf::from(self.db.db_path());
+        if let Some(options_path) = source_dir.parent().map(|p| p.join("options")) {
+            if options_path.exists() {
+                fs::copy(&options_path, path.join("options"))
+                    .into_diagnostic()
+                    .wrap_err_with(|| "when copying options file")?;
+            }
+        }
+
+        let store_path = path.join("data");
+        let store_path = store_path
+            .to_str()
+            .ok_or_else(|| miette!("bad path name"))?;
+        self.db.create_checkpoint(store_path).into_diagnostic()
+    }
+
     fn batch_put<'a>(
         &'a self,
         data: Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a>,
@@ -273,13 +449,6 @@ impl<'s> StoreTx<'s> for RocksDbTx {
         }
         Ok(count)
     }
-
-    fn total_scan<'a>(&'a self) -> Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a>
-    where
-        's: 'a,
-    {
-        self.range_scan(&[], &[u8::MAX])
-    }
 }
 
 pub(crate) struct RocksDbIterator {
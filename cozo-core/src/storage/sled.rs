@@ -7,18 +7,17 @@
  */
 
 use std::cmp::Ordering;
-use std::iter;
 use std::iter::Fuse;
 use std::path::Path;
 
 use itertools::Itertools;
-use miette::{miette, IntoDiagnostic, Result};
+use miette::{IntoDiagnostic, Result};
 use sled::{Batch, Config, Db, IVec, Iter, Mode};
 
 use crate::data::tuple::Tuple;
 use crate::data::value::ValidityTs;
 use crate::runtime::relation::decode_tuple_from_kv;
-use crate::storage::{Storage, StoreTx};
+use crate::storage::{SeekSkipIter, Storage, StoreTx};
 use crate::utils::{swap_option_result, TempCollector};
 
 /// Creates a Sled database object. Experimental.
@@ -55,10 +54,6 @@ impl Storage<'_> for SledStorage {
         })
     }
 
-    fn range_compact(&self, _lower: &[u8], _upper: &[u8]) -> Result<()> {
-        Ok(())
-    }
-
     fn batch_put<'a>(
         &'a self,
         data: Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a>,
@@ -79,6 +74,30 @@ pub struct SledTx {
 }
 
 impl SledTx {
+    fn raw_range(
+        &self,
+        lower: &[u8],
+        upper: &[u8],
+    ) -> Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>>> {
+        if let Some(changes) = &self.changes {
+            let change_iter = changes.range(lower.to_vec()..upper.to_vec()).fuse();
+            let db_iter = self.db.range(lower.to_vec()..upper.to_vec()).fuse();
+            Box::new(SledIterRaw {
+                change_iter,
+                db_iter,
+                change_cache: None,
+                db_cache: None,
+            })
+        } else {
+            Box::new(
+                self.db
+                    .range(lower.to_vec()..upper.to_vec())
+                    .map(|d| d.into_diagnostic())
+                    .map_ok(|(k, v)| (k.to_vec(), v.to_vec())),
+            )
+        }
+    }
+
     #[inline]
     fn ensure_changes_db(&mut self) -> Result<()> {
         if self.changes.is_none() {
@@ -132,7 +151,7 @@ impl<'s> StoreTx<'s> for SledTx {
     #[inline]
     fn del(&mut self, key: &[u8]) -> Result<()> {
         self.ensure_changes_db()?;
-        let val_to_write = [PUT_MARKER];
+        let val_to_write = [DEL_MARKER];
         self.changes
             .as_mut()
             .unwrap()
@@ -211,13 +230,12 @@ impl<'s> StoreTx<'s> for SledTx {
 
     fn range_skip_scan_tuple<'a>(
         &'a self,
-        _lower: &[u8],
-        _upper: &[u8],
-        _valid_at: ValidityTs,
+        lower: &[u8],
+        upper: &[u8],
+        valid_at: ValidityTs,
     ) -> Box<dyn Iterator<Item = Result<Tuple>> + 'a> {
-        Box::new(iter::once(Err(miette!(
-            "Sled backend does not support time travelling."
-        ))))
+        // Sled has no native skip scan
+        Box::new(SeekSkipIter::new(self, lower, upper, valid_at))
     }
 
     fn range_scan<'a>(
@@ -228,23 +246,7 @@ impl<'s> StoreTx<'s> for SledTx {
     where
         's: 'a,
     {
-        if let Some(changes) = &self.changes {
-            let change_iter = changes.range(lower.to_vec()..upper.to_vec()).fuse();
-            let db_iter = self.db.range(lower.to_vec()..upper.to_vec()).fuse();
-            Box::new(SledIterRaw {
-                change_iter,
-                db_iter,
-                change_cache: None,
-                db_cache: None,
-            })
-        } else {
-            Box::new(
-                self.db
-                    .range(lower.to_vec()..upper.to_vec())
-                    .map(|d| d.into_diagnostic())
-                    .map_ok(|(k, v)| (k.to_vec(), v.to_vec())),
-            )
-        }
+        self.raw_range(lower, upper)
     }
 
     fn range_count<'a>(&'a self, lower: &[u8], upper: &[u8]) -> Result<usize>
@@ -265,13 +267,6 @@ impl<'s> StoreTx<'s> for SledTx {
             self.db.range(lower.to_vec()..upper.to_vec()).count()
         })
     }
-
-    fn total_scan<'a>(&'a self) -> Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a>
-    where
-        's: 'a,
-    {
-        self.range_scan(&[], &[u8::MAX])
-    }
 }
 
 struct SledIterRaw {
@@ -6,6 +6,7 @@
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use std::iter;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
@@ -175,6 +176,17 @@ impl<'s> SqliteTx<'s> {
             *stmt = Some(prepared)
         }
     }
+    fn prepare_range(&self, idx: usize, lower: &[u8], upper: &[u8]) -> Result<Statement<'_>> {
+        let mut statement = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .prepare(QUERIES[idx])
+            .into_diagnostic()?;
+        statement.bind((1, lower)).into_diagnostic()?;
+        statement.bind((2, upper)).into_diagnostic()?;
+        Ok(statement)
+    }
 }
 
 impl<'s> StoreTx<'s> for SqliteTx<'s> {
@@ -234,11 +246,11 @@ impl<'s> StoreTx<'s> for SqliteTx<'s> {
         let query = r#"
                 delete from cozo where k >= ? and k < ?;
             "#;
-        let mut statement = self.conn.as_ref().unwrap().prepare(query).unwrap();
+        let mut statement = self.conn.as_ref().unwrap().prepare(query).into_diagnostic()?;
 
-        statement.bind((1, lower)).unwrap();
-        statement.bind((2, upper)).unwrap();
-        while statement.next().unwrap() != State::Done {}
+        statement.bind((1, lower)).into_diagnostic()?;
+        statement.bind((2, upper)).into_diagnostic()?;
+        while statement.next().into_diagnostic()? != State::Done {}
         Ok(())
     }
 
@@ -279,11 +291,10 @@ impl<'s> StoreTx<'s> for SqliteTx<'s> {
     {
         // Range scans cannot use cached prepared statements, as several of them
         // can be used at the same time.
-        let query = QUERIES[RANGE_QUERY];
-        let mut statement = self.conn.as_ref().unwrap().prepare(query).unwrap();
-        statement.bind((1, lower)).unwrap();
-        statement.bind((2, upper)).unwrap();
-        Box::new(TupleIter(statement))
+        match self.prepare_range(RANGE_QUERY, lower, upper) {
+            Ok(statement) => Box::new(TupleIter(statement)),
+            Err(err) => Box::new(iter::once(Err(err))),
+        }
     }
 
     fn range_skip_scan_tuple<'a>(
@@ -293,7 +304,10 @@ impl<'s> StoreTx<'s> for SqliteTx<'s> {
         valid_at: ValidityTs,
     ) -> Box<dyn Iterator<Item = Result<Tuple>> + 'a> {
         let query = QUERIES[SKIP_RANGE_QUERY];
-        let statement = self.conn.as_ref().unwrap().prepare(query).unwrap();
+        let statement = match self.conn.as_ref().unwrap().prepare(query) {
+            Ok(statement) => statement,
+            Err(err) => return Box::new(iter::once(Err(miette!(err)))),
+        };
         Box::new(SkipIter {
             stmt: statement,
             valid_at,
@@ -310,28 +324,23 @@ impl<'s> StoreTx<'s> for SqliteTx<'s> {
     where
         's: 'a,
     {
-        let query = QUERIES[RANGE_QUERY];
-        let mut statement = self.conn.as_ref().unwrap().prepare(query).unwrap();
-        statement.bind((1, lower)).unwrap();
-        statement.bind((2, upper)).unwrap();
-        Box::new(RawIter(statement))
+        match self.prepare_range(RANGE_QUERY, lower, upper) {
+            Ok(statement) => Box::new(RawIter(statement)),
+            Err(err) => Box::new(iter::once(Err(err))),
+        }
     }
 
     fn range_count<'a>(&'a self, lower: &[u8], upper: &[u8]) -> Result<usize>
     where
         's: 'a,
     {
-        let query = QUERIES[COUNT_RANGE_QUERY];
-        let mut statement = self.conn.as_ref().unwrap().prepare(query).unwrap();
-        statement.bind((1, lower)).unwrap();
-        statement.bind((2, upper)).unwrap();
-        match statement.next() {
-            Ok(State::Done) => bail!("range count query returned no rows"),
-            Ok(State::Row) => {
-                let k = statement.read::<i64, _>(0).unwrap();
+        let mut statement = self.prepare_range(COUNT_RANGE_QUERY, lower, upper)?;
+        match statement.next().into_diagnostic()? {
+            State::Done => bail!("range count query returned no rows"),
+            State::Row => {
+                let k = statement.read::<i64, _>(0).into_diagnostic()?;
                 Ok(k as usize)
             }
-            Err(err) => bail!(err),
         }
     }
 
@@ -339,13 +348,15 @@ impl<'s> StoreTx<'s> for SqliteTx<'s> {
     where
         's: 'a,
     {
-        let statement = self
+        match self
             .conn
             .as_ref()
             .unwrap()
             .prepare("select k, v from cozo order by k;")
-            .unwrap();
-        Box::new(RawIter(statement))
+        {
+            Ok(statement) => Box::new(RawIter(statement)),
+            Err(err) => Box::new(iter::once(Err(miette!(err)))),
+        }
     }
 }
 
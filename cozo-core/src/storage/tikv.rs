@@ -6,20 +6,19 @@
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::iter;
 use std::ops::Bound::{Excluded, Included};
 use std::sync::{Arc, Mutex};
 
 use itertools::Itertools;
 use lazy_static::lazy_static;
-use miette::{miette, IntoDiagnostic, Result};
-use tikv_client::{RawClient, Transaction, TransactionClient};
+use miette::{IntoDiagnostic, Result};
+use tikv_client::{Transaction, TransactionClient};
 use tokio::runtime::Runtime;
 
 use crate::data::tuple::Tuple;
 use crate::data::value::ValidityTs;
 use crate::runtime::relation::decode_tuple_from_kv;
-use crate::storage::{Storage, StoreTx};
+use crate::storage::{SeekSkipIter, Storage, StoreTx};
 use crate::utils::{swap_option_result, TempCollector};
 use crate::Db;
 
@@ -74,10 +73,6 @@ impl Storage<'_> for TiKvStorage {
         })
     }
 
-    fn range_compact(&self, _lower: &[u8], _upper: &[u8]) -> Result<()> {
-        Ok(())
-    }
-
     fn batch_put<'a>(
         &'a self,
         data: Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a>,
@@ -96,6 +91,18 @@ pub struct TiKvTx {
     tx: Arc<Mutex<Transaction>>,
 }
 
+impl TiKvTx {
+    fn first_in_range(&self, lower: &[u8], upper: &[u8]) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+        let mut tx = self.tx.lock().unwrap();
+        let fut = tx.scan((Included(lower.to_vec()), Excluded(upper.to_vec())), 1);
+        Ok(RT
+            .block_on(fut)
+            .into_diagnostic()?
+            .next()
+            .map(|pair| (pair.0.into(), pair.1)))
+    }
+}
+
 impl<'s> StoreTx<'s> for TiKvTx {
     fn get(&self, key: &[u8], for_update: bool) -> Result<Option<Vec<u8>>> {
         if for_update {
@@ -171,13 +178,13 @@ impl<'s> StoreTx<'s> for TiKvTx {
 
     fn range_skip_scan_tuple<'a>(
         &'a self,
-        _lower: &[u8],
-        _upper: &[u8],
-        _valid_at: ValidityTs,
+        lower: &[u8],
+        upper: &[u8],
+        valid_at: ValidityTs,
     ) -> Box<dyn Iterator<Item = Result<Tuple>> + 'a> {
-        Box::new(iter::once(Err(miette!(
-            "TiKV backend does not support time travelling."
-        ))))
+        // each seek fetches only the first key at or after the bound, so that older versions
+        // of a key are skipped on the server side instead of being transferred
+        Box::new(SeekSkipIter::new(self, lower, upper, valid_at).with_seek(TiKvTx::first_in_range))
     }
 
     fn range_scan<'a>(
@@ -190,20 +197,6 @@ impl<'s> StoreTx<'s> for TiKvTx {
     {
         Box::new(BatchScannerRaw::new(self.tx.clone(), lower, upper))
     }
-
-    fn range_count<'a>(&'a self, lower: &[u8], upper: &[u8]) -> Result<usize>
-    where
-        's: 'a,
-    {
-        Ok(BatchScannerRaw::new(self.tx.clone(), lower, upper).count())
-    }
-
-    fn total_scan<'a>(&'a self) -> Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a>
-    where
-        's: 'a,
-    {
-        self.range_scan(&[], &[u8::MAX])
-    }
 }
 
 struct BatchScannerRaw {
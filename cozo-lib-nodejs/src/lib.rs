@@ -190,6 +190,28 @@ fn value2js<'a>(cx: &mut impl Context<'a>, val: &DataValue) -> JsResult<'a, JsVa
             target_l.as_value(cx)
         }
         DataValue::Json(JsonData(j)) => json2js(cx, j)?,
+        DataValue::Timestamp(ts) => cx.string(ts.to_string()).as_value(cx),
+        DataValue::Decimal(d) => cx.string(d.to_string()).as_value(cx),
+        DataValue::Interval(iv) => {
+            let target_l = cx.empty_array();
+            let (start, end) = iv.bounds();
+            for (i, bound) in [start, end].into_iter().enumerate() {
+                let el = match bound {
+                    None => cx.null().as_value(cx),
+                    Some(ts) => cx.string(ts.to_string()).as_value(cx),
+                };
+                target_l.set(cx, i as u32, el)?;
+            }
+            target_l.as_value(cx)
+        }
+        DataValue::Point(p) => {
+            let target_l = cx.empty_array();
+            for (i, coord) in [p.lat(), p.lon()].into_iter().enumerate() {
+                let el = cx.number(coord);
+                target_l.set(cx, i as u32, el)?;
+            }
+            target_l.as_value(cx)
+        }
     })
 }
 
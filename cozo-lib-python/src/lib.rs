@@ -173,6 +173,17 @@ fn value_to_py(val: DataValue, py: Python<'_>) -> PyObject {
             }
         },
         DataValue::Json(JsonData(j)) => json_to_py(j, py),
+        DataValue::Timestamp(ts) => ts.to_string().into_py(py),
+        DataValue::Decimal(d) => d.to_string().into_py(py),
+        DataValue::Interval(iv) => {
+            let (start, end) = iv.bounds();
+            [
+                start.map(|ts| ts.to_string()).into_py(py),
+                end.map(|ts| ts.to_string()).into_py(py),
+            ]
+            .into_py(py)
+        }
+        DataValue::Point(p) => [p.lat(), p.lon()].into_py(py),
     }
 }
 
@@ -69,6 +69,18 @@ struct RocksDbBridge {
         write_status(db_->IngestExternalFile(cf, {std::move(path_)}, ifo), status);
     }
 
+    inline void create_checkpoint(rust::Str path, RocksDbStatus &status) const {
+        Checkpoint *checkpoint_ptr = nullptr;
+        auto s = Checkpoint::Create(get_base_db(), &checkpoint_ptr);
+        if (!s.ok()) {
+            write_status(s, status);
+            return;
+        }
+        unique_ptr<Checkpoint> checkpoint(checkpoint_ptr);
+        string path_(path);
+        write_status(checkpoint->CreateCheckpoint(path_), status);
+    }
+
     [[nodiscard]] inline const string &get_db_path() const {
         return db_path;
     }
@@ -110,6 +122,32 @@ struct RocksDbBridge {
         write_status(s, status);
     }
 
+    void approximate_size(RustBytes start, RustBytes end, uint64_t &size, uint64_t &deletions,
+                          RocksDbStatus &status) const {
+        auto cf = db->DefaultColumnFamily();
+        Range range(convert_slice(start), convert_slice(end));
+        SizeApproximationOptions options;
+        options.include_memtables = true;
+        options.include_files = true;
+        size = 0;
+        auto s = db->GetApproximateSizes(options, cf, &range, 1, &size);
+        if (!s.ok()) {
+            write_status(s, status);
+            return;
+        }
+        // counted for whole SST files overlapping the range, so this is an upper bound
+        TablePropertiesCollection props;
+        deletions = 0;
+        auto s2 = db->GetPropertiesOfTablesInRange(cf, &range, 1, &props);
+        if (!s2.ok()) {
+            write_status(s2, status);
+            return;
+        }
+        for (const auto &p: props) {
+            deletions += p.second->num_deletions;
+        }
+    }
+
     DB *get_base_db() const {
         return db->GetBaseDB();
     }
@@ -177,6 +177,23 @@ impl RocksDb {
             Err(status)
         }
     }
+    /// Approximate bytes on disk and number of deletion markers in the key range.
+    pub fn approximate_size(
+        &self,
+        lower: &[u8],
+        upper: &[u8],
+    ) -> Result<(u64, u64), RocksDbStatus> {
+        let mut status = RocksDbStatus::default();
+        let mut size = 0;
+        let mut deletions = 0;
+        self.inner
+            .approximate_size(lower, upper, &mut size, &mut deletions, &mut status);
+        if status.is_ok() {
+            Ok((size, deletions))
+        } else {
+            Err(status)
+        }
+    }
     pub fn get_sst_writer(&self, path: &str) -> Result<SstWriter, RocksDbStatus> {
         let mut status = RocksDbStatus::default();
         let ret = self.inner.get_sst_writer(path, &mut status);
@@ -195,6 +212,17 @@ impl RocksDb {
             Err(status)
         }
     }
+    /// Create a consistent on-disk snapshot of the database at `path`, which must not exist yet.
+    /// Files are hard-linked where possible, so this is cheap and can run alongside other writes.
+    pub fn create_checkpoint(&self, path: &str) -> Result<(), RocksDbStatus> {
+        let mut status = RocksDbStatus::default();
+        self.inner.create_checkpoint(path, &mut status);
+        if status.is_ok() {
+            Ok(())
+        } else {
+            Err(status)
+        }
+    }
 }
 
 pub struct SstWriter {
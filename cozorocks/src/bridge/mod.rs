@@ -134,12 +134,21 @@ pub(crate) mod ffi {
             upper: &[u8],
             status: &mut RocksDbStatus,
         );
+        fn approximate_size(
+            self: &RocksDbBridge,
+            lower: &[u8],
+            upper: &[u8],
+            size: &mut u64,
+            deletions: &mut u64,
+            status: &mut RocksDbStatus,
+        );
         fn get_sst_writer(
             self: &RocksDbBridge,
             path: &str,
             status: &mut RocksDbStatus,
         ) -> UniquePtr<SstFileWriterBridge>;
         fn ingest_sst(self: &RocksDbBridge, path: &str, status: &mut RocksDbStatus);
+        fn create_checkpoint(self: &RocksDbBridge, path: &str, status: &mut RocksDbStatus);
 
         type SstFileWriterBridge;
         fn put(